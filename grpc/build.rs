@@ -0,0 +1,9 @@
+// Compiling the service needs `protoc`, found on `PATH` or through the `PROTOC` environment
+// variable.
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    tonic_build::configure()
+        .build_client(std::env::var_os("CARGO_FEATURE_CLIENT").is_some())
+        .build_server(std::env::var_os("CARGO_FEATURE_SERVER").is_some())
+        .compile_protos(&["../proto/usi/v1/engine.proto"], &["../proto"])?;
+    Ok(())
+}
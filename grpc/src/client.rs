@@ -0,0 +1,108 @@
+use tonic::transport::{Channel, Endpoint};
+use tonic::Status;
+use usi::{BestMoveParams, InfoParams, ThinkParams};
+
+use crate::proto::engine_client::EngineClient;
+use crate::proto::{self, search_event::Event};
+
+/// A client of an engine served by `EngineService`.
+#[derive(Clone, Debug)]
+pub struct RemoteEngine {
+    client: EngineClient<Channel>,
+}
+
+impl RemoteEngine {
+    /// Connects to the service at `endpoint`, such as `http://127.0.0.1:50051`.
+    pub async fn connect<E>(endpoint: E) -> Result<Self, tonic::transport::Error>
+    where
+        E: TryInto<Endpoint>,
+        E::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
+        Ok(RemoteEngine {
+            client: EngineClient::connect(endpoint).await?,
+        })
+    }
+
+    /// Creates a new client on an established channel.
+    pub fn new(channel: Channel) -> Self {
+        RemoteEngine {
+            client: EngineClient::new(channel),
+        }
+    }
+
+    /// Returns the name and the author of the engine.
+    pub async fn info(&mut self) -> Result<(String, String), Status> {
+        let info = self
+            .client
+            .get_info(proto::GetInfoRequest {})
+            .await?
+            .into_inner();
+        Ok((info.name, info.author))
+    }
+
+    /// Sets an option, or pushes a button when `value` is `None`.
+    pub async fn set_option(&mut self, name: &str, value: Option<&str>) -> Result<(), Status> {
+        self.client
+            .set_option(proto::SetOptionRequest {
+                name: name.to_string(),
+                value: value.map(str::to_string),
+            })
+            .await?;
+        Ok(())
+    }
+
+    /// Waits for the engine to be ready, and starts a new game.
+    pub async fn new_game(&mut self) -> Result<(), Status> {
+        self.client.new_game(proto::NewGameRequest {}).await?;
+        Ok(())
+    }
+
+    /// Sets the position from a SFEN or `startpos`, followed by `moves` in USI notation.
+    pub async fn set_position(&mut self, sfen: &str, moves: &[&str]) -> Result<(), Status> {
+        self.client
+            .set_position(proto::SetPositionRequest {
+                sfen: sfen.to_string(),
+                moves: moves.iter().map(|m| m.to_string()).collect(),
+            })
+            .await?;
+        Ok(())
+    }
+
+    /// Searches the position, calling `on_info` for each `info`, and returns the best move.
+    ///
+    /// `ThinkParams::mate` is not supported by the service, and is ignored.
+    pub async fn go<F>(
+        &mut self,
+        params: ThinkParams,
+        mut on_info: F,
+    ) -> Result<BestMoveParams, Status>
+    where
+        F: FnMut(Vec<InfoParams>),
+    {
+        let mut stream = self
+            .client
+            .go(proto::GoRequest::from(&params))
+            .await?
+            .into_inner();
+        while let Some(event) = stream.message().await? {
+            match event.event {
+                Some(Event::Info(info)) => on_info(info.into()),
+                Some(Event::BestMove(bestmove)) => return Ok(bestmove.into()),
+                None => {}
+            }
+        }
+        Err(Status::aborted("the search ended without bestmove"))
+    }
+
+    /// Stops the running search, whose `go` returns the best move.
+    pub async fn stop(&mut self) -> Result<(), Status> {
+        self.client.stop(proto::StopRequest {}).await?;
+        Ok(())
+    }
+
+    /// Tells the running ponder search that the opponent played the move pondered on.
+    pub async fn ponderhit(&mut self) -> Result<(), Status> {
+        self.client.ponderhit(proto::PonderhitRequest {}).await?;
+        Ok(())
+    }
+}
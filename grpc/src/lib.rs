@@ -0,0 +1,233 @@
+//! A gRPC service hosting USI engines, defined in `proto/usi/v1/engine.proto`.
+//!
+//! `EngineService` serves an `usi::Engine` with the `server` feature, and `RemoteEngine`
+//! drives it from another process with the `client` feature. Both convert the messages
+//! from and to the types of usi, so the protocol is strongly typed end to end.
+//!
+//! Building the crate requires `protoc`, the protocol buffer compiler, on `PATH` or at the path
+//! in the `PROTOC` environment variable.
+//!
+//! ```no_run
+//! use usi::{Engine, ThinkParams};
+//! use usi_grpc::{EngineService, RemoteEngine};
+//!
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! // On the server.
+//! let engine = Engine::spawn("/path/to/usi_engine")?;
+//! tokio::spawn(
+//!     tonic::transport::Server::builder()
+//!         .add_service(EngineService::new(engine).into_server())
+//!         .serve("0.0.0.0:50051".parse()?),
+//! );
+//!
+//! // On the client.
+//! let mut remote = RemoteEngine::connect("http://127.0.0.1:50051").await?;
+//! remote.set_position("startpos", &["7g7f"]).await?;
+//! let bestmove = remote
+//!     .go(ThinkParams::new().byoyomi(std::time::Duration::from_secs(1)), |info| {
+//!         println!("{info:?}");
+//!     })
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+use std::time::Duration;
+
+use usi::{BestMoveParams, InfoParams, ScoreKind, ThinkParams};
+
+#[cfg(feature = "client")]
+mod client;
+#[cfg(feature = "server")]
+mod server;
+
+#[cfg(feature = "client")]
+pub use self::client::RemoteEngine;
+#[cfg(feature = "server")]
+pub use self::server::EngineService;
+
+/// The messages and the service generated from `engine.proto`.
+pub mod proto {
+    tonic::include_proto!("usi.v1");
+}
+
+use self::proto::score::{Bound, Value};
+
+impl From<&ThinkParams> for proto::GoRequest {
+    fn from(params: &ThinkParams) -> Self {
+        let millis = |t: Option<Duration>| t.map(|t| t.as_millis() as u64);
+        proto::GoRequest {
+            btime: millis(params.get_btime()),
+            wtime: millis(params.get_wtime()),
+            byoyomi: millis(params.get_byoyomi()),
+            binc: millis(params.get_binc()),
+            winc: millis(params.get_winc()),
+            depth: params.get_depth(),
+            infinite: params.is_infinite(),
+            ponder: params.is_ponder(),
+        }
+    }
+}
+
+impl From<proto::GoRequest> for ThinkParams {
+    fn from(go: proto::GoRequest) -> Self {
+        let mut params = ThinkParams::new();
+        if let Some(t) = go.btime {
+            params = params.btime(Duration::from_millis(t));
+        }
+        if let Some(t) = go.wtime {
+            params = params.wtime(Duration::from_millis(t));
+        }
+        if let Some(t) = go.byoyomi {
+            params = params.byoyomi(Duration::from_millis(t));
+        }
+        if let Some(t) = go.binc {
+            params = params.binc(Duration::from_millis(t));
+        }
+        if let Some(t) = go.winc {
+            params = params.winc(Duration::from_millis(t));
+        }
+        if let Some(depth) = go.depth {
+            params = params.depth(depth);
+        }
+        if go.infinite {
+            params = params.infinite();
+        }
+        if go.ponder {
+            params = params.ponder();
+        }
+        params
+    }
+}
+
+impl From<&[InfoParams]> for proto::Info {
+    fn from(params: &[InfoParams]) -> Self {
+        let mut info = proto::Info::default();
+        for param in params {
+            match param {
+                InfoParams::CurrMove(mv) => info.currmove = Some(mv.clone()),
+                InfoParams::Depth(depth, seldepth) => {
+                    info.depth = Some(*depth);
+                    info.seldepth = *seldepth;
+                }
+                InfoParams::HashFull(n) => info.hashfull = Some(*n),
+                InfoParams::MultiPv(n) => info.multipv = Some(*n),
+                InfoParams::Nodes(n) => info.nodes = Some(*n),
+                InfoParams::Nps(n) => info.nps = Some(*n),
+                InfoParams::Pv(moves) => info.pv = moves.clone(),
+                InfoParams::Score(value, kind) => info.score = Some(score(*value, *kind)),
+                InfoParams::Text(text) => info.text = Some(text.clone()),
+                InfoParams::Time(time) => info.time = Some(time.as_millis() as u64),
+            }
+        }
+        info
+    }
+}
+
+impl From<proto::Info> for Vec<InfoParams> {
+    fn from(info: proto::Info) -> Self {
+        let mut params = Vec::new();
+        if let Some(depth) = info.depth {
+            params.push(InfoParams::Depth(depth, info.seldepth));
+        }
+        if let Some(time) = info.time {
+            params.push(InfoParams::Time(Duration::from_millis(time)));
+        }
+        if let Some(nodes) = info.nodes {
+            params.push(InfoParams::Nodes(nodes));
+        }
+        if let Some(nps) = info.nps {
+            params.push(InfoParams::Nps(nps));
+        }
+        if let Some(n) = info.hashfull {
+            params.push(InfoParams::HashFull(n));
+        }
+        if let Some(n) = info.multipv {
+            params.push(InfoParams::MultiPv(n));
+        }
+        if let Some(proto::Score { value: Some(value), bound }) = info.score {
+            let bound = Bound::try_from(bound).unwrap_or(Bound::Exact);
+            params.push(match (value, bound) {
+                (Value::Cp(cp), Bound::Exact) => InfoParams::Score(cp, ScoreKind::CpExact),
+                (Value::Cp(cp), Bound::Lower) => InfoParams::Score(cp, ScoreKind::CpLowerbound),
+                (Value::Cp(cp), Bound::Upper) => InfoParams::Score(cp, ScoreKind::CpUpperbound),
+                (Value::Mate(n), Bound::Exact) => InfoParams::Score(n, ScoreKind::MateExact),
+                (Value::Mate(n), Bound::Lower) => InfoParams::Score(n, ScoreKind::MateLowerbound),
+                (Value::Mate(n), Bound::Upper) => InfoParams::Score(n, ScoreKind::MateUpperbound),
+            });
+        }
+        if let Some(mv) = info.currmove {
+            params.push(InfoParams::CurrMove(mv));
+        }
+        if !info.pv.is_empty() {
+            params.push(InfoParams::Pv(info.pv));
+        }
+        if let Some(text) = info.text {
+            params.push(InfoParams::Text(text));
+        }
+        params
+    }
+}
+
+fn score(value: i32, kind: ScoreKind) -> proto::Score {
+    let (value, bound) = match kind {
+        ScoreKind::CpExact => (Value::Cp(value), Bound::Exact),
+        ScoreKind::CpLowerbound => (Value::Cp(value), Bound::Lower),
+        ScoreKind::CpUpperbound => (Value::Cp(value), Bound::Upper),
+        ScoreKind::MateExact | ScoreKind::MateSignOnly => (Value::Mate(value), Bound::Exact),
+        ScoreKind::MateLowerbound => (Value::Mate(value), Bound::Lower),
+        ScoreKind::MateUpperbound => (Value::Mate(value), Bound::Upper),
+    };
+    proto::Score {
+        value: Some(value),
+        bound: bound.into(),
+    }
+}
+
+impl From<&BestMoveParams> for proto::BestMove {
+    fn from(params: &BestMoveParams) -> Self {
+        let (mv, ponder) = match params {
+            BestMoveParams::MakeMove(mv, ponder) => (mv.clone(), ponder.clone()),
+            BestMoveParams::Resign => ("resign".to_string(), None),
+            BestMoveParams::Win => ("win".to_string(), None),
+        };
+        proto::BestMove { r#move: mv, ponder }
+    }
+}
+
+impl From<proto::BestMove> for BestMoveParams {
+    fn from(bestmove: proto::BestMove) -> Self {
+        match bestmove.r#move.as_str() {
+            "resign" => BestMoveParams::Resign,
+            "win" => BestMoveParams::Win,
+            _ => BestMoveParams::MakeMove(bestmove.r#move, bestmove.ponder),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn info() {
+        let params = vec![
+            InfoParams::Depth(10, Some(12)),
+            InfoParams::Time(Duration::from_millis(300)),
+            InfoParams::Nodes(1000),
+            InfoParams::Score(-3, ScoreKind::MateLowerbound),
+            InfoParams::Pv(vec!["7g7f".to_string(), "3c3d".to_string()]),
+        ];
+        let info = proto::Info::from(params.as_slice());
+        assert_eq!(Some(12), info.seldepth);
+        assert_eq!(params, Vec::<InfoParams>::from(info));
+    }
+
+    #[test]
+    fn go() {
+        let params = ThinkParams::new()
+            .btime(Duration::from_secs(60))
+            .byoyomi(Duration::from_secs(10))
+            .ponder();
+        assert_eq!(params, ThinkParams::from(proto::GoRequest::from(&params)));
+    }
+}
@@ -0,0 +1,333 @@
+use std::sync::mpsc::{channel, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status};
+use usi::{Engine, Error, GuiCommand, SetOptionParams, ThinkParams};
+
+use crate::proto::engine_server::{self, EngineServer};
+use crate::proto::{self, search_event::Event};
+
+/// How often a quiet search checks `Stop`, `Ponderhit` and the client going away.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Serves an engine to remote `RemoteEngine`s.
+///
+/// Requests are run one at a time on the blocking thread pool of tokio. `Stop` and
+/// `Ponderhit` are sent to the search of the last `Go`, even before it starts, which is
+/// also stopped when the client goes away, so that the engine serves other requests.
+pub struct EngineService {
+    engine: Arc<Mutex<Engine>>,
+    control: Mutex<Option<Sender<Control>>>,
+}
+
+/// A command to the search of a `Go`.
+enum Control {
+    Stop,
+    Ponderhit,
+}
+
+impl EngineService {
+    /// Creates a new service serving `engine`.
+    pub fn new(engine: Engine) -> Self {
+        EngineService {
+            engine: Arc::new(Mutex::new(engine)),
+            control: Mutex::new(None),
+        }
+    }
+
+    /// Sends `control` to the search of the last `Go`.
+    fn send_control(&self, control: Control) -> Result<(), Status> {
+        let sender = self
+            .control
+            .lock()
+            .map_err(|_| Status::internal("control poisoned"))?;
+        if let Some(sender) = &*sender {
+            // The search has ended if the receiver is gone.
+            let _ = sender.send(control);
+        }
+        Ok(())
+    }
+
+    /// Wraps the service into a server for `tonic::transport::Server::add_service`.
+    pub fn into_server(self) -> EngineServer<Self> {
+        EngineServer::new(self)
+    }
+
+    async fn run<T, F>(&self, f: F) -> Result<Response<T>, Status>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Engine) -> Result<T, Error> + Send + 'static,
+    {
+        let engine = Arc::clone(&self.engine);
+        tokio::task::spawn_blocking(move || {
+            let mut engine = engine.lock().map_err(|_| Status::internal("engine poisoned"))?;
+            f(&mut engine).map_err(to_status)
+        })
+        .await
+        .map_err(|e| Status::internal(e.to_string()))?
+        .map(Response::new)
+    }
+}
+
+#[tonic::async_trait]
+impl engine_server::Engine for EngineService {
+    async fn get_info(
+        &self,
+        _: Request<proto::GetInfoRequest>,
+    ) -> Result<Response<proto::EngineInfo>, Status> {
+        self.run(|engine| {
+            Ok(proto::EngineInfo {
+                name: engine.info().name().to_string(),
+                author: engine.info().author().to_string(),
+            })
+        })
+        .await
+    }
+
+    async fn set_option(
+        &self,
+        request: Request<proto::SetOptionRequest>,
+    ) -> Result<Response<proto::SetOptionResponse>, Status> {
+        let request = request.into_inner();
+        self.run(move |engine| {
+            match request.value {
+                Some(value) => engine.set_option(&request.name, &value)?,
+                None => engine.send(&GuiCommand::SetOption(SetOptionParams::button(
+                    request.name,
+                )))?,
+            }
+            Ok(proto::SetOptionResponse {})
+        })
+        .await
+    }
+
+    async fn new_game(
+        &self,
+        _: Request<proto::NewGameRequest>,
+    ) -> Result<Response<proto::NewGameResponse>, Status> {
+        self.run(|engine| {
+            engine.prepare()?;
+            engine.send(&GuiCommand::UsiNewGame)?;
+            Ok(proto::NewGameResponse {})
+        })
+        .await
+    }
+
+    async fn set_position(
+        &self,
+        request: Request<proto::SetPositionRequest>,
+    ) -> Result<Response<proto::SetPositionResponse>, Status> {
+        let request = request.into_inner();
+        let mut position = match request.sfen.as_str() {
+            "startpos" => "startpos".to_string(),
+            sfen => format!("sfen {sfen}"),
+        };
+        if !request.moves.is_empty() {
+            position = format!("{position} moves {}", request.moves.join(" "));
+        }
+        let command = GuiCommand::parse(&format!("position {position}"))
+            .map_err(|_| Status::invalid_argument("invalid position"))?;
+        self.run(move |engine| {
+            engine.send(&command)?;
+            Ok(proto::SetPositionResponse {})
+        })
+        .await
+    }
+
+    type GoStream = ReceiverStream<Result<proto::SearchEvent, Status>>;
+
+    async fn go(
+        &self,
+        request: Request<proto::GoRequest>,
+    ) -> Result<Response<Self::GoStream>, Status> {
+        let params = ThinkParams::from(request.into_inner());
+        let engine = Arc::clone(&self.engine);
+        let (control, controls) = channel();
+        *self
+            .control
+            .lock()
+            .map_err(|_| Status::internal("control poisoned"))? = Some(control);
+        let (tx, rx) = mpsc::channel(64);
+        tokio::task::spawn_blocking(move || {
+            let event = |event| Ok(proto::SearchEvent { event: Some(event) });
+            let result = (|| {
+                let mut engine = engine.lock().map_err(|_| Status::internal("engine poisoned"))?;
+                let mut search = engine.go(params).map_err(to_status)?;
+                loop {
+                    // Stop if the client went away.
+                    if tx.is_closed() {
+                        return search.stop().map_err(to_status);
+                    }
+                    match controls.try_recv() {
+                        Ok(Control::Stop) => return search.stop().map_err(to_status),
+                        Ok(Control::Ponderhit) => search.ponderhit().map_err(to_status)?,
+                        Err(_) => {}
+                    }
+                    match search.next_info_timeout(POLL_INTERVAL).map_err(to_status)? {
+                        Some(info) => {
+                            let info = proto::Info::from(info.as_slice());
+                            let _ = tx.blocking_send(event(Event::Info(info)));
+                        }
+                        None if search.bestmove().is_some() => {
+                            return search.wait().map_err(to_status)
+                        }
+                        None => {}
+                    }
+                }
+            })();
+            let _ = tx.blocking_send(match result {
+                Ok(bestmove) => event(Event::BestMove(proto::BestMove::from(&bestmove))),
+                Err(status) => Err(status),
+            });
+        });
+        Ok(Response::new(ReceiverStream::new(rx)))
+    }
+
+    async fn stop(
+        &self,
+        _: Request<proto::StopRequest>,
+    ) -> Result<Response<proto::StopResponse>, Status> {
+        self.send_control(Control::Stop)?;
+        Ok(Response::new(proto::StopResponse {}))
+    }
+
+    async fn ponderhit(
+        &self,
+        _: Request<proto::PonderhitRequest>,
+    ) -> Result<Response<proto::PonderhitResponse>, Status> {
+        self.send_control(Control::Ponderhit)?;
+        Ok(Response::new(proto::PonderhitResponse {}))
+    }
+}
+
+fn to_status(err: Error) -> Status {
+    match err {
+        Error::UnknownOption { .. }
+        | Error::InvalidOptionValue { .. }
+        | Error::OptionOutOfRange { .. }
+        | Error::IllegalSyntax
+        | Error::IllegalNumberFormat(_)
+        | Error::InvalidArgument { .. } => Status::invalid_argument(err.to_string()),
+        Error::IllegalOperation | Error::IllegalState { .. } => {
+            Status::failed_precondition(err.to_string())
+        }
+        Error::Timeout { .. } => Status::deadline_exceeded(err.to_string()),
+        Error::EngineClosed | Error::EngineExited(_) => Status::unavailable(err.to_string()),
+        _ => Status::internal(err.to_string()),
+    }
+}
+
+#[cfg(all(test, feature = "client"))]
+mod tests {
+    use super::*;
+    use crate::proto::engine_client::EngineClient;
+    use crate::RemoteEngine;
+    use tokio::net::TcpListener;
+    use tokio_stream::wrappers::TcpListenerStream;
+    use usi::{
+        BestMoveParams, EngineCommand, InProcessTransport, InfoSender, Responder, StopToken,
+        UsiEngine,
+    };
+
+    /// Searches quietly until `stop` or `ponderhit`.
+    struct QuietEngine;
+
+    impl UsiEngine for QuietEngine {
+        fn name(&self) -> String {
+            "Quiet".to_string()
+        }
+
+        fn author(&self) -> String {
+            "usi-rs".to_string()
+        }
+
+        fn go(&mut self, _: ThinkParams, _: &Responder, _: InfoSender, _: StopToken) {}
+
+        fn stop(&mut self, responder: &Responder) {
+            responder.send(&bestmove("7g7f")).unwrap();
+        }
+
+        fn ponderhit(&mut self, responder: &Responder) {
+            responder.send(&bestmove("2g2f")).unwrap();
+        }
+    }
+
+    fn bestmove(mv: &str) -> EngineCommand {
+        EngineCommand::BestMove(BestMoveParams::MakeMove(mv.to_string(), None))
+    }
+
+    /// Serves `QuietEngine`, returning the endpoint.
+    async fn serve() -> String {
+        let engine = Engine::connect(InProcessTransport::spawn(QuietEngine)).unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(
+            tonic::transport::Server::builder()
+                .add_service(EngineService::new(engine).into_server())
+                .serve_with_incoming(TcpListenerStream::new(listener)),
+        );
+        format!("http://{addr}")
+    }
+
+    /// Runs `go` with `params` in the background, and ends it with `Ponderhit` or `Stop`.
+    async fn search(remote: &mut RemoteEngine, params: ThinkParams, ponderhit: bool) -> String {
+        let mut searching = remote.clone();
+        let search = tokio::spawn(async move { searching.go(params, |_| {}).await });
+        tokio::time::sleep(POLL_INTERVAL * 2).await;
+        if ponderhit {
+            remote.ponderhit().await.unwrap();
+        } else {
+            remote.stop().await.unwrap();
+        }
+        match search.await.unwrap().unwrap() {
+            BestMoveParams::MakeMove(mv, _) => mv,
+            bestmove => panic!("unexpected {bestmove:?}"),
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn round_trip() {
+        let mut remote = RemoteEngine::connect(serve().await).await.unwrap();
+        assert_eq!(
+            ("Quiet".to_string(), "usi-rs".to_string()),
+            remote.info().await.unwrap()
+        );
+        remote.new_game().await.unwrap();
+        remote.set_position("startpos", &["7g7f"]).await.unwrap();
+
+        let infinite = ThinkParams::new().infinite();
+        assert_eq!("7g7f", search(&mut remote, infinite, false).await);
+        let ponder = ThinkParams::new().ponder();
+        assert_eq!("2g2f", search(&mut remote, ponder, true).await);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn stop_before_search() {
+        let mut client = EngineClient::connect(serve().await).await.unwrap();
+        let go = proto::GoRequest::from(&ThinkParams::new().infinite());
+        let mut events = client.go(go).await.unwrap().into_inner();
+        // Likely before the search gets the engine.
+        client.stop(proto::StopRequest {}).await.unwrap();
+
+        let event = tokio::time::timeout(Duration::from_secs(5), events.message()).await;
+        let event = event.unwrap().unwrap().unwrap().event;
+        assert!(matches!(event, Some(Event::BestMove(_))));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn client_gone() {
+        let mut remote = RemoteEngine::connect(serve().await).await.unwrap();
+        let mut searching = remote.clone();
+        let params = ThinkParams::new().infinite();
+        let search = tokio::spawn(async move { searching.go(params, |_| {}).await });
+        tokio::time::sleep(POLL_INTERVAL * 2).await;
+        search.abort();
+
+        // The abandoned search is stopped, releasing the engine.
+        let info = tokio::time::timeout(Duration::from_secs(5), remote.info()).await;
+        assert!(info.unwrap().is_ok());
+    }
+}
@@ -0,0 +1,106 @@
+/*
+ * C interface of usi, built with the `ffi` feature:
+ *
+ *     cargo rustc --release --features ffi --crate-type cdylib
+ *
+ * Handles are freed with the matching usi_*_free function. Functions returning
+ * UsiStatus write their results through the output pointers on success, and
+ * set the message returned by usi_last_error on the calling thread. Strings
+ * returned through char ** are freed with usi_string_free, and those returned
+ * as const char * are borrowed from the handle.
+ */
+#ifndef USI_H
+#define USI_H
+
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef enum UsiStatus {
+    USI_OK = 0,
+    USI_NULL_POINTER = 1,
+    USI_INVALID_UTF8 = 2,
+    USI_ILLEGAL_SYNTAX = 3,
+    USI_NOT_FOUND = 4,
+    USI_INVALID_OPTION = 5,
+    USI_ENGINE_CLOSED = 6,
+    USI_TIMEOUT = 7,
+    USI_IO = 8,
+    USI_PANIC = 9,
+    USI_OTHER = 10
+} UsiStatus;
+
+typedef enum UsiCommandKind {
+    USI_COMMAND_ID = 0,
+    USI_COMMAND_BESTMOVE = 1,
+    USI_COMMAND_CHECKMATE = 2,
+    USI_COMMAND_INFO = 3,
+    USI_COMMAND_OPTION = 4,
+    USI_COMMAND_READYOK = 5,
+    USI_COMMAND_USIOK = 6,
+    USI_COMMAND_UNKNOWN = 7
+} UsiCommandKind;
+
+typedef enum UsiInfoField {
+    USI_INFO_DEPTH = 0,
+    USI_INFO_SELDEPTH = 1,
+    USI_INFO_TIME = 2, /* in milliseconds */
+    USI_INFO_NODES = 3,
+    USI_INFO_NPS = 4,
+    USI_INFO_HASHFULL = 5,
+    USI_INFO_MULTIPV = 6,
+    USI_INFO_SCORE_CP = 7,
+    USI_INFO_SCORE_MATE = 8
+} UsiInfoField;
+
+/* Negative values are unset. */
+typedef struct UsiGoParams {
+    int64_t btime_ms;
+    int64_t wtime_ms;
+    int64_t byoyomi_ms;
+    int64_t binc_ms;
+    int64_t winc_ms;
+    int32_t depth;
+} UsiGoParams;
+
+typedef struct UsiCommand UsiCommand;
+typedef struct UsiEngine UsiEngine;
+
+typedef void (*UsiInfoCallback)(const UsiCommand *info, void *data);
+
+const char *usi_last_error(void);
+void usi_string_free(char *s);
+
+UsiStatus usi_command_parse(const char *line, UsiCommand **out);
+void usi_command_free(UsiCommand *command);
+UsiCommandKind usi_command_kind(const UsiCommand *command);
+const char *usi_command_raw(const UsiCommand *command);
+UsiStatus usi_command_bestmove(const UsiCommand *command, char **mv, char **ponder);
+/* field is one of UsiInfoField. */
+UsiStatus usi_command_info(const UsiCommand *command, int32_t field, int64_t *out);
+UsiStatus usi_command_info_pv(const UsiCommand *command, char **out);
+
+UsiStatus usi_gui_command_normalize(const char *line, char **out);
+UsiGoParams usi_go_params_default(void);
+
+/* Sends usi and isready, and waits for usiok and readyok. */
+UsiStatus usi_engine_spawn(const char *path, UsiEngine **out);
+void usi_engine_free(UsiEngine *engine);
+const char *usi_engine_name(const UsiEngine *engine);
+const char *usi_engine_author(const UsiEngine *engine);
+UsiStatus usi_engine_set_option(UsiEngine *engine, const char *name, const char *value);
+UsiStatus usi_engine_new_game(UsiEngine *engine);
+UsiStatus usi_engine_position(UsiEngine *engine, const char *position);
+UsiStatus usi_engine_send(UsiEngine *engine, const char *line);
+UsiStatus usi_engine_recv(UsiEngine *engine, UsiCommand **out);
+/* params must set a time control or a depth, as the search can't be stopped. */
+UsiStatus usi_engine_go(UsiEngine *engine, const UsiGoParams *params, UsiInfoCallback callback,
+                        void *data, UsiCommand **out);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,351 @@
+//! Python bindings of usi, exposing the engine client, the protocol parser and the analysis.
+//!
+//! Build the module with [maturin](https://www.maturin.rs) from this directory:
+//!
+//! ```text
+//! $ maturin develop --release
+//! ```
+//!
+//! ```python
+//! import usi
+//!
+//! engine = usi.Engine("/path/to/usi_engine", options={"USI_Hash": "256"})
+//! report = engine.analyze("startpos moves 7g7f", depth=20, time=10.0)
+//! print(report["bestmove"], report["score"], " ".join(report["pv"]))
+//!
+//! usi.parse_engine_command("info depth 10 score cp 30 pv 7g7f")
+//! # {'command': 'info', 'depth': 10, 'score': {'cp': 30}, 'pv': ['7g7f']}
+//! ```
+use std::time::Duration;
+
+use pyo3::create_exception;
+use pyo3::exceptions::PyException;
+use pyo3::prelude::*;
+use pyo3::types::{PyBool, PyDict, PyList};
+
+use ::usi::{
+    AnalysisLimits, BestMoveParams, CheckmateParams, EngineCommand, GameOverKind, GuiCommand,
+    IdParams, InfoParams, ScoreKind, ThinkParams, STARTPOS_SFEN,
+};
+
+create_exception!(usi, UsiError, PyException, "An error raised by the USI engine client.");
+
+fn to_py_err(e: ::usi::Error) -> PyErr {
+    UsiError::new_err(e.to_string())
+}
+
+/// A USI engine process.
+#[pyclass(name = "Engine", module = "usi")]
+struct PyEngine {
+    inner: Option<::usi::Engine>,
+}
+
+impl PyEngine {
+    fn engine(&mut self) -> PyResult<&mut ::usi::Engine> {
+        self.inner
+            .as_mut()
+            .ok_or_else(|| UsiError::new_err("the engine is closed"))
+    }
+}
+
+#[pymethods]
+impl PyEngine {
+    /// Spawns the engine at `path`, and sets `options` given as names and values.
+    #[new]
+    #[pyo3(signature = (path, options = None))]
+    fn new(py: Python<'_>, path: String, options: Option<&Bound<'_, PyDict>>) -> PyResult<Self> {
+        // Options are set in the order of the dict, since some depend on others.
+        let options = match options {
+            Some(options) => options
+                .iter()
+                .map(|(name, value)| Ok((name.extract()?, option_value(&value)?)))
+                .collect::<PyResult<Vec<(String, String)>>>()?,
+            None => Vec::new(),
+        };
+        let engine = py.allow_threads(|| -> Result<_, ::usi::Error> {
+            let mut engine = ::usi::Engine::spawn(&path)?;
+            for (name, value) in &options {
+                engine.set_option(name, value)?;
+            }
+            Ok(engine)
+        });
+        Ok(PyEngine {
+            inner: Some(engine.map_err(to_py_err)?),
+        })
+    }
+
+    /// The name of the engine from `id name`.
+    #[getter]
+    fn name(&mut self) -> PyResult<String> {
+        Ok(self.engine()?.info().name().to_string())
+    }
+
+    /// The author of the engine from `id author`.
+    #[getter]
+    fn author(&mut self) -> PyResult<String> {
+        Ok(self.engine()?.info().author().to_string())
+    }
+
+    /// Sets the option `name` to `value`, checking it against the declared options.
+    fn set_option(&mut self, name: &str, value: &Bound<'_, PyAny>) -> PyResult<()> {
+        let value = option_value(value)?;
+        self.engine()?.set_option(name, &value).map_err(to_py_err)
+    }
+
+    /// Waits for `readyok`, and sends `usinewgame`.
+    fn new_game(&mut self, py: Python<'_>) -> PyResult<()> {
+        let engine = self.engine()?;
+        py.allow_threads(|| {
+            engine.prepare()?;
+            engine.send(&GuiCommand::UsiNewGame)
+        })
+        .map_err(to_py_err)
+    }
+
+    /// Searches `position` with the time control in seconds, and returns the best move
+    /// with the ponder move, or `"resign"` or `"win"` without a ponder move.
+    ///
+    /// `position` is a SFEN or `startpos`, optionally followed by `moves`.
+    #[pyo3(signature = (position, btime = None, wtime = None, byoyomi = None, binc = None, winc = None, depth = None))]
+    #[allow(clippy::too_many_arguments)]
+    fn go(
+        &mut self,
+        py: Python<'_>,
+        position: &str,
+        btime: Option<f64>,
+        wtime: Option<f64>,
+        byoyomi: Option<f64>,
+        binc: Option<f64>,
+        winc: Option<f64>,
+        depth: Option<i32>,
+    ) -> PyResult<(String, Option<String>)> {
+        let mut params = ThinkParams::new();
+        let secs = Duration::from_secs_f64;
+        if let Some(t) = btime {
+            params = params.btime(secs(t));
+        }
+        if let Some(t) = wtime {
+            params = params.wtime(secs(t));
+        }
+        if let Some(t) = byoyomi {
+            params = params.byoyomi(secs(t));
+        }
+        if let Some(t) = binc {
+            params = params.binc(secs(t));
+        }
+        if let Some(t) = winc {
+            params = params.winc(secs(t));
+        }
+        if let Some(depth) = depth {
+            params = params.depth(depth);
+        }
+        let position = expand_startpos(position);
+        let engine = self.engine()?;
+        let bestmove = py
+            .allow_threads(|| {
+                engine.send(&GuiCommand::Position(position))?;
+                engine.go(params)?.wait()
+            })
+            .map_err(to_py_err)?;
+        Ok(match bestmove {
+            BestMoveParams::MakeMove(mv, ponder) => (mv, ponder),
+            BestMoveParams::Resign => ("resign".to_string(), None),
+            BestMoveParams::Win => ("win".to_string(), None),
+        })
+    }
+
+    /// Analyzes `position` until any of the limits is reached, with `time` in seconds,
+    /// and returns the report as a dict with the same keys as `AnalysisReport::to_json`.
+    #[pyo3(signature = (position, depth = None, time = None, nodes = None))]
+    fn analyze(
+        &mut self,
+        py: Python<'_>,
+        position: &str,
+        depth: Option<i32>,
+        time: Option<f64>,
+        nodes: Option<u64>,
+    ) -> PyResult<PyObject> {
+        let mut limits = AnalysisLimits::new();
+        if let Some(depth) = depth {
+            limits = limits.depth(depth);
+        }
+        if let Some(time) = time {
+            limits = limits.time(Duration::from_secs_f64(time));
+        }
+        if let Some(nodes) = nodes {
+            limits = limits.nodes(nodes);
+        }
+        let position = expand_startpos(position);
+        let engine = self.engine()?;
+        let json = py
+            .allow_threads(|| engine.analyze(&position, limits)?.to_json())
+            .map_err(to_py_err)?;
+        let report = py
+            .import_bound("json")?
+            .call_method1("loads", (json,))?;
+        Ok(report.unbind())
+    }
+
+    /// Sends `gameover` with `result`, which is `"win"`, `"lose"` or `"draw"`.
+    fn game_over(&mut self, result: &str) -> PyResult<()> {
+        let kind = match result {
+            "win" => GameOverKind::Win,
+            "lose" => GameOverKind::Lose,
+            "draw" => GameOverKind::Draw,
+            _ => return Err(UsiError::new_err(format!("invalid result: {result}"))),
+        };
+        self.engine()?
+            .send(&GuiCommand::GameOver(kind))
+            .map_err(to_py_err)
+    }
+
+    /// Sends a command line to the engine, raising `UsiError` if it isn't a valid command.
+    fn send(&mut self, line: &str) -> PyResult<()> {
+        let command = GuiCommand::parse(line).map_err(to_py_err)?;
+        self.engine()?.send(&command).map_err(to_py_err)
+    }
+
+    /// Waits for a line from the engine, and returns it.
+    fn recv(&mut self, py: Python<'_>) -> PyResult<String> {
+        let engine = self.engine()?;
+        let output = py.allow_threads(|| engine.recv()).map_err(to_py_err)?;
+        Ok(output.raw_str().to_string())
+    }
+
+    /// Sends `quit` and waits for the engine to exit. Further calls raise `UsiError`.
+    fn close(&mut self, py: Python<'_>) -> PyResult<()> {
+        match self.inner.take() {
+            Some(engine) => py.allow_threads(|| engine.shutdown()).map_err(to_py_err),
+            None => Ok(()),
+        }
+    }
+
+    fn __enter__(slf: Py<Self>) -> Py<Self> {
+        slf
+    }
+
+    fn __exit__(
+        &mut self,
+        py: Python<'_>,
+        _exc_type: PyObject,
+        _exc_value: PyObject,
+        _traceback: PyObject,
+    ) -> PyResult<()> {
+        self.close(py)
+    }
+}
+
+/// Converts an option value to its USI form, where `True` and `False` are `true` and `false`.
+fn option_value(value: &Bound<'_, PyAny>) -> PyResult<String> {
+    match value.downcast::<PyBool>() {
+        Ok(value) => Ok(value.is_true().to_string()),
+        Err(_) => Ok(value.str()?.to_string()),
+    }
+}
+
+/// Replaces a leading `startpos` with the SFEN of the initial position.
+fn expand_startpos(position: &str) -> String {
+    match position.strip_prefix("startpos") {
+        Some(rest) => format!("{STARTPOS_SFEN}{rest}"),
+        None => position.to_string(),
+    }
+}
+
+/// Parses a line sent by an engine into a dict, whose `command` is the name of the command.
+///
+/// `info` is parsed into its fields, e.g. `{"command": "info", "depth": 10,
+/// "score": {"cp": 30}, "pv": ["7g7f"]}`, where the time is in seconds.
+#[pyfunction]
+fn parse_engine_command(py: Python<'_>, line: &str) -> PyResult<PyObject> {
+    let command = EngineCommand::parse(line).map_err(to_py_err)?;
+    let dict = PyDict::new_bound(py);
+    match command {
+        EngineCommand::Id(IdParams::Name(name)) => {
+            dict.set_item("command", "id")?;
+            dict.set_item("name", name)?;
+        }
+        EngineCommand::Id(IdParams::Author(author)) => {
+            dict.set_item("command", "id")?;
+            dict.set_item("author", author)?;
+        }
+        EngineCommand::BestMove(params) => {
+            dict.set_item("command", "bestmove")?;
+            let (mv, ponder) = match params {
+                BestMoveParams::MakeMove(mv, ponder) => (mv, ponder),
+                BestMoveParams::Resign => ("resign".to_string(), None),
+                BestMoveParams::Win => ("win".to_string(), None),
+            };
+            dict.set_item("move", mv)?;
+            dict.set_item("ponder", ponder)?;
+        }
+        EngineCommand::Checkmate(params) => {
+            dict.set_item("command", "checkmate")?;
+            match params {
+                CheckmateParams::Mate(moves) => dict.set_item("moves", moves)?,
+                CheckmateParams::NoMate => dict.set_item("result", "nomate")?,
+                CheckmateParams::NotImplemented => dict.set_item("result", "notimplemented")?,
+                CheckmateParams::Timeout => dict.set_item("result", "timeout")?,
+            }
+        }
+        EngineCommand::Info(params) => {
+            dict.set_item("command", "info")?;
+            for param in params {
+                set_info(py, &dict, param)?;
+            }
+        }
+        EngineCommand::Option(params) => {
+            dict.set_item("command", "option")?;
+            dict.set_item("name", &params.name)?;
+            dict.set_item("type", params.value.to_string())?;
+        }
+        EngineCommand::ReadyOk => dict.set_item("command", "readyok")?,
+        EngineCommand::UsiOk => dict.set_item("command", "usiok")?,
+        EngineCommand::Unknown => dict.set_item("command", py.None())?,
+    }
+    Ok(dict.into_any().unbind())
+}
+
+fn set_info(py: Python<'_>, dict: &Bound<'_, PyDict>, param: InfoParams) -> PyResult<()> {
+    match param {
+        InfoParams::CurrMove(mv) => dict.set_item("currmove", mv),
+        InfoParams::Depth(depth, seldepth) => {
+            dict.set_item("depth", depth)?;
+            if let Some(seldepth) = seldepth {
+                dict.set_item("seldepth", seldepth)?;
+            }
+            Ok(())
+        }
+        InfoParams::HashFull(n) => dict.set_item("hashfull", n),
+        InfoParams::MultiPv(n) => dict.set_item("multipv", n),
+        InfoParams::Nodes(n) => dict.set_item("nodes", n),
+        InfoParams::Nps(n) => dict.set_item("nps", n),
+        InfoParams::Pv(moves) => dict.set_item("pv", PyList::new_bound(py, moves)),
+        InfoParams::Score(value, kind) => {
+            let score = PyDict::new_bound(py);
+            let (key, bound) = match kind {
+                ScoreKind::CpExact => ("cp", None),
+                ScoreKind::CpLowerbound => ("cp", Some("lowerbound")),
+                ScoreKind::CpUpperbound => ("cp", Some("upperbound")),
+                ScoreKind::MateExact | ScoreKind::MateSignOnly => ("mate", None),
+                ScoreKind::MateLowerbound => ("mate", Some("lowerbound")),
+                ScoreKind::MateUpperbound => ("mate", Some("upperbound")),
+            };
+            score.set_item(key, value)?;
+            if let Some(bound) = bound {
+                score.set_item("bound", bound)?;
+            }
+            dict.set_item("score", score)
+        }
+        InfoParams::Text(text) => dict.set_item("string", text),
+        InfoParams::Time(time) => dict.set_item("time", time.as_secs_f64()),
+    }
+}
+
+#[pymodule]
+fn usi(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add("UsiError", m.py().get_type_bound::<UsiError>())?;
+    m.add("STARTPOS", STARTPOS_SFEN)?;
+    m.add_class::<PyEngine>()?;
+    m.add_function(wrap_pyfunction!(parse_engine_command, m)?)?;
+    Ok(())
+}
@@ -0,0 +1,60 @@
+import sys
+
+import pytest
+
+import usi
+
+
+def test_parse_info():
+    info = usi.parse_engine_command("info depth 10 seldepth 12 score cp -30 lowerbound pv 7g7f 3c3d")
+    assert info == {
+        "command": "info",
+        "depth": 10,
+        "seldepth": 12,
+        "score": {"cp": -30, "bound": "lowerbound"},
+        "pv": ["7g7f", "3c3d"],
+    }
+
+
+def test_parse_bestmove():
+    assert usi.parse_engine_command("bestmove 7g7f ponder 3c3d") == {
+        "command": "bestmove",
+        "move": "7g7f",
+        "ponder": "3c3d",
+    }
+    assert usi.parse_engine_command("bestmove resign")["move"] == "resign"
+
+
+FAKE_ENGINE = """#!/usr/bin/env python3
+import sys
+
+for line in sys.stdin:
+    command = line.split()[:1]
+    if command == ["usi"]:
+        print("id name Fake")
+        print("option name USI_Ponder type check default false")
+        print("usiok")
+    elif command == ["isready"]:
+        print("readyok")
+    elif command == ["setoption"]:
+        print("info string " + line.strip())
+    elif command == ["quit"]:
+        break
+    sys.stdout.flush()
+"""
+
+
+@pytest.mark.skipif(sys.platform == "win32", reason="the fake engine is a script")
+def test_bool_option(tmp_path):
+    path = tmp_path / "fake_engine"
+    path.write_text(FAKE_ENGINE)
+    path.chmod(0o755)
+
+    with usi.Engine(str(path), options={"USI_Ponder": True}) as engine:
+        assert engine.recv() == "info string setoption name USI_Ponder value true"
+        engine.set_option("USI_Ponder", False)
+        assert engine.recv() == "info string setoption name USI_Ponder value false"
+
+
+def test_startpos():
+    assert usi.STARTPOS == "lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL b - 1"
@@ -0,0 +1,263 @@
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::time::Duration;
+
+use super::score::Score;
+use crate::protocol::*;
+
+/// The latest state of a PV with a `multipv` index.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PvInfo {
+    /// The `multipv` index, which is 1 for the best line.
+    pub multipv: i32,
+    pub score: Option<Score>,
+    pub depth: Option<i32>,
+    pub seldepth: Option<i32>,
+    pub moves: Vec<String>,
+}
+
+/// A row of the candidate move table returned by `SearchSnapshot::pv_table`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PvLine {
+    /// The 1-based position in the table.
+    pub rank: usize,
+    pub score: Option<Score>,
+    pub depth: Option<i32>,
+    pub moves: Vec<String>,
+    /// `true` if the line was last updated in an iteration shallower than the best line.
+    pub stale: bool,
+}
+
+/// The current state of a search folded from `info` commands.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SearchSnapshot {
+    pub depth: Option<i32>,
+    pub seldepth: Option<i32>,
+    pub nodes: Option<u64>,
+    pub nps: Option<u64>,
+    pub hashfull: Option<i32>,
+    pub time: Option<Duration>,
+    pub currmove: Option<String>,
+    /// The last `info string` text.
+    pub text: Option<String>,
+    /// PVs by `multipv` index.
+    pub pvs: BTreeMap<i32, PvInfo>,
+}
+
+impl SearchSnapshot {
+    /// Returns the best PV, which has the smallest `multipv` index.
+    pub fn best(&self) -> Option<&PvInfo> {
+        self.pvs.values().next()
+    }
+
+    /// Returns the score of the best PV.
+    pub fn best_score(&self) -> Option<Score> {
+        self.best().and_then(|pv| pv.score)
+    }
+
+    /// Returns the moves of the best PV.
+    pub fn best_moves(&self) -> &[String] {
+        self.best().map_or(&[], |pv| &pv.moves)
+    }
+
+    /// Returns the PVs as rows of a candidate move table, ordered by `multipv`.
+    ///
+    /// Engines report `multipv` lines one by one, so while a new iteration is reported,
+    /// lower ranks still hold the previous iteration. Such lines are marked as stale,
+    /// and dropped if the move moved to another rank in the new iteration.
+    pub fn pv_table(&self) -> Vec<PvLine> {
+        let best_depth = self.best().and_then(|pv| pv.depth);
+        let is_stale = |pv: &PvInfo| matches!((pv.depth, best_depth), (Some(d), Some(b)) if d < b);
+        let fresh_moves: Vec<&String> = self
+            .pvs
+            .values()
+            .filter(|pv| !is_stale(pv))
+            .filter_map(|pv| pv.moves.first())
+            .collect();
+
+        self.pvs
+            .values()
+            .filter(|pv| {
+                !is_stale(pv) || pv.moves.first().is_none_or(|m| !fresh_moves.contains(&m))
+            })
+            .enumerate()
+            .map(|(i, pv)| PvLine {
+                rank: i + 1,
+                score: pv.score,
+                depth: pv.depth,
+                moves: pv.moves.clone(),
+                stale: is_stale(pv),
+            })
+            .collect()
+    }
+}
+
+/// `InfoAggregator` folds `info` commands into a `SearchSnapshot` of the latest values.
+///
+/// Scores and PVs are kept per `multipv` index; lines without `multipv` update the first PV.
+/// A score without a PV keeps the previous moves of the line.
+///
+/// # Examples
+/// ```
+/// use usi::{EngineCommand, InfoAggregator};
+///
+/// let mut aggregator = InfoAggregator::new();
+/// for line in [
+///     "info depth 10 seldepth 14 score cp 52 nodes 1000 nps 5000 pv 7g7f 3c3d",
+///     "info depth 11 nodes 2000",
+/// ] {
+///     if let EngineCommand::Info(params) = EngineCommand::parse(line).unwrap() {
+///         aggregator.push(&params);
+///     }
+/// }
+/// let snapshot = aggregator.snapshot();
+/// assert_eq!(Some(11), snapshot.depth);
+/// assert_eq!(Some(2000), snapshot.nodes);
+/// assert_eq!(Some(52), snapshot.best_score().map(|score| score.value));
+/// assert_eq!(["7g7f", "3c3d"], snapshot.best_moves());
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct InfoAggregator {
+    snapshot: SearchSnapshot,
+}
+
+impl InfoAggregator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Folds the parameters of an `info` command into the snapshot.
+    pub fn push(&mut self, info: &[InfoParams]) {
+        let snapshot = &mut self.snapshot;
+        let multipv = info
+            .iter()
+            .find_map(|params| match params {
+                InfoParams::MultiPv(n) => Some(*n),
+                _ => None,
+            })
+            .unwrap_or(1);
+
+        let mut line = None;
+        for params in info {
+            match params {
+                InfoParams::CurrMove(m) => snapshot.currmove = Some(m.clone()),
+                InfoParams::Depth(depth, seldepth) => {
+                    snapshot.depth = Some(*depth);
+                    snapshot.seldepth = *seldepth;
+                    line = Some((Some(*depth), *seldepth));
+                }
+                InfoParams::HashFull(n) => snapshot.hashfull = Some(*n),
+                InfoParams::Nodes(n) => snapshot.nodes = Some(*n),
+                InfoParams::Nps(n) => snapshot.nps = Some(*n),
+                InfoParams::Time(time) => snapshot.time = Some(*time),
+                InfoParams::Text(text) => snapshot.text = Some(text.clone()),
+                InfoParams::MultiPv(_) | InfoParams::Pv(_) | InfoParams::Score(..) => {}
+            }
+        }
+
+        let mut pv = None;
+        let mut score = None;
+        for params in info {
+            match params {
+                InfoParams::Pv(moves) => pv = Some(moves),
+                InfoParams::Score(value, kind) => score = Some(Score::new(*value, *kind)),
+                _ => {}
+            }
+        }
+        if pv.is_none() && score.is_none() {
+            return;
+        }
+
+        let entry = snapshot.pvs.entry(multipv).or_insert_with(|| PvInfo {
+            multipv,
+            ..PvInfo::default()
+        });
+        if let Some((depth, seldepth)) = line {
+            entry.depth = depth;
+            entry.seldepth = seldepth;
+        }
+        if let Some(score) = score {
+            entry.score = Some(score);
+        }
+        if let Some(moves) = pv {
+            entry.moves = moves.clone();
+        }
+    }
+
+    /// Returns the current snapshot.
+    pub fn snapshot(&self) -> &SearchSnapshot {
+        &self.snapshot
+    }
+
+    /// Clears the snapshot for a new search.
+    pub fn reset(&mut self) {
+        self.snapshot = SearchSnapshot::default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    fn info(line: &str) -> Vec<InfoParams> {
+        match EngineCommand::parse(line).unwrap() {
+            EngineCommand::Info(params) => params,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn push() {
+        let mut aggregator = InfoAggregator::new();
+        for line in [
+            "info depth 3 seldepth 5 multipv 2 score cp -20 pv 2g2f 8c8d",
+            "info depth 3 seldepth 5 multipv 1 score cp 30 pv 7g7f 3c3d",
+            "info depth 4 currmove 7g7f hashfull 10 time 120",
+            "info multipv 2 score cp 100 lowerbound",
+            "info string book move",
+        ] {
+            aggregator.push(&info(line));
+        }
+
+        let snapshot = aggregator.snapshot();
+        assert_eq!((Some(4), None), (snapshot.depth, snapshot.seldepth));
+        assert_eq!(Some("7g7f"), snapshot.currmove.as_deref());
+        assert_eq!(Some(10), snapshot.hashfull);
+        assert_eq!(Some(Duration::from_millis(120)), snapshot.time);
+        assert_eq!(Some("book move"), snapshot.text.as_deref());
+        assert_eq!(
+            Some(Score::new(30, ScoreKind::CpExact)),
+            snapshot.best_score()
+        );
+
+        let second = &snapshot.pvs[&2];
+        assert_eq!(Some(3), second.depth);
+        assert!(second.score.unwrap().is_bound());
+        assert_eq!(vec!["2g2f", "8c8d"], second.moves);
+
+        let table = snapshot.pv_table();
+        assert_eq!(
+            vec![(1, false), (2, false)],
+            table.iter().map(|l| (l.rank, l.stale)).collect::<Vec<_>>()
+        );
+
+        // 2g2f became the best move in the next iteration, before the second line is updated.
+        for line in [
+            "info depth 5 multipv 1 score cp 40 pv 2g2f 8c8d",
+            "info depth 4 multipv 3 score cp -50 pv 5g5f",
+        ] {
+            aggregator.push(&info(line));
+        }
+        let table = aggregator.snapshot().pv_table();
+        assert_eq!(2, table.len());
+        assert_eq!(vec!["2g2f", "8c8d"], table[0].moves);
+        assert_eq!((2, true), (table[1].rank, table[1].stale));
+        assert_eq!(vec!["5g5f"], table[1].moves);
+
+        aggregator.reset();
+        assert!(aggregator.snapshot().best().is_none());
+    }
+}
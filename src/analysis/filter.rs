@@ -0,0 +1,202 @@
+use alloc::vec::Vec;
+
+use crate::protocol::*;
+
+/// Items of a command stream which may hold an `EngineCommand`.
+pub trait AsEngineCommand {
+    fn as_engine_command(&self) -> Option<&EngineCommand>;
+}
+
+impl AsEngineCommand for EngineCommand {
+    fn as_engine_command(&self) -> Option<&EngineCommand> {
+        Some(self)
+    }
+}
+
+impl<T: AsEngineCommand> AsEngineCommand for &T {
+    fn as_engine_command(&self) -> Option<&EngineCommand> {
+        (**self).as_engine_command()
+    }
+}
+
+#[cfg(all(feature = "std", not(target_family = "wasm")))]
+impl AsEngineCommand for crate::EngineOutput {
+    fn as_engine_command(&self) -> Option<&EngineCommand> {
+        self.response().as_ref()
+    }
+}
+
+#[cfg(all(feature = "std", not(target_family = "wasm")))]
+impl AsEngineCommand for crate::EngineEvent {
+    fn as_engine_command(&self) -> Option<&EngineCommand> {
+        match self {
+            crate::EngineEvent::Output(output) => output.as_engine_command(),
+            _ => None,
+        }
+    }
+}
+
+/// `InfoFilter` decides which `info` commands to keep. Other commands are always kept.
+///
+/// Filters can be used with any stream through `InfoFilter::accepts`,
+/// or with iterators through `InfoFilterExt`.
+///
+/// # Examples
+/// ```
+/// use usi::{EngineCommand, InfoFilter, InfoKind};
+///
+/// let filter = InfoFilter::new().only_multipv(1).without_strings();
+/// let accepts = |line| filter.accepts(&EngineCommand::parse(line).unwrap());
+/// assert!(accepts("info depth 3 score cp 10 pv 7g7f"));
+/// assert!(!accepts("info multipv 2 score cp -20 pv 2g2f"));
+/// assert!(!accepts("info string book hit"));
+/// assert!(accepts("bestmove 7g7f"));
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct InfoFilter {
+    kinds: Option<Vec<InfoKind>>,
+    without_strings: bool,
+    multipv: Option<i32>,
+}
+
+impl InfoFilter {
+    /// Creates a filter keeping everything.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keeps only `info` commands with any of `kinds`.
+    #[must_use]
+    pub fn kinds(mut self, kinds: &[InfoKind]) -> Self {
+        self.kinds = Some(kinds.to_vec());
+        self
+    }
+
+    /// Drops `info string` commands.
+    #[must_use]
+    pub fn without_strings(mut self) -> Self {
+        self.without_strings = true;
+        self
+    }
+
+    /// Keeps only `info` commands of the PV with the `multipv` index,
+    /// where commands without `multipv` belong to the first PV.
+    #[must_use]
+    pub fn only_multipv(mut self, multipv: i32) -> Self {
+        self.multipv = Some(multipv);
+        self
+    }
+
+    /// Returns `true` if `command` passes the filter.
+    pub fn accepts(&self, command: &EngineCommand) -> bool {
+        let EngineCommand::Info(info) = command else {
+            return true;
+        };
+        if let Some(kinds) = &self.kinds {
+            if !info.iter().any(|params| kinds.contains(&params.kind())) {
+                return false;
+            }
+        }
+        if self.without_strings && info.iter().any(|params| params.kind() == InfoKind::Text) {
+            return false;
+        }
+        if let Some(multipv) = self.multipv {
+            let index = info.iter().find_map(|params| match params {
+                InfoParams::MultiPv(n) => Some(*n),
+                _ => None,
+            });
+            if index.unwrap_or(1) != multipv {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Adapters dropping `info` commands from iterators of commands, engine outputs or events.
+///
+/// # Examples
+#[cfg_attr(feature = "std", doc = "```no_run")]
+#[cfg_attr(not(feature = "std"), doc = "```ignore")]
+/// use usi::{Engine, InfoFilterExt, InfoKind};
+///
+/// let mut engine = Engine::spawn("/path/to/usi_engine").unwrap();
+/// let outputs = std::iter::from_fn(|| engine.recv().ok());
+/// for output in outputs.without_strings().filter_info_kinds(&[InfoKind::Score]) {
+///     println!("{}", output.raw_str());
+/// }
+/// ```
+pub trait InfoFilterExt: Iterator + Sized
+where
+    Self::Item: AsEngineCommand,
+{
+    /// Keeps only the items passing `filter`.
+    fn filter_info(self, filter: InfoFilter) -> impl Iterator<Item = Self::Item> {
+        self.filter(move |item| item.as_engine_command().is_none_or(|c| filter.accepts(c)))
+    }
+
+    /// Keeps only `info` commands with any of `kinds`.
+    fn filter_info_kinds(self, kinds: &[InfoKind]) -> impl Iterator<Item = Self::Item> {
+        self.filter_info(InfoFilter::new().kinds(kinds))
+    }
+
+    /// Drops `info string` commands.
+    fn without_strings(self) -> impl Iterator<Item = Self::Item> {
+        self.filter_info(InfoFilter::new().without_strings())
+    }
+
+    /// Keeps only `info` commands of the PV with the `multipv` index.
+    fn only_multipv(self, multipv: i32) -> impl Iterator<Item = Self::Item> {
+        self.filter_info(InfoFilter::new().only_multipv(multipv))
+    }
+}
+
+impl<I> InfoFilterExt for I
+where
+    I: Iterator,
+    I::Item: AsEngineCommand,
+{
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::{String, ToString};
+    use alloc::vec;
+
+    #[test]
+    fn filter() {
+        let commands: Vec<EngineCommand> = [
+            "info depth 1 nodes 10",
+            "info depth 1 multipv 1 score cp 10 pv 7g7f",
+            "info depth 1 multipv 2 score cp 5 pv 2g2f",
+            "info string hello",
+            "info currmove 7g7f",
+            "bestmove 7g7f",
+        ]
+        .iter()
+        .map(|line| EngineCommand::parse(line).unwrap())
+        .collect();
+
+        let lines = |iter: &mut dyn Iterator<Item = &EngineCommand>| -> Vec<String> {
+            iter.map(|c| c.to_string()).collect()
+        };
+        assert_eq!(
+            vec![
+                "info depth 1 multipv 1 score cp 10 pv 7g7f",
+                "info depth 1 multipv 2 score cp 5 pv 2g2f",
+                "bestmove 7g7f",
+            ],
+            lines(&mut commands.iter().filter_info_kinds(&[InfoKind::Pv]))
+        );
+        assert_eq!(
+            vec![
+                "info depth 1 nodes 10",
+                "info depth 1 multipv 1 score cp 10 pv 7g7f",
+                "info currmove 7g7f",
+                "bestmove 7g7f",
+            ],
+            lines(&mut commands.iter().without_strings().only_multipv(1))
+        );
+    }
+}
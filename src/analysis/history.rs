@@ -0,0 +1,189 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::Write;
+use core::time::Duration;
+
+use super::aggregator::SearchSnapshot;
+use super::score::Score;
+
+/// A sample of an evaluation graph.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ScoreSample {
+    /// The move number, or the ply of the position searched.
+    pub ply: u32,
+    pub score: Score,
+    pub depth: Option<i32>,
+    /// The search time, serialized in milliseconds.
+    #[cfg_attr(feature = "serde", serde(with = "super::millis"))]
+    pub time: Option<Duration>,
+}
+
+/// `ScoreHistory` collects the scores of each move during analysis or games
+/// for evaluation graphs.
+///
+/// A sample recorded again for the same ply replaces the previous one, so the history
+/// can be updated as the analysis of a position deepens.
+///
+/// # Examples
+/// ```
+/// use usi::{Score, ScoreHistory, ScoreKind, ScoreSample};
+///
+/// let mut history = ScoreHistory::new();
+/// history.push(ScoreSample {
+///     ply: 1,
+///     score: Score::new(50, ScoreKind::CpExact),
+///     depth: Some(20),
+///     time: None,
+/// });
+/// history.push(ScoreSample {
+///     ply: 2,
+///     score: Score::new(-7, ScoreKind::MateExact),
+///     depth: Some(22),
+///     time: None,
+/// });
+/// assert_eq!(
+///     "ply,cp,mate,depth,time_ms\n1,50,,20,\n2,,-7,22,\n",
+///     history.to_csv()
+/// );
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ScoreHistory {
+    samples: Vec<ScoreSample>,
+}
+
+impl ScoreHistory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a sample, replacing the sample of the same ply. Samples are kept ordered by ply.
+    pub fn push(&mut self, sample: ScoreSample) {
+        match self.samples.binary_search_by_key(&sample.ply, |s| s.ply) {
+            Ok(i) => self.samples[i] = sample,
+            Err(i) => self.samples.insert(i, sample),
+        }
+    }
+
+    /// Adds a sample of the best score in `snapshot` for `ply`.
+    /// Returns `false` if the snapshot has no score.
+    pub fn record(&mut self, ply: u32, snapshot: &SearchSnapshot) -> bool {
+        let Some(best) = snapshot.best() else {
+            return false;
+        };
+        let Some(score) = best.score else {
+            return false;
+        };
+        self.push(ScoreSample {
+            ply,
+            score,
+            depth: best.depth.or(snapshot.depth),
+            time: snapshot.time,
+        });
+        true
+    }
+
+    /// Returns the samples ordered by ply.
+    pub fn samples(&self) -> &[ScoreSample] {
+        &self.samples
+    }
+
+    /// Returns the sample of `ply`.
+    pub fn get(&self, ply: u32) -> Option<&ScoreSample> {
+        self.samples
+            .binary_search_by_key(&ply, |s| s.ply)
+            .ok()
+            .map(|i| &self.samples[i])
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// Returns the samples as CSV with a header row.
+    ///
+    /// Centipawn and mate scores are in separate columns, one of which is empty.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("ply,cp,mate,depth,time_ms\n");
+        for sample in &self.samples {
+            let (cp, mate) = if sample.score.is_mate() {
+                (None, Some(sample.score.value))
+            } else {
+                (Some(sample.score.value), None)
+            };
+            let _ = writeln!(
+                csv,
+                "{},{},{},{},{}",
+                sample.ply,
+                Cell(cp),
+                Cell(mate),
+                Cell(sample.depth),
+                Cell(sample.time.map(|t| t.as_millis()))
+            );
+        }
+        csv
+    }
+
+    /// Returns the history as JSON.
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> Result<String, crate::Error> {
+        Ok(serde_json::to_string(self)?)
+    }
+}
+
+/// Formats an optional CSV cell, which is empty for `None`.
+struct Cell<T>(Option<T>);
+
+impl<T: core::fmt::Display> core::fmt::Display for Cell<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match &self.0 {
+            Some(v) => v.fmt(f),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::*;
+    use crate::InfoAggregator;
+
+    #[test]
+    fn record() {
+        let mut history = ScoreHistory::new();
+        let mut aggregator = InfoAggregator::new();
+        assert!(!history.record(1, aggregator.snapshot()));
+
+        for (ply, line) in [
+            (2, "info depth 10 time 1500 score cp -30 pv 8c8d"),
+            (1, "info depth 12 time 2000 score cp 40 pv 7g7f"),
+            (2, "info depth 14 time 3000 score mate 5 pv 8c8d"),
+        ] {
+            if let EngineCommand::Info(params) = EngineCommand::parse(line).unwrap() {
+                aggregator.push(&params);
+            }
+            assert!(history.record(ply, aggregator.snapshot()));
+        }
+
+        assert_eq!(2, history.len());
+        assert_eq!(Some(14), history.get(2).unwrap().depth);
+        assert_eq!(
+            "ply,cp,mate,depth,time_ms\n1,40,,12,2000\n2,,5,14,3000\n",
+            history.to_csv()
+        );
+
+        #[cfg(feature = "serde")]
+        {
+            let json = history.to_json().unwrap();
+            assert!(json.contains(r#""time":2000"#));
+            let parsed: ScoreHistory = serde_json::from_str(&json).unwrap();
+            assert_eq!(history, parsed);
+        }
+    }
+}
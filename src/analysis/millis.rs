@@ -0,0 +1,25 @@
+use core::time::Duration;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Serializes an optional `Duration` in whole milliseconds.
+pub fn serialize<S: Serializer>(time: &Option<Duration>, s: S) -> Result<S::Ok, S::Error> {
+    time.map(|t| t.as_millis() as u64).serialize(s)
+}
+
+pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Option<Duration>, D::Error> {
+    Ok(Option::<u64>::deserialize(d)?.map(Duration::from_millis))
+}
+
+/// Serializes a `Duration` in whole milliseconds.
+#[cfg(not(target_family = "wasm"))]
+pub mod required {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(time: &Duration, s: S) -> Result<S::Ok, S::Error> {
+        (time.as_millis() as u64).serialize(s)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Duration, D::Error> {
+        Ok(Duration::from_millis(u64::deserialize(d)?))
+    }
+}
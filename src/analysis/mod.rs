@@ -0,0 +1,21 @@
+mod aggregator;
+mod filter;
+mod history;
+#[cfg(feature = "serde")]
+pub(crate) mod millis;
+mod review;
+mod score;
+mod stability;
+mod stats;
+#[cfg(feature = "std")]
+mod win_rate;
+
+pub use self::aggregator::{InfoAggregator, PvInfo, PvLine, SearchSnapshot};
+pub use self::filter::{AsEngineCommand, InfoFilter, InfoFilterExt};
+pub use self::history::{ScoreHistory, ScoreSample};
+pub use self::review::{BlunderDetector, MoveQuality, MoveReview, PlayerSummary, PositionEval};
+pub use self::score::Score;
+pub use self::stability::PvStability;
+pub use self::stats::{DepthStat, SearchStats};
+#[cfg(feature = "std")]
+pub use self::win_rate::WinRate;
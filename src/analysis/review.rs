@@ -0,0 +1,275 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use super::score::Score;
+use crate::clock::Color;
+
+/// The analysis of a position, from the side to move.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct PositionEval {
+    pub score: Score,
+    pub best_move: Option<String>,
+}
+
+impl PositionEval {
+    pub fn new(score: Score, best_move: Option<String>) -> Self {
+        PositionEval { score, best_move }
+    }
+}
+
+/// The classification of a move by its centipawn loss.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum MoveQuality {
+    /// The move the engine chose.
+    Best,
+    Good,
+    Inaccuracy,
+    Mistake,
+    Blunder,
+}
+
+/// The review of a move returned by `BlunderDetector::review`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct MoveReview {
+    /// The 1-based move number.
+    pub ply: u32,
+    pub color: Color,
+    /// The move played.
+    pub played: String,
+    /// The move the engine chose in the position.
+    pub best_move: Option<String>,
+    /// The centipawns lost by the move, which is never negative.
+    pub loss: i32,
+    pub quality: MoveQuality,
+}
+
+/// The statistics of a player's moves returned by `PlayerSummary::new`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PlayerSummary {
+    pub moves: u32,
+    /// The average centipawn loss per move.
+    pub average_loss: f64,
+    pub inaccuracies: u32,
+    pub mistakes: u32,
+    pub blunders: u32,
+    /// The percentage of moves which are `MoveQuality::Best` or `MoveQuality::Good`.
+    pub accuracy: f64,
+}
+
+impl PlayerSummary {
+    /// Summarizes the moves of `color` in `reviews`.
+    pub fn new(reviews: &[MoveReview], color: Color) -> Self {
+        let mut summary = PlayerSummary::default();
+        let mut total_loss = 0i64;
+        let mut good = 0;
+        for review in reviews.iter().filter(|r| r.color == color) {
+            summary.moves += 1;
+            total_loss += i64::from(review.loss);
+            match review.quality {
+                MoveQuality::Best | MoveQuality::Good => good += 1,
+                MoveQuality::Inaccuracy => summary.inaccuracies += 1,
+                MoveQuality::Mistake => summary.mistakes += 1,
+                MoveQuality::Blunder => summary.blunders += 1,
+            }
+        }
+        if summary.moves > 0 {
+            summary.average_loss = total_loss as f64 / f64::from(summary.moves);
+            summary.accuracy = f64::from(good) * 100.0 / f64::from(summary.moves);
+        }
+        summary
+    }
+}
+
+/// `BlunderDetector` computes the centipawn loss of each move of a game from the
+/// evaluations of the positions, and classifies the moves by thresholds.
+///
+/// The loss of a move is the score of the position before the move minus the score
+/// after it, both from the side making the move. Scores are capped, and mates count
+/// as the cap, so that moves in decided positions don't dominate the statistics.
+/// Playing the engine's best move never loses.
+///
+/// # Examples
+/// ```
+/// use usi::{BlunderDetector, Color, MoveQuality, PlayerSummary, PositionEval, Score, ScoreKind};
+///
+/// let cp = |value| Score::new(value, ScoreKind::CpExact);
+/// let moves = ["7g7f", "3c3d", "8h2b+"].map(String::from);
+/// let evals = [
+///     PositionEval::new(cp(50), Some("7g7f".to_string())),
+///     PositionEval::new(cp(-40), Some("8c8d".to_string())),
+///     PositionEval::new(cp(100), Some("2g2f".to_string())),
+///     PositionEval::new(cp(600), None),
+/// ];
+/// let reviews = BlunderDetector::new().review(&moves, &evals, Color::Black);
+/// assert_eq!(MoveQuality::Best, reviews[0].quality);
+/// assert_eq!(60, reviews[1].loss);
+/// assert_eq!(MoveQuality::Blunder, reviews[2].quality);
+///
+/// let summary = PlayerSummary::new(&reviews, Color::Black);
+/// assert_eq!(1, summary.blunders);
+/// assert_eq!(50.0, summary.accuracy);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct BlunderDetector {
+    inaccuracy: i32,
+    mistake: i32,
+    blunder: i32,
+    cap: i32,
+}
+
+impl Default for BlunderDetector {
+    fn default() -> Self {
+        BlunderDetector {
+            inaccuracy: 100,
+            mistake: 300,
+            blunder: 600,
+            cap: 3000,
+        }
+    }
+}
+
+impl BlunderDetector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the minimum loss of an inaccuracy. Defaults to 100.
+    #[must_use]
+    pub fn inaccuracy(mut self, cp: i32) -> Self {
+        self.inaccuracy = cp;
+        self
+    }
+
+    /// Sets the minimum loss of a mistake. Defaults to 300.
+    #[must_use]
+    pub fn mistake(mut self, cp: i32) -> Self {
+        self.mistake = cp;
+        self
+    }
+
+    /// Sets the minimum loss of a blunder. Defaults to 600.
+    #[must_use]
+    pub fn blunder(mut self, cp: i32) -> Self {
+        self.blunder = cp;
+        self
+    }
+
+    /// Sets the absolute value scores are capped at, which mates count as. Defaults to 3000.
+    #[must_use]
+    pub fn score_cap(mut self, cp: i32) -> Self {
+        self.cap = cp.max(0);
+        self
+    }
+
+    /// Reviews `moves` played from the position of `evals[0]`, where `first` is to move.
+    ///
+    /// `evals[i]` is the evaluation of the position before `moves[i]`, so a game of `n` moves
+    /// has `n + 1` evaluations. Moves without the evaluation of the next position are skipped.
+    pub fn review(
+        &self,
+        moves: &[String],
+        evals: &[PositionEval],
+        first: Color,
+    ) -> Vec<MoveReview> {
+        let mut color = first;
+        moves
+            .iter()
+            .zip(evals.windows(2))
+            .enumerate()
+            .map(|(i, (played, evals))| {
+                let before = &evals[0];
+                let is_best = before.best_move.as_ref() == Some(played);
+                let loss = self.loss(played, before, &evals[1]);
+                let review = MoveReview {
+                    ply: i as u32 + 1,
+                    color,
+                    played: played.clone(),
+                    best_move: before.best_move.clone(),
+                    loss,
+                    quality: self.classify(loss, is_best),
+                };
+                color = color.flip();
+                review
+            })
+            .collect()
+    }
+
+    /// Returns the centipawns lost by `played` in the position of `before`,
+    /// which leads to the position of `after`.
+    pub fn loss(&self, played: &str, before: &PositionEval, after: &PositionEval) -> i32 {
+        if before.best_move.as_deref() == Some(played) {
+            return 0;
+        }
+        (self.centipawns(before.score) + self.centipawns(after.score)).max(0)
+    }
+
+    /// Returns the quality of a move losing `loss` centipawns.
+    pub fn classify(&self, loss: i32, is_best: bool) -> MoveQuality {
+        match loss {
+            _ if is_best => MoveQuality::Best,
+            loss if loss >= self.blunder => MoveQuality::Blunder,
+            loss if loss >= self.mistake => MoveQuality::Mistake,
+            loss if loss >= self.inaccuracy => MoveQuality::Inaccuracy,
+            _ => MoveQuality::Good,
+        }
+    }
+
+    fn centipawns(&self, score: Score) -> i32 {
+        if score.is_mate() {
+            if score.is_winning_mate() {
+                self.cap
+            } else {
+                -self.cap
+            }
+        } else {
+            score.value.clamp(-self.cap, self.cap)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn review() {
+        let eval = |value, kind| PositionEval::new(Score::new(value, kind), None);
+        let moves: Vec<String> = ["7g7f", "3c3d", "2g2f", "4a3b"].map(String::from).to_vec();
+        let evals = [
+            // The winning score is capped.
+            eval(5000, ScoreKind::CpExact),
+            eval(-1000, ScoreKind::CpExact),
+            eval(1200, ScoreKind::CpExact),
+            eval(3, ScoreKind::MateExact),
+        ];
+        let detector = BlunderDetector::new().score_cap(2000).blunder(2000);
+        let reviews = detector.review(&moves, &evals, Color::White);
+
+        assert_eq!(3, reviews.len());
+        assert_eq!(Color::White, reviews[0].color);
+        assert_eq!(
+            (1000, MoveQuality::Mistake),
+            (reviews[0].loss, reviews[0].quality)
+        );
+        assert_eq!(
+            (200, MoveQuality::Inaccuracy),
+            (reviews[1].loss, reviews[1].quality)
+        );
+        assert_eq!(
+            (3200, MoveQuality::Blunder),
+            (reviews[2].loss, reviews[2].quality)
+        );
+        assert_eq!("2g2f".to_string(), reviews[2].played);
+
+        let summary = PlayerSummary::new(&reviews, Color::White);
+        assert_eq!(2, summary.moves);
+        assert_eq!(2100.0, summary.average_loss);
+        assert_eq!(0.0, summary.accuracy);
+        assert_eq!(
+            PlayerSummary::default(),
+            PlayerSummary::new(&[], Color::Black)
+        );
+    }
+}
@@ -0,0 +1,179 @@
+use alloc::format;
+use alloc::string::String;
+use core::cmp::Ordering;
+use core::fmt;
+
+use crate::protocol::*;
+
+/// Represents a score reported by `info score`, from the side to move.
+///
+/// Scores are ordered from the side to move: shorter mates are better than longer ones,
+/// any mate is better than any centipawn score, and being mated later is better than
+/// being mated sooner. A mate without a distance (`mate +` or `mate -`) is ordered as
+/// the longest mate.
+///
+/// # Examples
+/// ```
+/// use usi::{Score, ScoreKind};
+///
+/// let mate_in_3 = Score::new(3, ScoreKind::MateExact);
+/// let mate_in_13 = Score::new(13, ScoreKind::MateExact);
+/// let cp = Score::new(3000, ScoreKind::CpExact);
+/// let mated_in_5 = Score::new(-5, ScoreKind::MateExact);
+/// assert!(mate_in_3 > mate_in_13 && mate_in_13 > cp && cp > mated_in_5);
+///
+/// assert_eq!("+M13", mate_in_13.to_string());
+/// assert_eq!("-M5", mated_in_5.to_string());
+/// assert_eq!("+3000", cp.to_string());
+/// assert_eq!("詰み13手", mate_in_13.to_japanese());
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Score {
+    /// Centipawns, or the number of plies to mate. Negative if the side to move is mated.
+    pub value: i32,
+    pub kind: ScoreKind,
+}
+
+impl Score {
+    pub fn new(value: i32, kind: ScoreKind) -> Self {
+        Score { value, kind }
+    }
+
+    /// Returns `true` if the score is a mate score.
+    pub fn is_mate(&self) -> bool {
+        matches!(
+            self.kind,
+            ScoreKind::MateExact
+                | ScoreKind::MateSignOnly
+                | ScoreKind::MateLowerbound
+                | ScoreKind::MateUpperbound
+        )
+    }
+
+    /// Returns `true` if the score is only a bound of the actual score.
+    pub fn is_bound(&self) -> bool {
+        matches!(
+            self.kind,
+            ScoreKind::CpLowerbound
+                | ScoreKind::CpUpperbound
+                | ScoreKind::MateLowerbound
+                | ScoreKind::MateUpperbound
+        )
+    }
+
+    /// Returns the number of plies to mate, negative if the side to move is mated,
+    /// or `None` for centipawn scores and mates without a distance.
+    pub fn mate_distance(&self) -> Option<i32> {
+        (self.is_mate() && self.kind != ScoreKind::MateSignOnly).then_some(self.value)
+    }
+
+    /// Returns `true` if the side to move mates.
+    pub fn is_winning_mate(&self) -> bool {
+        self.is_mate() && self.value > 0
+    }
+
+    /// Returns the score as Japanese text, e.g. `詰み13手` or `被詰み5手` for mates.
+    /// Centipawn scores are the same as `Display`.
+    pub fn to_japanese(&self) -> String {
+        if !self.is_mate() {
+            return format!("{self}");
+        }
+        let prefix = if self.is_winning_mate() {
+            "詰み"
+        } else {
+            "被詰み"
+        };
+        match self.mate_distance() {
+            Some(plies) => format!("{prefix}{}手", plies.abs()),
+            None => format!("{prefix}あり"),
+        }
+    }
+
+    /// The key `Ord` compares by: the outcome first, then the distance or centipawns.
+    fn order_key(&self) -> (u8, i64) {
+        match (self.is_mate(), self.is_winning_mate(), self.mate_distance()) {
+            (false, ..) => (1, i64::from(self.value)),
+            (true, true, Some(plies)) => (2, -i64::from(plies)),
+            (true, true, None) => (2, i64::MIN),
+            (true, false, Some(plies)) => (0, -i64::from(plies)),
+            (true, false, None) => (0, i64::MAX),
+        }
+    }
+
+    /// Orders the kinds of otherwise equal scores, so that `Ord` agrees with `Eq`.
+    fn kind_index(&self) -> u8 {
+        match self.kind {
+            ScoreKind::CpUpperbound | ScoreKind::MateUpperbound => 0,
+            ScoreKind::CpExact | ScoreKind::MateExact | ScoreKind::MateSignOnly => 1,
+            ScoreKind::CpLowerbound | ScoreKind::MateLowerbound => 2,
+        }
+    }
+}
+
+impl Ord for Score {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.order_key()
+            .cmp(&other.order_key())
+            .then_with(|| self.kind_index().cmp(&other.kind_index()))
+            .then_with(|| self.value.cmp(&other.value))
+    }
+}
+
+impl PartialOrd for Score {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Formats centipawns with a sign, e.g. `+50`, and mates as `+M13` or `-M5`.
+impl fmt::Display for Score {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if !self.is_mate() {
+            return write!(f, "{:+}", self.value);
+        }
+        let sign = if self.is_winning_mate() { '+' } else { '-' };
+        match self.mate_distance() {
+            Some(plies) => write!(f, "{sign}M{}", plies.abs()),
+            None => write!(f, "{sign}M"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    #[test]
+    fn order() {
+        let mut scores = [
+            Score::new(-3, ScoreKind::MateExact),
+            Score::new(5, ScoreKind::MateExact),
+            Score::new(-1, ScoreKind::MateSignOnly),
+            Score::new(100, ScoreKind::CpLowerbound),
+            Score::new(0, ScoreKind::MateExact),
+            Score::new(1, ScoreKind::MateSignOnly),
+            Score::new(-20000, ScoreKind::CpExact),
+            Score::new(100, ScoreKind::CpExact),
+            Score::new(1, ScoreKind::MateExact),
+        ];
+        scores.sort();
+        let formatted: Vec<String> = scores.iter().map(|s| s.to_string()).collect();
+        assert_eq!(
+            vec!["-M0", "-M3", "-M", "-20000", "+100", "+100", "+M", "+M5", "+M1"],
+            formatted
+        );
+        assert_eq!(ScoreKind::CpLowerbound, scores[5].kind);
+        assert_eq!(
+            Some(Ordering::Equal),
+            scores[1].partial_cmp(&Score::new(-3, ScoreKind::MateExact))
+        );
+
+        assert_eq!("被詰み3手", scores[1].to_japanese());
+        assert_eq!("詰みあり", scores[6].to_japanese());
+        assert_eq!("+0", Score::new(0, ScoreKind::CpExact).to_japanese());
+    }
+}
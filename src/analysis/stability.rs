@@ -0,0 +1,156 @@
+use alloc::string::String;
+use core::time::Duration;
+
+use crate::protocol::*;
+
+/// `PvStability` watches the PVs of a search and tells when the best move has settled.
+///
+/// An iteration is counted for each new depth of the first PV. The best move is stable
+/// once it stayed the same for the minimum number of iterations and, if set, the minimum
+/// time measured by the `time` reported by the engine.
+///
+/// # Examples
+/// ```
+/// use usi::{EngineCommand, PvStability};
+///
+/// let mut stability = PvStability::new(3);
+/// for line in [
+///     "info depth 1 pv 2g2f",
+///     "info depth 2 pv 7g7f 3c3d",
+///     "info depth 3 pv 7g7f 8c8d",
+///     "info depth 4 pv 7g7f 8c8d 2g2f",
+/// ] {
+///     if let EngineCommand::Info(params) = EngineCommand::parse(line).unwrap() {
+///         stability.push(&params);
+///     }
+/// }
+/// assert!(stability.is_stable());
+/// assert_eq!(Some("7g7f"), stability.best_move());
+/// ```
+#[derive(Clone, Debug)]
+pub struct PvStability {
+    min_iterations: u32,
+    min_time: Option<Duration>,
+    best_move: Option<String>,
+    iterations: u32,
+    depth: Option<i32>,
+    since: Option<Duration>,
+    time: Option<Duration>,
+}
+
+impl PvStability {
+    /// Creates a watcher requiring the best move to be the same for `min_iterations`.
+    pub fn new(min_iterations: u32) -> Self {
+        PvStability {
+            min_iterations,
+            min_time: None,
+            best_move: None,
+            iterations: 0,
+            depth: None,
+            since: None,
+            time: None,
+        }
+    }
+
+    /// Additionally requires the best move to be the same for `time`.
+    #[must_use]
+    pub fn min_time(mut self, time: Duration) -> Self {
+        self.min_time = Some(time);
+        self
+    }
+
+    /// Updates the state with an `info` command and returns `PvStability::is_stable`.
+    /// Only the first PV is considered.
+    pub fn push(&mut self, info: &[InfoParams]) -> bool {
+        let mut multipv = 1;
+        let mut depth = None;
+        let mut first_move = None;
+        for params in info {
+            match params {
+                InfoParams::MultiPv(n) => multipv = *n,
+                InfoParams::Depth(d, _) => depth = Some(*d),
+                InfoParams::Pv(moves) => first_move = moves.first(),
+                InfoParams::Time(time) => self.time = Some(*time),
+                _ => {}
+            }
+        }
+
+        if let (1, Some(first_move)) = (multipv, first_move) {
+            if self.best_move.as_ref() != Some(first_move) {
+                self.best_move = Some(first_move.clone());
+                self.iterations = 1;
+                self.depth = depth;
+                self.since = self.time;
+            } else if depth.is_some() && depth != self.depth {
+                self.iterations += 1;
+                self.depth = depth;
+            }
+        }
+        self.is_stable()
+    }
+
+    /// Returns the current best move.
+    pub fn best_move(&self) -> Option<&str> {
+        self.best_move.as_deref()
+    }
+
+    /// Returns the number of iterations the best move has been the same for.
+    pub fn stable_iterations(&self) -> u32 {
+        self.iterations
+    }
+
+    /// Returns how long the best move has been the same for,
+    /// or `None` if the engine reported no time.
+    pub fn stable_time(&self) -> Option<Duration> {
+        Some(self.time?.saturating_sub(self.since.unwrap_or_default()))
+    }
+
+    /// Returns `true` if the best move has settled.
+    pub fn is_stable(&self) -> bool {
+        if self.best_move.is_none() || self.iterations < self.min_iterations {
+            return false;
+        }
+        match self.min_time {
+            Some(min_time) => self.stable_time().is_some_and(|time| time >= min_time),
+            None => true,
+        }
+    }
+
+    /// Clears the state for a new search.
+    pub fn reset(&mut self) {
+        *self = PvStability {
+            min_time: self.min_time,
+            ..PvStability::new(self.min_iterations)
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn push() {
+        let mut stability = PvStability::new(2).min_time(Duration::from_secs(1));
+        let lines = [
+            ("info depth 1 time 100 pv 7g7f", false),
+            ("info depth 1 multipv 2 time 200 pv 2g2f", false),
+            ("info depth 2 time 300 pv 2g2f", false),
+            ("info depth 2 time 400 score cp 30 pv 2g2f 8c8d", false),
+            ("info depth 3 time 900 pv 2g2f 8c8d", false),
+            ("info nodes 1000 time 1300", true),
+            ("info depth 4 time 1400 pv 7g7f", false),
+        ];
+        for (line, stable) in lines {
+            if let EngineCommand::Info(params) = EngineCommand::parse(line).unwrap() {
+                assert_eq!(stable, stability.push(&params), "{line}");
+            }
+        }
+        assert_eq!(1, stability.stable_iterations());
+        assert_eq!(Some(Duration::ZERO), stability.stable_time());
+
+        stability.reset();
+        assert_eq!(None, stability.best_move());
+        assert!(!stability.is_stable());
+    }
+}
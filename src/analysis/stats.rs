@@ -0,0 +1,230 @@
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use core::time::Duration;
+
+use crate::protocol::*;
+
+/// The default number of NPS samples averaged.
+const DEFAULT_WINDOW: usize = 10;
+
+/// The number of recent depths the effective branching factor is estimated from.
+const BRANCHING_DEPTHS: usize = 4;
+
+/// The nodes and time when a depth was first reported.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct DepthStat {
+    pub depth: i32,
+    pub nodes: Option<u64>,
+    pub time: Option<Duration>,
+}
+
+/// `SearchStats` accumulates node statistics of a search from `info` commands.
+///
+/// NPS is averaged over the recent samples, taken from `nps` or computed from
+/// `nodes` and `time`. The effective branching factor is the geometric mean of
+/// the growth of nodes per depth over the last few depths.
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+/// use usi::{EngineCommand, SearchStats};
+///
+/// let mut stats = SearchStats::new();
+/// for line in [
+///     "info depth 1 nodes 100 time 1 nps 100000",
+///     "info depth 2 nodes 400 time 2 nps 200000",
+///     "info depth 3 nodes 1600 time 4 nps 400000",
+/// ] {
+///     if let EngineCommand::Info(params) = EngineCommand::parse(line).unwrap() {
+///         stats.push(&params);
+///     }
+/// }
+/// assert_eq!(Some(1600), stats.total_nodes());
+/// assert_eq!(Some(233333), stats.nps());
+/// assert_eq!(Some(4.0), stats.branching_factor());
+/// assert_eq!(Some(Duration::from_millis(2)), stats.time_to_depth(2));
+/// ```
+#[derive(Clone, Debug)]
+pub struct SearchStats {
+    window: usize,
+    nps_samples: VecDeque<u64>,
+    nodes: Option<u64>,
+    time: Option<Duration>,
+    depths: Vec<DepthStat>,
+}
+
+impl Default for SearchStats {
+    fn default() -> Self {
+        SearchStats {
+            window: DEFAULT_WINDOW,
+            nps_samples: VecDeque::new(),
+            nodes: None,
+            time: None,
+            depths: Vec::new(),
+        }
+    }
+}
+
+impl SearchStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the number of NPS samples averaged. Defaults to 10.
+    #[must_use]
+    pub fn window(mut self, samples: usize) -> Self {
+        self.window = samples.max(1);
+        self
+    }
+
+    /// Updates the statistics with an `info` command.
+    pub fn push(&mut self, info: &[InfoParams]) {
+        let mut depth = None;
+        let mut nodes = None;
+        let mut nps = None;
+        let mut time = None;
+        for params in info {
+            match params {
+                InfoParams::Depth(d, _) => depth = Some(*d),
+                InfoParams::Nodes(n) => nodes = Some(*n),
+                InfoParams::Nps(n) => nps = Some(*n),
+                InfoParams::Time(t) => time = Some(*t),
+                _ => {}
+            }
+        }
+        if nodes.is_some() {
+            self.nodes = nodes;
+        }
+        if time.is_some() {
+            self.time = time;
+        }
+
+        let nps = nps.or_else(|| match (nodes, time) {
+            (Some(nodes), Some(time)) if !time.is_zero() => {
+                Some((nodes as u128 * 1000 / time.as_millis()) as u64)
+            }
+            _ => None,
+        });
+        if let Some(nps) = nps {
+            if self.nps_samples.len() == self.window {
+                self.nps_samples.pop_front();
+            }
+            self.nps_samples.push_back(nps);
+        }
+
+        if let Some(depth) = depth {
+            if self.depths.last().is_none_or(|last| last.depth < depth) {
+                self.depths.push(DepthStat {
+                    depth,
+                    nodes: self.nodes,
+                    time: self.time,
+                });
+            }
+        }
+    }
+
+    /// Returns the number of nodes searched so far.
+    pub fn total_nodes(&self) -> Option<u64> {
+        self.nodes
+    }
+
+    /// Returns the time elapsed reported by the engine.
+    pub fn elapsed(&self) -> Option<Duration> {
+        self.time
+    }
+
+    /// Returns the rolling average of NPS.
+    pub fn nps(&self) -> Option<u64> {
+        let count = self.nps_samples.len() as u64;
+        (count > 0).then(|| self.nps_samples.iter().sum::<u64>() / count)
+    }
+
+    /// Returns the effective branching factor estimated from the last few depths.
+    pub fn branching_factor(&self) -> Option<f64> {
+        let recent: Vec<(i32, u64)> = self
+            .depths
+            .iter()
+            .filter_map(|stat| Some((stat.depth, stat.nodes.filter(|&n| n > 0)?)))
+            .collect();
+        let recent = &recent[recent.len().saturating_sub(BRANCHING_DEPTHS)..];
+        let (&(first_depth, first_nodes), &(last_depth, last_nodes)) =
+            (recent.first()?, recent.last()?);
+        if last_depth <= first_depth {
+            return None;
+        }
+        let growth = last_nodes as f64 / first_nodes as f64;
+        Some(nth_root(growth, last_depth - first_depth))
+    }
+
+    /// Returns the time when `depth` was first reported.
+    pub fn time_to_depth(&self, depth: i32) -> Option<Duration> {
+        self.depths
+            .iter()
+            .find(|stat| stat.depth == depth)
+            .and_then(|stat| stat.time)
+    }
+
+    /// Returns the nodes and time of each depth reported.
+    pub fn depths(&self) -> &[DepthStat] {
+        &self.depths
+    }
+
+    /// Clears the statistics for a new search.
+    pub fn reset(&mut self) {
+        *self = SearchStats::new().window(self.window);
+    }
+}
+
+#[cfg(feature = "std")]
+fn nth_root(x: f64, n: i32) -> f64 {
+    x.powf(1.0 / f64::from(n))
+}
+
+/// `f64::powf` is not available without `std`, so the root is found by bisection.
+#[cfg(not(feature = "std"))]
+fn nth_root(x: f64, n: i32) -> f64 {
+    let (mut lo, mut hi) = (0.0, x.max(1.0));
+    for _ in 0..64 {
+        let mid = (lo + hi) / 2.0;
+        if (0..n).fold(1.0, |p, _| p * mid) < x {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+    hi
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn push() {
+        let mut stats = SearchStats::new().window(2);
+        for line in [
+            "info depth 1 nodes 10 time 0",
+            "info depth 2 nodes 20 time 10",
+            "info depth 2 seldepth 3 nodes 1000 time 20",
+            "info depth 3 nodes 1200 time 30",
+            "info depth 5 nodes 6000 time 1000",
+        ] {
+            if let EngineCommand::Info(params) = EngineCommand::parse(line).unwrap() {
+                stats.push(&params);
+            }
+        }
+        assert_eq!(4, stats.depths().len());
+        assert_eq!(Some(Duration::from_millis(30)), stats.time_to_depth(3));
+        assert_eq!(None, stats.time_to_depth(4));
+        assert_eq!(Some(6000), stats.total_nodes());
+        // The last two samples: 1200 nodes in 30 ms and 6000 nodes in 1000 ms.
+        assert_eq!(Some((40000 + 6000) / 2), stats.nps());
+        // 10 to 6000 nodes over 4 depths.
+        let bf = stats.branching_factor().unwrap();
+        assert!((bf - 600f64.powf(0.25)).abs() < 1e-9);
+
+        stats.reset();
+        assert_eq!(None, stats.nps());
+        assert_eq!(None, stats.branching_factor());
+    }
+}
@@ -0,0 +1,102 @@
+use super::score::Score;
+
+/// The default scale of `WinRate`, common among shogi engines.
+const DEFAULT_SCALE: f64 = 600.0;
+
+/// `WinRate` converts scores to the expected win probability of the side to move
+/// with the logistic function `1 / (1 + exp(-cp / scale))`.
+///
+/// Mate scores are certain wins or losses. Engines tune their evaluation differently,
+/// so the scale may be adjusted, e.g. around 750 for dlshogi.
+///
+/// # Examples
+/// ```
+/// use usi::{Score, ScoreKind, WinRate};
+///
+/// let win_rate = WinRate::default();
+/// assert_eq!(0.5, win_rate.probability(Score::new(0, ScoreKind::CpExact)));
+/// assert!((win_rate.probability(Score::new(600, ScoreKind::CpExact)) - 0.731).abs() < 1e-3);
+/// assert_eq!(0.0, win_rate.probability(Score::new(-3, ScoreKind::MateExact)));
+/// assert_eq!(600, win_rate.to_cp(win_rate.from_cp(600)));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WinRate {
+    scale: f64,
+}
+
+impl Default for WinRate {
+    fn default() -> Self {
+        WinRate::new(DEFAULT_SCALE)
+    }
+}
+
+impl WinRate {
+    /// Creates a converter with `scale` centipawns, where a score of `scale`
+    /// is about 73% to win.
+    pub fn new(scale: f64) -> Self {
+        WinRate { scale }
+    }
+
+    /// Returns the scale in centipawns.
+    pub fn scale(&self) -> f64 {
+        self.scale
+    }
+
+    /// Returns the win probability of the side to move for `score`, between 0 and 1.
+    pub fn probability(&self, score: Score) -> f64 {
+        if score.is_mate() {
+            // `mate 0` or `mate -` means the side to move is mated.
+            return if score.value > 0 { 1.0 } else { 0.0 };
+        }
+        self.from_cp(score.value)
+    }
+
+    /// Returns the win probability for a centipawn score.
+    pub fn from_cp(&self, cp: i32) -> f64 {
+        1.0 / (1.0 + (-f64::from(cp) / self.scale).exp())
+    }
+
+    /// Returns the centipawn score for a win probability, the inverse of `WinRate::from_cp`.
+    pub fn to_cp(&self, probability: f64) -> i32 {
+        let p = probability.clamp(f64::EPSILON, 1.0 - f64::EPSILON);
+        (self.scale * (p / (1.0 - p)).ln()).round() as i32
+    }
+}
+
+impl Score {
+    /// Returns the win probability of the side to move with the default `WinRate`.
+    pub fn win_rate(&self) -> f64 {
+        WinRate::default().probability(*self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::*;
+
+    #[test]
+    fn probability() {
+        let win_rate = WinRate::new(750.0);
+        let cp = |value| Score::new(value, ScoreKind::CpExact);
+        assert!(win_rate.probability(cp(300)) > 0.5);
+        assert!(win_rate.probability(cp(300)) < cp(300).win_rate());
+        assert!(
+            (win_rate.probability(cp(-300)) + win_rate.probability(cp(300)) - 1.0).abs() < 1e-12
+        );
+        assert_eq!(
+            1.0,
+            win_rate.probability(Score::new(1, ScoreKind::MateSignOnly))
+        );
+        assert_eq!(
+            0.0,
+            win_rate.probability(Score::new(0, ScoreKind::MateExact))
+        );
+        assert_eq!(
+            1.0,
+            win_rate.probability(Score::new(9, ScoreKind::MateLowerbound))
+        );
+        assert_eq!(-1200, win_rate.to_cp(win_rate.from_cp(-1200)));
+        assert!(win_rate.to_cp(1.0) > 20000);
+    }
+}
@@ -0,0 +1,336 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use super::moves::Move;
+use super::piece::{Piece, PieceKind, Square};
+use super::position::Board;
+use crate::clock::Color;
+use crate::error::Error;
+
+const FILES: [char; 9] = ['１', '２', '３', '４', '５', '６', '７', '８', '９'];
+const RANKS: [char; 9] = ['一', '二', '三', '四', '五', '六', '七', '八', '九'];
+
+/// Returns the Japanese name of `kind`, e.g. `歩` or `成銀`.
+pub(crate) fn kind_name(kind: PieceKind) -> &'static str {
+    match kind {
+        PieceKind::Pawn => "歩",
+        PieceKind::Lance => "香",
+        PieceKind::Knight => "桂",
+        PieceKind::Silver => "銀",
+        PieceKind::Gold => "金",
+        PieceKind::Bishop => "角",
+        PieceKind::Rook => "飛",
+        PieceKind::King => "玉",
+        PieceKind::ProPawn => "と",
+        PieceKind::ProLance => "成香",
+        PieceKind::ProKnight => "成桂",
+        PieceKind::ProSilver => "成銀",
+        PieceKind::Horse => "馬",
+        PieceKind::Dragon => "龍",
+    }
+}
+
+/// Returns the single letter name of `kind` used in board diagrams, e.g. `全` for `成銀`.
+pub(crate) fn kind_letter(kind: PieceKind) -> char {
+    match kind {
+        PieceKind::ProLance => '杏',
+        PieceKind::ProKnight => '圭',
+        PieceKind::ProSilver => '全',
+        kind => kind_name(kind).chars().next().unwrap(),
+    }
+}
+
+/// Returns the Japanese name of `sq`, e.g. `７六`.
+pub(crate) fn square_name(sq: Square) -> [char; 2] {
+    [
+        FILES[usize::from(sq.file() - 1)],
+        RANKS[usize::from(sq.rank() - 1)],
+    ]
+}
+
+/// Returns the mark of the side to move, `▲` for black and `△` for white.
+pub(crate) fn color_mark(color: Color) -> char {
+    match color {
+        Color::Black => '▲',
+        Color::White => '△',
+    }
+}
+
+/// Returns the numeral of `n` from 1 to 9 in kanji.
+pub(crate) fn kanji_numeral(n: u8) -> char {
+    RANKS[usize::from(n - 1)]
+}
+
+/// Formats `mv` by the side to move in KIF, e.g. `７六歩(77)` or `同　角成(88)`,
+/// where `previous` is the destination of the previous move.
+pub(crate) fn kif_move(
+    board: &Board,
+    mv: &Move,
+    previous: Option<Square>,
+) -> Result<String, Error> {
+    let piece = moving_piece(board, mv)?;
+    let mut s = destination(mv.to(), previous);
+    s.push_str(kind_name(piece.kind));
+    s.push_str(promotion(piece, mv));
+    match *mv {
+        Move::Normal { from, .. } => {
+            s.push('(');
+            s.push(char::from(b'0' + from.file()));
+            s.push(char::from(b'0' + from.rank()));
+            s.push(')');
+        }
+        Move::Drop { .. } => s.push('打'),
+    }
+    Ok(s)
+}
+
+/// Formats `mv` by the side to move in KI2, e.g. `７六歩` or `同　金左`, with the relative
+/// position and movement of the piece when another piece of the same kind can also reach
+/// the destination.
+pub(crate) fn ki2_move(
+    board: &Board,
+    mv: &Move,
+    previous: Option<Square>,
+) -> Result<String, Error> {
+    let piece = moving_piece(board, mv)?;
+    let to = mv.to();
+    let mut s = destination(to, previous);
+    s.push_str(kind_name(piece.kind));
+    let others = reachers(board, piece, to)
+        .into_iter()
+        .filter(|&sq| !matches!(*mv, Move::Normal { from, .. } if from == sq))
+        .collect::<Vec<_>>();
+    match *mv {
+        Move::Normal { from, .. } => {
+            s.push_str(relative(piece, from, to, &others));
+            s.push_str(promotion(piece, mv));
+        }
+        Move::Drop { .. } if !others.is_empty() => s.push('打'),
+        Move::Drop { .. } => {}
+    }
+    Ok(s)
+}
+
+/// Returns the piece moved by `mv` before promotion.
+fn moving_piece(board: &Board, mv: &Move) -> Result<Piece, Error> {
+    let piece = board.moved_piece(mv)?;
+    Ok(match *mv {
+        Move::Normal { promote: true, .. } => Piece::new(piece.color, piece.kind.unpromote()),
+        _ => piece,
+    })
+}
+
+fn destination(to: Square, previous: Option<Square>) -> String {
+    if previous == Some(to) {
+        String::from("同　")
+    } else {
+        square_name(to).iter().collect()
+    }
+}
+
+/// Returns `成` for a promotion, and `不成` if the piece could promote but didn't.
+fn promotion(piece: Piece, mv: &Move) -> &'static str {
+    match *mv {
+        Move::Normal { promote: true, .. } => "成",
+        Move::Normal { from, to, .. }
+            if piece.kind.promote().is_some()
+                && (from.is_promotion_zone(piece.color) || to.is_promotion_zone(piece.color)) =>
+        {
+            "不成"
+        }
+        _ => "",
+    }
+}
+
+/// Returns the squares of the pieces equal to `piece` which can move to `to`.
+fn reachers(board: &Board, piece: Piece, to: Square) -> Vec<Square> {
+    (1..=9)
+        .flat_map(|file| (1..=9).filter_map(move |rank| Square::new(file, rank)))
+        .filter(|&sq| board.piece_at(sq) == Some(piece) && can_reach(board, piece, sq, to))
+        .collect()
+}
+
+/// Returns the distance `sq` is from the back rank of `color`, so larger is more forward.
+fn forward(color: Color, sq: Square) -> i8 {
+    match color {
+        Color::Black => 9 - sq.rank() as i8,
+        Color::White => sq.rank() as i8 - 1,
+    }
+}
+
+/// Returns the distance `sq` is from the right edge seen from `color`.
+fn rightward(color: Color, sq: Square) -> i8 {
+    match color {
+        Color::Black => sq.file() as i8,
+        Color::White => 10 - sq.file() as i8,
+    }
+}
+
+/// Returns `上`, `引` or `寄` for a move from `from` to `to`.
+fn vertical(color: Color, from: Square, to: Square) -> &'static str {
+    match forward(color, to).cmp(&forward(color, from)) {
+        core::cmp::Ordering::Greater => "上",
+        core::cmp::Ordering::Less => "引",
+        core::cmp::Ordering::Equal => "寄",
+    }
+}
+
+/// Returns `右` or `左` if `from` is right or left of all `others`.
+fn horizontal(color: Color, from: Square, others: &[Square]) -> Option<&'static str> {
+    let x = rightward(color, from);
+    if others.iter().all(|&sq| rightward(color, sq) > x) {
+        Some("右")
+    } else if others.iter().all(|&sq| rightward(color, sq) < x) {
+        Some("左")
+    } else {
+        None
+    }
+}
+
+/// Returns the relative position and movement distinguishing the move from `from`
+/// from the moves of `others` to `to`.
+fn relative(piece: Piece, from: Square, to: Square, others: &[Square]) -> &'static str {
+    if others.is_empty() {
+        return "";
+    }
+    let color = piece.color;
+    let v = vertical(color, from, to);
+    if others.iter().all(|&sq| vertical(color, sq, to) != v) {
+        return v;
+    }
+    let ranged = matches!(piece.kind, PieceKind::Horse | PieceKind::Dragon);
+    if !ranged && v == "上" && from.file() == to.file() {
+        return "直";
+    }
+    if let Some(h) = horizontal(color, from, others) {
+        return h;
+    }
+    let same = others
+        .iter()
+        .copied()
+        .filter(|&sq| vertical(color, sq, to) == v)
+        .collect::<Vec<_>>();
+    match (horizontal(color, from, &same), v) {
+        (Some("右"), "上") => "右上",
+        (Some("右"), "引") => "右引",
+        (Some("右"), _) => "右寄",
+        (Some(_), "上") => "左上",
+        (Some(_), "引") => "左引",
+        (Some(_), _) => "左寄",
+        (None, _) => "",
+    }
+}
+
+/// Returns `true` if `piece` on `from` can move to `to`, ignoring checks.
+fn can_reach(board: &Board, piece: Piece, from: Square, to: Square) -> bool {
+    if from == to {
+        return false;
+    }
+    // The steps from the side of `piece`: `dy` is positive forward, `dx` to the left.
+    let dy = forward(piece.color, to) - forward(piece.color, from);
+    let dx = rightward(piece.color, to) - rightward(piece.color, from);
+    let step = dx.abs() <= 1 && dy.abs() <= 1;
+    let gold = step && (dy >= 0 || dx == 0);
+    let diagonal = dx.abs() == dy.abs();
+    let straight = dx == 0 || dy == 0;
+    match piece.kind {
+        PieceKind::Pawn => dx == 0 && dy == 1,
+        PieceKind::Lance => dx == 0 && dy > 0 && is_clear(board, from, to),
+        PieceKind::Knight => dx.abs() == 1 && dy == 2,
+        PieceKind::Silver => step && (dy == 1 || (dy == -1 && dx != 0)),
+        PieceKind::Gold
+        | PieceKind::ProPawn
+        | PieceKind::ProLance
+        | PieceKind::ProKnight
+        | PieceKind::ProSilver => gold,
+        PieceKind::King => step,
+        PieceKind::Bishop => diagonal && is_clear(board, from, to),
+        PieceKind::Rook => straight && is_clear(board, from, to),
+        PieceKind::Horse => step || (diagonal && is_clear(board, from, to)),
+        PieceKind::Dragon => step || (straight && is_clear(board, from, to)),
+    }
+}
+
+/// Returns `true` if the squares between `from` and `to` on a line are empty.
+fn is_clear(board: &Board, from: Square, to: Square) -> bool {
+    let df = (to.file() as i8 - from.file() as i8).signum();
+    let dr = (to.rank() as i8 - from.rank() as i8).signum();
+    let (mut file, mut rank) = (from.file() as i8 + df, from.rank() as i8 + dr);
+    while (file, rank) != (to.file() as i8, to.rank() as i8) {
+        let sq = Square::new(file as u8, rank as u8).unwrap();
+        if board.piece_at(sq).is_some() {
+            return false;
+        }
+        file += df;
+        rank += dr;
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    fn format_ki2(sfen: &str, mv: &str) -> String {
+        let board = Board::from_sfen(sfen).unwrap();
+        ki2_move(&board, &mv.parse().unwrap(), None).unwrap()
+    }
+
+    #[test]
+    fn kif() {
+        let mut board = Board::startpos();
+        let mut previous = None;
+        let mut moves = Vec::new();
+        for mv in ["7g7f", "3c3d", "8h2b+", "3a2b", "B*4e"] {
+            let mv: Move = mv.parse().unwrap();
+            moves.push(kif_move(&board, &mv, previous).unwrap());
+            board.make_move(&mv).unwrap();
+            previous = Some(mv.to());
+        }
+        assert_eq!(
+            vec![
+                "７六歩(77)",
+                "３四歩(33)",
+                "２二角成(88)",
+                "同　銀(31)",
+                "４五角打"
+            ],
+            moves
+        );
+    }
+
+    #[test]
+    fn ki2() {
+        // Golds on 6i and 4i can both move to 5h.
+        let sfen = "4k4/9/9/9/9/9/9/9/3GKG3 b - 1";
+        assert_eq!("５八金右", format_ki2(sfen, "4i5h"));
+        assert_eq!("５八金左", format_ki2(sfen, "6i5h"));
+        assert_eq!("４八金", format_ki2(sfen, "4i4h"));
+
+        // Golds on 5i and 4h can both move to 5h.
+        let sfen = "4k4/9/9/9/9/9/7K1/5G3/4G4 b - 1";
+        assert_eq!("５八金上", format_ki2(sfen, "5i5h"));
+        assert_eq!("５八金寄", format_ki2(sfen, "4h5h"));
+
+        // Golds on 6i, 5i and 4i can all move to 5h.
+        let sfen = "4k4/9/9/9/9/9/9/9/3GGG2K b - 1";
+        assert_eq!("５八金直", format_ki2(sfen, "5i5h"));
+        assert_eq!("５八金右", format_ki2(sfen, "4i5h"));
+
+        // Silvers on 4g and 6i can both move to 5h.
+        let sfen = "4k4/9/9/9/9/9/5S3/9/3S4K b - 1";
+        assert_eq!("５八銀引", format_ki2(sfen, "4g5h"));
+        assert_eq!("５八銀上", format_ki2(sfen, "6i5h"));
+
+        // Dropping where a piece on the board can also move.
+        let sfen = "4k4/9/9/9/9/9/9/5G3/4K4 b G 1";
+        assert_eq!("４七金打", format_ki2(sfen, "G*4g"));
+        assert_eq!("６七金", format_ki2(sfen, "G*6g"));
+
+        assert_eq!(
+            "２二角不成",
+            format_ki2("4k4/7p1/9/9/9/9/9/1B7/4K4 b - 1", "8h2b")
+        );
+    }
+}
@@ -0,0 +1,8 @@
+pub(crate) mod japanese;
+mod moves;
+mod piece;
+mod position;
+
+pub use self::moves::Move;
+pub use self::piece::{Piece, PieceKind, Square};
+pub use self::position::Board;
@@ -0,0 +1,88 @@
+use core::fmt;
+use core::str::FromStr;
+
+use super::piece::{PieceKind, Square};
+use crate::error::Error;
+
+/// Represents a move in USI notation.
+///
+/// # Examples
+/// ```
+/// use usi::{Move, PieceKind, Square};
+///
+/// let mv: Move = "8h2b+".parse().unwrap();
+/// assert_eq!(Square::new(2, 2).unwrap(), mv.to());
+/// assert_eq!("8h2b+", mv.to_string());
+///
+/// let drop: Move = "P*5e".parse().unwrap();
+/// assert_eq!(Move::Drop { to: Square::new(5, 5).unwrap(), kind: PieceKind::Pawn }, drop);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Move {
+    Normal {
+        from: Square,
+        to: Square,
+        promote: bool,
+    },
+    Drop {
+        to: Square,
+        kind: PieceKind,
+    },
+}
+
+impl Move {
+    /// Returns the destination of the move.
+    pub fn to(&self) -> Square {
+        match *self {
+            Move::Normal { to, .. } | Move::Drop { to, .. } => to,
+        }
+    }
+}
+
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Move::Normal { from, to, promote } => {
+                write!(f, "{from}{to}")?;
+                if promote {
+                    write!(f, "+")?;
+                }
+                Ok(())
+            }
+            Move::Drop { to, kind } => write!(f, "{}*{to}", kind.sfen_letter()),
+        }
+    }
+}
+
+impl FromStr for Move {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some((kind, to)) = s.split_once('*') {
+            let mut chars = kind.chars();
+            let kind = match (chars.next(), chars.next()) {
+                (Some(c), None) if c.is_ascii_uppercase() && c != 'K' => {
+                    PieceKind::from_sfen_letter(c).ok_or(Error::IllegalSyntax)?
+                }
+                _ => return Err(Error::IllegalSyntax),
+            };
+            return Ok(Move::Drop {
+                to: to.parse()?,
+                kind,
+            });
+        }
+
+        let (s, promote) = match s.strip_suffix('+') {
+            Some(s) => (s, true),
+            None => (s, false),
+        };
+        if s.len() != 4 || !s.is_char_boundary(2) {
+            return Err(Error::IllegalSyntax);
+        }
+        Ok(Move::Normal {
+            from: s[..2].parse()?,
+            to: s[2..].parse()?,
+            promote,
+        })
+    }
+}
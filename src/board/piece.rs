@@ -0,0 +1,254 @@
+use core::fmt;
+
+use crate::clock::Color;
+use crate::error::Error;
+
+/// Represents a kind of shogi piece, including the promoted ones.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum PieceKind {
+    Pawn,
+    Lance,
+    Knight,
+    Silver,
+    Gold,
+    Bishop,
+    Rook,
+    King,
+    ProPawn,
+    ProLance,
+    ProKnight,
+    ProSilver,
+    Horse,
+    Dragon,
+}
+
+impl PieceKind {
+    /// The kinds which can be held in hand, in the order of SFEN.
+    pub const HAND: [PieceKind; 7] = [
+        PieceKind::Rook,
+        PieceKind::Bishop,
+        PieceKind::Gold,
+        PieceKind::Silver,
+        PieceKind::Knight,
+        PieceKind::Lance,
+        PieceKind::Pawn,
+    ];
+
+    /// Returns the promoted kind, or `None` if the kind can't promote.
+    pub fn promote(self) -> Option<PieceKind> {
+        match self {
+            PieceKind::Pawn => Some(PieceKind::ProPawn),
+            PieceKind::Lance => Some(PieceKind::ProLance),
+            PieceKind::Knight => Some(PieceKind::ProKnight),
+            PieceKind::Silver => Some(PieceKind::ProSilver),
+            PieceKind::Bishop => Some(PieceKind::Horse),
+            PieceKind::Rook => Some(PieceKind::Dragon),
+            _ => None,
+        }
+    }
+
+    /// Returns the kind before promotion, which is the kind once captured.
+    pub fn unpromote(self) -> PieceKind {
+        match self {
+            PieceKind::ProPawn => PieceKind::Pawn,
+            PieceKind::ProLance => PieceKind::Lance,
+            PieceKind::ProKnight => PieceKind::Knight,
+            PieceKind::ProSilver => PieceKind::Silver,
+            PieceKind::Horse => PieceKind::Bishop,
+            PieceKind::Dragon => PieceKind::Rook,
+            kind => kind,
+        }
+    }
+
+    /// Returns `true` if the kind is a promoted piece.
+    pub fn is_promoted(self) -> bool {
+        self.unpromote() != self
+    }
+
+    /// Returns the SFEN letter of the unpromoted kind, in uppercase.
+    pub fn sfen_letter(self) -> char {
+        match self.unpromote() {
+            PieceKind::Pawn => 'P',
+            PieceKind::Lance => 'L',
+            PieceKind::Knight => 'N',
+            PieceKind::Silver => 'S',
+            PieceKind::Gold => 'G',
+            PieceKind::Bishop => 'B',
+            PieceKind::Rook => 'R',
+            _ => 'K',
+        }
+    }
+
+    /// Parses an SFEN letter, in either case, into the unpromoted kind.
+    pub fn from_sfen_letter(c: char) -> Option<PieceKind> {
+        Some(match c.to_ascii_uppercase() {
+            'P' => PieceKind::Pawn,
+            'L' => PieceKind::Lance,
+            'N' => PieceKind::Knight,
+            'S' => PieceKind::Silver,
+            'G' => PieceKind::Gold,
+            'B' => PieceKind::Bishop,
+            'R' => PieceKind::Rook,
+            'K' => PieceKind::King,
+            _ => return None,
+        })
+    }
+
+    /// Returns the two letter name of the kind in the CSA format, e.g. `FU`.
+    pub fn csa(self) -> &'static str {
+        match self {
+            PieceKind::Pawn => "FU",
+            PieceKind::Lance => "KY",
+            PieceKind::Knight => "KE",
+            PieceKind::Silver => "GI",
+            PieceKind::Gold => "KI",
+            PieceKind::Bishop => "KA",
+            PieceKind::Rook => "HI",
+            PieceKind::King => "OU",
+            PieceKind::ProPawn => "TO",
+            PieceKind::ProLance => "NY",
+            PieceKind::ProKnight => "NK",
+            PieceKind::ProSilver => "NG",
+            PieceKind::Horse => "UM",
+            PieceKind::Dragon => "RY",
+        }
+    }
+
+    /// Parses the two letter name of the kind in the CSA format.
+    pub fn from_csa(s: &str) -> Option<PieceKind> {
+        Some(match s {
+            "FU" => PieceKind::Pawn,
+            "KY" => PieceKind::Lance,
+            "KE" => PieceKind::Knight,
+            "GI" => PieceKind::Silver,
+            "KI" => PieceKind::Gold,
+            "KA" => PieceKind::Bishop,
+            "HI" => PieceKind::Rook,
+            "OU" => PieceKind::King,
+            "TO" => PieceKind::ProPawn,
+            "NY" => PieceKind::ProLance,
+            "NK" => PieceKind::ProKnight,
+            "NG" => PieceKind::ProSilver,
+            "UM" => PieceKind::Horse,
+            "RY" => PieceKind::Dragon,
+            _ => return None,
+        })
+    }
+}
+
+/// Represents a piece on the board or in hand.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Piece {
+    pub color: Color,
+    pub kind: PieceKind,
+}
+
+impl Piece {
+    pub fn new(color: Color, kind: PieceKind) -> Self {
+        Piece { color, kind }
+    }
+}
+
+impl fmt::Display for Piece {
+    /// Formats the piece in SFEN, e.g. `+p` for a promoted pawn of white.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.kind.is_promoted() {
+            write!(f, "+")?;
+        }
+        let letter = self.kind.sfen_letter();
+        match self.color {
+            Color::Black => write!(f, "{letter}"),
+            Color::White => write!(f, "{}", letter.to_ascii_lowercase()),
+        }
+    }
+}
+
+/// Represents a square of the board by the file and the rank, both from 1 to 9.
+///
+/// # Examples
+/// ```
+/// use usi::Square;
+///
+/// let sq: Square = "7g".parse().unwrap();
+/// assert_eq!((7, 7), (sq.file(), sq.rank()));
+/// assert_eq!("7g", sq.to_string());
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Square {
+    file: u8,
+    rank: u8,
+}
+
+impl Square {
+    /// Returns the square, or `None` unless both `file` and `rank` are from 1 to 9.
+    pub fn new(file: u8, rank: u8) -> Option<Square> {
+        ((1..=9).contains(&file) && (1..=9).contains(&rank)).then_some(Square { file, rank })
+    }
+
+    pub fn file(&self) -> u8 {
+        self.file
+    }
+
+    pub fn rank(&self) -> u8 {
+        self.rank
+    }
+
+    /// Returns `true` if the square is in the promotion zone of `color`.
+    pub fn is_promotion_zone(&self, color: Color) -> bool {
+        match color {
+            Color::Black => self.rank <= 3,
+            Color::White => self.rank >= 7,
+        }
+    }
+}
+
+impl fmt::Display for Square {
+    /// Formats the square in USI, e.g. `7g`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", self.file, (b'a' + self.rank - 1) as char)
+    }
+}
+
+impl core::str::FromStr for Square {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.as_bytes() {
+            &[file @ b'1'..=b'9', rank @ b'a'..=b'i'] => Ok(Square {
+                file: file - b'0',
+                rank: rank - b'a' + 1,
+            }),
+            _ => Err(Error::IllegalSyntax),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn piece() {
+        assert_eq!(
+            "+p",
+            Piece::new(Color::White, PieceKind::ProPawn).to_string()
+        );
+        assert_eq!("R", Piece::new(Color::Black, PieceKind::Rook).to_string());
+        assert_eq!(Some(PieceKind::Horse), PieceKind::Bishop.promote());
+        assert_eq!(None, PieceKind::Gold.promote());
+        assert_eq!(PieceKind::Rook, PieceKind::Dragon.unpromote());
+        assert_eq!(Some(PieceKind::ProKnight), PieceKind::from_csa("NK"));
+        assert_eq!(Some(PieceKind::Knight), PieceKind::from_sfen_letter('n'));
+    }
+
+    #[test]
+    fn square() {
+        assert_eq!(Square::new(1, 9), "1i".parse().ok());
+        assert!("0a".parse::<Square>().is_err());
+        assert!("1j".parse::<Square>().is_err());
+        assert_eq!(None, Square::new(10, 1));
+        assert!(Square::new(5, 3).unwrap().is_promotion_zone(Color::Black));
+        assert!(!Square::new(5, 3).unwrap().is_promotion_zone(Color::White));
+    }
+}
@@ -0,0 +1,479 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+use super::japanese::{color_mark, ki2_move};
+use super::moves::Move;
+use super::piece::{Piece, PieceKind, Square};
+use crate::clock::Color;
+use crate::error::Error;
+use crate::protocol::STARTPOS_SFEN;
+
+/// `Board` is a shogi position: the pieces on the board and in hand, and the side to move.
+///
+/// It follows the moves of a game to convert them between notations, and checks only that
+/// the moved piece exists and belongs to the side to move. Legality, e.g. checks, is left
+/// to the engines and servers.
+///
+/// # Examples
+/// ```
+/// use usi::{Board, Color, Piece, PieceKind, Square};
+///
+/// let mut board = Board::startpos();
+/// board.make_move(&"7g7f".parse().unwrap()).unwrap();
+/// board.make_move(&"3c3d".parse().unwrap()).unwrap();
+/// board.make_move(&"8h2b+".parse().unwrap()).unwrap();
+///
+/// let sq = Square::new(2, 2).unwrap();
+/// assert_eq!(Some(Piece::new(Color::Black, PieceKind::Horse)), board.piece_at(sq));
+/// assert_eq!(1, board.hand(Color::Black, PieceKind::Bishop));
+/// assert_eq!(
+///     "lnsgkgsnl/1r5+B1/pppppp1pp/6p2/9/2P6/PP1PPPPPP/7R1/LNSGKGSNL w B 4",
+///     board.to_sfen()
+/// );
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Board {
+    // In the order of SFEN, from 9a to 1a, then to 1i.
+    squares: [Option<Piece>; 81],
+    hands: [[u8; 7]; 2],
+    side_to_move: Color,
+    ply: u32,
+}
+
+impl Default for Board {
+    fn default() -> Self {
+        Board::empty()
+    }
+}
+
+impl Board {
+    /// Returns a board without any pieces, with black to move.
+    pub fn empty() -> Self {
+        Board {
+            squares: [None; 81],
+            hands: [[0; 7]; 2],
+            side_to_move: Color::Black,
+            ply: 1,
+        }
+    }
+
+    /// Returns the initial position of an even game.
+    pub fn startpos() -> Self {
+        Board::from_sfen(STARTPOS_SFEN).unwrap()
+    }
+
+    /// Parses an SFEN. The move number is optional and defaults to 1.
+    pub fn from_sfen(sfen: &str) -> Result<Self, Error> {
+        let mut fields = sfen.split_whitespace();
+        let (Some(rows), Some(side), Some(hands)) = (fields.next(), fields.next(), fields.next())
+        else {
+            return Err(Error::IllegalSyntax);
+        };
+        let ply = fields.next().map(str::parse).transpose()?.unwrap_or(1);
+        if fields.next().is_some() {
+            return Err(Error::IllegalSyntax);
+        }
+
+        let mut board = Board::empty();
+        board.ply = ply;
+        board.side_to_move = match side {
+            "b" => Color::Black,
+            "w" => Color::White,
+            _ => return Err(Error::IllegalSyntax),
+        };
+
+        let mut rank = 0;
+        for row in rows.split('/') {
+            rank += 1;
+            let mut file = 10u8;
+            let mut promoted = false;
+            for c in row.chars() {
+                match c {
+                    '+' => promoted = true,
+                    '1'..='9' => file = file.saturating_sub(c as u8 - b'0'),
+                    _ => {
+                        file = file.checked_sub(1).ok_or(Error::IllegalSyntax)?;
+                        let sq = Square::new(file, rank).ok_or(Error::IllegalSyntax)?;
+                        let mut kind =
+                            PieceKind::from_sfen_letter(c).ok_or(Error::IllegalSyntax)?;
+                        if promoted {
+                            kind = kind.promote().ok_or(Error::IllegalSyntax)?;
+                            promoted = false;
+                        }
+                        board.set_piece(sq, Some(Piece::new(color_of(c), kind)));
+                    }
+                }
+            }
+            if file != 1 || promoted {
+                return Err(Error::IllegalSyntax);
+            }
+        }
+        if rank != 9 {
+            return Err(Error::IllegalSyntax);
+        }
+
+        if hands != "-" {
+            let mut count = 0u8;
+            for c in hands.chars() {
+                match c {
+                    '0'..='9' => {
+                        count = count
+                            .checked_mul(10)
+                            .and_then(|count| count.checked_add(c as u8 - b'0'))
+                            .ok_or(Error::IllegalSyntax)?
+                    }
+                    _ => {
+                        let kind = PieceKind::from_sfen_letter(c)
+                            .filter(|&kind| kind != PieceKind::King)
+                            .ok_or(Error::IllegalSyntax)?;
+                        board.set_hand(color_of(c), kind, count.max(1));
+                        count = 0;
+                    }
+                }
+            }
+        }
+        Ok(board)
+    }
+
+    /// Parses a position as in `GuiCommand::Position`, which is an SFEN optionally followed by
+    /// `moves`, and plays the moves. The arguments of the `position` command, `startpos` or
+    /// `sfen` followed by an SFEN, are accepted as well.
+    pub fn from_position(position: &str) -> Result<Self, Error> {
+        let (sfen, moves) = match position.split_once(" moves") {
+            Some((sfen, moves)) => (sfen, moves),
+            None => (position, ""),
+        };
+        let sfen = match sfen.trim() {
+            "startpos" => STARTPOS_SFEN,
+            sfen => sfen.strip_prefix("sfen ").unwrap_or(sfen),
+        };
+        let mut board = Board::from_sfen(sfen)?;
+        for mv in moves.split_whitespace() {
+            board.make_move(&mv.parse()?)?;
+        }
+        Ok(board)
+    }
+
+    /// Returns the SFEN of the position.
+    pub fn to_sfen(&self) -> String {
+        let mut sfen = String::new();
+        for rank in 1..=9 {
+            if rank > 1 {
+                sfen.push('/');
+            }
+            let mut empty = 0;
+            for file in (1..=9).rev() {
+                match self.piece_at(Square::new(file, rank).unwrap()) {
+                    Some(piece) => {
+                        if empty > 0 {
+                            let _ = write!(sfen, "{empty}");
+                            empty = 0;
+                        }
+                        let _ = write!(sfen, "{piece}");
+                    }
+                    None => empty += 1,
+                }
+            }
+            if empty > 0 {
+                let _ = write!(sfen, "{empty}");
+            }
+        }
+
+        sfen.push_str(match self.side_to_move {
+            Color::Black => " b ",
+            Color::White => " w ",
+        });
+
+        let mut hands = String::new();
+        for color in [Color::Black, Color::White] {
+            for kind in PieceKind::HAND {
+                let count = self.hand(color, kind);
+                if count > 1 {
+                    let _ = write!(hands, "{count}");
+                }
+                if count > 0 {
+                    let _ = write!(hands, "{}", Piece::new(color, kind));
+                }
+            }
+        }
+        if hands.is_empty() {
+            hands.push('-');
+        }
+        let _ = write!(sfen, "{hands} {}", self.ply);
+        sfen
+    }
+
+    /// Returns the piece on `sq`.
+    pub fn piece_at(&self, sq: Square) -> Option<Piece> {
+        self.squares[index(sq)]
+    }
+
+    /// Puts `piece` on `sq`, or clears the square if `None`.
+    pub fn set_piece(&mut self, sq: Square, piece: Option<Piece>) {
+        self.squares[index(sq)] = piece;
+    }
+
+    /// Returns the number of pieces of `kind` in the hand of `color`.
+    /// Promoted kinds count as the unpromoted ones.
+    pub fn hand(&self, color: Color, kind: PieceKind) -> u8 {
+        hand_index(kind).map_or(0, |i| self.hands[color_index(color)][i])
+    }
+
+    /// Sets the number of pieces of `kind` in the hand of `color`.
+    /// Promoted kinds are set as the unpromoted ones, and kings are ignored.
+    pub fn set_hand(&mut self, color: Color, kind: PieceKind, count: u8) {
+        if let Some(i) = hand_index(kind) {
+            self.hands[color_index(color)][i] = count;
+        }
+    }
+
+    /// Returns the side to move.
+    pub fn side_to_move(&self) -> Color {
+        self.side_to_move
+    }
+
+    /// Sets the side to move.
+    pub fn set_side_to_move(&mut self, color: Color) {
+        self.side_to_move = color;
+    }
+
+    /// Returns the move number of the SFEN, which is incremented by each move.
+    pub fn ply(&self) -> u32 {
+        self.ply
+    }
+
+    /// Returns the piece `mv` moves, after promotion.
+    /// Returns `Error::IllegalMove` if the side to move has no such piece.
+    pub fn moved_piece(&self, mv: &Move) -> Result<Piece, Error> {
+        let illegal = || Error::IllegalMove(mv.to_string());
+        let side = self.side_to_move;
+        let kind = match *mv {
+            Move::Normal { from, promote, .. } => {
+                let piece = self
+                    .piece_at(from)
+                    .filter(|piece| piece.color == side)
+                    .ok_or_else(illegal)?;
+                if promote {
+                    piece.kind.promote().ok_or_else(illegal)?
+                } else {
+                    piece.kind
+                }
+            }
+            Move::Drop { kind, .. } if self.hand(side, kind) > 0 => kind,
+            Move::Drop { .. } => return Err(illegal()),
+        };
+        Ok(Piece::new(side, kind))
+    }
+
+    /// Returns the piece `mv` captures, if any.
+    pub fn captured_piece(&self, mv: &Move) -> Option<Piece> {
+        self.piece_at(mv.to())
+    }
+
+    /// Plays `mv` by the side to move.
+    ///
+    /// Returns `Error::IllegalMove` if the side to move has no such piece
+    /// or captures its own piece, leaving the board unchanged.
+    pub fn make_move(&mut self, mv: &Move) -> Result<(), Error> {
+        let piece = self.moved_piece(mv)?;
+        let captured = self.captured_piece(mv);
+        if captured.is_some_and(|captured| captured.color == piece.color) {
+            return Err(Error::IllegalMove(mv.to_string()));
+        }
+        if let Move::Drop { to: _, kind } = *mv {
+            if captured.is_some() {
+                return Err(Error::IllegalMove(mv.to_string()));
+            }
+            self.set_hand(piece.color, kind, self.hand(piece.color, kind) - 1);
+        }
+
+        if let Some(captured) = captured {
+            let kind = captured.kind.unpromote();
+            self.set_hand(piece.color, kind, self.hand(piece.color, kind) + 1);
+        }
+        if let Move::Normal { from, .. } = *mv {
+            self.set_piece(from, None);
+        }
+        self.set_piece(mv.to(), Some(piece));
+        self.side_to_move = self.side_to_move.flip();
+        self.ply += 1;
+        Ok(())
+    }
+
+    /// Formats `mv` by the side to move in traditional Japanese notation, e.g. `▲７六歩`.
+    ///
+    /// `previous` is the destination of the previous move, written as `同　` when `mv` moves
+    /// there. When other pieces of the same kind can reach the destination, the relative position
+    /// and movement of the piece are added, e.g. `△５二金右`.
+    ///
+    /// # Examples
+    /// ```
+    /// use usi::{Board, Square};
+    ///
+    /// let mut board = Board::startpos();
+    /// for mv in ["7g7f", "3c3d", "8h2b+"] {
+    ///     board.make_move(&mv.parse().unwrap()).unwrap();
+    /// }
+    /// let mv = "3a2b".parse().unwrap();
+    /// assert_eq!("△２二銀", board.japanese_move(&mv, None).unwrap());
+    /// assert_eq!("△同　銀", board.japanese_move(&mv, Square::new(2, 2)).unwrap());
+    /// ```
+    pub fn japanese_move(&self, mv: &Move, previous: Option<Square>) -> Result<String, Error> {
+        let mut s = String::new();
+        s.push(color_mark(self.side_to_move));
+        s.push_str(&ki2_move(self, mv, previous)?);
+        Ok(s)
+    }
+
+    /// Formats `moves` played from the position in traditional Japanese notation.
+    /// See `Board::japanese_move`.
+    ///
+    /// # Examples
+    /// ```
+    /// use usi::Board;
+    ///
+    /// let moves = ["7g7f", "3c3d", "8h2b+", "3a2b"].map(|mv| mv.parse().unwrap());
+    /// assert_eq!(
+    ///     vec!["▲７六歩", "△３四歩", "▲２二角成", "△同　銀"],
+    ///     Board::startpos().japanese_moves(&moves).unwrap()
+    /// );
+    /// ```
+    pub fn japanese_moves(&self, moves: &[Move]) -> Result<Vec<String>, Error> {
+        let mut board = self.clone();
+        let mut previous = None;
+        moves
+            .iter()
+            .map(|mv| {
+                let s = board.japanese_move(mv, previous)?;
+                board.make_move(mv)?;
+                previous = Some(mv.to());
+                Ok(s)
+            })
+            .collect()
+    }
+}
+
+fn color_of(c: char) -> Color {
+    if c.is_ascii_uppercase() {
+        Color::Black
+    } else {
+        Color::White
+    }
+}
+
+fn index(sq: Square) -> usize {
+    usize::from(sq.rank() - 1) * 9 + usize::from(9 - sq.file())
+}
+
+fn color_index(color: Color) -> usize {
+    match color {
+        Color::Black => 0,
+        Color::White => 1,
+    }
+}
+
+fn hand_index(kind: PieceKind) -> Option<usize> {
+    let kind = kind.unpromote();
+    PieceKind::HAND.iter().position(|&k| k == kind)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn sfen() {
+        let cases = [
+            STARTPOS_SFEN,
+            "lnsgkgsnl/1r5+B1/pppppp1pp/6p2/9/2P6/PP1PPPPPP/7R1/LNSGKGSNL w B 4",
+            "8l/1l+R2P3/p2pBG1pp/kps1p4/Nn1P2G2/P1P1P2PP/1PS6/1KSG3+r1/LN2+p3L w Sbgn3p 124",
+        ];
+        for sfen in cases {
+            assert_eq!(sfen, Board::from_sfen(sfen).unwrap().to_sfen());
+        }
+        assert_eq!(
+            Board::from_sfen(STARTPOS_SFEN).unwrap(),
+            Board::from_sfen("lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL b -")
+                .unwrap()
+        );
+
+        let illegal = [
+            "lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1 b - 1",
+            "lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSN b - 1",
+            "lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSG+KGSNL b - 1",
+            "lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL x - 1",
+            "lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL b K 1",
+            "lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL b 999P 1",
+        ];
+        for sfen in illegal {
+            assert!(Board::from_sfen(sfen).is_err(), "{sfen}");
+        }
+    }
+
+    #[test]
+    fn make_move() {
+        let mut board = Board::from_position(&alloc::format!(
+            "{STARTPOS_SFEN} moves 7g7f 3c3d 8h2b+ 3a2b"
+        ))
+        .unwrap();
+        assert_eq!(1, board.hand(Color::White, PieceKind::Bishop));
+        assert_eq!(Color::Black, board.side_to_move());
+        assert_eq!(
+            board,
+            Board::from_position("startpos moves 7g7f 3c3d 8h2b+ 3a2b").unwrap()
+        );
+        assert_eq!(
+            board,
+            Board::from_position(&alloc::format!(
+                "sfen {STARTPOS_SFEN} moves 7g7f 3c3d 8h2b+ 3a2b"
+            ))
+            .unwrap()
+        );
+
+        let drop: Move = "B*5e".parse().unwrap();
+        assert!(board.make_move(&"G*5e".parse().unwrap()).is_err());
+        // Moving a piece of the opponent.
+        assert!(board.make_move(&"2b3c".parse().unwrap()).is_err());
+        // Capturing an own piece.
+        assert!(board.make_move(&"2h2g".parse().unwrap()).is_err());
+        board.make_move(&"2g2f".parse().unwrap()).unwrap();
+        board.make_move(&drop).unwrap();
+        assert_eq!(0, board.hand(Color::White, PieceKind::Bishop));
+        assert_eq!(
+            Some(Piece::new(Color::White, PieceKind::Bishop)),
+            board.piece_at(Square::new(5, 5).unwrap())
+        );
+        assert_eq!(7, board.ply());
+    }
+
+    #[test]
+    fn japanese_move() {
+        // Golds on 6a and 4a can both move to 5b.
+        let board = Board::from_sfen("3gkg3/9/9/9/9/9/9/9/4K4 w - 1").unwrap();
+        let moves = ["6a5b", "4a5b"].map(|mv| mv.parse().unwrap());
+        assert_eq!("△５二金右", board.japanese_move(&moves[0], None).unwrap());
+        assert_eq!("△５二金左", board.japanese_move(&moves[1], None).unwrap());
+        assert_eq!(
+            vec!["△５二金右", "▲５八玉"],
+            board
+                .japanese_moves(&[moves[0], "5i5h".parse().unwrap()])
+                .unwrap()
+        );
+        assert!(board.japanese_moves(&moves).is_err());
+    }
+
+    #[test]
+    fn parse_move() {
+        for mv in ["7g7f", "8h2b+", "P*5e", "R*1a"] {
+            assert_eq!(
+                mv,
+                alloc::string::ToString::to_string(&mv.parse::<Move>().unwrap())
+            );
+        }
+        for mv in ["7g7", "7g7f=", "K*5e", "p*5e", "PP*5e", "0a1a", "7g7f++"] {
+            assert!(mv.parse::<Move>().is_err(), "{mv}");
+        }
+    }
+}
@@ -0,0 +1,518 @@
+use std::time::{Duration, Instant};
+
+use super::engine::Engine;
+use crate::analysis::{InfoAggregator, PositionEval, PvInfo, PvLine, Score, SearchStats};
+use crate::error::{Error, TimeoutPhase};
+use crate::protocol::*;
+
+/// How long an engine searching with `go depth` may take to send `bestmove` after reporting
+/// the depth, before `stop` is sent.
+const DEPTH_GRACE: Duration = Duration::from_millis(100);
+
+/// Limits of `Engine::analyze`. The analysis stops when any of the limits is reached.
+///
+/// The limits are checked against the `info` output, since USI has no standard
+/// `go` parameters for depth or nodes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AnalysisLimits {
+    /// Serialized in milliseconds.
+    #[cfg_attr(feature = "serde", serde(with = "crate::analysis::millis"))]
+    time: Option<Duration>,
+    depth: Option<i32>,
+    nodes: Option<u64>,
+}
+
+impl AnalysisLimits {
+    pub fn new() -> Self {
+        AnalysisLimits::default()
+    }
+
+    /// Stops the analysis after `t` of wall clock time.
+    #[must_use]
+    pub fn time(mut self, t: Duration) -> Self {
+        self.time = Some(t);
+        self
+    }
+
+    /// Stops the analysis once an iteration of `depth` is completed.
+    #[must_use]
+    pub fn depth(mut self, depth: i32) -> Self {
+        self.depth = Some(depth);
+        self
+    }
+
+    /// Stops the analysis once the engine searched `nodes`.
+    #[must_use]
+    pub fn nodes(mut self, nodes: u64) -> Self {
+        self.nodes = Some(nodes);
+        self
+    }
+
+    pub fn get_time(&self) -> Option<Duration> {
+        self.time
+    }
+
+    pub fn get_depth(&self) -> Option<i32> {
+        self.depth
+    }
+
+    pub fn get_nodes(&self) -> Option<u64> {
+        self.nodes
+    }
+
+    /// Returns `true` if no limit is set.
+    pub fn is_unlimited(&self) -> bool {
+        self.time.is_none() && self.depth.is_none() && self.nodes.is_none()
+    }
+
+    fn is_reached(
+        &self,
+        elapsed: Duration,
+        completed: Option<&PvInfo>,
+        stats: &SearchStats,
+    ) -> bool {
+        let completed_depth = completed.and_then(|pv| pv.depth);
+        self.time.is_some_and(|t| elapsed >= t)
+            || matches!((self.depth, completed_depth), (Some(limit), Some(d)) if d >= limit)
+            || matches!((self.nodes, stats.total_nodes()), (Some(limit), Some(n)) if n >= limit)
+    }
+}
+
+/// The result of `Engine::analyze`.
+///
+/// With the `serde` feature, reports can be serialized to cache or send them elsewhere.
+/// Durations are serialized in milliseconds, and `bestmove` as the arguments of
+/// `bestmove` command, e.g. `"7g7f ponder 8c8d"`.
+///
+/// # Examples
+/// ```no_run
+/// # #[cfg(feature = "serde")]
+/// # {
+/// use std::time::Duration;
+/// use usi::{AnalysisLimits, AnalysisReport, Engine};
+///
+/// let mut engine = Engine::spawn("/path/to/usi_engine").unwrap();
+/// let sfen = "lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL b - 1";
+/// let report = engine.analyze(sfen, AnalysisLimits::new().depth(20)).unwrap();
+/// let json = report.to_json().unwrap();
+/// assert_eq!(report, AnalysisReport::from_json(&json).unwrap());
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AnalysisReport {
+    /// The name of the engine from `id name`.
+    pub engine_name: String,
+    /// The author of the engine from `id author`.
+    pub engine_author: String,
+    /// The SFEN of the position analyzed, as sent with `position` command.
+    pub position: String,
+    pub limits: AnalysisLimits,
+    #[cfg_attr(feature = "serde", serde(with = "bestmove"))]
+    pub bestmove: BestMoveParams,
+    /// The score of the best PV of the deepest completed iteration.
+    pub score: Option<Score>,
+    pub depth: Option<i32>,
+    pub seldepth: Option<i32>,
+    pub nodes: Option<u64>,
+    pub nps: Option<u64>,
+    /// The search time reported by the engine.
+    #[cfg_attr(feature = "serde", serde(with = "crate::analysis::millis"))]
+    pub time: Option<Duration>,
+    /// The wall clock time of the analysis.
+    #[cfg_attr(feature = "serde", serde(with = "crate::analysis::millis::required"))]
+    pub elapsed: Duration,
+    /// The moves of the best PV of the deepest completed iteration.
+    pub pv: Vec<String>,
+    /// The candidate move table, which has a row per PV with `MultiPV`.
+    pub lines: Vec<PvLine>,
+}
+
+impl AnalysisReport {
+    /// Returns the move the engine chose, or `None` if it resigned or declared a win.
+    pub fn best_move(&self) -> Option<&str> {
+        match &self.bestmove {
+            BestMoveParams::MakeMove(mv, _) => Some(mv),
+            _ => None,
+        }
+    }
+
+    /// Returns the evaluation of the position for `BlunderDetector`, or `None` without a score.
+    pub fn eval(&self) -> Option<PositionEval> {
+        Some(PositionEval::new(
+            self.score?,
+            self.best_move().map(String::from),
+        ))
+    }
+
+    /// Returns the report as JSON.
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> Result<String, Error> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    /// Parses a report from JSON.
+    #[cfg(feature = "serde")]
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        Ok(serde_json::from_str(json)?)
+    }
+}
+
+#[cfg(feature = "serde")]
+mod bestmove {
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    use crate::protocol::*;
+
+    pub fn serialize<S: Serializer>(params: &BestMoveParams, s: S) -> Result<S::Ok, S::Error> {
+        let command = EngineCommand::BestMove(params.clone()).to_string();
+        s.serialize_str(command.trim_start_matches("bestmove "))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<BestMoveParams, D::Error> {
+        let args = String::deserialize(d)?;
+        match EngineCommand::parse(&format!("bestmove {args}")) {
+            Ok(EngineCommand::BestMove(params)) => Ok(params),
+            _ => Err(de::Error::custom(format!("invalid bestmove: {args}"))),
+        }
+    }
+}
+
+impl Engine {
+    /// Analyzes `position` until any of `limits` is reached, and returns the report.
+    ///
+    /// `position` is the SFEN of the position, optionally followed by `moves`,
+    /// as in `GuiCommand::Position`.
+    /// The engine searches with `go infinite`, the `info` output is aggregated, and `stop`
+    /// is sent when a limit is reached. `info` commands until `bestmove` are included
+    /// in the report.
+    ///
+    /// An iteration is completed when the best PV is reported at a depth with an exact score.
+    /// The PV of an iteration interrupted by `stop`, which is often a bound, is reported
+    /// only if no iteration was completed.
+    ///
+    /// Returns `Error::IllegalState` while searching, `Error::InvalidArgument` if `limits`
+    /// has no limit, and `Error::Timeout` if `bestmove` is not received within `Timeouts::bestmove`
+    /// after `stop`. The time limit requires a transport supporting `Transport::set_read_timeout`
+    /// if the engine stays silent.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use std::time::Duration;
+    /// use usi::{AnalysisLimits, Engine};
+    ///
+    /// let mut engine = Engine::spawn("/path/to/usi_engine").unwrap();
+    /// let limits = AnalysisLimits::new().depth(20).time(Duration::from_secs(10));
+    /// let sfen = "lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL b - 1 moves 7g7f";
+    /// let report = engine.analyze(sfen, limits).unwrap();
+    /// println!("{:?} {:?} {}", report.best_move(), report.score, report.pv.join(" "));
+    /// ```
+    pub fn analyze(
+        &mut self,
+        position: &str,
+        limits: AnalysisLimits,
+    ) -> Result<AnalysisReport, Error> {
+        self.run_analysis(position, ThinkParams::new().infinite(), limits)
+    }
+
+    /// Analyzes `position` until an iteration of `depth` is reported, or `time_cap` has passed.
+    ///
+    /// The depth is requested with `go depth`. Since some engines ignore the depth limit,
+    /// the search is stopped unless `bestmove` follows shortly after the `info` output
+    /// confirms the depth. The report holds the deepest
+    /// completed iteration, which may be shallower than `depth` if the time cap was hit.
+    /// Returns `Error::InvalidArgument` if `depth` is not positive. See `Engine::analyze`
+    /// for the other errors.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use std::time::Duration;
+    /// use usi::Engine;
+    ///
+    /// let mut engine = Engine::spawn("/path/to/usi_engine").unwrap();
+    /// let sfen = "lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL b - 1";
+    /// let report = engine.analyze_to_depth(sfen, 18, Duration::from_secs(30)).unwrap();
+    /// if report.depth < Some(18) {
+    ///     println!("only reached depth {:?}", report.depth);
+    /// }
+    /// ```
+    pub fn analyze_to_depth(
+        &mut self,
+        position: &str,
+        depth: i32,
+        time_cap: Duration,
+    ) -> Result<AnalysisReport, Error> {
+        if depth < 1 {
+            return Err(Error::InvalidArgument {
+                reason: "the depth must be positive",
+            });
+        }
+        let limits = AnalysisLimits::new().depth(depth).time(time_cap);
+        self.run_analysis(position, ThinkParams::new().depth(depth), limits)
+    }
+
+    fn run_analysis(
+        &mut self,
+        position: &str,
+        params: ThinkParams,
+        limits: AnalysisLimits,
+    ) -> Result<AnalysisReport, Error> {
+        if self.is_searching() {
+            return Err(Error::IllegalState {
+                reason: "the engine is searching",
+            });
+        }
+        if limits.is_unlimited() {
+            return Err(Error::InvalidArgument {
+                reason: "no analysis limit is set",
+            });
+        }
+        self.send(&GuiCommand::Position(position.to_string()))?;
+        let depth_limited = params.get_depth().is_some();
+        self.send(&GuiCommand::Go(params))?;
+
+        let start = Instant::now();
+        let mut aggregator = InfoAggregator::new();
+        let mut stats = SearchStats::new();
+        // The best PV and the candidate moves of the deepest completed iteration.
+        let mut completed: Option<(PvInfo, Vec<PvLine>)> = None;
+        let mut deadline = limits.time.map(|t| start + t);
+        let mut stopped = false;
+        let mut grace = false;
+        let bestmove = loop {
+            let output = match deadline {
+                Some(deadline) => {
+                    let remaining = deadline.saturating_duration_since(Instant::now());
+                    if remaining.is_zero() {
+                        None
+                    } else {
+                        self.recv_timeout(remaining)?
+                    }
+                }
+                None => Some(self.recv()?),
+            };
+            match output
+                .as_ref()
+                .and_then(|output| output.response().as_ref())
+            {
+                Some(EngineCommand::Info(params)) => {
+                    aggregator.push(params);
+                    stats.push(params);
+                    if let Some(best) = aggregator.snapshot().best() {
+                        let is_complete = best.depth.is_some()
+                            && !best.moves.is_empty()
+                            && best.score.is_some_and(|score| !score.is_bound());
+                        if is_complete
+                            && completed
+                                .as_ref()
+                                .is_none_or(|(c, _)| best.depth >= c.depth)
+                        {
+                            completed = Some((best.clone(), aggregator.snapshot().pv_table()));
+                        }
+                    }
+                }
+                Some(EngineCommand::BestMove(params)) => break params.clone(),
+                _ if output.is_none() && stopped => {
+                    return Err(Error::Timeout {
+                        phase: TimeoutPhase::BestMove,
+                    })
+                }
+                _ => {}
+            }
+
+            if stopped || !self.is_searching() {
+                continue;
+            }
+            let elapsed = start.elapsed();
+            let completed_pv = completed.as_ref().map(|(pv, _)| pv);
+            let time_over = limits.time.is_some_and(|t| elapsed >= t);
+            if output.is_some() && !time_over && depth_limited {
+                // The engine may finish the `go depth` search by itself.
+                if !grace && limits.is_reached(elapsed, completed_pv, &stats) {
+                    grace = true;
+                    let end = Instant::now() + DEPTH_GRACE;
+                    deadline = Some(deadline.map_or(end, |d| d.min(end)));
+                }
+            } else if output.is_none() || limits.is_reached(elapsed, completed_pv, &stats) {
+                self.send(&GuiCommand::Stop)?;
+                stopped = true;
+                deadline = self.timeouts().bestmove.map(|t| Instant::now() + t);
+            }
+        };
+
+        let snapshot = aggregator.snapshot();
+        let (best, lines) = match &completed {
+            Some((pv, lines)) => (Some(pv), lines.clone()),
+            None => (snapshot.best(), snapshot.pv_table()),
+        };
+        Ok(AnalysisReport {
+            engine_name: self.info().name().to_string(),
+            engine_author: self.info().author().to_string(),
+            position: position.to_string(),
+            limits,
+            bestmove,
+            score: best.and_then(|pv| pv.score),
+            depth: best.and_then(|pv| pv.depth).or(snapshot.depth),
+            seldepth: best.and_then(|pv| pv.seldepth).or(snapshot.seldepth),
+            nodes: stats.total_nodes(),
+            nps: snapshot.nps,
+            time: snapshot.time,
+            elapsed: start.elapsed(),
+            pv: best.map(|pv| pv.moves.clone()).unwrap_or_default(),
+            lines,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::SharedBuffer;
+    use crate::{InProcessTransport, InfoSender, IoTransport, Responder, StopToken, UsiEngine};
+
+    const SFEN: &str = "lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL b - 1";
+
+    #[test]
+    fn analyze() {
+        let output = "usiok\nreadyok\n\
+                      info depth 1 nodes 100 score cp 10 pv 2g2f\n\
+                      info depth 2 seldepth 4 nodes 500 score cp 30 pv 7g7f 3c3d\n\
+                      info depth 3 nodes 2000 nps 100000 time 20 score cp 25 pv 7g7f 8c8d\n\
+                      bestmove 7g7f ponder 8c8d\n";
+        let written = SharedBuffer::default();
+        let mut engine =
+            Engine::connect(IoTransport::new(output.as_bytes(), written.clone())).unwrap();
+
+        assert!(matches!(
+            engine.analyze(SFEN, AnalysisLimits::new()),
+            Err(Error::InvalidArgument { .. })
+        ));
+
+        let report = engine
+            .analyze(SFEN, AnalysisLimits::new().depth(2))
+            .unwrap();
+        let written = String::from_utf8(written.0.lock().unwrap().clone()).unwrap();
+        assert!(written.ends_with(&format!("position sfen {SFEN}\ngo infinite\nstop\n")));
+
+        assert_eq!(Some("7g7f"), report.best_move());
+        assert_eq!(Some(Score::new(25, ScoreKind::CpExact)), report.score);
+        assert_eq!(Some(3), report.depth);
+        assert_eq!(Some(2000), report.nodes);
+        assert_eq!(Some(Duration::from_millis(20)), report.time);
+        assert_eq!(vec!["7g7f", "8c8d"], report.pv);
+        assert_eq!(1, report.lines.len());
+        assert!(!engine.is_searching());
+
+        #[cfg(feature = "serde")]
+        {
+            // Durations are serialized in whole milliseconds.
+            let report = AnalysisReport {
+                elapsed: Duration::from_millis(3),
+                ..report
+            };
+            let json = report.to_json().unwrap();
+            assert!(json.contains(r#""bestmove":"7g7f ponder 8c8d""#));
+            assert!(json.contains(r#""limits":{"time":null,"depth":2,"nodes":null}"#));
+            assert_eq!(report, AnalysisReport::from_json(&json).unwrap());
+        }
+    }
+
+    #[test]
+    fn lines_of_completed_iteration() {
+        // The second iteration is interrupted, so the report holds the first one.
+        let output = "usiok\nreadyok\n\
+                      info depth 1 multipv 1 score cp 10 pv 2g2f\n\
+                      info depth 1 multipv 2 score cp 5 pv 7g7f\n\
+                      info depth 2 multipv 1 score cp 40 lowerbound pv 7g7f\n\
+                      bestmove 7g7f\n";
+        let mut engine = Engine::connect(IoTransport::new(output.as_bytes(), Vec::new())).unwrap();
+
+        let report = engine
+            .analyze(SFEN, AnalysisLimits::new().depth(5))
+            .unwrap();
+        assert_eq!(vec!["2g2f"], report.pv);
+        assert_eq!(Some(Score::new(10, ScoreKind::CpExact)), report.score);
+        assert_eq!(2, report.lines.len());
+        assert_eq!(report.pv, report.lines[0].moves);
+        assert_eq!(vec!["7g7f"], report.lines[1].moves);
+    }
+
+    #[test]
+    fn analyze_to_depth() {
+        // The engine finishes the search by itself after reporting the depth.
+        let output = "usiok\nreadyok\n\
+                      info depth 2 score cp 30 pv 7g7f 3c3d\n\
+                      info depth 3 score cp 35 pv 7g7f 8c8d\n\
+                      bestmove 7g7f\n";
+        let written = SharedBuffer::default();
+        let mut engine =
+            Engine::connect(IoTransport::new(output.as_bytes(), written.clone())).unwrap();
+
+        assert!(matches!(
+            engine.analyze_to_depth(SFEN, 0, Duration::from_secs(60)),
+            Err(Error::InvalidArgument { .. })
+        ));
+
+        let report = engine
+            .analyze_to_depth(SFEN, 3, Duration::from_secs(60))
+            .unwrap();
+        let written = String::from_utf8(written.0.lock().unwrap().clone()).unwrap();
+        assert!(written.ends_with("go depth 3\n"));
+
+        assert_eq!(Some("7g7f"), report.best_move());
+        assert_eq!(Some(3), report.depth);
+        assert_eq!(Some(Score::new(35, ScoreKind::CpExact)), report.score);
+        assert_eq!(vec!["7g7f", "8c8d"], report.pv);
+    }
+
+    /// Ignores the depth limit, and reports a bound of depth 4 on `stop`.
+    struct DeepEngine;
+
+    impl UsiEngine for DeepEngine {
+        fn name(&self) -> String {
+            "Deep".to_string()
+        }
+
+        fn author(&self) -> String {
+            "usi-rs".to_string()
+        }
+
+        fn go(&mut self, _: ThinkParams, responder: &Responder, info: InfoSender, stop: StopToken) {
+            let responder = responder.clone();
+            std::thread::spawn(move || {
+                info.send_now(vec![
+                    InfoParams::Depth(3, None),
+                    InfoParams::Score(35, ScoreKind::CpExact),
+                    InfoParams::Pv(vec!["7g7f".to_string(), "8c8d".to_string()]),
+                ])
+                .unwrap();
+                while !stop.is_stopped() {
+                    std::thread::sleep(Duration::from_millis(1));
+                }
+                info.send_now(vec![
+                    InfoParams::Depth(4, None),
+                    InfoParams::Score(90, ScoreKind::CpLowerbound),
+                    InfoParams::Pv(vec!["2g2f".to_string()]),
+                ])
+                .unwrap();
+                let bestmove = BestMoveParams::MakeMove("2g2f".to_string(), None);
+                responder.send(&EngineCommand::BestMove(bestmove)).unwrap();
+            });
+        }
+    }
+
+    #[test]
+    fn analyze_to_depth_stops() {
+        let mut engine = Engine::connect(InProcessTransport::spawn(DeepEngine)).unwrap();
+
+        let report = engine
+            .analyze_to_depth(SFEN, 3, Duration::from_secs(60))
+            .unwrap();
+        assert_eq!(Some("2g2f"), report.best_move());
+        assert_eq!(Some(3), report.depth);
+        assert_eq!(Some(Score::new(35, ScoreKind::CpExact)), report.score);
+        assert_eq!(vec!["7g7f", "8c8d"], report.pv);
+        assert!(!engine.is_searching());
+    }
+}
@@ -0,0 +1,296 @@
+use super::analyze::{AnalysisLimits, AnalysisReport};
+use super::engine::Engine;
+use super::pool::EnginePool;
+use crate::analysis::{BlunderDetector, MoveQuality, PvLine, Score};
+use crate::board::Board;
+use crate::clock::Color;
+use crate::error::Error;
+use crate::record::{GameRecord, RecordedMove};
+
+/// A warning about a mate annotated to a move.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MateWarning {
+    /// The side to move had a mate in `plies`, but the move lets it slip.
+    MissedMate { plies: Option<i32> },
+    /// The move allows the opponent to mate in `plies`.
+    AllowsMate { plies: Option<i32> },
+}
+
+/// A move of `AnnotatedGame`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AnnotatedMove {
+    /// The 1-based move number.
+    pub ply: u32,
+    pub color: Color,
+    /// The move played.
+    pub played: String,
+    /// The score of the position before the move, from the side making it.
+    pub score: Option<Score>,
+    /// The move the engine chose in the position.
+    pub best_move: Option<String>,
+    /// The candidate moves of the engine other than the move played.
+    pub alternatives: Vec<PvLine>,
+    /// The centipawns lost by the move, if both positions were scored.
+    pub loss: Option<i32>,
+    pub quality: Option<MoveQuality>,
+    pub mate_warning: Option<MateWarning>,
+}
+
+/// A game record annotated by `GameAnnotator`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AnnotatedGame {
+    /// The SFEN of the initial position.
+    pub start: String,
+    pub moves: Vec<AnnotatedMove>,
+    /// The analysis of each position, including the initial and the final positions.
+    pub reports: Vec<AnalysisReport>,
+}
+
+impl AnnotatedGame {
+    /// Converts the game into a `GameRecord` to export it as KIF or KI2, with the score
+    /// of each move, and a comment with the quality and the engine's move unless the move
+    /// was the best.
+    pub fn to_record(&self) -> GameRecord {
+        let mut record = GameRecord::new(&self.start);
+        for annotated in &self.moves {
+            let mut mv = RecordedMove::new(&annotated.played);
+            mv.score = annotated.score;
+            mv.comment = match (annotated.quality, &annotated.best_move) {
+                (None | Some(MoveQuality::Best), _) => None,
+                (Some(quality), Some(best)) => Some(format!("{quality:?}, best {best}")),
+                (Some(quality), None) => Some(format!("{quality:?}")),
+            };
+            record.moves.push(mv);
+        }
+        record
+    }
+}
+
+/// `GameAnnotator` reviews a game with an engine, annotating each move with the evaluation,
+/// the engine's alternatives, the move quality by `BlunderDetector`, and mate warnings.
+///
+/// Each position of the game is analyzed with `Engine::analyze`. Set `MultiPV` on the engine
+/// to get more than one alternative per move.
+///
+/// # Examples
+/// ```no_run
+/// use usi::{AnalysisLimits, Engine, GameAnnotator};
+///
+/// let mut engine = Engine::spawn("/path/to/usi_engine").unwrap();
+/// let start = "startpos";
+/// let moves = ["7g7f", "3c3d", "8h2b+"].map(String::from);
+/// let annotator = GameAnnotator::new(AnalysisLimits::new().depth(16));
+/// let game = annotator.annotate(&mut engine, start, &moves).unwrap();
+/// for mv in &game.moves {
+///     println!("{} {} {:?} {:?}", mv.ply, mv.played, mv.quality, mv.mate_warning);
+/// }
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct GameAnnotator {
+    limits: AnalysisLimits,
+    detector: BlunderDetector,
+}
+
+impl GameAnnotator {
+    /// Creates an annotator analyzing each position within `limits`.
+    pub fn new(limits: AnalysisLimits) -> Self {
+        GameAnnotator {
+            limits,
+            detector: BlunderDetector::new(),
+        }
+    }
+
+    /// Sets the detector classifying the moves.
+    #[must_use]
+    pub fn detector(mut self, detector: BlunderDetector) -> Self {
+        self.detector = detector;
+        self
+    }
+
+    /// Returns the limits of the analysis of each position.
+    pub fn limits(&self) -> AnalysisLimits {
+        self.limits
+    }
+
+    /// Annotates `moves` played from `start`, which is the SFEN of the initial position
+    /// or `startpos`.
+    pub fn annotate(
+        &self,
+        engine: &mut Engine,
+        start: &str,
+        moves: &[String],
+    ) -> Result<AnnotatedGame, Error> {
+        let start = start_sfen(start)?;
+        let reports = positions(&start, moves)
+            .iter()
+            .map(|position| engine.analyze(position, self.limits))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(self.build(&start, moves, reports))
+    }
+
+    /// Annotates `moves` like `GameAnnotator::annotate` with an engine of `pool`.
+    /// See `GameAnnotator::annotate_parallel` to use all the engines of the pool.
+    pub fn annotate_with_pool(
+        &self,
+        pool: &EnginePool,
+        start: &str,
+        moves: &[String],
+    ) -> Result<AnnotatedGame, Error> {
+        let mut engine = pool.get()?;
+        self.annotate(&mut engine, start, moves)
+    }
+
+    /// Builds the annotated game from the reports of each position.
+    pub(crate) fn build(
+        &self,
+        start: &str,
+        moves: &[String],
+        reports: Vec<AnalysisReport>,
+    ) -> AnnotatedGame {
+        let mut color = side_to_move(start);
+        let moves = moves
+            .iter()
+            .zip(reports.windows(2))
+            .enumerate()
+            .map(|(i, (played, reports))| {
+                let (before, after) = (reports[0].eval(), reports[1].eval());
+                let loss = before
+                    .as_ref()
+                    .zip(after.as_ref())
+                    .map(|(before, after)| self.detector.loss(played, before, after));
+                let is_best = reports[0].best_move() == Some(played.as_str());
+                let annotated = AnnotatedMove {
+                    ply: i as u32 + 1,
+                    color,
+                    played: played.clone(),
+                    score: reports[0].score,
+                    best_move: reports[0].best_move().map(String::from),
+                    alternatives: reports[0]
+                        .lines
+                        .iter()
+                        .filter(|line| line.moves.first() != Some(played))
+                        .cloned()
+                        .collect(),
+                    loss,
+                    quality: loss.map(|loss| self.detector.classify(loss, is_best)),
+                    mate_warning: mate_warning(reports[0].score, reports[1].score),
+                };
+                color = color.flip();
+                annotated
+            })
+            .collect();
+        AnnotatedGame {
+            start: start.to_string(),
+            moves,
+            reports,
+        }
+    }
+}
+
+/// Returns the SFEN of `start`, which may be `startpos` or an SFEN followed by moves.
+pub(crate) fn start_sfen(start: &str) -> Result<String, Error> {
+    Ok(Board::from_position(start)?.to_sfen())
+}
+
+/// Returns the positions before each move and the final position.
+pub(crate) fn positions(start: &str, moves: &[String]) -> Vec<String> {
+    (0..=moves.len())
+        .map(|i| match i {
+            0 => start.to_string(),
+            _ => format!("{} moves {}", start, moves[..i].join(" ")),
+        })
+        .collect()
+}
+
+/// Returns the side to move of an SFEN, which is black unless the second field is `w`.
+fn side_to_move(sfen: &str) -> Color {
+    match sfen.split_whitespace().nth(1) {
+        Some("w") => Color::White,
+        _ => Color::Black,
+    }
+}
+
+/// Compares the scores before and after a move, where `after` is from the opponent.
+fn mate_warning(before: Option<Score>, after: Option<Score>) -> Option<MateWarning> {
+    let plies = |score: Score| score.mate_distance().map(i32::abs);
+    let had_mate = before.filter(Score::is_winning_mate);
+    let allows_mate = after.filter(Score::is_winning_mate);
+    match (had_mate, allows_mate) {
+        (_, Some(after)) if before.is_none_or(|b| !b.is_mate() || b.is_winning_mate()) => {
+            Some(MateWarning::AllowsMate {
+                plies: plies(after),
+            })
+        }
+        (Some(before), _) if after.is_some_and(|a| !a.is_mate()) => Some(MateWarning::MissedMate {
+            plies: plies(before),
+        }),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::*;
+    use crate::IoTransport;
+
+    const SFEN: &str = "lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL b - 1";
+
+    #[test]
+    fn annotate() {
+        let output = "usiok\nreadyok\n\
+                      info depth 1 score cp 50 pv 7g7f\nbestmove 7g7f\n\
+                      info depth 1 multipv 1 score cp -30 pv 3c3d\n\
+                      info depth 1 multipv 2 score cp -60 pv 8c8d\nbestmove 3c3d\n\
+                      info depth 1 score mate 3 pv 2g2f\nbestmove 2g2f\n";
+        let mut engine = Engine::connect(IoTransport::new(output.as_bytes(), Vec::new())).unwrap();
+        let moves = ["7g7f", "8c8d"].map(String::from);
+        let game = GameAnnotator::new(AnalysisLimits::new().depth(1))
+            .annotate(&mut engine, SFEN, &moves)
+            .unwrap();
+
+        assert_eq!(3, game.reports.len());
+        assert_eq!(format!("{SFEN} moves 7g7f 8c8d"), game.reports[2].position);
+
+        let first = &game.moves[0];
+        assert_eq!(Some(MoveQuality::Best), first.quality);
+        assert_eq!(None, first.mate_warning);
+
+        let second = &game.moves[1];
+        assert_eq!(Color::White, second.color);
+        assert_eq!(Some("3c3d".to_string()), second.best_move);
+        assert_eq!(1, second.alternatives.len());
+        assert_eq!(Some(2970), second.loss);
+        assert_eq!(Some(MoveQuality::Blunder), second.quality);
+        assert_eq!(
+            Some(MateWarning::AllowsMate { plies: Some(3) }),
+            second.mate_warning
+        );
+
+        let record = game.to_record();
+        assert_eq!(vec!["7g7f", "8c8d"], record.usi_moves());
+        assert_eq!(None, record.moves[0].comment);
+        assert_eq!(
+            Some("Blunder, best 3c3d".to_string()),
+            record.moves[1].comment
+        );
+    }
+
+    #[test]
+    fn warnings() {
+        let cp = |value| Some(Score::new(value, ScoreKind::CpExact));
+        let mate = |value| Some(Score::new(value, ScoreKind::MateExact));
+        assert_eq!(
+            Some(MateWarning::MissedMate { plies: Some(5) }),
+            mate_warning(mate(5), cp(-800))
+        );
+        assert_eq!(None, mate_warning(mate(5), mate(-4)));
+        // Being mated already is not a new warning.
+        assert_eq!(None, mate_warning(mate(-2), mate(1)));
+        assert_eq!(
+            Some(MateWarning::AllowsMate { plies: None }),
+            mate_warning(cp(0), Some(Score::new(1, ScoreKind::MateSignOnly)))
+        );
+        assert_eq!(Color::White, side_to_move("4k4/9/9/9/9/9/9/9/4K4 w - 2"));
+    }
+}
@@ -0,0 +1,690 @@
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_channel::mpsc;
+use futures_util::future::{self, Either};
+use futures_util::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, BufReader};
+use futures_util::{ready, Sink, SinkExt, Stream, StreamExt};
+
+use super::runtime::Runtime;
+use crate::error::Error;
+use crate::process::{EngineInfo, EngineOutput};
+use crate::protocol::*;
+
+/// `AsyncEngine` is an asynchronous client of a USI engine.
+///
+/// The engine output is read on a background task, so `recv()` never blocks the runtime.
+/// Like `Engine`, the `usi`/`isready` handshake is performed when created.
+///
+/// `AsyncEngine::spawn` runs on tokio. With `async-std` or `smol` features,
+/// `AsyncEngine::spawn_on` spawns the engine process for the given `Runtime`.
+///
+/// # Examples
+/// ```no_run
+/// use usi::{AsyncEngine, EngineCommand, GuiCommand, ThinkParams};
+///
+/// # async fn run() -> Result<(), usi::Error> {
+/// let mut engine = AsyncEngine::spawn("/path/to/usi_engine").await?;
+/// engine.send(&GuiCommand::UsiNewGame).await?;
+/// engine.send(&GuiCommand::Go(ThinkParams::new().infinite())).await?;
+///
+/// while let Ok(output) = engine.recv().await {
+///     if let Some(EngineCommand::BestMove(_)) = output.response() {
+///         break;
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct AsyncEngine {
+    writer: Pin<Box<dyn AsyncWrite + Send>>,
+    // Commands passed to the sink and not written yet.
+    buffer: Vec<u8>,
+    receiver: mpsc::UnboundedReceiver<Result<EngineOutput, Error>>,
+    process: Option<Box<dyn AsyncChild>>,
+    info: EngineInfo,
+    searching: bool,
+    subscribers: Vec<mpsc::UnboundedSender<EngineOutput>>,
+    #[cfg(feature = "tracing")]
+    trace_level: tracing::Level,
+}
+
+impl fmt::Debug for AsyncEngine {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("AsyncEngine")
+            .field("info", &self.info)
+            .finish_non_exhaustive()
+    }
+}
+
+impl AsyncEngine {
+    /// Spawns a new process of the USI engine on tokio and performs the handshake.
+    #[cfg(feature = "tokio")]
+    pub async fn spawn<P: AsRef<std::ffi::OsStr>>(engine_path: P) -> Result<AsyncEngine, Error> {
+        let process = tokio::process::Command::new(engine_path)
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()?;
+
+        AsyncEngine::from_process(process).await
+    }
+
+    /// Performs the handshake with an already spawned tokio process.
+    /// Both stdin and stdout of `process` must be piped.
+    #[cfg(feature = "tokio")]
+    pub async fn from_process(mut process: tokio::process::Child) -> Result<AsyncEngine, Error> {
+        use tokio_util::compat::{TokioAsyncReadCompatExt, TokioAsyncWriteCompatExt};
+
+        let (Some(stdin), Some(stdout)) = (process.stdin.take(), process.stdout.take()) else {
+            return Err(Error::InvalidArgument {
+                reason: "stdin and stdout of the process must be piped",
+            });
+        };
+
+        let mut engine = AsyncEngine::new::<super::runtime::TokioRuntime, _, _>(
+            stdout.compat(),
+            stdin.compat_write(),
+        );
+        engine.process = Some(Box::new(process));
+        engine.handshake().await?;
+
+        Ok(engine)
+    }
+
+    /// Spawns a new process of the USI engine using `async-process` and performs the handshake.
+    /// Background tasks are spawned on `R`.
+    #[cfg(any(feature = "async-std", feature = "smol"))]
+    pub async fn spawn_on<R: Runtime, P: AsRef<std::ffi::OsStr>>(
+        engine_path: P,
+    ) -> Result<AsyncEngine, Error> {
+        let process = async_process::Command::new(engine_path)
+            .stdin(async_process::Stdio::piped())
+            .stdout(async_process::Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()?;
+
+        AsyncEngine::from_async_process::<R>(process).await
+    }
+
+    /// Performs the handshake with an already spawned `async-process` process.
+    /// Both stdin and stdout of `process` must be piped.
+    #[cfg(any(feature = "async-std", feature = "smol"))]
+    pub async fn from_async_process<R: Runtime>(
+        mut process: async_process::Child,
+    ) -> Result<AsyncEngine, Error> {
+        let (Some(stdin), Some(stdout)) = (process.stdin.take(), process.stdout.take()) else {
+            return Err(Error::InvalidArgument {
+                reason: "stdin and stdout of the process must be piped",
+            });
+        };
+
+        let mut engine = AsyncEngine::new::<R, _, _>(stdout, stdin);
+        engine.process = Some(Box::new(process));
+        engine.handshake().await?;
+
+        Ok(engine)
+    }
+
+    /// Performs the handshake with an engine connected through `reader` and `writer`.
+    /// `reader` is consumed by a background task spawned on `R`.
+    pub async fn from_io<R, Rd, W>(reader: Rd, writer: W) -> Result<AsyncEngine, Error>
+    where
+        R: Runtime,
+        Rd: AsyncRead + Send + Unpin + 'static,
+        W: AsyncWrite + Send + 'static,
+    {
+        let mut engine = AsyncEngine::new::<R, _, _>(reader, writer);
+        engine.handshake().await?;
+
+        Ok(engine)
+    }
+
+    fn new<R, Rd, W>(reader: Rd, writer: W) -> AsyncEngine
+    where
+        R: Runtime,
+        Rd: AsyncRead + Send + Unpin + 'static,
+        W: AsyncWrite + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::unbounded();
+        R::spawn(read_output(reader, sender));
+
+        AsyncEngine {
+            writer: Box::pin(writer),
+            buffer: Vec::new(),
+            receiver,
+            process: None,
+            info: EngineInfo::default(),
+            searching: false,
+            subscribers: Vec::new(),
+            #[cfg(feature = "tracing")]
+            trace_level: tracing::Level::DEBUG,
+        }
+    }
+
+    /// Returns the metadata collected during the handshake.
+    pub fn info(&self) -> &EngineInfo {
+        &self.info
+    }
+
+    /// Sends a command to the engine.
+    pub async fn send(&mut self, command: &GuiCommand) -> Result<(), Error> {
+        SinkExt::send(self, command.clone()).await
+    }
+
+    /// Waits for the next command received from the engine.
+    /// Lines which cannot be parsed are skipped.
+    /// Returns `Error::EngineClosed` when the engine closed its output.
+    pub async fn recv(&mut self) -> Result<EngineOutput, Error> {
+        future::poll_fn(|cx| self.poll_recv(cx)).await
+    }
+
+    fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Result<EngineOutput, Error>> {
+        let output = match self.receiver.poll_next_unpin(cx) {
+            Poll::Ready(Some(output)) => output?,
+            Poll::Ready(None) => return Poll::Ready(Err(Error::EngineClosed)),
+            Poll::Pending => return Poll::Pending,
+        };
+
+        #[cfg(feature = "tracing")]
+        super::trace::traffic(self.trace_level, "recv", self.info.name(), output.raw_str());
+        if let Some(EngineCommand::BestMove(_)) = output.response() {
+            self.searching = false;
+        }
+        self.subscribers
+            .retain(|subscriber| subscriber.unbounded_send(output.clone()).is_ok());
+        Poll::Ready(Ok(output))
+    }
+
+    /// Returns a stream of every output received from the engine from now on.
+    ///
+    /// Any number of subscribers can be created, and each of them receives a copy
+    /// of the outputs consumed by `recv()` or the stream of `AsyncEngine` itself.
+    /// Dropped subscribers are unsubscribed.
+    pub fn subscribe(&mut self) -> mpsc::UnboundedReceiver<EngineOutput> {
+        let (sender, receiver) = mpsc::unbounded();
+        self.subscribers.push(sender);
+        receiver
+    }
+
+    /// Returns `true` if a search was started and `bestmove` has not been received.
+    pub fn is_searching(&self) -> bool {
+        self.searching
+    }
+
+    /// Sends `stop` command if the engine is searching, and waits for `bestmove`.
+    /// Returns `None` if the engine was not searching.
+    pub async fn stop(&mut self) -> Result<Option<BestMoveParams>, Error> {
+        if !self.searching {
+            return Ok(None);
+        }
+
+        self.send(&GuiCommand::Stop).await?;
+        loop {
+            if let Some(EngineCommand::BestMove(params)) = self.recv().await?.response() {
+                return Ok(Some(params.clone()));
+            }
+        }
+    }
+
+    /// Starts a search and waits until `bestmove` is received or `cancel` completes.
+    /// `on_info` is called with the parameters of each `info` command.
+    ///
+    /// When `cancel` completes, e.g. `CancellationToken::cancelled()`, `stop` is sent
+    /// and the trailing `bestmove` is returned. If the returned future is dropped
+    /// during the search, the search is stopped at the next `go_until` or `stop` call,
+    /// so `go infinite` can be used in `select!` loops safely.
+    pub async fn go_until<C, F>(
+        &mut self,
+        params: ThinkParams,
+        cancel: C,
+        mut on_info: F,
+    ) -> Result<BestMoveParams, Error>
+    where
+        C: Future<Output = ()>,
+        F: FnMut(&[InfoParams]),
+    {
+        // Clean up a search abandoned by a dropped future.
+        self.stop().await?;
+        self.send(&GuiCommand::Go(params)).await?;
+
+        let mut cancel = Box::pin(cancel);
+        loop {
+            let output = match future::select(Box::pin(self.recv()), cancel.as_mut()).await {
+                Either::Left((output, _)) => Some(output?),
+                Either::Right(_) => None,
+            };
+            let Some(output) = output else {
+                return self.stop().await?.ok_or(Error::EngineClosed);
+            };
+            match output.response() {
+                Some(EngineCommand::Info(params)) => on_info(params),
+                Some(EngineCommand::BestMove(params)) => return Ok(params.clone()),
+                _ => {}
+            }
+        }
+    }
+
+    /// Sets the level of `tracing` events logging the protocol traffic. Defaults to `DEBUG`.
+    #[cfg(feature = "tracing")]
+    pub fn set_trace_level(&mut self, level: tracing::Level) {
+        self.trace_level = level;
+    }
+
+    /// Returns the OS-assigned process identifier of the engine process, if spawned by `AsyncEngine`.
+    pub fn id(&self) -> Option<u32> {
+        self.process.as_ref().and_then(|p| p.id())
+    }
+
+    /// Forces the engine process to exit, if spawned by `AsyncEngine`.
+    pub fn kill(&mut self) -> Result<(), Error> {
+        if let Some(p) = &mut self.process {
+            p.start_kill()?;
+        }
+        Ok(())
+    }
+
+    async fn handshake(&mut self) -> Result<(), Error> {
+        self.send(&GuiCommand::Usi).await?;
+        loop {
+            match self.recv().await?.response() {
+                Some(EngineCommand::UsiOk) => break,
+                Some(cmd) => self.info.update(cmd),
+                None => {}
+            }
+        }
+
+        self.send(&GuiCommand::IsReady).await?;
+        while !matches!(self.recv().await?.response(), Some(EngineCommand::ReadyOk)) {}
+
+        Ok(())
+    }
+}
+
+/// Kills the engine process, if spawned by `AsyncEngine`.
+///
+/// The exit status is collected if available; otherwise the runtime reaps the dropped process
+/// in the background, so no zombie process is left behind.
+impl Drop for AsyncEngine {
+    fn drop(&mut self) {
+        if let Some(p) = &mut self.process {
+            let _ = p.start_kill();
+            let _ = p.try_wait();
+        }
+    }
+}
+
+/// Process handles of the supported runtimes.
+trait AsyncChild: Send {
+    fn id(&self) -> Option<u32>;
+    fn start_kill(&mut self) -> std::io::Result<()>;
+    fn try_wait(&mut self) -> std::io::Result<Option<std::process::ExitStatus>>;
+}
+
+#[cfg(feature = "tokio")]
+impl AsyncChild for tokio::process::Child {
+    fn id(&self) -> Option<u32> {
+        tokio::process::Child::id(self)
+    }
+
+    fn start_kill(&mut self) -> std::io::Result<()> {
+        tokio::process::Child::start_kill(self)
+    }
+
+    fn try_wait(&mut self) -> std::io::Result<Option<std::process::ExitStatus>> {
+        tokio::process::Child::try_wait(self)
+    }
+}
+
+#[cfg(any(feature = "async-std", feature = "smol"))]
+impl AsyncChild for async_process::Child {
+    fn id(&self) -> Option<u32> {
+        Some(async_process::Child::id(self))
+    }
+
+    fn start_kill(&mut self) -> std::io::Result<()> {
+        async_process::Child::kill(self)
+    }
+
+    fn try_wait(&mut self) -> std::io::Result<Option<std::process::ExitStatus>> {
+        async_process::Child::try_status(self)
+    }
+}
+
+/// `AsyncEngine` is a stream of commands received from the engine,
+/// which ends when the engine closes its output.
+///
+/// # Examples
+/// ```no_run
+/// use futures_util::StreamExt;
+/// use usi::{AsyncEngine, EngineCommand, GuiCommand, ThinkParams};
+///
+/// # async fn run() -> Result<(), usi::Error> {
+/// let mut engine = AsyncEngine::spawn("/path/to/usi_engine").await?;
+/// engine.send(&GuiCommand::Go(ThinkParams::new())).await?;
+///
+/// let mut infos = (&mut engine)
+///     .filter_map(|cmd| async move {
+///         match cmd {
+///             Ok(EngineCommand::Info(params)) => Some(params),
+///             _ => None,
+///         }
+///     })
+///     .boxed();
+/// while let Some(params) = infos.next().await {
+///     println!("{params:?}");
+/// }
+/// # Ok(())
+/// # }
+/// ```
+impl Stream for AsyncEngine {
+    type Item = Result<EngineCommand, Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        match self.get_mut().poll_recv(cx) {
+            Poll::Ready(Ok(output)) => Poll::Ready(Some(Ok(output
+                .response()
+                .clone()
+                .unwrap_or(EngineCommand::Unknown)))),
+            Poll::Ready(Err(Error::EngineClosed)) => Poll::Ready(None),
+            Poll::Ready(Err(e)) => Poll::Ready(Some(Err(e))),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// `AsyncEngine` is a sink of commands sent to the engine.
+/// Commands are buffered until the sink is flushed.
+impl Sink<GuiCommand> for AsyncEngine {
+    type Error = Error;
+
+    fn poll_ready(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn start_send(self: Pin<&mut Self>, command: GuiCommand) -> Result<(), Error> {
+        let this = self.get_mut();
+        let line = command.to_string();
+        #[cfg(feature = "tracing")]
+        super::trace::traffic(this.trace_level, "send", this.info.name(), &line);
+
+        this.buffer.extend_from_slice(line.as_bytes());
+        this.buffer.push(b'\n');
+        if let GuiCommand::Go(_) = command {
+            this.searching = true;
+        }
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        let this = self.get_mut();
+        while !this.buffer.is_empty() {
+            let n = ready!(this.writer.as_mut().poll_write(cx, &this.buffer))?;
+            if n == 0 {
+                return Poll::Ready(Err(Error::EngineClosed));
+            }
+            this.buffer.drain(..n);
+        }
+        ready!(this.writer.as_mut().poll_flush(cx))?;
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+        ready!(self.as_mut().poll_flush(cx))?;
+        ready!(self.get_mut().writer.as_mut().poll_close(cx))?;
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// Forwards parsed engine output to `sender` until the engine closes its output.
+/// A read error is forwarded before stopping.
+async fn read_output<R: AsyncRead + Unpin>(
+    reader: R,
+    sender: mpsc::UnboundedSender<Result<EngineOutput, Error>>,
+) {
+    let mut lines = BufReader::new(reader).lines();
+
+    while let Some(line) = lines.next().await {
+        let line = match line {
+            Ok(line) => line,
+            Err(e) => {
+                let _ = sender.unbounded_send(Err(e.into()));
+                break;
+            }
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        // Ignore illegal commands.
+        if let Ok(cmd) = EngineCommand::parse(&line) {
+            if sender
+                .unbounded_send(Ok(EngineOutput::new(Some(cmd), line)))
+                .is_err()
+            {
+                break;
+            }
+        }
+    }
+}
+
+#[cfg(all(
+    test,
+    unix,
+    any(feature = "tokio", feature = "async-std", feature = "smol")
+))]
+mod tests {
+    use super::*;
+
+    const SCRIPT: &str = "read l; echo 'id name Toy Engine'; echo usiok; \
+                          read l; echo readyok; \
+                          read l; echo 'bestmove resign'";
+
+    async fn search(engine: &mut AsyncEngine) {
+        assert_eq!("Toy Engine", engine.info().name());
+
+        engine
+            .send(&GuiCommand::Go(ThinkParams::new().infinite()))
+            .await
+            .unwrap();
+        assert_eq!(
+            Some(EngineCommand::BestMove(BestMoveParams::Resign)),
+            *engine.recv().await.unwrap().response()
+        );
+        assert!(matches!(engine.recv().await, Err(Error::EngineClosed)));
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn tokio_handshake() {
+        let process = tokio::process::Command::new("sh")
+            .arg("-c")
+            .arg(SCRIPT)
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .spawn()
+            .unwrap();
+
+        let mut engine = AsyncEngine::from_process(process)
+            .await
+            .expect("handshake failed");
+        search(&mut engine).await;
+    }
+
+    #[cfg(all(feature = "tokio", target_os = "linux"))]
+    #[tokio::test]
+    async fn tokio_drop() {
+        let process = tokio::process::Command::new("sh")
+            .arg("-c")
+            .arg("read l; echo usiok; read l; echo readyok; exec sleep 30")
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .spawn()
+            .unwrap();
+        let engine = AsyncEngine::from_process(process).await.unwrap();
+        let stat = format!("/proc/{}/stat", engine.id().unwrap());
+        drop(engine);
+
+        // The process is killed and reaped, so it doesn't remain as a zombie.
+        for _ in 0..500 {
+            if std::fs::metadata(&stat).is_err() {
+                return;
+            }
+            tokio::task::yield_now().await;
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+        panic!("the engine process was not reaped");
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn tokio_stream() {
+        let process = tokio::process::Command::new("sh")
+            .arg("-c")
+            .arg(
+                "read l; echo usiok; read l; echo readyok; \
+                 read l; echo 'info depth 1'; echo 'info depth 2'; echo 'bestmove 7g7f'",
+            )
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .spawn()
+            .unwrap();
+        let mut engine = AsyncEngine::from_process(process).await.unwrap();
+        let subscriber = engine.subscribe();
+        engine
+            .send(&GuiCommand::Go(ThinkParams::new()))
+            .await
+            .unwrap();
+
+        let commands = (&mut engine).map(Result::unwrap).collect::<Vec<_>>().await;
+        assert_eq!(3, commands.len());
+        assert_eq!(
+            EngineCommand::BestMove(BestMoveParams::MakeMove("7g7f".to_string(), None)),
+            commands[2]
+        );
+        assert!(!engine.is_searching());
+        drop(engine);
+        assert_eq!(3, subscriber.count().await);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn tokio_sink() {
+        let process = tokio::process::Command::new("sh")
+            .arg("-c")
+            .arg(
+                "read l; echo usiok; read l; echo readyok; \
+                 read l; read l; read l; echo \"bestmove $l\"",
+            )
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .spawn()
+            .unwrap();
+        let engine = AsyncEngine::from_process(process).await.unwrap();
+        let (mut sink, mut stream) = engine.split();
+
+        let commands = [
+            GuiCommand::UsiNewGame,
+            GuiCommand::Position("startpos-sfen".to_string()),
+            GuiCommand::Go(ThinkParams::new()),
+        ];
+        sink.send_all(&mut futures_util::stream::iter(commands.map(Ok)))
+            .await
+            .unwrap();
+        // The last line read by the engine is echoed back.
+        assert_eq!(
+            Some(EngineCommand::BestMove(BestMoveParams::MakeMove(
+                "go".to_string(),
+                None
+            ))),
+            stream.next().await.transpose().unwrap()
+        );
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn tokio_cancel() {
+        use tokio_util::sync::CancellationToken;
+
+        let script = "read l; echo usiok; read l; echo readyok; \
+                      read l; echo 'info depth 1'; read l; echo 'bestmove 7g7f'; \
+                      read l; read l; echo 'bestmove 2g2f'; read l; read l; echo 'bestmove 3g3f'";
+        let process = tokio::process::Command::new("sh")
+            .arg("-c")
+            .arg(script)
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .spawn()
+            .unwrap();
+        let mut engine = AsyncEngine::from_process(process).await.unwrap();
+
+        // Cancelled after the first `info`.
+        let token = CancellationToken::new();
+        let bestmove = engine
+            .go_until(
+                ThinkParams::new().infinite(),
+                token.clone().cancelled_owned(),
+                |_| token.cancel(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(BestMoveParams::MakeMove("7g7f".to_string(), None), bestmove);
+        assert!(!engine.is_searching());
+
+        // The abandoned search is stopped before the next one starts.
+        {
+            use futures_util::FutureExt;
+
+            let mut search =
+                Box::pin(engine.go_until(ThinkParams::new(), future::pending(), |_| {}));
+            assert!(search.as_mut().now_or_never().is_none());
+        }
+        assert!(engine.is_searching());
+        let token = CancellationToken::new();
+        token.cancel();
+        let bestmove = engine
+            .go_until(ThinkParams::new(), token.cancelled_owned(), |_| {})
+            .await
+            .unwrap();
+        assert_eq!(BestMoveParams::MakeMove("3g3f".to_string(), None), bestmove);
+    }
+
+    #[cfg(feature = "async-std")]
+    #[test]
+    fn async_std_handshake() {
+        async_std::task::block_on(async {
+            let process = async_process::Command::new("sh")
+                .arg("-c")
+                .arg(SCRIPT)
+                .stdin(async_process::Stdio::piped())
+                .stdout(async_process::Stdio::piped())
+                .spawn()
+                .unwrap();
+
+            let mut engine = AsyncEngine::from_async_process::<crate::AsyncStdRuntime>(process)
+                .await
+                .expect("handshake failed");
+            search(&mut engine).await;
+        });
+    }
+
+    #[cfg(feature = "smol")]
+    #[test]
+    fn smol_handshake() {
+        smol::block_on(async {
+            let process = async_process::Command::new("sh")
+                .arg("-c")
+                .arg(SCRIPT)
+                .stdin(async_process::Stdio::piped())
+                .stdout(async_process::Stdio::piped())
+                .spawn()
+                .unwrap();
+
+            let mut engine = AsyncEngine::from_async_process::<crate::SmolRuntime>(process)
+                .await
+                .expect("handshake failed");
+            search(&mut engine).await;
+        });
+    }
+}
@@ -0,0 +1,277 @@
+use std::ffi::{OsStr, OsString};
+use std::io;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::Arc;
+
+use super::encoding::Encoding;
+use super::engine::{Engine, Timeouts};
+use super::framing::Framing;
+use super::transport::{ProcessTransport, Transport, DEFAULT_BUF_SIZE};
+use crate::error::Error;
+use crate::protocol::{Dialect, Usi};
+
+/// `EngineBuilder` configures how a USI engine process is spawned.
+///
+/// Many engines load evaluation files relative to the working directory,
+/// so they need to be launched from their own directory.
+///
+/// # Examples
+/// ```no_run
+/// use std::time::Duration;
+/// use usi::{EngineBuilder, Timeouts};
+///
+/// let engine = EngineBuilder::new("./YaneuraOu-by-gcc")
+///     .current_dir("/opt/engines/YaneuraOu")
+///     .env("OMP_NUM_THREADS", "4")
+///     .timeouts(Timeouts {
+///         readyok: Some(Duration::from_secs(300)),
+///         ..Timeouts::default()
+///     })
+///     .spawn()
+///     .unwrap();
+/// ```
+#[derive(Clone, Debug)]
+pub struct EngineBuilder {
+    program: OsString,
+    args: Vec<OsString>,
+    envs: Vec<(OsString, Option<OsString>)>,
+    env_clear: bool,
+    current_dir: Option<PathBuf>,
+    read_capacity: usize,
+    write_capacity: usize,
+    capture_stderr: bool,
+    encoding: Encoding,
+    framing: Framing,
+    timeouts: Timeouts,
+    max_restarts: u32,
+    dialect: Arc<dyn Dialect + Send + Sync>,
+}
+
+impl EngineBuilder {
+    pub fn new<S: AsRef<OsStr>>(program: S) -> Self {
+        EngineBuilder {
+            program: program.as_ref().to_os_string(),
+            args: Vec::new(),
+            envs: Vec::new(),
+            env_clear: false,
+            current_dir: None,
+            read_capacity: DEFAULT_BUF_SIZE,
+            write_capacity: DEFAULT_BUF_SIZE,
+            capture_stderr: true,
+            encoding: Encoding::default(),
+            framing: Framing::native(),
+            timeouts: Timeouts::default(),
+            max_restarts: 0,
+            dialect: Arc::new(Usi),
+        }
+    }
+
+    /// Adds a command-line argument passed to the engine.
+    #[must_use]
+    pub fn arg<S: AsRef<OsStr>>(mut self, arg: S) -> Self {
+        self.args.push(arg.as_ref().to_os_string());
+        self
+    }
+
+    /// Adds command-line arguments passed to the engine.
+    #[must_use]
+    pub fn args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        self.args
+            .extend(args.into_iter().map(|a| a.as_ref().to_os_string()));
+        self
+    }
+
+    /// Sets an environment variable of the engine.
+    #[must_use]
+    pub fn env<K: AsRef<OsStr>, V: AsRef<OsStr>>(mut self, key: K, value: V) -> Self {
+        self.envs.push((
+            key.as_ref().to_os_string(),
+            Some(value.as_ref().to_os_string()),
+        ));
+        self
+    }
+
+    /// Removes an environment variable inherited from the current process.
+    #[must_use]
+    pub fn env_remove<K: AsRef<OsStr>>(mut self, key: K) -> Self {
+        self.envs.push((key.as_ref().to_os_string(), None));
+        self
+    }
+
+    /// Clears the environment variables inherited from the current process.
+    #[must_use]
+    pub fn env_clear(mut self) -> Self {
+        self.env_clear = true;
+        self.envs.clear();
+        self
+    }
+
+    /// Sets the working directory of the engine.
+    #[must_use]
+    pub fn current_dir<P: Into<PathBuf>>(mut self, dir: P) -> Self {
+        self.current_dir = Some(dir.into());
+        self
+    }
+
+    /// Sets the buffer capacity of reading the engine's stdout. Defaults to 8 KiB.
+    #[must_use]
+    pub fn read_buffer_size(mut self, capacity: usize) -> Self {
+        self.read_capacity = capacity;
+        self
+    }
+
+    /// Sets the buffer capacity of writing to the engine's stdin. Defaults to 8 KiB.
+    /// Each command is flushed regardless of the capacity.
+    #[must_use]
+    pub fn write_buffer_size(mut self, capacity: usize) -> Self {
+        self.write_capacity = capacity;
+        self
+    }
+
+    /// Sets whether stderr of the engine is captured. Defaults to `true`.
+    /// Otherwise, stderr is inherited from the current process.
+    #[must_use]
+    pub fn capture_stderr(mut self, capture: bool) -> Self {
+        self.capture_stderr = capture;
+        self
+    }
+
+    /// Sets the encoding of the engine's stdout. Defaults to `Encoding::Auto`.
+    #[must_use]
+    pub fn encoding(mut self, encoding: Encoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// Sets how lines are split and terminated. Defaults to `Framing::native()`.
+    #[must_use]
+    pub fn framing(mut self, framing: Framing) -> Self {
+        self.framing = framing;
+        self
+    }
+
+    /// Sets the time limits of waiting for responses, including the handshake.
+    #[must_use]
+    pub fn timeouts(mut self, timeouts: Timeouts) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    /// Sets how many times the engine is restarted when it terminates unexpectedly.
+    /// Defaults to 0, which disables restarts.
+    #[must_use]
+    pub fn max_restarts(mut self, max_restarts: u32) -> Self {
+        self.max_restarts = max_restarts;
+        self
+    }
+
+    /// Sets the protocol spoken by the engine, e.g. `Uci` for chess engines. Defaults to `Usi`.
+    #[must_use]
+    pub fn dialect<D: Dialect + Send + Sync + 'static>(mut self, dialect: D) -> Self {
+        self.dialect = Arc::new(dialect);
+        self
+    }
+
+    /// Spawns the engine process and performs the handshake.
+    pub fn spawn(&self) -> Result<Engine, Error> {
+        let builder = self.clone();
+        let mut engine = Engine::start(
+            Box::new(move || Ok(Box::new(builder.spawn_transport()?) as Box<dyn Transport>)),
+            self.timeouts,
+            self.dialect.clone(),
+        )?;
+        engine.set_max_restarts(self.max_restarts)?;
+        // A relative `current_dir` is relative to the working directory of this process.
+        if let Ok(dir) = std::env::current_dir() {
+            engine.set_working_dir(match &self.current_dir {
+                Some(current_dir) => dir.join(current_dir),
+                None => dir,
+            });
+        }
+        Ok(engine)
+    }
+
+    /// Spawns the engine process without performing the handshake.
+    pub fn spawn_transport(&self) -> io::Result<ProcessTransport> {
+        let process = self
+            .command()
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(if self.capture_stderr {
+                Stdio::piped()
+            } else {
+                Stdio::inherit()
+            })
+            .spawn()?;
+
+        let mut transport =
+            ProcessTransport::with_capacity(process, self.read_capacity, self.write_capacity)?;
+        transport.set_encoding(self.encoding)?;
+        transport.set_framing(self.framing)?;
+        Ok(transport)
+    }
+
+    fn command(&self) -> Command {
+        let mut cmd = Command::new(&self.program);
+        cmd.args(&self.args);
+        if self.env_clear {
+            cmd.env_clear();
+        }
+        for (key, value) in &self.envs {
+            match value {
+                Some(value) => cmd.env(key, value),
+                None => cmd.env_remove(key),
+            };
+        }
+        if let Some(dir) = &self.current_dir {
+            cmd.current_dir(dir);
+        }
+        cmd
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spawn() {
+        let dir = std::env::temp_dir();
+        let script = "read l; echo \"id name $ENGINE_NAME $1 $(pwd)\"; echo usiok; \
+                      read l; echo readyok; read l";
+        let engine = EngineBuilder::new("sh")
+            .args(["-c", script, "sh"])
+            .arg("v1.0")
+            .env("ENGINE_NAME", "Toy")
+            .current_dir(&dir)
+            .read_buffer_size(16)
+            .write_buffer_size(16)
+            .max_restarts(1)
+            .spawn()
+            .unwrap();
+
+        let pwd = dir.canonicalize().unwrap();
+        assert_eq!(format!("Toy v1.0 {}", pwd.display()), engine.info().name());
+        assert_eq!(Some(dir.as_path()), engine.working_dir());
+        engine.shutdown().unwrap();
+    }
+
+    #[test]
+    fn encoding() {
+        let script = "read l; printf 'id name \\217\\253\\212\\373\\nusiok\\n'; \
+                      read l; echo readyok; read l";
+        let engine = EngineBuilder::new("sh")
+            .args(["-c", script])
+            .encoding(Encoding::ShiftJis)
+            .spawn()
+            .unwrap();
+
+        assert_eq!("将棋", engine.info().name());
+        engine.shutdown().unwrap();
+    }
+}
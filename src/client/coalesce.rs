@@ -0,0 +1,125 @@
+use std::time::{Duration, Instant};
+
+use crate::protocol::*;
+
+/// `multipv` and `depth` of an `info` command.
+type InfoKey = (i32, Option<i32>);
+
+/// `InfoCoalescer` batches `info` commands and keeps only the latest one per
+/// `multipv` and `depth` within a time window.
+///
+/// `info` commands without `multipv` are regarded as the first PV, and commands
+/// without `depth`, e.g. `currmove` or `nodes` only updates, share the same slot.
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+/// use usi::{EngineCommand, InfoCoalescer};
+///
+/// let mut coalescer = InfoCoalescer::new(Duration::from_secs(60));
+/// for line in ["info depth 1 pv 7g7f", "info depth 1 pv 2g2f", "info depth 2 pv 2g2f 8c8d"] {
+///     if let EngineCommand::Info(params) = EngineCommand::parse(line).unwrap() {
+///         assert!(coalescer.push(params).is_none());
+///     }
+/// }
+/// assert_eq!(2, coalescer.flush().len());
+/// ```
+#[derive(Clone, Debug)]
+pub struct InfoCoalescer {
+    window: Duration,
+    started: Option<Instant>,
+    pending: Vec<(InfoKey, Vec<InfoParams>)>,
+}
+
+impl InfoCoalescer {
+    pub fn new(window: Duration) -> Self {
+        InfoCoalescer {
+            window,
+            started: None,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Adds an `info` command. Returns the batch of pending commands
+    /// in the order received if the window has elapsed since the first pending one.
+    pub fn push(&mut self, info: Vec<InfoParams>) -> Option<Vec<Vec<InfoParams>>> {
+        let key = info_key(&info);
+        match self.pending.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, latest)) => *latest = info,
+            None => self.pending.push((key, info)),
+        }
+
+        let started = *self.started.get_or_insert_with(Instant::now);
+        if started.elapsed() >= self.window {
+            Some(self.flush())
+        } else {
+            None
+        }
+    }
+
+    /// Returns `true` if no command is pending.
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Takes the pending commands regardless of the window.
+    pub fn flush(&mut self) -> Vec<Vec<InfoParams>> {
+        self.started = None;
+        self.pending.drain(..).map(|(_, info)| info).collect()
+    }
+}
+
+fn info_key(info: &[InfoParams]) -> InfoKey {
+    let mut multipv = 1;
+    let mut depth = None;
+    for param in info {
+        match *param {
+            InfoParams::MultiPv(n) => multipv = n,
+            InfoParams::Depth(d, _) => depth = Some(d),
+            _ => {}
+        }
+    }
+    (multipv, depth)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(line: &str) -> Vec<InfoParams> {
+        match EngineCommand::parse(line).unwrap() {
+            EngineCommand::Info(params) => params,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn coalesce() {
+        let mut coalescer = InfoCoalescer::new(Duration::from_secs(60));
+        for line in [
+            "info depth 10 multipv 1 score cp 30 pv 7g7f",
+            "info depth 10 multipv 2 score cp 10 pv 2g2f",
+            "info nodes 1000",
+            "info depth 10 multipv 1 score cp 35 pv 7g7f 3c3d",
+            "info nodes 2000",
+        ] {
+            assert!(coalescer.push(info(line)).is_none());
+        }
+
+        assert_eq!(
+            vec![
+                info("info depth 10 multipv 1 score cp 35 pv 7g7f 3c3d"),
+                info("info depth 10 multipv 2 score cp 10 pv 2g2f"),
+                info("info nodes 2000"),
+            ],
+            coalescer.flush()
+        );
+        assert!(coalescer.is_empty());
+
+        let mut coalescer = InfoCoalescer::new(Duration::ZERO);
+        assert_eq!(
+            Some(vec![info("info depth 1")]),
+            coalescer.push(info("info depth 1"))
+        );
+    }
+}
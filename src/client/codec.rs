@@ -0,0 +1,133 @@
+use tokio_util::bytes::{BufMut, BytesMut};
+use tokio_util::codec::{Decoder, Encoder};
+
+use crate::error::Error;
+use crate::protocol::*;
+
+/// `UsiCodec` frames the connection to a USI engine into typed commands.
+///
+/// Commands sent to the engine are encoded as lines, and lines received from the engine
+/// are decoded into `EngineCommand`. Empty lines and lines which cannot be parsed are skipped,
+/// and both `\n` and `\r\n` terminators are accepted.
+///
+/// # Examples
+/// ```no_run
+/// use futures_util::{SinkExt, StreamExt};
+/// use std::process::Stdio;
+/// use tokio::process::Command;
+/// use tokio_util::codec::{FramedRead, FramedWrite};
+/// use usi::{GuiCommand, UsiCodec};
+///
+/// # async fn run() -> Result<(), usi::Error> {
+/// let mut child = Command::new("/path/to/usi_engine")
+///     .stdin(Stdio::piped())
+///     .stdout(Stdio::piped())
+///     .spawn()?;
+/// let mut writer = FramedWrite::new(child.stdin.take().unwrap(), UsiCodec::new());
+/// let mut reader = FramedRead::new(child.stdout.take().unwrap(), UsiCodec::new());
+///
+/// writer.send(GuiCommand::Usi).await?;
+/// while let Some(cmd) = reader.next().await {
+///     println!("{:?}", cmd?);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug, Default)]
+pub struct UsiCodec {
+    // Bytes already searched for a line terminator.
+    scanned: usize,
+}
+
+impl UsiCodec {
+    pub fn new() -> Self {
+        UsiCodec::default()
+    }
+}
+
+impl Decoder for UsiCodec {
+    type Item = EngineCommand;
+    type Error = Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<EngineCommand>, Error> {
+        loop {
+            let Some(pos) = src[self.scanned..].iter().position(|b| *b == b'\n') else {
+                self.scanned = src.len();
+                return Ok(None);
+            };
+            let line = src.split_to(self.scanned + pos + 1);
+            self.scanned = 0;
+
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\r', '\n']);
+            if line.trim().is_empty() {
+                continue;
+            }
+            // Ignore illegal commands.
+            if let Ok(cmd) = EngineCommand::parse(line) {
+                return Ok(Some(cmd));
+            }
+        }
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<EngineCommand>, Error> {
+        if let Some(cmd) = self.decode(src)? {
+            return Ok(Some(cmd));
+        }
+        // The last line without a terminator.
+        if src.is_empty() {
+            return Ok(None);
+        }
+        src.put_u8(b'\n');
+        let cmd = self.decode(src)?;
+        src.clear();
+        Ok(cmd)
+    }
+}
+
+impl Encoder<GuiCommand> for UsiCodec {
+    type Error = Error;
+
+    fn encode(&mut self, command: GuiCommand, dst: &mut BytesMut) -> Result<(), Error> {
+        let line = command.to_string();
+        dst.reserve(line.len() + 1);
+        dst.put_slice(line.as_bytes());
+        dst.put_u8(b'\n');
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode() {
+        let mut codec = UsiCodec::new();
+        let mut buf = BytesMut::from("id name Toy\r\n\nid foo bar\nusi");
+        assert_eq!(
+            Some(EngineCommand::Id(IdParams::Name("Toy".to_string()))),
+            codec.decode(&mut buf).unwrap()
+        );
+        assert_eq!(None, codec.decode(&mut buf).unwrap());
+        assert_eq!(b"usi", &buf[..]);
+
+        buf.extend_from_slice(b"ok\nreadyok");
+        assert_eq!(Some(EngineCommand::UsiOk), codec.decode(&mut buf).unwrap());
+        assert_eq!(None, codec.decode(&mut buf).unwrap());
+        assert_eq!(
+            Some(EngineCommand::ReadyOk),
+            codec.decode_eof(&mut buf).unwrap()
+        );
+        assert_eq!(None, codec.decode_eof(&mut buf).unwrap());
+    }
+
+    #[test]
+    fn encode() {
+        let mut codec = UsiCodec::new();
+        let mut buf = BytesMut::new();
+        codec.encode(GuiCommand::Usi, &mut buf).unwrap();
+        codec.encode(GuiCommand::IsReady, &mut buf).unwrap();
+        assert_eq!(b"usi\nisready\n", &buf[..]);
+    }
+}
@@ -0,0 +1,160 @@
+use std::thread;
+
+use super::analyze::{AnalysisLimits, AnalysisReport};
+use super::engine::Engine;
+use crate::error::Error;
+
+/// The analyses of a position by the two engines of `EngineComparison`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PositionDiff {
+    /// The SFEN of the position, optionally followed by `moves`.
+    pub position: String,
+    pub first: AnalysisReport,
+    pub second: AnalysisReport,
+}
+
+impl PositionDiff {
+    /// Returns `true` if both engines chose the same move.
+    pub fn same_move(&self) -> bool {
+        self.first.best_move() == self.second.best_move()
+    }
+
+    /// Returns the centipawn score of the second engine minus the first,
+    /// or `None` unless both scores are centipawns.
+    pub fn score_diff(&self) -> Option<i32> {
+        let (first, second) = (self.first.score?, self.second.score?);
+        (!first.is_mate() && !second.is_mate()).then(|| second.value - first.value)
+    }
+
+    /// Returns `true` if the engines chose the same move with scores within `tolerance`
+    /// centipawns, or the same mate score.
+    pub fn agrees(&self, tolerance: i32) -> bool {
+        self.same_move()
+            && match self.score_diff() {
+                Some(diff) => diff.abs() <= tolerance,
+                None => self.first.score == self.second.score,
+            }
+    }
+}
+
+/// `EngineComparison` is a structured diff of the analyses of positions by two engines,
+/// e.g. two versions of an engine, or two engines for a second opinion.
+///
+/// # Examples
+/// ```no_run
+/// use usi::{AnalysisLimits, Engine, EngineComparison};
+///
+/// let mut old = Engine::spawn("/path/to/old_engine").unwrap();
+/// let mut new = Engine::spawn("/path/to/new_engine").unwrap();
+/// let positions = ["lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL b - 1".to_string()];
+/// let limits = AnalysisLimits::new().depth(15);
+/// let comparison = EngineComparison::run(&mut old, &mut new, &positions, limits).unwrap();
+/// println!("agreement: {:.1}%", comparison.agreement_rate(50) * 100.0);
+/// for diff in comparison.disagreements(50) {
+///     println!("{}: {:?} vs {:?}", diff.position, diff.first.best_move(), diff.second.best_move());
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EngineComparison {
+    /// The name of the first engine from `id name`.
+    pub first_engine: String,
+    /// The name of the second engine from `id name`.
+    pub second_engine: String,
+    pub positions: Vec<PositionDiff>,
+}
+
+impl EngineComparison {
+    /// Analyzes `positions` with both engines within `limits`, and compares the results.
+    ///
+    /// The engines analyze the positions in parallel, each on a thread.
+    pub fn run(
+        first: &mut Engine,
+        second: &mut Engine,
+        positions: &[String],
+        limits: AnalysisLimits,
+    ) -> Result<Self, Error> {
+        let analyze_all = |engine: &mut Engine| -> Result<Vec<AnalysisReport>, Error> {
+            positions
+                .iter()
+                .map(|position| engine.analyze(position, limits))
+                .collect()
+        };
+        let (first_reports, second_reports) = thread::scope(|s| {
+            let handle = s.spawn(|| analyze_all(second));
+            let first_reports = analyze_all(first);
+            (first_reports, handle.join().unwrap())
+        });
+
+        Ok(EngineComparison {
+            first_engine: first.info().name().to_string(),
+            second_engine: second.info().name().to_string(),
+            positions: positions
+                .iter()
+                .zip(first_reports?.into_iter().zip(second_reports?))
+                .map(|(position, (first, second))| PositionDiff {
+                    position: position.clone(),
+                    first,
+                    second,
+                })
+                .collect(),
+        })
+    }
+
+    /// Returns the positions the engines don't agree on within `tolerance` centipawns.
+    pub fn disagreements(&self, tolerance: i32) -> impl Iterator<Item = &PositionDiff> {
+        self.positions
+            .iter()
+            .filter(move |diff| !diff.agrees(tolerance))
+    }
+
+    /// Returns the fraction of positions the engines agree on within `tolerance` centipawns.
+    pub fn agreement_rate(&self, tolerance: i32) -> f64 {
+        if self.positions.is_empty() {
+            return 0.0;
+        }
+        let agreed = self.positions.len() - self.disagreements(tolerance).count();
+        agreed as f64 / self.positions.len() as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::IoTransport;
+
+    #[test]
+    fn run() {
+        let first = "id name First\nusiok\nreadyok\n\
+                     info depth 1 score cp 50 pv 7g7f\nbestmove 7g7f\n\
+                     info depth 1 score cp 20 pv 3c3d\nbestmove 3c3d\n\
+                     info depth 1 score mate 3 pv G*5b\nbestmove G*5b\n";
+        let second = "id name Second\nusiok\nreadyok\n\
+                      info depth 1 score cp 80 pv 7g7f\nbestmove 7g7f\n\
+                      info depth 1 score cp 20 pv 8c8d\nbestmove 8c8d\n\
+                      info depth 1 score mate 3 pv G*5b\nbestmove G*5b\n";
+        let mut first = Engine::connect(IoTransport::new(first.as_bytes(), Vec::new())).unwrap();
+        let mut second = Engine::connect(IoTransport::new(second.as_bytes(), Vec::new())).unwrap();
+        let positions = ["a", "b", "c"].map(String::from);
+        let comparison = EngineComparison::run(
+            &mut first,
+            &mut second,
+            &positions,
+            AnalysisLimits::new().depth(1),
+        )
+        .unwrap();
+
+        assert_eq!("First", comparison.first_engine);
+        assert_eq!("Second", comparison.second_engine);
+        assert_eq!(Some(30), comparison.positions[0].score_diff());
+        assert!(!comparison.positions[1].same_move());
+        assert_eq!(None, comparison.positions[2].score_diff());
+        assert!(comparison.positions[2].agrees(0));
+
+        let disagreements: Vec<&str> = comparison
+            .disagreements(10)
+            .map(|diff| diff.position.as_str())
+            .collect();
+        assert_eq!(vec!["a", "b"], disagreements);
+        assert_eq!(2.0 / 3.0, comparison.agreement_rate(50));
+    }
+}
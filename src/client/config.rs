@@ -0,0 +1,170 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::engine::Engine;
+use super::options::EngineOptions;
+use crate::error::Error;
+use crate::protocol::*;
+
+/// `EngineConfig` is a saved set of option values chosen for an engine.
+///
+/// Only the values differing from the defaults declared by the engine are kept,
+/// so a configuration stays valid when the engine changes its defaults.
+///
+/// # Examples
+/// ```no_run
+/// use usi::{Engine, EngineConfig};
+///
+/// let mut engine = Engine::spawn("/path/to/usi_engine").unwrap();
+/// engine.set_option("USI_Hash", "1024").unwrap();
+/// EngineConfig::from_engine(&engine, "/path/to/usi_engine")
+///     .save("engine.json")
+///     .unwrap();
+///
+/// // Later on, start the engine with the same settings.
+/// let engine = EngineConfig::load("engine.json").unwrap().spawn().unwrap();
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EngineConfig {
+    /// The engine name.
+    pub name: String,
+    /// The path to the engine executable.
+    pub path: PathBuf,
+    /// The chosen option values by option name.
+    #[serde(default)]
+    pub options: BTreeMap<String, String>,
+}
+
+impl EngineConfig {
+    /// Creates an empty configuration of the engine at `path`.
+    pub fn new<S: Into<String>, P: Into<PathBuf>>(name: S, path: P) -> Self {
+        EngineConfig {
+            name: name.into(),
+            path: path.into(),
+            options: BTreeMap::new(),
+        }
+    }
+
+    /// Captures the options of `engine` changed by `setoption`.
+    pub fn from_engine<P: Into<PathBuf>>(engine: &Engine, path: P) -> Self {
+        let mut config = EngineConfig::new(engine.info().name(), path);
+        for option in engine.options() {
+            if let (true, Some(value)) = (option.is_modified(), option.value()) {
+                if !matches!(option.kind(), OptionKind::Button { .. }) {
+                    config
+                        .options
+                        .insert(option.name().to_string(), value.to_string());
+                }
+            }
+        }
+        config
+    }
+
+    /// Sets the value of an option.
+    #[must_use]
+    pub fn option<S: Into<String>, V: Into<String>>(mut self, name: S, value: V) -> Self {
+        self.options.insert(name.into(), value.into());
+        self
+    }
+
+    /// Reads a configuration from a JSON file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        Self::from_json(&fs::read_to_string(path)?)
+    }
+
+    /// Writes the configuration to a JSON file.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), Error> {
+        fs::write(path, self.to_json()?)?;
+        Ok(())
+    }
+
+    /// Parses a configuration from JSON.
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// Returns the configuration as pretty-printed JSON.
+    pub fn to_json(&self) -> Result<String, Error> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Returns `setoption` parameters for the values differing from the defaults in `options`,
+    /// in the order the engine declared them.
+    ///
+    /// Values of options the engine no longer declares are skipped.
+    pub fn changed_options(&self, options: &EngineOptions) -> Vec<SetOptionParams> {
+        options
+            .iter()
+            .filter_map(|option| {
+                let value = self.options.get(option.name())?;
+                if option.default_value().as_ref() == Some(value) {
+                    return None;
+                }
+                Some(SetOptionParams::new(option.name(), value.clone()))
+            })
+            .collect()
+    }
+
+    /// Sends the values differing from the defaults to `engine`.
+    ///
+    /// Returns `Error::InvalidOptionValue` if a saved value is no longer valid for the option.
+    pub fn apply(&self, engine: &mut Engine) -> Result<(), Error> {
+        for params in self.changed_options(engine.options()) {
+            engine.set_option(&params.name, params.value.as_deref().unwrap_or_default())?;
+        }
+        Ok(())
+    }
+
+    /// Spawns the engine at `path` and applies the configuration.
+    pub fn spawn(&self) -> Result<Engine, Error> {
+        let mut engine = Engine::spawn(&self.path)?;
+        self.apply(&mut engine)?;
+        Ok(engine)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::ToyEngine;
+    use crate::{InProcessTransport, OptionRegistry};
+
+    fn config_engine() -> ToyEngine {
+        ToyEngine::new().name("Config").options(
+            OptionRegistry::new()
+                .spin("Threads", 1, 1, 64)
+                .check("OwnBook", true),
+        )
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut engine = Engine::connect(InProcessTransport::spawn(config_engine())).unwrap();
+        engine.set_option("Threads", "4").unwrap();
+        engine.set_option("OwnBook", "true").unwrap();
+
+        let config = EngineConfig::from_engine(&engine, "engine");
+        assert_eq!(
+            EngineConfig::new("Config", "engine").option("Threads", "4"),
+            config
+        );
+
+        let path = std::env::temp_dir().join(format!("usi-config-{}.json", std::process::id()));
+        config.save(&path).unwrap();
+        let loaded = EngineConfig::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(config, loaded);
+
+        let mut engine = Engine::connect(InProcessTransport::spawn(config_engine())).unwrap();
+        let loaded = loaded.option("OwnBook", "true").option("Removed", "1");
+        assert_eq!(
+            vec![SetOptionParams::new("Threads", "4")],
+            loaded.changed_options(engine.options())
+        );
+        loaded.apply(&mut engine).unwrap();
+        assert_eq!(Some("4"), engine.options().value("Threads"));
+    }
+}
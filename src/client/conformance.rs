@@ -0,0 +1,484 @@
+use std::fmt;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::builder::EngineBuilder;
+use super::engine::Engine;
+use super::mate::MateResult;
+use crate::board::{Board, Move};
+use crate::error::Error;
+use crate::protocol::*;
+
+/// A position where `G*5b` is the only mate in 1.
+const MATE_IN_ONE: &str = "4k4/9/4P4/9/9/9/9/9/4K4 b G 1";
+const MATE_MOVE: &str = "G*5b";
+
+/// A scenario of the protocol checked by `ConformanceSuite`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Scenario {
+    /// `usi` is answered with `id name`, `id author` and `usiok`, and `isready` with `readyok`.
+    Handshake,
+    /// The declared defaults are valid, and setting every option to its default is accepted.
+    Options,
+    /// `go infinite` is ended by `stop` with a legal `bestmove` within `Timeouts::bestmove`.
+    Stop,
+    /// A ponder search doesn't end before `ponderhit`, and ends with `bestmove` after it.
+    /// Skipped unless the engine declares `USI_Ponder` and sends a ponder move.
+    Ponder,
+    /// `go mate` finds a mate in 1. Skipped if the engine answers `checkmate notimplemented`.
+    MateSearch,
+}
+
+impl Scenario {
+    /// All the scenarios in the order they are run.
+    pub const ALL: [Scenario; 5] = [
+        Scenario::Handshake,
+        Scenario::Options,
+        Scenario::Stop,
+        Scenario::Ponder,
+        Scenario::MateSearch,
+    ];
+
+    /// Returns the name of the scenario in snake case, e.g. `mate_search`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Scenario::Handshake => "handshake",
+            Scenario::Options => "options",
+            Scenario::Stop => "stop",
+            Scenario::Ponder => "ponder",
+            Scenario::MateSearch => "mate_search",
+        }
+    }
+}
+
+impl fmt::Display for Scenario {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// The outcome of a scenario.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum ConformanceOutcome {
+    Passed,
+    /// The engine violated the scenario, for the reason.
+    Failed(String),
+    /// The scenario doesn't apply to the engine, for the reason.
+    Skipped(String),
+}
+
+/// The result of a scenario in `ConformanceReport`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ScenarioResult {
+    pub scenario: Scenario,
+    pub outcome: ConformanceOutcome,
+    /// The wall clock time of the scenario including the handshake.
+    /// Serialized in milliseconds.
+    #[cfg_attr(feature = "serde", serde(with = "crate::analysis::millis::required"))]
+    pub elapsed: Duration,
+}
+
+/// The result of `ConformanceSuite::run`.
+///
+/// It is displayed as a table of the scenarios, and with the `serde` feature, it can be
+/// serialized to vet engines in scripts.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ConformanceReport {
+    /// The name of the engine from `id name`, empty if the handshake failed.
+    pub engine_name: String,
+    pub results: Vec<ScenarioResult>,
+}
+
+impl ConformanceReport {
+    /// Returns `true` if no scenario failed.
+    pub fn passed(&self) -> bool {
+        self.failures().next().is_none()
+    }
+
+    /// Returns the results of the failed scenarios.
+    pub fn failures(&self) -> impl Iterator<Item = &ScenarioResult> {
+        self.results
+            .iter()
+            .filter(|r| matches!(r.outcome, ConformanceOutcome::Failed(_)))
+    }
+
+    /// Returns the outcome of `scenario`, or `None` if it wasn't run.
+    pub fn outcome(&self, scenario: Scenario) -> Option<&ConformanceOutcome> {
+        self.results
+            .iter()
+            .find(|r| r.scenario == scenario)
+            .map(|r| &r.outcome)
+    }
+
+    /// Returns the report as JSON.
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> Result<String, Error> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    /// Parses a report from JSON.
+    #[cfg(feature = "serde")]
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        Ok(serde_json::from_str(json)?)
+    }
+}
+
+impl fmt::Display for ConformanceReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Engine: {}", self.engine_name)?;
+        for result in &self.results {
+            let (status, reason) = match &result.outcome {
+                ConformanceOutcome::Passed => ("passed", ""),
+                ConformanceOutcome::Failed(reason) => ("FAILED", reason.as_str()),
+                ConformanceOutcome::Skipped(reason) => ("skipped", reason.as_str()),
+            };
+            let line = format!(
+                "{:<12} {:<8} {:>6}ms {}",
+                result.scenario.name(),
+                status,
+                result.elapsed.as_millis(),
+                reason
+            );
+            writeln!(f, "{}", line.trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+type EngineFactory = Box<dyn FnMut() -> Result<Engine, Error>>;
+
+/// `ConformanceSuite` drives an engine through scenarios of the protocol and reports which
+/// of them it passes, to vet engines before they are used with GUIs.
+///
+/// Each scenario runs on a new engine, so a failure doesn't affect the following scenarios.
+///
+/// # Examples
+/// ```no_run
+/// use usi::{ConformanceSuite, EngineBuilder};
+///
+/// let report = ConformanceSuite::from_builder(EngineBuilder::new("/path/to/usi_engine")).run();
+/// println!("{report}");
+/// assert!(report.passed());
+/// ```
+pub struct ConformanceSuite {
+    factory: EngineFactory,
+    scenarios: Vec<Scenario>,
+    search_time: Duration,
+}
+
+impl fmt::Debug for ConformanceSuite {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ConformanceSuite")
+            .field("scenarios", &self.scenarios)
+            .field("search_time", &self.search_time)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ConformanceSuite {
+    /// Creates a suite running all the scenarios on the engines connected by `factory`.
+    pub fn new<F>(factory: F) -> Self
+    where
+        F: FnMut() -> Result<Engine, Error> + 'static,
+    {
+        ConformanceSuite {
+            factory: Box::new(factory),
+            scenarios: Scenario::ALL.to_vec(),
+            search_time: Duration::from_millis(500),
+        }
+    }
+
+    /// Creates a suite running all the scenarios on the engines spawned by `builder`.
+    pub fn from_builder(builder: EngineBuilder) -> Self {
+        ConformanceSuite::new(move || builder.spawn())
+    }
+
+    /// Sets the scenarios to run, in the order of `Scenario::ALL`.
+    #[must_use]
+    pub fn scenarios<I: IntoIterator<Item = Scenario>>(mut self, scenarios: I) -> Self {
+        let scenarios = scenarios.into_iter().collect::<Vec<_>>();
+        self.scenarios = Scenario::ALL
+            .into_iter()
+            .filter(|s| scenarios.contains(s))
+            .collect();
+        self
+    }
+
+    /// Sets how long the engine searches in each scenario. Defaults to 500 milliseconds.
+    #[must_use]
+    pub fn search_time(mut self, t: Duration) -> Self {
+        self.search_time = t;
+        self
+    }
+
+    /// Runs the scenarios.
+    pub fn run(&mut self) -> ConformanceReport {
+        let mut report = ConformanceReport {
+            engine_name: String::new(),
+            results: Vec::new(),
+        };
+        for scenario in self.scenarios.clone() {
+            let start = Instant::now();
+            let outcome = match (self.factory)() {
+                Ok(mut engine) => {
+                    if report.engine_name.is_empty() {
+                        report.engine_name = engine.info().name().to_string();
+                    }
+                    let outcome = self
+                        .run_scenario(&mut engine, scenario)
+                        .unwrap_or_else(|e| ConformanceOutcome::Failed(e.to_string()));
+                    // The outcome is decided; a failure of `quit` is not a part of it.
+                    let _ = engine.shutdown();
+                    outcome
+                }
+                Err(e) if scenario == Scenario::Handshake => {
+                    ConformanceOutcome::Failed(e.to_string())
+                }
+                Err(e) => ConformanceOutcome::Failed(format!("handshake failed: {e}")),
+            };
+            report.results.push(ScenarioResult {
+                scenario,
+                outcome,
+                elapsed: start.elapsed(),
+            });
+        }
+        report
+    }
+
+    fn run_scenario(
+        &self,
+        engine: &mut Engine,
+        scenario: Scenario,
+    ) -> Result<ConformanceOutcome, Error> {
+        match scenario {
+            Scenario::Handshake => handshake(engine),
+            Scenario::Options => options(engine),
+            Scenario::Stop => self.stop(engine),
+            Scenario::Ponder => self.ponder(engine),
+            Scenario::MateSearch => self.mate_search(engine),
+        }
+    }
+
+    fn stop(&self, engine: &mut Engine) -> Result<ConformanceOutcome, Error> {
+        new_game(engine, "")?;
+        engine.send(&GuiCommand::Go(ThinkParams::new().infinite()))?;
+        thread::sleep(self.search_time);
+        let bestmove = engine.stop()?;
+        Ok(check_bestmove(&Board::startpos(), &bestmove))
+    }
+
+    fn ponder(&self, engine: &mut Engine) -> Result<ConformanceOutcome, Error> {
+        if !engine.supports_ponder() {
+            return Ok(ConformanceOutcome::Skipped(String::from(
+                "the engine doesn't declare USI_Ponder",
+            )));
+        }
+        engine.set_option("USI_Ponder", "true")?;
+        new_game(engine, "")?;
+        let params = ThinkParams::new().byoyomi(self.search_time);
+        engine.send(&GuiCommand::Go(params.clone()))?;
+        let Some(bestmove) = self.wait_bestmove(engine, self.search_time)? else {
+            return Ok(ConformanceOutcome::Failed(String::from(
+                "no bestmove within byoyomi",
+            )));
+        };
+        let (mv, ponder) = match bestmove {
+            BestMoveParams::MakeMove(mv, Some(ponder)) => (mv, ponder),
+            _ => {
+                return Ok(ConformanceOutcome::Skipped(String::from(
+                    "the engine sent no ponder move",
+                )))
+            }
+        };
+
+        let moves = format!("{mv} {ponder}");
+        engine.send(&GuiCommand::Position(format!(
+            "{STARTPOS_SFEN} moves {moves}"
+        )))?;
+        engine.send(&GuiCommand::Go(params.ponder()))?;
+        if self.wait_bestmove(engine, self.search_time)?.is_some() {
+            return Ok(ConformanceOutcome::Failed(String::from(
+                "bestmove was sent before ponderhit",
+            )));
+        }
+        engine.send(&GuiCommand::Ponderhit)?;
+        let Some(bestmove) = self.wait_bestmove(engine, self.search_time)? else {
+            return Ok(ConformanceOutcome::Failed(String::from(
+                "no bestmove within byoyomi after ponderhit",
+            )));
+        };
+        let board = Board::from_position(&format!("{STARTPOS_SFEN} moves {moves}"))?;
+        Ok(check_bestmove(&board, &bestmove))
+    }
+
+    fn mate_search(&self, engine: &mut Engine) -> Result<ConformanceOutcome, Error> {
+        new_game(engine, MATE_IN_ONE)?;
+        let limit = MateParam::Timeout(self.search_time);
+        Ok(match engine.solve_mate(MATE_IN_ONE, limit)? {
+            MateResult::Mate(moves) if moves.first().map(String::as_str) == Some(MATE_MOVE) => {
+                ConformanceOutcome::Passed
+            }
+            MateResult::Mate(moves) => {
+                ConformanceOutcome::Failed(format!("found a wrong mate: {}", moves.join(" ")))
+            }
+            MateResult::NoMate => {
+                ConformanceOutcome::Failed(String::from("reported no mate in a mate in 1"))
+            }
+            MateResult::Timeout => {
+                ConformanceOutcome::Failed(String::from("couldn't find a mate in 1"))
+            }
+            MateResult::Unsupported => {
+                ConformanceOutcome::Skipped(String::from("the engine doesn't support go mate"))
+            }
+        })
+    }
+
+    /// Waits for `bestmove` within `limit` and the grace period of `Timeouts::bestmove`.
+    /// Returns `None` if it is not received in time.
+    fn wait_bestmove(
+        &self,
+        engine: &mut Engine,
+        limit: Duration,
+    ) -> Result<Option<BestMoveParams>, Error> {
+        let deadline = Instant::now() + limit + engine.timeouts().bestmove.unwrap_or_default();
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Ok(None);
+            }
+            if let Some(output) = engine.recv_timeout(remaining)? {
+                if let Some(EngineCommand::BestMove(params)) = output.response() {
+                    return Ok(Some(params.clone()));
+                }
+            }
+        }
+    }
+}
+
+fn handshake(engine: &mut Engine) -> Result<ConformanceOutcome, Error> {
+    if engine.info().name().is_empty() {
+        return Ok(ConformanceOutcome::Failed(String::from(
+            "id name is missing",
+        )));
+    }
+    if engine.info().author().is_empty() {
+        return Ok(ConformanceOutcome::Failed(String::from(
+            "id author is missing",
+        )));
+    }
+    engine.prepare()?;
+    Ok(ConformanceOutcome::Passed)
+}
+
+fn options(engine: &mut Engine) -> Result<ConformanceOutcome, Error> {
+    if let Some(option) = engine.options().invalid_defaults().next() {
+        return Ok(ConformanceOutcome::Failed(format!(
+            "the default of option {} is invalid",
+            option.name()
+        )));
+    }
+    let defaults = engine
+        .options()
+        .iter()
+        .filter_map(|option| Some((option.name().to_string(), option.default_value()?)))
+        .collect::<Vec<_>>();
+    for (name, value) in defaults {
+        engine.set_option(&name, &value)?;
+    }
+    engine.prepare()?;
+    Ok(ConformanceOutcome::Passed)
+}
+
+/// Starts a new game from `sfen`, or the initial position if it is empty.
+fn new_game(engine: &mut Engine, sfen: &str) -> Result<(), Error> {
+    engine.prepare()?;
+    engine.send(&GuiCommand::UsiNewGame)?;
+    let sfen = if sfen.is_empty() { STARTPOS_SFEN } else { sfen };
+    engine.send(&GuiCommand::Position(sfen.to_string()))
+}
+
+/// Checks that `bestmove` can be played on `board`.
+fn check_bestmove(board: &Board, bestmove: &BestMoveParams) -> ConformanceOutcome {
+    let BestMoveParams::MakeMove(mv, _) = bestmove else {
+        return ConformanceOutcome::Passed;
+    };
+    match mv.parse::<Move>() {
+        Ok(parsed) if board.clone().make_move(&parsed).is_ok() => ConformanceOutcome::Passed,
+        _ => ConformanceOutcome::Failed(format!("bestmove {mv} can't be played")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::{InProcessTransport, Timeouts};
+    use crate::server::OptionRegistry;
+    use crate::test_util::ToyEngine;
+
+    fn run(hang: bool) -> ConformanceReport {
+        ConformanceSuite::new(move || {
+            // Answers `ponderhit` with the reply to `3c3d` pondered on.
+            let toy = ToyEngine::new()
+                .options(OptionRegistry::new().with_usi_ponder())
+                .bestmove("7g7f", Some("3c3d"))
+                .ponderhit_bestmove("2g2f", Some("3c3d"))
+                .mate(&[MATE_MOVE]);
+            let toy = if hang { toy.hang() } else { toy };
+            let mut engine = Engine::connect(InProcessTransport::spawn(toy))?;
+            engine.set_timeouts(Timeouts {
+                bestmove: Some(Duration::from_millis(50)),
+                ..Timeouts::default()
+            });
+            Ok(engine)
+        })
+        .search_time(Duration::from_millis(20))
+        .run()
+    }
+
+    #[test]
+    fn passed() {
+        let report = run(false);
+        assert!(report.passed(), "{report}");
+        assert_eq!("Toy", report.engine_name);
+        assert_eq!(5, report.results.len());
+    }
+
+    #[test]
+    fn failed() {
+        let report = run(true);
+        assert!(!report.passed());
+        assert!(matches!(
+            report.outcome(Scenario::Stop),
+            Some(ConformanceOutcome::Failed(_))
+        ));
+        assert_eq!(
+            Some(&ConformanceOutcome::Passed),
+            report.outcome(Scenario::Ponder)
+        );
+        assert!(report.to_string().contains("stop         FAILED"));
+
+        let report = ConformanceSuite::new(|| Err(Error::EngineClosed))
+            .scenarios([Scenario::Stop, Scenario::Handshake])
+            .run();
+        assert_eq!(
+            vec![Scenario::Handshake, Scenario::Stop],
+            report
+                .results
+                .iter()
+                .map(|r| r.scenario)
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            Some(&ConformanceOutcome::Failed(String::from(
+                "handshake failed: the engine closed the connection"
+            ))),
+            report.outcome(Scenario::Stop)
+        );
+    }
+}
@@ -0,0 +1,269 @@
+use std::sync::mpsc::{Receiver, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::engine::Engine;
+use crate::analysis::{InfoAggregator, SearchSnapshot};
+use crate::error::Error;
+use crate::protocol::*;
+
+/// `ContinuousAnalysis` analyzes positions with `go infinite` for kibitzing,
+/// delivering an aggregated snapshot of the search at a fixed interval.
+///
+/// Switching to another position stops the current search and waits for its `bestmove`
+/// before the new position is searched, so the snapshots never mix the two positions.
+/// Like `SearchHandle`, it borrows the engine, so the analysis must be finished by
+/// `ContinuousAnalysis::stop` before other commands are sent. Dropping it stops the search
+/// the same way, discarding the `bestmove`.
+///
+/// # Examples
+/// ```no_run
+/// use std::sync::mpsc;
+/// use std::time::Duration;
+/// use usi::Engine;
+///
+/// let mut engine = Engine::spawn("/path/to/usi_engine").unwrap();
+/// let (positions, receiver) = mpsc::channel();
+/// positions
+///     .send("lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL b - 1".to_string())
+///     .unwrap();
+/// // Send positions from the UI thread as the user navigates, and drop the sender to finish.
+/// # drop(positions);
+///
+/// let mut analysis = engine.analyze_continuous(Duration::from_millis(500));
+/// analysis
+///     .run(&receiver, |position, snapshot| {
+///         println!("{position}: {:?} {:?}", snapshot.best_score(), snapshot.best_moves());
+///     })
+///     .unwrap();
+/// ```
+#[derive(Debug)]
+pub struct ContinuousAnalysis<'a> {
+    engine: &'a mut Engine,
+    interval: Duration,
+    position: Option<String>,
+    aggregator: InfoAggregator,
+    last_delivery: Instant,
+}
+
+impl<'a> ContinuousAnalysis<'a> {
+    pub(crate) fn new(engine: &'a mut Engine, interval: Duration) -> Self {
+        ContinuousAnalysis {
+            engine,
+            interval,
+            position: None,
+            aggregator: InfoAggregator::new(),
+            last_delivery: Instant::now(),
+        }
+    }
+
+    /// Returns the position being analyzed.
+    pub fn position(&self) -> Option<&str> {
+        self.position.as_deref()
+    }
+
+    /// Returns the latest state of the search of the current position.
+    pub fn snapshot(&self) -> &SearchSnapshot {
+        self.aggregator.snapshot()
+    }
+
+    /// Stops the current search, waits for its `bestmove`, and starts analyzing `position`,
+    /// which is the SFEN optionally followed by `moves` as in `GuiCommand::Position`.
+    pub fn set_position(&mut self, position: &str) -> Result<(), Error> {
+        self.halt()?;
+        self.aggregator.reset();
+        self.engine
+            .send(&GuiCommand::Position(position.to_string()))?;
+        self.engine
+            .send(&GuiCommand::Go(ThinkParams::new().infinite()))?;
+        self.position = Some(position.to_string());
+        self.last_delivery = Instant::now();
+        Ok(())
+    }
+
+    /// Aggregates the `info` output until the interval has passed since the previous
+    /// snapshot, and returns the snapshot.
+    ///
+    /// If the engine finished the search by itself, e.g. on finding a mate,
+    /// the last snapshot is kept. Transports without `Transport::set_read_timeout`
+    /// support block until the next line.
+    pub fn poll(&mut self) -> Result<&SearchSnapshot, Error> {
+        let deadline = self.last_delivery + self.interval;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                break;
+            }
+            if !self.engine.is_searching() {
+                thread::sleep(remaining);
+                break;
+            }
+            let Some(output) = self.engine.recv_timeout(remaining)? else {
+                break;
+            };
+            if let Some(EngineCommand::Info(params)) = output.response() {
+                self.aggregator.push(params);
+            }
+        }
+        self.last_delivery = Instant::now();
+        Ok(self.aggregator.snapshot())
+    }
+
+    /// Analyzes the positions received from `positions`, calling `callback` with the position
+    /// and its snapshot every interval.
+    ///
+    /// When several positions were received within an interval, only the latest is analyzed.
+    /// Returns after the senders of `positions` are dropped and the search is stopped.
+    pub fn run<F>(&mut self, positions: &Receiver<String>, mut callback: F) -> Result<(), Error>
+    where
+        F: FnMut(&str, &SearchSnapshot),
+    {
+        loop {
+            let mut latest = None;
+            loop {
+                match positions.try_recv() {
+                    Ok(position) => latest = Some(position),
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => return self.halt().map(|_| ()),
+                }
+            }
+            if latest.is_none() && self.position.is_none() {
+                match positions.recv() {
+                    Ok(position) => latest = Some(position),
+                    Err(_) => return Ok(()),
+                }
+            }
+            if let Some(position) = latest {
+                self.set_position(&position)?;
+            }
+
+            self.poll()?;
+            if let Some(position) = &self.position {
+                callback(position, self.aggregator.snapshot());
+            }
+        }
+    }
+
+    /// Stops the search, and returns its `bestmove` unless the engine had finished it already.
+    pub fn stop(mut self) -> Result<Option<BestMoveParams>, Error> {
+        self.halt()
+    }
+
+    fn halt(&mut self) -> Result<Option<BestMoveParams>, Error> {
+        if self.engine.is_searching() {
+            self.engine.stop().map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl Drop for ContinuousAnalysis<'_> {
+    fn drop(&mut self) {
+        let _ = self.halt();
+    }
+}
+
+impl Engine {
+    /// Starts continuous analysis delivering snapshots every `interval`.
+    /// See `ContinuousAnalysis`.
+    pub fn analyze_continuous(&mut self, interval: Duration) -> ContinuousAnalysis<'_> {
+        ContinuousAnalysis::new(self, interval)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{InProcessTransport, InfoSender, Responder, StopToken, UsiEngine};
+    use std::sync::mpsc;
+
+    const SFEN: &str = "lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL b - 1";
+
+    /// Reports the last move of the position as the PV until stopped.
+    #[derive(Default)]
+    struct EchoEngine {
+        position: String,
+    }
+
+    impl UsiEngine for EchoEngine {
+        fn name(&self) -> String {
+            "Echo".to_string()
+        }
+
+        fn author(&self) -> String {
+            "usi-rs".to_string()
+        }
+
+        fn set_position(&mut self, sfen: &str) {
+            self.position = sfen.to_string();
+        }
+
+        fn go(
+            &mut self,
+            _params: ThinkParams,
+            responder: &Responder,
+            info: InfoSender,
+            stop: StopToken,
+        ) {
+            let mv = self.position.split(' ').next_back().unwrap().to_string();
+            let responder = responder.clone();
+            thread::spawn(move || {
+                let pv = InfoParams::Pv(vec![mv.clone()]);
+                info.send_now(vec![InfoParams::Depth(1, None), pv]).unwrap();
+                while !stop.is_stopped() {
+                    thread::sleep(Duration::from_millis(1));
+                }
+                let bestmove = EngineCommand::BestMove(BestMoveParams::MakeMove(mv, None));
+                responder.send(&bestmove).unwrap();
+            });
+        }
+    }
+
+    #[test]
+    fn run() {
+        let transport = InProcessTransport::spawn(EchoEngine::default());
+        let mut engine = Engine::connect(transport).unwrap();
+        let (sender, receiver) = mpsc::channel();
+        sender.send(format!("{SFEN} moves 7g7f")).unwrap();
+
+        let mut sender = Some(sender);
+        let mut delivered = Vec::new();
+        let mut analysis = engine.analyze_continuous(Duration::from_millis(10));
+        analysis
+            .run(&receiver, |position, snapshot| {
+                delivered.push((position.to_string(), snapshot.best_moves().to_vec()));
+                match delivered.len() {
+                    2 => {
+                        let position = format!("{SFEN} moves 7g7f 3c3d");
+                        sender.as_ref().unwrap().send(position).unwrap();
+                    }
+                    4 => sender = None,
+                    _ => {}
+                }
+            })
+            .unwrap();
+        drop(analysis);
+        assert!(!engine.is_searching());
+
+        assert_eq!(4, delivered.len());
+        assert_eq!(format!("{SFEN} moves 7g7f"), delivered[1].0);
+        assert_eq!(vec!["7g7f"], delivered[1].1);
+        assert_eq!(format!("{SFEN} moves 7g7f 3c3d"), delivered[3].0);
+        assert_eq!(vec!["3c3d"], delivered[3].1);
+    }
+
+    #[test]
+    fn drop_stops() {
+        let transport = InProcessTransport::spawn(EchoEngine::default());
+        let mut engine = Engine::connect(transport).unwrap();
+
+        let mut analysis = engine.analyze_continuous(Duration::from_millis(10));
+        analysis
+            .set_position(&format!("{SFEN} moves 7g7f"))
+            .unwrap();
+        assert_eq!(vec!["7g7f"], analysis.poll().unwrap().best_moves());
+        drop(analysis);
+        assert!(!engine.is_searching());
+    }
+}
@@ -0,0 +1,483 @@
+use std::borrow::Cow;
+use std::io;
+
+use super::framing::Framing;
+use super::jis0208::{IBM_EXTENSIONS, JIS0208};
+
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// Pointers of the user-defined area of CP932, mapped to the private use area.
+const USER_DEFINED: std::ops::Range<usize> = 8836..10716;
+
+/// The first pointer of the NEC-selected IBM extensions in rows 89 to 92.
+const NEC_SELECTED_IBM_EXTENSIONS_START: usize = 8272;
+
+/// `Encoding` is the character encoding of the lines an engine reads and writes.
+///
+/// Lines are transcoded to UTF-8 by the transport before they are parsed, so engines writing
+/// legacy Japanese encodings in `id`, `option` defaults and `info string` can be driven as is.
+/// Lines sent to the engine are encoded back, e.g. `setoption` of an option named in Shift_JIS.
+///
+/// By default, the encoding is detected from the first line which is not ASCII, and lines are
+/// sent in UTF-8 until then. Set a specific encoding to override the detection.
+///
+/// # Examples
+/// ```
+/// use usi::{Encoding, Engine, IoTransport, Transport};
+///
+/// // "id name 将棋" in Shift_JIS.
+/// let output = b"id name \x8f\xab\x8a\xfb\nusiok\nreadyok\n";
+///
+/// let mut transport = IoTransport::new(&output[..], Vec::new());
+/// transport.set_encoding(Encoding::ShiftJis).unwrap();
+/// let engine = Engine::connect(transport).unwrap();
+/// assert_eq!("将棋", engine.info().name());
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Default)]
+pub enum Encoding {
+    /// Detects UTF-8, Shift_JIS or EUC-JP from the first line which is not ASCII.
+    #[default]
+    Auto,
+    /// UTF-8. Lines with invalid sequences fail with `io::ErrorKind::InvalidData`.
+    Utf8,
+    /// Shift_JIS, decoded as its Windows superset CP932.
+    ShiftJis,
+    /// EUC-JP. Characters of JIS X 0212 are replaced with U+FFFD.
+    EucJp,
+}
+
+impl Encoding {
+    /// Decodes `bytes` into a string, replacing malformed sequences with U+FFFD.
+    ///
+    /// # Examples
+    /// ```
+    /// use usi::Encoding;
+    ///
+    /// assert_eq!("▲７六歩", Encoding::ShiftJis.decode(b"\x81\xa3\x82\x56\x98\x5a\x95\xe0"));
+    /// assert_eq!("ｱ\u{FFFD}", Encoding::ShiftJis.decode(b"\xb1\x81"));
+    /// assert_eq!("▲７六歩", Encoding::Auto.decode(b"\xa2\xa5\xa3\xb7\xcf\xbb\xca\xe2"));
+    /// ```
+    pub fn decode<'a>(&self, bytes: &'a [u8]) -> Cow<'a, str> {
+        if let Ok(s) = std::str::from_utf8(bytes) {
+            // ASCII is shared by all the encodings.
+            if *self == Encoding::Utf8 || s.is_ascii() {
+                return Cow::Borrowed(s);
+            }
+        }
+        match self.or_detect(bytes) {
+            Encoding::ShiftJis => Cow::Owned(decode_shift_jis(bytes)),
+            Encoding::EucJp => Cow::Owned(decode_euc_jp(bytes)),
+            _ => String::from_utf8_lossy(bytes),
+        }
+    }
+
+    /// Encodes `s`, or returns `None` if it has characters the encoding doesn't have.
+    /// `Auto` encodes in UTF-8.
+    ///
+    /// # Examples
+    /// ```
+    /// use usi::Encoding;
+    ///
+    /// assert_eq!(b"\x81\xa3\x82\x56\x98\x5a\x95\xe0", &Encoding::ShiftJis.encode("▲７六歩").unwrap()[..]);
+    /// assert_eq!(b"\xa2\xa5\xa3\xb7\xcf\xbb\xca\xe2", &Encoding::EucJp.encode("▲７六歩").unwrap()[..]);
+    /// assert_eq!(None, Encoding::ShiftJis.encode("🐟"));
+    /// ```
+    pub fn encode<'a>(&self, s: &'a str) -> Option<Cow<'a, [u8]>> {
+        // ASCII is shared by all the encodings.
+        if matches!(self, Encoding::Auto | Encoding::Utf8) || s.is_ascii() {
+            return Some(Cow::Borrowed(s.as_bytes()));
+        }
+        let mut bytes = Vec::with_capacity(s.len());
+        for c in s.chars() {
+            match self {
+                Encoding::ShiftJis => encode_shift_jis(c, &mut bytes)?,
+                _ => encode_euc_jp(c, &mut bytes)?,
+            }
+        }
+        Some(Cow::Owned(bytes))
+    }
+
+    /// Guesses the encoding of `bytes`, or returns `None` if they are ASCII.
+    ///
+    /// Valid UTF-8 is taken as UTF-8. Otherwise, EUC-JP is preferred when `bytes` are valid
+    /// in it and are read as half-width katakana in Shift_JIS, which engines rarely write.
+    ///
+    /// # Examples
+    /// ```
+    /// use usi::Encoding;
+    ///
+    /// assert_eq!(None, Encoding::detect(b"info string ok"));
+    /// assert_eq!(Some(Encoding::Utf8), Encoding::detect("将棋".as_bytes()));
+    /// assert_eq!(Some(Encoding::ShiftJis), Encoding::detect(b"\x8f\xab\x8a\xfb"));
+    /// assert_eq!(Some(Encoding::EucJp), Encoding::detect(b"\xbe\xad\xb4\xfd"));
+    /// ```
+    pub fn detect(bytes: &[u8]) -> Option<Encoding> {
+        if bytes.is_ascii() {
+            return None;
+        }
+        if std::str::from_utf8(bytes).is_ok() {
+            return Some(Encoding::Utf8);
+        }
+        let shift_jis = decode_shift_jis(bytes);
+        let valid = |s: &str| !s.contains(char::REPLACEMENT_CHARACTER);
+        let half_width = shift_jis
+            .chars()
+            .any(|c| ('\u{FF61}'..='\u{FF9F}').contains(&c));
+        if valid(&decode_euc_jp(bytes)) && (half_width || !valid(&shift_jis)) {
+            Some(Encoding::EucJp)
+        } else {
+            Some(Encoding::ShiftJis)
+        }
+    }
+
+    /// Returns the encoding, or detects it from `bytes` if it is `Auto`.
+    fn or_detect(self, bytes: &[u8]) -> Encoding {
+        match self {
+            Encoding::Auto => Encoding::detect(bytes).unwrap_or(Encoding::Utf8),
+            encoding => encoding,
+        }
+    }
+}
+
+/// `LineDecoder` decodes the lines received from an engine.
+///
+/// With `Encoding::Auto`, the encoding detected from the first line which is not ASCII
+/// is used for all the following lines.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct LineDecoder {
+    encoding: Encoding,
+    framing: Framing,
+    received: bool,
+}
+
+impl LineDecoder {
+    pub(crate) fn set_encoding(&mut self, encoding: Encoding) {
+        self.encoding = encoding;
+    }
+
+    /// Returns the encoding, which is `Auto` until it is detected.
+    pub(crate) fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    pub(crate) fn set_framing(&mut self, framing: Framing) {
+        self.framing = framing;
+    }
+
+    /// Decodes a line, removing the line terminator.
+    pub(crate) fn decode(&mut self, mut line: Vec<u8>) -> io::Result<String> {
+        if line.last() == Some(&b'\n') {
+            line.pop();
+        }
+        if self.framing.strip_cr {
+            while line.last() == Some(&b'\r') {
+                line.pop();
+            }
+        }
+        if !std::mem::replace(&mut self.received, true)
+            && self.framing.strip_bom
+            && line.starts_with(UTF8_BOM)
+        {
+            line.drain(..UTF8_BOM.len());
+            if self.encoding == Encoding::Auto {
+                self.encoding = Encoding::Utf8;
+            }
+        }
+        if self.encoding == Encoding::Auto {
+            match Encoding::detect(&line) {
+                Some(encoding) => self.encoding = encoding,
+                // Saves detecting again when the line is ASCII.
+                None => return Ok(String::from_utf8(line).unwrap_or_default()),
+            }
+        }
+        match self.encoding {
+            Encoding::Utf8 => {
+                String::from_utf8(line).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+            }
+            encoding => Ok(encoding.decode(&line).into_owned()),
+        }
+    }
+}
+
+/// Encodes a line sent to an engine in `encoding`, without the line terminator.
+pub(crate) fn encode_line(encoding: Encoding, line: &str) -> io::Result<Cow<'_, [u8]>> {
+    encoding.encode(line).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("the line can't be encoded in {encoding:?}"),
+        )
+    })
+}
+
+fn decode_shift_jis(bytes: &[u8]) -> String {
+    let mut s = String::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let b = bytes[i];
+        i += 1;
+        match b {
+            0x00..=0x80 => s.push(char::from(b)),
+            // Half-width katakana.
+            0xA1..=0xDF => s.push(char::from_u32(0xFF61 + u32::from(b - 0xA1)).unwrap()),
+            0x81..=0x9F | 0xE0..=0xFC => {
+                let Some(&trail) = bytes.get(i) else {
+                    s.push(char::REPLACEMENT_CHARACTER);
+                    break;
+                };
+                let lead_offset = if b < 0xA0 { 0x81 } else { 0xC1 };
+                let trail_offset = if trail < 0x7F { 0x40 } else { 0x41 };
+                if let 0x40..=0x7E | 0x80..=0xFC = trail {
+                    i += 1;
+                    let pointer =
+                        usize::from(b - lead_offset) * 188 + usize::from(trail - trail_offset);
+                    s.push(jis0208_char(pointer).unwrap_or(char::REPLACEMENT_CHARACTER));
+                } else {
+                    // An ASCII trail byte is not a part of the malformed sequence.
+                    if !trail.is_ascii() {
+                        i += 1;
+                    }
+                    s.push(char::REPLACEMENT_CHARACTER);
+                }
+            }
+            _ => s.push(char::REPLACEMENT_CHARACTER),
+        }
+    }
+    s
+}
+
+fn decode_euc_jp(bytes: &[u8]) -> String {
+    let mut s = String::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let b = bytes[i];
+        i += 1;
+        if b.is_ascii() {
+            s.push(char::from(b));
+            continue;
+        }
+        let trail = bytes.get(i).copied().unwrap_or(0);
+        let c = match (b, trail) {
+            // Half-width katakana.
+            (0x8E, 0xA1..=0xDF) => char::from_u32(0xFF61 + u32::from(trail - 0xA1)),
+            // JIS X 0212, which is not supported.
+            (0x8F, 0xA1..=0xFE) if matches!(bytes.get(i + 1), Some(0xA1..=0xFE)) => {
+                i += 1;
+                None
+            }
+            (0xA1..=0xFE, 0xA1..=0xFE) => {
+                jis0208_char(usize::from(b - 0xA1) * 94 + usize::from(trail - 0xA1))
+            }
+            _ => {
+                // An ASCII trail byte is not a part of the malformed sequence.
+                s.push(char::REPLACEMENT_CHARACTER);
+                if !trail.is_ascii() {
+                    i += 1;
+                }
+                continue;
+            }
+        };
+        i += 1;
+        s.push(c.unwrap_or(char::REPLACEMENT_CHARACTER));
+    }
+    s
+}
+
+fn encode_shift_jis(c: char, bytes: &mut Vec<u8>) -> Option<()> {
+    let pointer = match c {
+        '\0'..='\u{80}' => {
+            bytes.push(c as u8);
+            return Some(());
+        }
+        '\u{FF61}'..='\u{FF9F}' => {
+            bytes.push((u32::from(c) - 0xFF61 + 0xA1) as u8);
+            return Some(());
+        }
+        '\u{E000}'..='\u{E757}' => USER_DEFINED.start + (u32::from(c) - 0xE000) as usize,
+        // CP932 encodes the IBM extensions rather than their NEC-selected copies.
+        _ => match jis0208_pointer(c)? {
+            NEC_SELECTED_IBM_EXTENSIONS_START.. => {
+                let code = pointer_code(c)?;
+                USER_DEFINED.end + IBM_EXTENSIONS.iter().position(|&x| x == code)?
+            }
+            pointer => pointer,
+        },
+    };
+    let (lead, trail) = (pointer / 188, pointer % 188);
+    let lead_offset = if lead < 0x1F { 0x81 } else { 0xC1 };
+    let trail_offset = if trail < 0x3F { 0x40 } else { 0x41 };
+    bytes.extend([(lead + lead_offset) as u8, (trail + trail_offset) as u8]);
+    Some(())
+}
+
+fn encode_euc_jp(c: char, bytes: &mut Vec<u8>) -> Option<()> {
+    match c {
+        '\0'..='\u{7F}' => bytes.push(c as u8),
+        '\u{FF61}'..='\u{FF9F}' => bytes.extend([0x8E, (u32::from(c) - 0xFF61 + 0xA1) as u8]),
+        _ => {
+            let pointer = jis0208_pointer(c)?;
+            bytes.extend([(pointer / 94 + 0xA1) as u8, (pointer % 94 + 0xA1) as u8]);
+        }
+    }
+    Some(())
+}
+
+fn pointer_code(c: char) -> Option<u16> {
+    u16::try_from(u32::from(c)).ok().filter(|&code| code != 0)
+}
+
+/// Returns the first JIS X 0208 pointer of a character, excluding the user-defined area.
+fn jis0208_pointer(c: char) -> Option<usize> {
+    let code = pointer_code(c)?;
+    JIS0208.iter().position(|&x| x == code)
+}
+
+/// Returns the character of a JIS X 0208 pointer.
+fn jis0208_char(pointer: usize) -> Option<char> {
+    let code = if USER_DEFINED.contains(&pointer) {
+        0xE000 + (pointer - USER_DEFINED.start) as u32
+    } else if pointer < USER_DEFINED.start {
+        u32::from(JIS0208[pointer])
+    } else {
+        u32::from(*IBM_EXTENSIONS.get(pointer - USER_DEFINED.end)?)
+    };
+    char::from_u32(code).filter(|&c| c != '\0')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shift_jis() {
+        let cases: [(&[u8], &str); 7] = [
+            (
+                b"info string \x8e\x9f\x82\xcd\x8e\xe8",
+                "info string 次は手",
+            ),
+            // NEC and IBM extensions of CP932.
+            (b"\x87\x40\xfa\x40", "①ⅰ"),
+            // User-defined area.
+            (b"\xf0\x40\xf9\xfc", "\u{E000}\u{E757}"),
+            (b"\xfd", "\u{FFFD}"),
+            (b"\x82\x20a", "\u{FFFD} a"),
+            (b"\x85\x40", "\u{FFFD}"),
+            (b"a\x82", "a\u{FFFD}"),
+        ];
+        for (bytes, expected) in cases {
+            assert_eq!(expected, Encoding::ShiftJis.decode(bytes), "{bytes:x?}");
+        }
+    }
+
+    #[test]
+    fn euc_jp() {
+        let cases: [(&[u8], &str); 5] = [
+            (b"\xbc\xa1\xa4\xcf\xbc\xea", "次は手"),
+            (b"\x8e\xb1\xad\xa1", "ｱ①"),
+            (b"\x8f\xb0\xa1a", "\u{FFFD}a"),
+            (b"\xa4 ", "\u{FFFD} "),
+            (b"\xa4", "\u{FFFD}"),
+        ];
+        for (bytes, expected) in cases {
+            assert_eq!(expected, Encoding::EucJp.decode(bytes), "{bytes:x?}");
+        }
+    }
+
+    #[test]
+    fn encode() {
+        let cases: [(&str, &[u8]); 5] = [
+            ("setoption name 定跡", b"setoption name \x92\xe8\x90\xd5"),
+            ("ｱ①", b"\xb1\x87\x40"),
+            // The IBM extensions, rather than their NEC-selected copies.
+            ("ⅰ纊", b"\xfa\x40\xfa\x5c"),
+            ("\u{E000}", b"\xf0\x40"),
+            ("\u{80}", b"\x80"),
+        ];
+        for (s, expected) in cases {
+            assert_eq!(
+                Some(expected),
+                Encoding::ShiftJis.encode(s).as_deref(),
+                "{s}"
+            );
+            assert_eq!(s, Encoding::ShiftJis.decode(expected));
+        }
+
+        assert_eq!(
+            Some(&b"\xbc\xa1\xa4\xcf\xbc\xea \x8e\xb1"[..]),
+            Encoding::EucJp.encode("次は手 ｱ").as_deref()
+        );
+        assert_eq!(None, Encoding::EucJp.encode("\u{E000}"));
+        assert_eq!(
+            Some("将棋".as_bytes()),
+            Encoding::Auto.encode("将棋").as_deref()
+        );
+    }
+
+    #[test]
+    fn detect() {
+        let mut decoder = LineDecoder::default();
+        assert_eq!("usiok", decoder.decode(b"usiok\n".to_vec()).unwrap());
+        assert_eq!(Encoding::Auto, decoder.encoding);
+        assert_eq!(
+            "info string 次は手",
+            decoder
+                .decode(b"info string \xbc\xa1\xa4\xcf\xbc\xea".to_vec())
+                .unwrap()
+        );
+        assert_eq!(Encoding::EucJp, decoder.encoding);
+
+        // Shift_JIS is decoded as EUC-JP once EUC-JP is detected.
+        assert_eq!(
+            "\u{FFFD}\u{FFFD}",
+            decoder.decode(b"\x8f\xab\x8a\xfb".to_vec()).unwrap()
+        );
+    }
+
+    #[test]
+    fn framing() {
+        let mut decoder = LineDecoder::default();
+        assert_eq!(
+            "usiok",
+            decoder.decode(b"\xEF\xBB\xBFusiok\r\n".to_vec()).unwrap()
+        );
+        assert_eq!(Encoding::Utf8, decoder.encoding);
+        assert_eq!(
+            "\u{FEFF}readyok",
+            decoder
+                .decode(b"\xEF\xBB\xBFreadyok\r\r\n".to_vec())
+                .unwrap()
+        );
+
+        let mut decoder = LineDecoder::default();
+        decoder.set_framing(Framing {
+            strip_cr: false,
+            strip_bom: false,
+            ..Framing::default()
+        });
+        assert_eq!(
+            "\u{FEFF}usiok\r",
+            decoder.decode(b"\xEF\xBB\xBFusiok\r\n".to_vec()).unwrap()
+        );
+    }
+
+    #[test]
+    fn decode_line() {
+        let decoder = |encoding| {
+            let mut decoder = LineDecoder::default();
+            decoder.set_encoding(encoding);
+            decoder
+        };
+        assert_eq!(
+            "option name 定跡 type string default <empty>",
+            decoder(Encoding::ShiftJis)
+                .decode(b"option name \x92\xe8\x90\xd5 type string default <empty>\r\n".to_vec())
+                .unwrap()
+        );
+        assert_eq!(
+            io::ErrorKind::InvalidData,
+            decoder(Encoding::Utf8)
+                .decode(b"id name \x8f\xab\x8a\xfb\n".to_vec())
+                .unwrap_err()
+                .kind()
+        );
+    }
+}
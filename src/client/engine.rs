@@ -0,0 +1,1533 @@
+use std::collections::VecDeque;
+use std::ffi::OsStr;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Child;
+use std::sync::{mpsc, Arc};
+use std::time::{Duration, Instant};
+
+use super::builder::EngineBuilder;
+use super::coalesce::InfoCoalescer;
+use super::options::{EngineOption, EngineOptions, OptionChange};
+use super::quirks::Quirks;
+use super::search::SearchHandle;
+use super::transport::{ProcessTransport, Transport};
+use crate::error::{EngineCrashReport, Error, TimeoutPhase};
+use crate::process::{EngineInfo, EngineOutput};
+use crate::protocol::*;
+
+/// Represents an event observed by `Engine`.
+#[derive(Clone, Debug)]
+pub enum EngineEvent {
+    /// A command received from the engine.
+    Output(EngineOutput),
+    /// The engine terminated unexpectedly and was restarted.
+    /// Options, the current game and the last position were sent to the new engine,
+    /// but a search in progress was lost.
+    Restarted,
+    /// The engine didn't respond to `isready` within the time limit of the health check.
+    Unhealthy,
+    /// The engine sent nothing for the time limit of the watchdog during a search.
+    /// The action of the watchdog has been performed.
+    SearchStalled,
+    /// An option value was changed by `setoption` or re-declared by the engine.
+    OptionChanged(OptionChange),
+}
+
+/// Represents an action performed when a search is stalled.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum StallAction {
+    /// Only `EngineEvent::SearchStalled` is notified.
+    Notify,
+    /// `stop` command is sent, so the engine is expected to send `bestmove`.
+    Stop,
+    /// The connection is closed and the engine process is killed.
+    /// The engine is restarted if restarts are enabled.
+    Kill,
+}
+
+/// `Watchdog` detects engines which hang silently during a search.
+///
+/// Watchdogs take effect only with transports supporting `Transport::set_read_timeout`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Watchdog {
+    /// Time limit of silence since the last line received during a search.
+    pub timeout: Duration,
+    pub action: StallAction,
+}
+
+/// Time limits of waiting for responses from the engine.
+///
+/// `None` waits indefinitely. Timeouts take effect only with transports supporting
+/// `Transport::set_read_timeout`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Timeouts {
+    /// Time limit of waiting for `usiok` after `usi`. Defaults to 10 seconds.
+    pub usiok: Option<Duration>,
+    /// Time limit of waiting for `readyok` after `isready`. Defaults to 60 seconds,
+    /// as engines may load evaluation files and allocate hash tables before responding.
+    pub readyok: Option<Duration>,
+    /// Time limit of waiting for `bestmove` after `stop`. Defaults to 10 seconds.
+    pub bestmove: Option<Duration>,
+    /// Grace period for the engine to exit after `quit` before it is killed. Defaults to 5 seconds.
+    pub quit: Option<Duration>,
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Timeouts {
+            usiok: Some(Duration::from_secs(10)),
+            readyok: Some(Duration::from_secs(60)),
+            bestmove: Some(Duration::from_secs(10)),
+            quit: Some(Duration::from_secs(5)),
+        }
+    }
+}
+
+impl Timeouts {
+    /// Returns timeouts which wait indefinitely in every phase.
+    pub fn none() -> Self {
+        Timeouts {
+            usiok: None,
+            readyok: None,
+            bestmove: None,
+            quit: None,
+        }
+    }
+}
+
+type EventHandler = Box<dyn FnMut(&EngineEvent) + Send>;
+
+/// The number of protocol lines kept for crash reports.
+const RECENT_LINES: usize = 20;
+
+/// The number of events queued for each subscriber by default.
+const SUBSCRIBER_CAPACITY: usize = 1024;
+
+pub(crate) type TransportFactory = Box<dyn FnMut() -> Result<Box<dyn Transport>, Error> + Send>;
+
+/// `Engine` is a client of a USI engine which completed the initial handshake.
+///
+/// `Engine` sends `usi` and `isready` commands, records `id` and `option` commands
+/// until `usiok` is received, and waits for `readyok` before returning.
+/// The engine is connected through a `Transport`; `Engine::spawn` uses a subprocess.
+///
+/// Engines created by `Engine::spawn` or `Engine::connect_with` can be restarted automatically
+/// when they terminate unexpectedly. See `Engine::set_max_restarts`.
+///
+/// # Examples
+/// ```no_run
+/// use usi::{Engine, EngineCommand, GuiCommand, ThinkParams};
+///
+/// let mut engine = Engine::spawn("/path/to/usi_engine").unwrap();
+/// println!("{} by {}", engine.info().name(), engine.info().author());
+///
+/// engine.send(&GuiCommand::UsiNewGame).unwrap();
+/// engine.send(&GuiCommand::Position("lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL b - 1".to_string())).unwrap();
+/// engine.send(&GuiCommand::Go(ThinkParams::new().infinite())).unwrap();
+///
+/// loop {
+///     let output = engine.recv().unwrap();
+///     if let Some(EngineCommand::BestMove(_)) = output.response() {
+///         break;
+///     }
+/// }
+/// ```
+pub struct Engine {
+    transport: Box<dyn Transport>,
+    info: EngineInfo,
+    factory: Option<TransportFactory>,
+    max_restarts: u32,
+    restarts: u32,
+    restarted: bool,
+    options: EngineOptions,
+    sent_options: Vec<SetOptionParams>,
+    in_game: bool,
+    position: Option<String>,
+    timeouts: Timeouts,
+    handlers: Vec<EventHandler>,
+    subscribers: Vec<mpsc::SyncSender<EngineEvent>>,
+    searching: bool,
+    watchdog: Option<Watchdog>,
+    last_output: Instant,
+    last_activity: Instant,
+    healthy: bool,
+    mate_search: Option<bool>,
+    recent: VecDeque<String>,
+    working_dir: Option<PathBuf>,
+    quirks: Option<Quirks>,
+    dialect: Arc<dyn Dialect + Send + Sync>,
+    #[cfg(feature = "tracing")]
+    trace_level: tracing::Level,
+    closed: bool,
+}
+
+impl fmt::Debug for Engine {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Engine")
+            .field("info", &self.info)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for Engine {
+    fn drop(&mut self) {
+        if !self.closed {
+            let _ = self.write_command(&GuiCommand::Quit);
+            let _ = self.transport.close();
+        }
+    }
+}
+
+impl Engine {
+    /// Spawns a new process of the USI engine and performs the handshake.
+    /// Use `EngineBuilder` to configure the process.
+    pub fn spawn<P: AsRef<OsStr>>(engine_path: P) -> Result<Engine, Error> {
+        EngineBuilder::new(engine_path).spawn()
+    }
+
+    /// Performs the handshake with an already spawned engine process.
+    /// Both stdin and stdout of `process` must be piped.
+    pub fn from_process(process: Child) -> Result<Engine, Error> {
+        Engine::connect(ProcessTransport::from_process(process)?)
+    }
+
+    /// Performs the handshake with an engine connected through `transport`.
+    pub fn connect<T: Transport + 'static>(transport: T) -> Result<Engine, Error> {
+        Engine::connect_with_timeouts(transport, Timeouts::default())
+    }
+
+    /// Performs the handshake like `Engine::connect`, waiting for the responses within `timeouts`.
+    pub fn connect_with_timeouts<T: Transport + 'static>(
+        transport: T,
+        timeouts: Timeouts,
+    ) -> Result<Engine, Error> {
+        Engine::handshake_with(Box::new(transport), timeouts, Arc::new(Usi))
+    }
+
+    /// Performs the handshake like `Engine::connect` with an engine speaking `dialect`,
+    /// such as a UCI chess engine.
+    ///
+    /// Commands are translated from and to the dialect on the wire, so `GuiCommand` and
+    /// `EngineCommand` are used as for USI engines.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use usi::{Engine, EngineBuilder, ThinkParams, Uci};
+    ///
+    /// let mut engine = EngineBuilder::new("stockfish").dialect(Uci).spawn().unwrap();
+    /// let bestmove = engine.go(ThinkParams::new().nodes(100000)).unwrap().wait().unwrap();
+    /// ```
+    pub fn connect_with_dialect<T, D>(
+        transport: T,
+        dialect: D,
+        timeouts: Timeouts,
+    ) -> Result<Engine, Error>
+    where
+        T: Transport + 'static,
+        D: Dialect + Send + Sync + 'static,
+    {
+        Engine::handshake_with(Box::new(transport), timeouts, Arc::new(dialect))
+    }
+
+    /// Performs the handshake with an engine connected through a transport created by `factory`.
+    /// `factory` is called again when the engine is restarted.
+    pub fn connect_with<F>(factory: F) -> Result<Engine, Error>
+    where
+        F: FnMut() -> Result<Box<dyn Transport>, Error> + Send + 'static,
+    {
+        Engine::start(Box::new(factory), Timeouts::default(), Arc::new(Usi))
+    }
+
+    pub(crate) fn start(
+        mut factory: TransportFactory,
+        timeouts: Timeouts,
+        dialect: Arc<dyn Dialect + Send + Sync>,
+    ) -> Result<Engine, Error> {
+        let mut engine = Engine::handshake_with(factory()?, timeouts, dialect)?;
+        engine.factory = Some(factory);
+        Ok(engine)
+    }
+
+    fn handshake_with(
+        transport: Box<dyn Transport>,
+        timeouts: Timeouts,
+        dialect: Arc<dyn Dialect + Send + Sync>,
+    ) -> Result<Engine, Error> {
+        let mut engine = Engine {
+            transport,
+            info: EngineInfo::default(),
+            factory: None,
+            max_restarts: 0,
+            restarts: 0,
+            restarted: false,
+            options: EngineOptions::default(),
+            sent_options: Vec::new(),
+            in_game: false,
+            position: None,
+            timeouts,
+            handlers: Vec::new(),
+            subscribers: Vec::new(),
+            searching: false,
+            watchdog: None,
+            last_output: Instant::now(),
+            last_activity: Instant::now(),
+            healthy: true,
+            mate_search: None,
+            recent: VecDeque::with_capacity(RECENT_LINES),
+            working_dir: None,
+            quirks: None,
+            dialect,
+            #[cfg(feature = "tracing")]
+            trace_level: tracing::Level::DEBUG,
+            closed: false,
+        };
+        engine.handshake()?;
+
+        Ok(engine)
+    }
+
+    /// Sets how many times the engine is restarted when it terminates unexpectedly.
+    /// Defaults to 0, which disables restarts.
+    ///
+    /// Returns `Error::IllegalState` if the engine was created by `Engine::connect`,
+    /// as there is no way to create a new connection.
+    pub fn set_max_restarts(&mut self, max_restarts: u32) -> Result<(), Error> {
+        if self.factory.is_none() {
+            return Err(Error::IllegalState {
+                reason: "the engine can't be reconnected",
+            });
+        }
+        self.max_restarts = max_restarts;
+        Ok(())
+    }
+
+    /// Returns the working directory of the engine, against which the engine resolves
+    /// relative paths in filename options.
+    ///
+    /// It is known for engines spawned by `Engine::spawn` or `EngineBuilder`, and `None`
+    /// for other connections unless set by `Engine::set_working_dir`.
+    pub fn working_dir(&self) -> Option<&Path> {
+        self.working_dir.as_deref()
+    }
+
+    /// Sets the working directory of the engine, such as the directory on a remote host.
+    pub fn set_working_dir<P: Into<PathBuf>>(&mut self, dir: P) {
+        self.working_dir = Some(dir.into());
+    }
+
+    /// Returns the path of a filename option resolved against the working directory.
+    /// Relative paths are returned as is if the working directory is unknown.
+    pub fn option_path(&self, name: &str) -> Option<PathBuf> {
+        let option = self.options.get(name)?;
+        match &self.working_dir {
+            Some(dir) => option.resolve_path(dir),
+            None => option.path(),
+        }
+    }
+
+    /// Sends `setoption` command with `path` as the value of a filename option.
+    ///
+    /// If `must_exist` is `true`, `path` is resolved against the working directory and
+    /// `Error::EngineIo` with `io::ErrorKind::NotFound` is returned if it doesn't exist,
+    /// so a bad evaluation file is caught before the engine tries to load it.
+    /// Returns `Error::InvalidOptionValue` if the option is not a filename option.
+    pub fn set_option_path<P: AsRef<Path>>(
+        &mut self,
+        name: &str,
+        path: P,
+        must_exist: bool,
+    ) -> Result<(), Error> {
+        let path = path.as_ref();
+        let option = self.options.get(name).ok_or_else(|| Error::UnknownOption {
+            name: name.to_string(),
+        })?;
+        if !matches!(option.kind(), OptionKind::Filename { .. }) {
+            return Err(Error::InvalidOptionValue {
+                name: name.to_string(),
+                value: path.display().to_string(),
+            });
+        }
+        if must_exist {
+            let resolved = match &self.working_dir {
+                Some(dir) => dir.join(path),
+                None => path.to_path_buf(),
+            };
+            if !resolved.exists() {
+                return Err(Error::EngineIo(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("{} does not exist", resolved.display()),
+                )));
+            }
+        }
+        self.set_option(name, &path.to_string_lossy())
+    }
+
+    /// Applies `quirks` to the engine.
+    ///
+    /// The `readyok` timeout is extended to the one the engine needs, the default options
+    /// declared by the engine are sent, and `Engine::set_option` accepts standard option names
+    /// for the aliases. Lines received afterwards are rewritten by the quirks.
+    pub fn set_quirks(&mut self, quirks: Quirks) -> Result<(), Error> {
+        if let Some(timeout) = quirks.readyok() {
+            if self
+                .timeouts
+                .readyok
+                .is_some_and(|readyok| readyok < timeout)
+            {
+                self.timeouts.readyok = Some(timeout);
+            }
+        }
+        for params in quirks.defaults() {
+            if self.options.contains(&params.name) {
+                self.set_option(&params.name, params.value.as_deref().unwrap_or_default())?;
+            }
+        }
+        self.quirks = Some(quirks);
+        Ok(())
+    }
+
+    /// Applies the built-in quirks matching the `id name` of the engine, if any.
+    /// Returns `true` if quirks were found.
+    pub fn apply_known_quirks(&mut self) -> Result<bool, Error> {
+        match Quirks::detect(self.info.name()) {
+            Some(quirks) => self.set_quirks(quirks).map(|_| true),
+            None => Ok(false),
+        }
+    }
+
+    /// Returns the quirks applied to the engine.
+    pub fn quirks(&self) -> Option<&Quirks> {
+        self.quirks.as_ref()
+    }
+
+    /// Returns the protocol spoken by the engine.
+    pub fn dialect(&self) -> &(dyn Dialect + Send + Sync) {
+        self.dialect.as_ref()
+    }
+
+    /// Returns the time limits of waiting for responses.
+    pub fn timeouts(&self) -> &Timeouts {
+        &self.timeouts
+    }
+
+    /// Sets the time limits of waiting for responses.
+    /// The handshake of `Engine::connect` uses `Timeouts::default()`;
+    /// use `Engine::connect_with_timeouts` or `EngineBuilder::timeouts` to change them.
+    pub fn set_timeouts(&mut self, timeouts: Timeouts) {
+        self.timeouts = timeouts;
+    }
+
+    /// Returns how many times the engine has been restarted.
+    pub fn restart_count(&self) -> u32 {
+        self.restarts
+    }
+
+    /// Returns the metadata collected during the handshake.
+    pub fn info(&self) -> &EngineInfo {
+        &self.info
+    }
+
+    /// Returns the options declared by the engine with their current values.
+    pub fn options(&self) -> &EngineOptions {
+        &self.options
+    }
+
+    /// Returns `true` if the engine declared `USI_Ponder`.
+    pub fn supports_ponder(&self) -> bool {
+        self.options.supports_ponder()
+    }
+
+    /// Returns `true` if the engine can report multiple PVs.
+    pub fn supports_multipv(&self) -> bool {
+        self.options.supports_multipv()
+    }
+
+    /// Returns whether the engine supports `go mate`.
+    ///
+    /// USI has no option declaring it, so this is `None` until the engine answers
+    /// a `checkmate` command, and `Some(false)` if it answered `checkmate notimplemented`.
+    pub fn supports_mate_search(&self) -> Option<bool> {
+        self.mate_search
+    }
+
+    /// Returns the option of the hash size, `USI_Hash` or `Hash`.
+    pub fn hash_option(&self) -> Option<&EngineOption> {
+        self.options.hash_option()
+    }
+
+    /// Returns the latest lines the engine wrote to stderr.
+    /// Empty if the transport doesn't capture stderr.
+    pub fn stderr_lines(&mut self) -> Vec<String> {
+        self.transport.stderr_lines().unwrap_or_default()
+    }
+
+    /// Sends a command to the engine.
+    ///
+    /// If the engine has terminated and restarts are enabled, the engine is restarted
+    /// and the command is sent to the new engine.
+    pub fn send(&mut self, command: &GuiCommand) -> Result<(), Error> {
+        let line = self.dialect.format_gui(command)?;
+        match self.write_line(&line) {
+            Ok(()) => {}
+            Err(_) if self.can_restart() => {
+                self.restart()?;
+                self.write_line(&line)?;
+            }
+            Err(e) => return Err(e.into()),
+        }
+        self.last_activity = Instant::now();
+
+        match command {
+            GuiCommand::SetOption(params) => {
+                self.sent_options.retain(|o| o.name != params.name);
+                self.sent_options.push(params.clone());
+                if let Some(change) = self.options.set_value(params) {
+                    self.notify(&EngineEvent::OptionChanged(change));
+                }
+            }
+            GuiCommand::UsiNewGame => {
+                self.in_game = true;
+                self.position = None;
+            }
+            GuiCommand::Position(sfen) => self.position = Some(sfen.clone()),
+            GuiCommand::GameOver(_) => self.in_game = false,
+            GuiCommand::Go(_) => {
+                self.searching = true;
+                self.last_output = Instant::now();
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Registers a handler called with every event observed by the engine,
+    /// including outputs read while waiting in `Engine::prepare` or `Engine::stop`.
+    ///
+    /// Handlers are called on the thread receiving from the engine, so call
+    /// `Engine::dispatch` in the event loop to drive them.
+    pub fn on_event<F: FnMut(&EngineEvent) + Send + 'static>(&mut self, handler: F) {
+        self.handlers.push(Box::new(handler));
+    }
+
+    /// Registers a handler called with the parameters of each `info` command.
+    pub fn on_info<F: FnMut(&[InfoParams]) + Send + 'static>(&mut self, mut handler: F) {
+        self.on_event(move |event| {
+            if let Some(EngineCommand::Info(params)) = event_command(event) {
+                handler(params);
+            }
+        });
+    }
+
+    /// Registers a handler called with batches of `info` commands coalesced by `InfoCoalescer`.
+    /// A batch is delivered when an `info` command arrives after `window` has elapsed,
+    /// and the rest are delivered before `bestmove` is handled.
+    pub fn on_info_batch<F>(&mut self, window: Duration, mut handler: F)
+    where
+        F: FnMut(Vec<Vec<InfoParams>>) + Send + 'static,
+    {
+        let mut coalescer = InfoCoalescer::new(window);
+        self.on_event(move |event| match event_command(event) {
+            Some(EngineCommand::Info(params)) => {
+                if let Some(batch) = coalescer.push(params.clone()) {
+                    handler(batch);
+                }
+            }
+            Some(EngineCommand::BestMove(_)) if !coalescer.is_empty() => {
+                handler(coalescer.flush());
+            }
+            _ => {}
+        });
+    }
+
+    /// Registers a handler called with each `bestmove` command.
+    pub fn on_bestmove<F: FnMut(&BestMoveParams) + Send + 'static>(&mut self, mut handler: F) {
+        self.on_event(move |event| {
+            if let Some(EngineCommand::BestMove(params)) = event_command(event) {
+                handler(params);
+            }
+        });
+    }
+
+    /// Registers a handler called with each change of an option value,
+    /// e.g. to keep a settings UI in sync with options changed programmatically.
+    pub fn on_option_change<F: FnMut(&OptionChange) + Send + 'static>(&mut self, mut handler: F) {
+        self.on_event(move |event| {
+            if let EngineEvent::OptionChanged(change) = event {
+                handler(change);
+            }
+        });
+    }
+
+    /// Registers a handler called with the raw line of each command unknown to the protocol.
+    pub fn on_unknown<F: FnMut(&str) + Send + 'static>(&mut self, mut handler: F) {
+        self.on_event(move |event| {
+            if let EngineEvent::Output(output) = event {
+                if let Some(EngineCommand::Unknown) = output.response() {
+                    handler(output.raw_str());
+                }
+            }
+        });
+    }
+
+    /// Blocks until the next event and passes it to the registered handlers.
+    pub fn dispatch(&mut self) -> Result<(), Error> {
+        self.recv_event().map(|_| ())
+    }
+
+    /// Sets the level of `tracing` events logging the protocol traffic. Defaults to `DEBUG`.
+    #[cfg(feature = "tracing")]
+    pub fn set_trace_level(&mut self, level: tracing::Level) {
+        self.trace_level = level;
+    }
+
+    fn write_command(&mut self, command: &GuiCommand) -> Result<(), Error> {
+        let line = self.dialect.format_gui(command)?;
+        self.write_line(&line)?;
+        Ok(())
+    }
+
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        #[cfg(feature = "tracing")]
+        super::trace::traffic(self.trace_level, "send", self.info.name(), line);
+        self.remember(">", line);
+        self.transport.write_line(line)
+    }
+
+    fn read_line(&mut self) -> io::Result<Option<String>> {
+        let line = self.transport.read_line()?;
+        if let Some(line) = &line {
+            #[cfg(feature = "tracing")]
+            super::trace::traffic(self.trace_level, "recv", self.info.name(), line);
+            self.remember("<", line);
+        }
+        Ok(line)
+    }
+
+    fn remember(&mut self, direction: &str, line: &str) {
+        if self.recent.len() == RECENT_LINES {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(format!("{} {}", direction, line));
+    }
+
+    /// Builds the error for the engine closing its output.
+    /// Engines backed by a process are reported as `Error::EngineExited`.
+    fn closed_error(&mut self) -> Error {
+        let stderr = self.transport.stderr_lines();
+        let status = self.transport.exit_status();
+        if stderr.is_none() && status.is_none() {
+            return Error::EngineClosed;
+        }
+
+        #[cfg(unix)]
+        let signal = {
+            use std::os::unix::process::ExitStatusExt;
+            status.and_then(|status| status.signal())
+        };
+        #[cfg(not(unix))]
+        let signal = None;
+
+        Error::EngineExited(EngineCrashReport {
+            exit_code: status.and_then(|status| status.code()),
+            signal,
+            stderr: stderr.unwrap_or_default(),
+            protocol: self.recent.iter().cloned().collect(),
+        })
+    }
+
+    /// Returns a receiver of every event observed by the engine from now on.
+    ///
+    /// Any number of receivers can be created, e.g. for an evaluation graph, a move list
+    /// and a logger, and each of them receives a copy of the events. Events are delivered
+    /// while the engine is received from, so a receiver can live on another thread.
+    /// Dropped receivers are unsubscribed.
+    ///
+    /// Up to 1024 events are queued for each receiver. Events arriving while the queue is full
+    /// are dropped for that receiver, so a slow receiver neither blocks the engine nor grows
+    /// the memory without limit. Use `Engine::subscribe_with_capacity` to change the limit.
+    pub fn subscribe(&mut self) -> mpsc::Receiver<EngineEvent> {
+        self.subscribe_with_capacity(SUBSCRIBER_CAPACITY)
+    }
+
+    /// Returns a receiver of events like `Engine::subscribe`, queuing up to `capacity` events.
+    pub fn subscribe_with_capacity(&mut self, capacity: usize) -> mpsc::Receiver<EngineEvent> {
+        let (sender, receiver) = mpsc::sync_channel(capacity);
+        self.subscribers.push(sender);
+        receiver
+    }
+
+    fn notify(&mut self, event: &EngineEvent) {
+        for handler in &mut self.handlers {
+            handler(event);
+        }
+        self.subscribers
+            .retain(|subscriber| match subscriber.try_send(event.clone()) {
+                Ok(()) | Err(mpsc::TrySendError::Full(_)) => true,
+                Err(mpsc::TrySendError::Disconnected(_)) => false,
+            });
+    }
+
+    /// Sends `setoption` command after validating `value` against the option declared by the engine.
+    ///
+    /// Returns `Error::UnknownOption` if the engine didn't declare the option,
+    /// `Error::OptionOutOfRange` if `value` is outside the range of a spin option, or
+    /// `Error::InvalidOptionValue` if `value` is not valid for the option type.
+    /// Use an empty value for buttons.
+    pub fn set_option(&mut self, name: &str, value: &str) -> Result<(), Error> {
+        self.set_option_with(name, value, EngineOption::validate)
+    }
+
+    /// Sends `setoption` command like `Engine::set_option`, except that values outside
+    /// the range of a spin option are snapped to the minimum or maximum.
+    pub fn set_option_clamped(&mut self, name: &str, value: &str) -> Result<(), Error> {
+        self.set_option_with(name, value, EngineOption::clamp)
+    }
+
+    fn set_option_with<F>(&mut self, name: &str, value: &str, check: F) -> Result<(), Error>
+    where
+        F: FnOnce(&EngineOption, &str) -> Result<String, Error>,
+    {
+        let option = self
+            .option_by_alias(name)
+            .ok_or_else(|| Error::UnknownOption {
+                name: name.to_string(),
+            })?;
+        let name = option.name();
+        let value = check(option, value)?;
+        let params = match option.kind() {
+            OptionKind::Button { .. } => SetOptionParams::button(name),
+            _ => SetOptionParams::new(name, value),
+        };
+        self.send(&GuiCommand::SetOption(params))
+    }
+
+    /// Returns the option named `name`, or its alias in the quirks of the engine.
+    fn option_by_alias(&self, name: &str) -> Option<&EngineOption> {
+        self.options.get(name).or_else(|| {
+            let quirks = self.quirks.as_ref()?;
+            quirks
+                .aliases_of(name)
+                .find_map(|alias| self.options.get(alias))
+        })
+    }
+
+    /// Blocks until the next command is received from the engine.
+    /// Lines which cannot be parsed are skipped.
+    /// Returns `Error::EngineClosed` when the engine closed the connection, or
+    /// `Error::EngineExited` with an `EngineCrashReport` if the engine is a process.
+    ///
+    /// Restarts are performed transparently; use `recv_event` to be notified.
+    pub fn recv(&mut self) -> Result<EngineOutput, Error> {
+        loop {
+            if let EngineEvent::Output(output) = self.recv_event()? {
+                return Ok(output);
+            }
+        }
+    }
+
+    /// Blocks until the next command is received from the engine or the engine is restarted.
+    pub fn recv_event(&mut self) -> Result<EngineEvent, Error> {
+        if self.restarted {
+            self.restarted = false;
+            return Ok(EngineEvent::Restarted);
+        }
+
+        let res = match self.watchdog {
+            Some(watchdog) if self.searching => self.read_output_watched(watchdog),
+            _ => self.read_output().map(EngineEvent::Output),
+        };
+        match res {
+            Ok(event) => Ok(event),
+            Err(Error::EngineClosed | Error::EngineExited(_)) if self.can_restart() => {
+                self.restart()?;
+                self.restarted = false;
+                Ok(EngineEvent::Restarted)
+            }
+            Err(Error::EngineIo(e))
+                if e.kind() != io::ErrorKind::TimedOut && self.can_restart() =>
+            {
+                self.restart()?;
+                self.restarted = false;
+                Ok(EngineEvent::Restarted)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Sets the watchdog of searches. `None` disables it, which is the default.
+    pub fn set_watchdog(&mut self, watchdog: Option<Watchdog>) {
+        self.watchdog = watchdog;
+    }
+
+    /// Reads the next output, or performs the action of `watchdog` if the search is stalled.
+    fn read_output_watched(&mut self, watchdog: Watchdog) -> Result<EngineEvent, Error> {
+        let remaining =
+            (self.last_output + watchdog.timeout).saturating_duration_since(Instant::now());
+        if !remaining.is_zero() {
+            // Without timeout support, the read blocks until the next line.
+            let timed = self.transport.set_read_timeout(Some(remaining)).is_ok();
+            let res = self.read_output();
+            if timed {
+                let _ = self.transport.set_read_timeout(None);
+            }
+            match res {
+                Ok(output) => return Ok(EngineEvent::Output(output)),
+                Err(Error::EngineIo(e)) if e.kind() == io::ErrorKind::TimedOut => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        // Measure the next stall from now.
+        self.last_output = Instant::now();
+        match watchdog.action {
+            StallAction::Notify => {}
+            StallAction::Stop => self.send(&GuiCommand::Stop)?,
+            StallAction::Kill => {
+                self.searching = false;
+                let _ = self.transport.close();
+            }
+        }
+        self.notify(&EngineEvent::SearchStalled);
+        Ok(EngineEvent::SearchStalled)
+    }
+
+    /// Reads the next output within `timeout`, or returns `None` if nothing was received.
+    /// Transports without timeout support block until the next line.
+    pub(crate) fn recv_timeout(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<Option<EngineOutput>, Error> {
+        let timed = self.transport.set_read_timeout(Some(timeout)).is_ok();
+        let res = self.read_output();
+        if timed {
+            let _ = self.transport.set_read_timeout(None);
+        }
+        match res {
+            Ok(output) => Ok(Some(output)),
+            Err(Error::EngineIo(e)) if e.kind() == io::ErrorKind::TimedOut => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn read_output(&mut self) -> Result<EngineOutput, Error> {
+        loop {
+            let Some(line) = self.read_line()? else {
+                return Err(self.closed_error());
+            };
+            self.last_activity = Instant::now();
+            self.last_output = self.last_activity;
+            let line = match &self.quirks {
+                Some(quirks) => match quirks.rewrite(line) {
+                    Some(line) => line,
+                    None => continue,
+                },
+                None => line,
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            // Ignore illegal commands.
+            if let Ok(cmd) = self.dialect.parse_engine(&line) {
+                let mut change = None;
+                match &cmd {
+                    EngineCommand::Option(params) => change = self.options.declare(params),
+                    EngineCommand::BestMove(_) => self.searching = false,
+                    EngineCommand::Checkmate(CheckmateParams::NotImplemented) => {
+                        self.searching = false;
+                        self.mate_search = Some(false)
+                    }
+                    EngineCommand::Checkmate(_) => {
+                        self.searching = false;
+                        self.mate_search = Some(true)
+                    }
+                    _ => {}
+                }
+                let event = EngineEvent::Output(EngineOutput::new(Some(cmd), line));
+                self.notify(&event);
+                if let Some(change) = change {
+                    self.notify(&EngineEvent::OptionChanged(change));
+                }
+                match event {
+                    EngineEvent::Output(output) => return Ok(output),
+                    _ => unreachable!(),
+                }
+            }
+        }
+    }
+
+    /// Returns `false` if the last health check failed.
+    pub fn is_healthy(&self) -> bool {
+        self.healthy
+    }
+
+    /// Returns `true` if a search was started and `bestmove` or `checkmate` has not been received.
+    pub fn is_searching(&self) -> bool {
+        self.searching
+    }
+
+    /// Returns how long the engine has been idle, i.e. no command was sent or received.
+    pub fn idle_time(&self) -> Duration {
+        self.last_activity.elapsed()
+    }
+
+    /// Sends `isready` command and checks that `readyok` is received within `timeout`.
+    ///
+    /// If the engine doesn't respond in time or has closed the connection,
+    /// the engine is flagged as unhealthy, `EngineEvent::Unhealthy` is notified to
+    /// the handlers, and `Ok(false)` is returned.
+    /// Returns `Error::IllegalState` while searching.
+    pub fn check_health(&mut self, timeout: Duration) -> Result<bool, Error> {
+        if self.searching {
+            return Err(Error::IllegalState {
+                reason: "the engine is searching",
+            });
+        }
+
+        let timeouts = self.timeouts;
+        self.timeouts.readyok = Some(timeout);
+        let res = self.prepare();
+        self.timeouts = timeouts;
+
+        self.healthy = match res {
+            Ok(()) => true,
+            Err(Error::Timeout { .. } | Error::EngineClosed | Error::EngineExited(_)) => false,
+            Err(e) => return Err(e),
+        };
+        if !self.healthy {
+            self.notify(&EngineEvent::Unhealthy);
+        }
+        Ok(self.healthy)
+    }
+
+    /// Sends `go` command and returns a handle tracking the search.
+    pub fn go(&mut self, params: ThinkParams) -> Result<SearchHandle<'_>, Error> {
+        self.send(&GuiCommand::Go(params))?;
+        Ok(SearchHandle::new(self))
+    }
+
+    /// Sends `isready` command and waits until `readyok` is received.
+    /// Other commands received in the meantime are discarded.
+    pub fn prepare(&mut self) -> Result<(), Error> {
+        self.send(&GuiCommand::IsReady)?;
+        self.wait_for(TimeoutPhase::ReadyOk, |cmd| {
+            matches!(cmd, EngineCommand::ReadyOk)
+        })?;
+        Ok(())
+    }
+
+    /// Sends `stop` command and waits until `bestmove` is received.
+    /// `info` commands received in the meantime are discarded.
+    pub fn stop(&mut self) -> Result<BestMoveParams, Error> {
+        self.send(&GuiCommand::Stop)?;
+        match self.wait_for(TimeoutPhase::BestMove, |cmd| {
+            matches!(cmd, EngineCommand::BestMove(_))
+        })? {
+            EngineCommand::BestMove(params) => Ok(params),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Runs the nonstandard `bench` command with `args` and collects the reported numbers.
+    ///
+    /// `isready` is sent after `bench`, and the output is collected until `readyok`,
+    /// as engines handle commands in order. No timeout applies as benchmarks take long.
+    /// Returns `Error::IllegalState` while searching.
+    pub fn bench(&mut self, args: &[&str]) -> Result<BenchReport, Error> {
+        if self.searching {
+            return Err(Error::IllegalState {
+                reason: "the engine is searching",
+            });
+        }
+        self.send(&GuiCommand::Bench(
+            args.iter().map(|arg| arg.to_string()).collect(),
+        ))?;
+        self.send(&GuiCommand::IsReady)?;
+
+        let mut report = BenchReport::default();
+        loop {
+            let output = self.recv()?;
+            if let Some(EngineCommand::ReadyOk) = output.response() {
+                return Ok(report);
+            }
+            report.push_line(output.raw_str());
+        }
+    }
+
+    /// Closes the connection to the engine immediately.
+    /// Engine processes are killed without sending `quit`.
+    pub fn close(mut self) -> Result<(), Error> {
+        self.closed = true;
+        self.transport.close()?;
+        Ok(())
+    }
+
+    /// Sends `quit` command and waits for the engine to close the connection
+    /// within the grace period of `Timeouts::quit`, then closes the connection.
+    /// Engine processes which don't exit in time are killed.
+    pub fn shutdown(mut self) -> Result<(), Error> {
+        self.closed = true;
+        // The engine may have exited already.
+        let _ = self.write_command(&GuiCommand::Quit);
+
+        if let Some(grace) = self.timeouts.quit {
+            let deadline = Instant::now() + grace;
+            loop {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                if remaining.is_zero() || self.transport.set_read_timeout(Some(remaining)).is_err()
+                {
+                    break;
+                }
+                if !matches!(self.read_line(), Ok(Some(_))) {
+                    break;
+                }
+            }
+        }
+
+        self.transport.close()?;
+        Ok(())
+    }
+
+    /// Reads commands until `pred` returns `true`, within the time limit of `phase`.
+    fn wait_for<F>(&mut self, phase: TimeoutPhase, mut pred: F) -> Result<EngineCommand, Error>
+    where
+        F: FnMut(&EngineCommand) -> bool,
+    {
+        let timeout = match phase {
+            TimeoutPhase::UsiOk => self.timeouts.usiok,
+            TimeoutPhase::ReadyOk => self.timeouts.readyok,
+            TimeoutPhase::BestMove => self.timeouts.bestmove,
+        };
+        let deadline = timeout.map(|t| Instant::now() + t);
+
+        let res = loop {
+            if let Some(deadline) = deadline {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                if remaining.is_zero() {
+                    break Err(Error::Timeout { phase });
+                }
+                // Transports without timeout support block until the next line.
+                let _ = self.transport.set_read_timeout(Some(remaining));
+            }
+
+            match self.read_output() {
+                Ok(output) => match output.response() {
+                    Some(cmd) if pred(cmd) => break Ok(cmd.clone()),
+                    Some(cmd) if phase == TimeoutPhase::UsiOk => self.info.update(cmd),
+                    _ => {}
+                },
+                Err(Error::EngineIo(e)) if e.kind() == io::ErrorKind::TimedOut => {
+                    break Err(Error::Timeout { phase })
+                }
+                Err(e) => break Err(e),
+            }
+        };
+
+        if deadline.is_some() {
+            let _ = self.transport.set_read_timeout(None);
+        }
+        res
+    }
+
+    fn handshake(&mut self) -> Result<(), Error> {
+        self.info = EngineInfo::default();
+        self.options = EngineOptions::default();
+        self.write_command(&GuiCommand::Usi)?;
+        self.wait_for(TimeoutPhase::UsiOk, |cmd| {
+            matches!(cmd, EngineCommand::UsiOk)
+        })?;
+
+        for params in self.sent_options.clone() {
+            self.options.set_value(&params);
+            self.write_command(&GuiCommand::SetOption(params))?;
+        }
+
+        self.write_command(&GuiCommand::IsReady)?;
+        self.wait_for(TimeoutPhase::ReadyOk, |cmd| {
+            matches!(cmd, EngineCommand::ReadyOk)
+        })?;
+
+        Ok(())
+    }
+
+    fn can_restart(&self) -> bool {
+        self.factory.is_some() && self.restarts < self.max_restarts
+    }
+
+    /// Replaces the connection with a new engine and restores the state.
+    fn restart(&mut self) -> Result<(), Error> {
+        // The old engine is gone, so failures on closing it are not relevant.
+        let _ = self.transport.close();
+
+        let factory = self.factory.as_mut().ok_or(Error::IllegalState {
+            reason: "the engine can't be reconnected",
+        })?;
+        self.restarts += 1;
+        self.transport = factory()?;
+        // The search of the old engine is lost.
+        self.searching = false;
+        self.last_output = Instant::now();
+        self.handshake()?;
+
+        if self.in_game {
+            let line = self.dialect.format_gui(&GuiCommand::UsiNewGame)?;
+            self.transport.write_line(&line)?;
+        }
+        if let Some(sfen) = &self.position {
+            let command = GuiCommand::Position(sfen.clone());
+            self.write_command(&command)?;
+        }
+        self.restarted = true;
+        self.notify(&EngineEvent::Restarted);
+
+        Ok(())
+    }
+}
+
+fn event_command(event: &EngineEvent) -> Option<&EngineCommand> {
+    match event {
+        EngineEvent::Output(output) => output.response().as_ref(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::{SharedBuffer, ToyEngine};
+    use crate::{InProcessTransport, IoTransport, OptionRegistry};
+
+    #[test]
+    fn handshake_timeout() {
+        let mock = crate::MockEngine::new("Slow")
+            .expect("usi")
+            .reply_after(Duration::from_millis(500), "usiok");
+        let timeouts = Timeouts {
+            usiok: Some(Duration::from_millis(50)),
+            ..Timeouts::default()
+        };
+        assert!(matches!(
+            Engine::connect_with_timeouts(mock, timeouts),
+            Err(Error::Timeout {
+                phase: TimeoutPhase::UsiOk
+            })
+        ));
+    }
+
+    #[test]
+    fn handshake() {
+        let output = "id name Toy Engine\nid author nobody\n\
+                      option name USI_Hash type spin default 16 min 1 max 1024\n\
+                      garbage line\nusiok\nreadyok\nbestmove 7g7f\n";
+        let mut engine = Engine::connect(IoTransport::new(output.as_bytes(), Vec::new()))
+            .expect("handshake failed");
+
+        assert_eq!("Toy Engine", engine.info().name());
+        assert_eq!("nobody", engine.info().author());
+        assert_eq!(
+            Some(&"16".to_string()),
+            engine.info().options().get("USI_Hash")
+        );
+        assert_eq!(Some("16"), engine.options().value("USI_Hash"));
+        assert!(engine.hash_option().is_some());
+        assert!(!engine.supports_ponder());
+        assert_eq!(None, engine.supports_mate_search());
+        engine
+            .send(&GuiCommand::SetOption(SetOptionParams::new(
+                "USI_Hash", "256",
+            )))
+            .unwrap();
+        assert_eq!(Some("256"), engine.options().value("USI_Hash"));
+        engine.set_option("USI_Hash", "1024").unwrap();
+        assert_eq!(Some("1024"), engine.options().value("USI_Hash"));
+        assert!(matches!(
+            engine.set_option("USI_Hash", "2048"),
+            Err(Error::OptionOutOfRange { given: 2048, .. })
+        ));
+        assert_eq!(Some("1024"), engine.options().value("USI_Hash"));
+        engine.set_option_clamped("USI_Hash", "2048").unwrap();
+        assert!(matches!(
+            engine.set_option("Threads", "4"),
+            Err(Error::UnknownOption { .. })
+        ));
+        assert_eq!(Some("1024"), engine.options().value("USI_Hash"));
+
+        engine
+            .send(&GuiCommand::Go(ThinkParams::new().infinite()))
+            .unwrap();
+        assert!(matches!(
+            engine.recv().unwrap().response(),
+            Some(EngineCommand::BestMove(BestMoveParams::MakeMove(..)))
+        ));
+        assert!(matches!(engine.recv(), Err(Error::EngineClosed)));
+    }
+
+    #[test]
+    fn handlers() {
+        use std::sync::{Arc, Mutex};
+
+        let output = "usiok\nreadyok\ninfo depth 1 score cp 30\nhello\n\
+                      info depth 2 score cp 10\nbestmove 7g7f\n";
+        let mut engine = Engine::connect(IoTransport::new(output.as_bytes(), Vec::new())).unwrap();
+
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let l = log.clone();
+        engine.on_info(move |params| l.lock().unwrap().push(format!("info {}", params.len())));
+        let l = log.clone();
+        engine.on_bestmove(move |params| l.lock().unwrap().push(format!("{params:?}")));
+        let l = log.clone();
+        engine.on_unknown(move |line| l.lock().unwrap().push(line.to_string()));
+
+        engine.dispatch().unwrap();
+        engine.dispatch().unwrap();
+        assert_eq!(
+            vec!["info 2".to_string(), "hello".to_string()],
+            *log.lock().unwrap()
+        );
+
+        let batches = Arc::new(Mutex::new(Vec::new()));
+        let b = batches.clone();
+        engine.on_info_batch(Duration::from_secs(60), move |batch| {
+            b.lock().unwrap().push(batch.len())
+        });
+
+        let events = engine.subscribe();
+        let slow = engine.subscribe_with_capacity(1);
+        let dropped = engine.subscribe();
+        drop(dropped);
+
+        assert!(engine.stop().is_ok());
+        assert_eq!(4, log.lock().unwrap().len());
+        assert_eq!(2, events.try_iter().count());
+        // The events which didn't fit are dropped.
+        assert_eq!(1, slow.try_iter().count());
+        assert_eq!(2, engine.subscribers.len());
+        assert_eq!(vec![1], *batches.lock().unwrap());
+        assert!(matches!(engine.dispatch(), Err(Error::EngineClosed)));
+    }
+
+    #[test]
+    fn closed_during_handshake() {
+        let output = "id name Toy Engine\n";
+        assert!(matches!(
+            Engine::connect(IoTransport::new(output.as_bytes(), Vec::new())),
+            Err(Error::EngineClosed)
+        ));
+    }
+
+    #[test]
+    fn restart() {
+        let outputs = ["usiok\nreadyok\nbestmove resign\n", "usiok\nreadyok\n"];
+        let written = SharedBuffer::default();
+        let mut count = 0;
+        let buf = written.clone();
+        let mut engine = Engine::connect_with(move || {
+            count += 1;
+            buf.0.lock().unwrap().clear();
+            let output = outputs.get(count - 1).copied().unwrap_or("");
+            Ok(Box::new(IoTransport::new(output.as_bytes(), buf.clone())) as Box<dyn Transport>)
+        })
+        .unwrap();
+
+        engine
+            .send(&GuiCommand::SetOption(SetOptionParams::new(
+                "USI_Hash", "256",
+            )))
+            .unwrap();
+        engine.send(&GuiCommand::UsiNewGame).unwrap();
+        engine
+            .send(&GuiCommand::Position("startpos-sfen".to_string()))
+            .unwrap();
+        engine.send(&GuiCommand::Go(ThinkParams::new())).unwrap();
+        assert!(engine.recv().is_ok());
+
+        // Restarts are disabled by default.
+        assert!(matches!(engine.recv_event(), Err(Error::EngineClosed)));
+
+        engine.set_max_restarts(1).unwrap();
+        assert!(matches!(engine.recv_event(), Ok(EngineEvent::Restarted)));
+        assert_eq!(1, engine.restart_count());
+        assert_eq!(
+            "usi\nsetoption name USI_Hash value 256\nisready\nusinewgame\nposition sfen startpos-sfen\n",
+            std::str::from_utf8(&written.0.lock().unwrap()).unwrap()
+        );
+
+        // The limit of restarts is reached.
+        assert!(matches!(engine.recv_event(), Err(Error::EngineClosed)));
+    }
+
+    #[test]
+    fn restart_while_searching() {
+        let mut count = 0;
+        let mut engine = Engine::connect_with(move || {
+            count += 1;
+            Ok(if count == 1 {
+                // Crashes during the search.
+                Box::new(IoTransport::new("usiok\nreadyok\n".as_bytes(), Vec::new()))
+            } else {
+                Box::new(InProcessTransport::spawn(ToyEngine::new())) as Box<dyn Transport>
+            })
+        })
+        .unwrap();
+        engine.set_max_restarts(1).unwrap();
+
+        let mut search = engine.go(ThinkParams::new().infinite()).unwrap();
+        assert!(matches!(search.next_info(), Err(Error::EngineClosed)));
+        drop(search);
+        assert_eq!(1, engine.restart_count());
+        assert!(!engine.is_searching());
+        assert!(engine.check_health(Duration::from_secs(1)).unwrap());
+    }
+
+    #[test]
+    fn uci() {
+        let output = "id name Stockfish 16\n\
+                      option name Hash type spin default 16 min 1 max 33554432\n\
+                      uciok\nreadyok\n\
+                      info depth 1 seldepth 1 multipv 1 score cp 20 wdl 50 900 50 nodes 20 \
+                      nps 10000 hashfull 0 tbhits 0 time 2 pv e2e4\n\
+                      info depth 5 currmove e2e4 currmovenumber 1\n\
+                      info depth 9 seldepth 12 multipv 1 score mate 3 nodes 5000 pv f3f7 e8f7 c4f7\n\
+                      bestmove f3f7 ponder e8f7\n";
+        let written = SharedBuffer::default();
+        let transport = IoTransport::new(output.as_bytes(), written.clone());
+        let mut engine = Engine::connect_with_dialect(transport, Uci, Timeouts::default()).unwrap();
+        assert_eq!("UCI", engine.dialect().name());
+        assert_eq!("Stockfish 16", engine.info().name());
+        assert!(engine.options().get("Hash").is_some());
+
+        assert!(matches!(
+            engine.send(&GuiCommand::GameOver(GameOverKind::Win)),
+            Err(Error::InvalidArgument { .. })
+        ));
+        let fen = "r1bqkbnr/pppp1ppp/2n5/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4";
+        engine.send(&GuiCommand::Position(fen.to_string())).unwrap();
+        let mut search = engine
+            .go(ThinkParams::new().nodes(5000).movestogo(20))
+            .unwrap();
+        let infos = search.by_ref().collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(3, infos.len());
+        // Mate in 3 moves is 5 plies.
+        assert!(infos[2].contains(&InfoParams::Score(5, ScoreKind::MateExact)));
+        assert_eq!(
+            BestMoveParams::MakeMove("f3f7".to_string(), Some("e8f7".to_string())),
+            search.wait().unwrap()
+        );
+
+        assert_eq!(
+            format!("uci\nisready\nposition fen {fen}\ngo nodes 5000 movestogo 20\n"),
+            std::str::from_utf8(&written.0.lock().unwrap()).unwrap()
+        );
+    }
+
+    #[test]
+    fn timeout() {
+        let mut engine = Engine::connect(InProcessTransport::spawn(
+            ToyEngine::new()
+                .info(vec![InfoParams::Depth(1, None)])
+                .wait()
+                .hang(),
+        ))
+        .unwrap();
+        engine.prepare().unwrap();
+
+        engine.set_timeouts(Timeouts {
+            bestmove: Some(Duration::from_millis(50)),
+            ..Timeouts::default()
+        });
+        engine.send(&GuiCommand::Go(ThinkParams::new())).unwrap();
+        assert!(matches!(
+            engine.stop(),
+            Err(Error::Timeout {
+                phase: TimeoutPhase::BestMove
+            })
+        ));
+    }
+
+    #[test]
+    fn bench() {
+        let toy = ToyEngine::new().bench(BenchReport {
+            nodes: Some(1000),
+            nps: Some(500),
+            time: Some(Duration::from_secs(2)),
+        });
+        let calls = toy.calls();
+        let mut engine = Engine::connect(InProcessTransport::spawn(toy)).unwrap();
+        let report = engine.bench(&["64", "1"]).unwrap();
+        assert!(calls.lock().unwrap().contains(&"bench 64 1".to_string()));
+        assert_eq!(Some(1000), report.nodes);
+        assert_eq!(Some(500), report.nps);
+        assert_eq!(Some(Duration::from_secs(2)), report.time);
+
+        engine
+            .send(&GuiCommand::Go(ThinkParams::new().infinite()))
+            .unwrap();
+        assert!(matches!(engine.bench(&[]), Err(Error::IllegalState { .. })));
+    }
+
+    #[test]
+    fn option_change() {
+        let mut engine = Engine::connect(InProcessTransport::spawn(
+            ToyEngine::new().options(OptionRegistry::new().spin("Threads", 1, 1, 64)),
+        ))
+        .unwrap();
+        let (sender, changes) = mpsc::channel();
+        engine.on_option_change(move |change| sender.send(change.clone()).unwrap());
+
+        engine.set_option("Threads", "4").unwrap();
+        assert_eq!(
+            OptionChange {
+                name: "Threads".to_string(),
+                old: Some("1".to_string()),
+                new: Some("4".to_string()),
+                acknowledged: false,
+            },
+            changes.try_recv().unwrap()
+        );
+
+        // Setting the same value again is not a change.
+        engine.set_option("Threads", "4").unwrap();
+        assert!(changes.try_recv().is_err());
+
+        // The engine declares the option again with the original default,
+        // which keeps the value set.
+        engine.send(&GuiCommand::Usi).unwrap();
+        while !matches!(
+            engine.recv().unwrap().response(),
+            Some(EngineCommand::UsiOk)
+        ) {}
+        assert!(changes.try_recv().is_err());
+        assert_eq!(Some("4"), engine.options().value("Threads"));
+    }
+
+    #[test]
+    fn option_path() {
+        let options = OptionRegistry::new()
+            .filename("EvalFile", "nn.bin")
+            .with_usi_hash();
+        let toy = ToyEngine::new().options(options);
+        let mut engine = Engine::connect(InProcessTransport::spawn(toy)).unwrap();
+        assert_eq!(None, engine.working_dir());
+        assert_eq!(
+            Some(PathBuf::from("nn.bin")),
+            engine.option_path("EvalFile")
+        );
+
+        let dir = std::env::temp_dir();
+        engine.set_working_dir(&dir);
+        assert_eq!(Some(dir.join("nn.bin")), engine.option_path("EvalFile"));
+        assert!(matches!(
+            engine.set_option_path("EvalFile", "missing/nn.bin", true),
+            Err(Error::EngineIo(e)) if e.kind() == io::ErrorKind::NotFound
+        ));
+        engine
+            .set_option_path("EvalFile", "missing/nn.bin", false)
+            .unwrap();
+        assert_eq!(
+            Some(dir.join("missing/nn.bin")),
+            engine.option_path("EvalFile")
+        );
+        engine.set_option_path("EvalFile", &dir, true).unwrap();
+        assert!(matches!(
+            engine.set_option_path("USI_Hash", "hash.bin", false),
+            Err(Error::InvalidOptionValue { .. })
+        ));
+    }
+
+    #[test]
+    fn watchdog() {
+        let toy = ToyEngine::new()
+            .info(vec![InfoParams::Depth(1, None)])
+            .wait();
+        let mut engine = Engine::connect(InProcessTransport::spawn(toy)).unwrap();
+        engine.set_watchdog(Some(Watchdog {
+            timeout: Duration::from_millis(50),
+            action: StallAction::Stop,
+        }));
+        let events = engine.subscribe();
+
+        let search = engine.go(ThinkParams::new().infinite()).unwrap();
+        assert_eq!(BestMoveParams::Resign, search.wait().unwrap());
+        assert!(events
+            .try_iter()
+            .any(|event| matches!(event, EngineEvent::SearchStalled)));
+    }
+
+    #[test]
+    fn restart_unavailable() {
+        let output = "usiok\nreadyok\n";
+        let mut engine = Engine::connect(IoTransport::new(output.as_bytes(), Vec::new())).unwrap();
+        assert!(matches!(
+            engine.set_max_restarts(1),
+            Err(Error::IllegalState { .. })
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn spawn_process() {
+        let process = std::process::Command::new("sh")
+            .arg("-c")
+            .arg("read l; echo usiok; read l; echo readyok; read l; echo 'bestmove resign'")
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .spawn()
+            .unwrap();
+
+        let mut engine = Engine::from_process(process).expect("handshake failed");
+        engine
+            .send(&GuiCommand::Go(ThinkParams::new().infinite()))
+            .unwrap();
+        assert_eq!(
+            Some(EngineCommand::BestMove(BestMoveParams::Resign)),
+            *engine.recv().unwrap().response()
+        );
+        engine.close().unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn stderr() {
+        let process = std::process::Command::new("sh")
+            .arg("-c")
+            .arg("read l; echo 'loading eval' >&2; echo 'eval file not found' >&2; exit 1")
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .spawn()
+            .unwrap();
+
+        match Engine::from_process(process) {
+            Err(Error::EngineExited(report)) => {
+                assert_eq!(Some(1), report.exit_code);
+                assert_eq!(None, report.signal);
+                assert_eq!(vec!["loading eval", "eval file not found"], report.stderr);
+                assert_eq!(vec!["> usi"], report.protocol);
+            }
+            res => panic!("unexpected result: {res:?}"),
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn shutdown() {
+        // The engine ignores `quit`, so it is killed after the grace period.
+        let process = std::process::Command::new("sh")
+            .arg("-c")
+            .arg("read l; echo usiok; read l; echo readyok; while read l; do :; done; sleep 10")
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .spawn()
+            .unwrap();
+
+        let mut engine = Engine::from_process(process).expect("handshake failed");
+        engine.set_timeouts(Timeouts {
+            quit: Some(Duration::from_millis(100)),
+            ..Timeouts::default()
+        });
+        let start = Instant::now();
+        engine.shutdown().unwrap();
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+}
@@ -0,0 +1,206 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::engine::Engine;
+use super::options::EngineOption;
+use crate::error::Error;
+
+/// Evaluation files smaller than this are unlikely to be valid.
+const MIN_EVAL_FILE_SIZE: u64 = 1024 * 1024;
+
+/// Represents a problem found by `Engine::check_eval_files`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EvalFileIssue {
+    /// The path set to the option doesn't exist.
+    Missing { option: String, path: PathBuf },
+    /// A directory was expected but the path is a file, or vice versa.
+    WrongKind { option: String, path: PathBuf },
+    /// The evaluation file is too small to be valid.
+    TooSmall {
+        option: String,
+        path: PathBuf,
+        size: u64,
+    },
+    /// The evaluation directory contains no file large enough to be an evaluation file.
+    NoEvalFile { option: String, path: PathBuf },
+}
+
+impl fmt::Display for EvalFileIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EvalFileIssue::Missing { option, path } => {
+                write!(f, "{}: {} does not exist", option, path.display())
+            }
+            EvalFileIssue::WrongKind { option, path } => {
+                write!(f, "{}: {} has the wrong file type", option, path.display())
+            }
+            EvalFileIssue::TooSmall { option, path, size } => write!(
+                f,
+                "{}: {} is too small for an evaluation file ({} bytes)",
+                option,
+                path.display(),
+                size
+            ),
+            EvalFileIssue::NoEvalFile { option, path } => {
+                write!(
+                    f,
+                    "{}: {} contains no evaluation file",
+                    option,
+                    path.display()
+                )
+            }
+        }
+    }
+}
+
+impl Engine {
+    /// Checks the evaluation and book paths set to `EvalDir`, `EvalFile` and `BookDir`
+    /// options, or their spellings in Apery and dlshogi.
+    ///
+    /// Misconfigured evaluation paths are the most common reason engines fail to start,
+    /// often only reported as a crash after `isready`. Relative paths are resolved against
+    /// the working directory of the engine.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use usi::Engine;
+    ///
+    /// let mut engine = Engine::spawn("/path/to/usi_engine").unwrap();
+    /// engine.set_eval_dir("eval").unwrap();
+    /// for issue in engine.check_eval_files() {
+    ///     eprintln!("{}", issue);
+    /// }
+    /// ```
+    pub fn check_eval_files(&self) -> Vec<EvalFileIssue> {
+        let options = self.options();
+        let mut issues = Vec::new();
+        if let Some(option) = options.eval_dir_option() {
+            issues.extend(self.check_path(option, true, true));
+        }
+        if let Some(option) = options.eval_file_option() {
+            issues.extend(self.check_path(option, false, true));
+        }
+        if let Some(option) = options.book_dir_option() {
+            issues.extend(self.check_path(option, true, false));
+        }
+        issues
+    }
+
+    /// Sets the directory of evaluation files, which must exist.
+    ///
+    /// Returns `Error::UnknownOption` if the engine has no option of the evaluation directory.
+    pub fn set_eval_dir<P: AsRef<Path>>(&mut self, path: P) -> Result<(), Error> {
+        let name = option_name(self.options().eval_dir_option(), "EvalDir")?;
+        self.set_option_path(&name, path, true)
+    }
+
+    /// Sets the evaluation file, which must exist.
+    ///
+    /// Returns `Error::UnknownOption` if the engine has no option of the evaluation file.
+    pub fn set_eval_file<P: AsRef<Path>>(&mut self, path: P) -> Result<(), Error> {
+        let name = option_name(self.options().eval_file_option(), "EvalFile")?;
+        self.set_option_path(&name, path, true)
+    }
+
+    fn check_path(&self, option: &EngineOption, dir: bool, eval: bool) -> Option<EvalFileIssue> {
+        let name = option.name().to_string();
+        let path = self.option_path(option.name())?;
+        let Ok(metadata) = fs::metadata(&path) else {
+            return Some(EvalFileIssue::Missing { option: name, path });
+        };
+        if metadata.is_dir() != dir {
+            return Some(EvalFileIssue::WrongKind { option: name, path });
+        }
+        if !eval {
+            return None;
+        }
+        if !dir {
+            let size = metadata.len();
+            return (size < MIN_EVAL_FILE_SIZE).then_some(EvalFileIssue::TooSmall {
+                option: name,
+                path,
+                size,
+            });
+        }
+        let has_eval_file = fs::read_dir(&path).ok()?.any(|entry| {
+            entry
+                .and_then(|entry| entry.metadata())
+                .is_ok_and(|metadata| metadata.is_file() && metadata.len() >= MIN_EVAL_FILE_SIZE)
+        });
+        (!has_eval_file).then_some(EvalFileIssue::NoEvalFile { option: name, path })
+    }
+}
+
+fn option_name(option: Option<&EngineOption>, name: &str) -> Result<String, Error> {
+    option
+        .map(|option| option.name().to_string())
+        .ok_or_else(|| Error::UnknownOption {
+            name: name.to_string(),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::ToyEngine;
+    use crate::{InProcessTransport, OptionRegistry};
+
+    fn nnue_engine() -> ToyEngine {
+        ToyEngine::new().options(
+            OptionRegistry::new()
+                .filename("EvalDir", "eval")
+                .filename("EvalFile", "nn.bin")
+                .filename("BookDir", "book"),
+        )
+    }
+
+    #[test]
+    fn check_eval_files() {
+        let dir = std::env::temp_dir().join(format!("usi-eval-{}", std::process::id()));
+        fs::create_dir_all(dir.join("eval")).unwrap();
+        fs::write(
+            dir.join("eval/nn.bin"),
+            vec![0; MIN_EVAL_FILE_SIZE as usize],
+        )
+        .unwrap();
+        fs::write(dir.join("small.bin"), b"nnue").unwrap();
+
+        let mut engine = Engine::connect(InProcessTransport::spawn(nnue_engine())).unwrap();
+        engine.set_working_dir(&dir);
+        assert_eq!(
+            vec![
+                EvalFileIssue::Missing {
+                    option: "EvalFile".to_string(),
+                    path: dir.join("nn.bin"),
+                },
+                EvalFileIssue::Missing {
+                    option: "BookDir".to_string(),
+                    path: dir.join("book"),
+                },
+            ],
+            engine.check_eval_files()
+        );
+
+        engine.set_eval_file("eval/nn.bin").unwrap();
+        engine
+            .set_option_path("BookDir", "small.bin", false)
+            .unwrap();
+        assert_eq!(
+            vec![EvalFileIssue::WrongKind {
+                option: "BookDir".to_string(),
+                path: dir.join("small.bin"),
+            }],
+            engine.check_eval_files()
+        );
+
+        engine.set_eval_file("small.bin").unwrap();
+        engine.set_eval_dir(".").unwrap();
+        let issues = engine.check_eval_files();
+        assert!(matches!(issues[0], EvalFileIssue::NoEvalFile { .. }));
+        assert!(matches!(issues[1], EvalFileIssue::TooSmall { size: 4, .. }));
+        assert!(engine.set_eval_dir("missing").is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
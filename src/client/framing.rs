@@ -0,0 +1,86 @@
+use std::fmt;
+
+/// `Newline` is the terminator of the lines written to an engine.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Newline {
+    /// `\n`.
+    Lf,
+    /// `\r\n`.
+    CrLf,
+}
+
+impl Newline {
+    /// Returns the terminator of the current platform, which is `CrLf` on Windows and `Lf` elsewhere.
+    pub fn native() -> Self {
+        if cfg!(windows) {
+            Newline::CrLf
+        } else {
+            Newline::Lf
+        }
+    }
+
+    /// Returns the terminator as a string.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Newline::Lf => "\n",
+            Newline::CrLf => "\r\n",
+        }
+    }
+}
+
+impl fmt::Display for Newline {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// How lines are read from and written to an engine.
+///
+/// `ProcessTransport` writes the terminator of the current platform, as engines spawned locally
+/// are built for it. Other transports write `\n` unless configured by `Transport::set_framing`,
+/// because the platform of the remote engine is unknown.
+///
+/// # Examples
+/// ```
+/// use usi::{Framing, IoTransport, Newline, Transport};
+///
+/// let mut transport = IoTransport::new("\u{FEFF}usiok\r\n".as_bytes(), Vec::new());
+/// transport
+///     .set_framing(Framing {
+///         newline: Newline::CrLf,
+///         ..Framing::default()
+///     })
+///     .unwrap();
+/// transport.write_line("usi").unwrap();
+/// assert_eq!(Some("usiok".to_string()), transport.read_line().unwrap());
+/// assert_eq!(b"usi\r\n", &transport.writer()[..]);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Framing {
+    /// Removes carriage returns before the line feed of received lines. Defaults to `true`.
+    pub strip_cr: bool,
+    /// Removes a UTF-8 byte order mark from the first received line. Defaults to `true`.
+    pub strip_bom: bool,
+    /// The terminator of written lines. Defaults to `Newline::Lf`.
+    pub newline: Newline,
+}
+
+impl Default for Framing {
+    fn default() -> Self {
+        Framing {
+            strip_cr: true,
+            strip_bom: true,
+            newline: Newline::Lf,
+        }
+    }
+}
+
+impl Framing {
+    /// Returns the default framing with the terminator of the current platform.
+    pub fn native() -> Self {
+        Framing {
+            newline: Newline::native(),
+            ..Framing::default()
+        }
+    }
+}
@@ -0,0 +1,374 @@
+use std::env;
+use std::ffi::OsStr;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use super::transcript::{
+    read_entries, Direction, RecordingTransport, SharedBuffer, TranscriptEntry,
+};
+use super::transport::Transport;
+use crate::error::Error;
+
+/// The environment variable which makes `GoldenTranscript` record even if the file exists.
+const RECORD_VAR: &str = "USI_RECORD_GOLDEN";
+
+/// The environment variable set by CI services, where missing golden files are not recorded.
+const CI_VAR: &str = "CI";
+
+/// Keywords followed by a time in milliseconds in `go` and `info` lines.
+const TIME_KEYWORDS: [&str; 6] = ["time", "btime", "wtime", "byoyomi", "binc", "winc"];
+
+/// Keywords followed by a node count in `info` lines.
+const NODE_KEYWORDS: [&str; 2] = ["nodes", "nps"];
+
+/// Represents whether a `GoldenTranscript` records a session or verifies one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum GoldenMode {
+    /// Writes the session to the golden file.
+    Record,
+    /// Compares the session with the golden file.
+    Verify,
+}
+
+/// The differences allowed between a golden transcript and a new session.
+///
+/// `None` ignores the kind of value entirely.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Tolerance {
+    /// The absolute difference allowed in the elapsed times of entries and the times in
+    /// `go` and `info` lines. Defaults to 500 milliseconds.
+    pub time: Option<Duration>,
+    /// The relative difference allowed in `nodes` and `nps` of `info` lines. Defaults to `0.1`.
+    pub nodes: Option<f64>,
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Tolerance {
+            time: Some(Duration::from_millis(500)),
+            nodes: Some(0.1),
+        }
+    }
+}
+
+impl Tolerance {
+    /// Returns a tolerance allowing no difference.
+    pub fn exact() -> Self {
+        Tolerance {
+            time: Some(Duration::ZERO),
+            nodes: Some(0.0),
+        }
+    }
+
+    /// Compares `actual` with `expected` and returns the mismatches.
+    ///
+    /// The lines of each direction are compared in order, so the interleaving of sent and
+    /// received lines may differ.
+    pub fn diff(
+        &self,
+        expected: &[TranscriptEntry],
+        actual: &[TranscriptEntry],
+    ) -> Vec<GoldenMismatch> {
+        let mut mismatches = Vec::new();
+        for direction in [Direction::Sent, Direction::Received] {
+            let expected: Vec<_> = expected
+                .iter()
+                .filter(|e| e.direction == direction)
+                .collect();
+            let actual: Vec<_> = actual.iter().filter(|e| e.direction == direction).collect();
+            for index in 0..expected.len().max(actual.len()) {
+                let (e, a) = (expected.get(index), actual.get(index));
+                let matches = match (e, a) {
+                    (Some(e), Some(a)) => self.entry_matches(e, a),
+                    _ => false,
+                };
+                if !matches {
+                    mismatches.push(GoldenMismatch {
+                        direction,
+                        index,
+                        expected: e.map(|&e| e.clone()),
+                        actual: a.map(|&a| a.clone()),
+                    });
+                }
+            }
+        }
+        mismatches
+    }
+
+    fn entry_matches(&self, expected: &TranscriptEntry, actual: &TranscriptEntry) -> bool {
+        if let Some(time) = self.time {
+            if expected.elapsed.abs_diff(actual.elapsed) > time {
+                return false;
+            }
+        }
+        self.line_matches(&expected.line, &actual.line)
+    }
+
+    fn line_matches(&self, expected: &str, actual: &str) -> bool {
+        let expected: Vec<_> = expected.split_whitespace().collect();
+        let actual: Vec<_> = actual.split_whitespace().collect();
+        if expected.len() != actual.len() {
+            return false;
+        }
+        let tolerant = matches!(expected.first(), Some(&"info") | Some(&"go"));
+
+        let mut previous = "";
+        for (i, (e, a)) in expected.iter().zip(&actual).enumerate() {
+            if e != a {
+                let within = tolerant
+                    && match (e.parse::<u64>(), a.parse::<u64>()) {
+                        (Ok(e), Ok(a)) => self.number_matches(previous, e, a),
+                        _ => false,
+                    };
+                if !within {
+                    return false;
+                }
+            }
+            // Anything after `string` is free text.
+            if *e == "string" {
+                return expected[i..] == actual[i..];
+            }
+            previous = e;
+        }
+        true
+    }
+
+    fn number_matches(&self, keyword: &str, expected: u64, actual: u64) -> bool {
+        if TIME_KEYWORDS.contains(&keyword) {
+            self.time
+                .is_none_or(|t| expected.abs_diff(actual) as u128 <= t.as_millis())
+        } else if NODE_KEYWORDS.contains(&keyword) {
+            self.nodes
+                .is_none_or(|r| expected.abs_diff(actual) as f64 <= expected as f64 * r)
+        } else {
+            false
+        }
+    }
+}
+
+/// Represents a line of a session which differs from the golden transcript.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GoldenMismatch {
+    /// The direction of the lines compared.
+    pub direction: Direction,
+    /// The index of the line among the lines of `direction`.
+    pub index: usize,
+    /// The line in the golden transcript, or `None` if the session has more lines.
+    pub expected: Option<TranscriptEntry>,
+    /// The line in the session, or `None` if the session ended early.
+    pub actual: Option<TranscriptEntry>,
+}
+
+impl fmt::Display for GoldenMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let direction = match self.direction {
+            Direction::Sent => "sent",
+            Direction::Received => "received",
+        };
+        write!(f, "{} line {}: expected ", direction, self.index)?;
+        match &self.expected {
+            Some(entry) => write!(f, "{:?}", entry.to_string())?,
+            None => f.write_str("nothing")?,
+        }
+        f.write_str(" but got ")?;
+        match &self.actual {
+            Some(entry) => write!(f, "{:?}", entry.to_string()),
+            None => f.write_str("nothing"),
+        }
+    }
+}
+
+/// `GoldenTranscript` records an engine session to a file once, and verifies later sessions
+/// against it, to regression test GUIs and engines.
+///
+/// The mode is `GoldenMode::Record` if the `USI_RECORD_GOLDEN` environment variable is set to
+/// a value other than `0` or `false`, or if the file doesn't exist outside CI, where the `CI`
+/// environment variable is set. Otherwise it is `GoldenMode::Verify`, and a missing file fails
+/// `GoldenTranscript::finish`. Sessions are compared with `Tolerance`, so timing and node counts
+/// may differ slightly between runs.
+///
+/// # Examples
+/// ```
+/// use usi::{Engine, GoldenMode, GoldenTranscript, MockEngine, Tolerance};
+///
+/// let path = std::env::temp_dir().join(format!("usi-golden-doc-{}.txt", std::process::id()));
+/// // Recorded once, as with `USI_RECORD_GOLDEN=1`.
+/// let golden = GoldenTranscript::open(&path).mode(GoldenMode::Record);
+/// let engine = Engine::connect(golden.wrap(MockEngine::new("Mock"))).unwrap();
+/// engine.shutdown().unwrap();
+/// golden.finish().unwrap();
+///
+/// // Verified by later runs.
+/// let golden = GoldenTranscript::open(&path).tolerance(Tolerance::default());
+/// let engine = Engine::connect(golden.wrap(MockEngine::new("Mock"))).unwrap();
+/// engine.shutdown().unwrap();
+/// assert!(golden.finish().unwrap().is_empty());
+/// # std::fs::remove_file(&path).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct GoldenTranscript {
+    path: PathBuf,
+    mode: GoldenMode,
+    tolerance: Tolerance,
+    buffer: SharedBuffer,
+}
+
+impl GoldenTranscript {
+    /// Creates a golden transcript at `path`, choosing the mode from the file and environment.
+    pub fn open<P: AsRef<Path>>(path: P) -> Self {
+        let path = path.as_ref().to_path_buf();
+        let record = enabled(env::var_os(RECORD_VAR).as_deref());
+        let ci = enabled(env::var_os(CI_VAR).as_deref());
+        let mode = if record || (!path.exists() && !ci) {
+            GoldenMode::Record
+        } else {
+            GoldenMode::Verify
+        };
+        GoldenTranscript {
+            path,
+            mode,
+            tolerance: Tolerance::default(),
+            buffer: SharedBuffer::default(),
+        }
+    }
+
+    /// Overrides the mode.
+    #[must_use]
+    pub fn mode(mut self, mode: GoldenMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Sets the differences allowed in `GoldenMode::Verify`.
+    #[must_use]
+    pub fn tolerance(mut self, tolerance: Tolerance) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    /// Returns the mode.
+    pub fn get_mode(&self) -> GoldenMode {
+        self.mode
+    }
+
+    /// Wraps `inner` to record the session into this transcript.
+    pub fn wrap<T: Transport>(&self, inner: T) -> impl Transport {
+        RecordingTransport::new(inner, self.buffer.clone())
+    }
+
+    /// Ends the session, writing the golden file in `GoldenMode::Record` and returning the
+    /// mismatches against it in `GoldenMode::Verify`, which fails if the file doesn't exist.
+    pub fn finish(&self) -> Result<Vec<GoldenMismatch>, Error> {
+        let session = self.buffer.0.lock().unwrap().clone();
+        match self.mode {
+            GoldenMode::Record => {
+                if let Some(parent) = self.path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::write(&self.path, session)?;
+                Ok(Vec::new())
+            }
+            GoldenMode::Verify => {
+                let expected = read_entries(BufReader::new(File::open(&self.path)?))?;
+                let actual = read_entries(&session[..])?;
+                Ok(self.tolerance.diff(&expected, &actual))
+            }
+        }
+    }
+}
+
+/// Returns `true` if an environment variable is set to a value other than empty, `0` or `false`.
+fn enabled(value: Option<&OsStr>) -> bool {
+    value.is_some_and(|value| !matches!(value.to_str(), Some("" | "0" | "false")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::{Engine, MockEngine};
+    use crate::protocol::ThinkParams;
+
+    fn session(golden: &GoldenTranscript, nodes: u64) {
+        let mock = MockEngine::new("Mock")
+            .expect("go byoyomi 1000")
+            .reply(format!(
+                "info depth 3 time 12 nodes {nodes} score cp 40 pv 7g7f"
+            ))
+            .reply("bestmove 7g7f");
+        let mut engine = Engine::connect(golden.wrap(mock)).unwrap();
+        let params = ThinkParams::new().byoyomi(Duration::from_secs(1));
+        engine.go(params).unwrap().wait().unwrap();
+        engine.shutdown().unwrap();
+    }
+
+    #[test]
+    fn record_and_verify() {
+        let path = env::temp_dir().join(format!("usi-golden-{}.txt", std::process::id()));
+        let golden = GoldenTranscript::open(&path).mode(GoldenMode::Record);
+        session(&golden, 1000);
+        assert!(golden.finish().unwrap().is_empty());
+
+        let golden = GoldenTranscript::open(&path).mode(GoldenMode::Verify);
+        session(&golden, 1050);
+        assert!(golden.finish().unwrap().is_empty());
+
+        let golden = GoldenTranscript::open(&path).mode(GoldenMode::Verify);
+        session(&golden, 2000);
+        let mismatches = golden.finish().unwrap();
+        assert_eq!(1, mismatches.len());
+        assert_eq!(Direction::Received, mismatches[0].direction);
+
+        fs::remove_file(&path).unwrap();
+        let golden = GoldenTranscript::open(&path).mode(GoldenMode::Verify);
+        session(&golden, 1000);
+        assert!(matches!(golden.finish(), Err(Error::EngineIo(_))));
+    }
+
+    #[test]
+    fn environment() {
+        assert!(!enabled(None));
+        for value in ["", "0", "false"] {
+            assert!(!enabled(Some(OsStr::new(value))), "{value}");
+        }
+        for value in ["1", "true"] {
+            assert!(enabled(Some(OsStr::new(value))), "{value}");
+        }
+    }
+
+    #[test]
+    fn tolerance() {
+        let entry = |elapsed: u64, direction, line: &str| TranscriptEntry {
+            elapsed: Duration::from_millis(elapsed),
+            direction,
+            line: line.to_string(),
+        };
+        let expected = [
+            entry(0, Direction::Sent, "go btime 1000 wtime 1000 byoyomi 0"),
+            entry(10, Direction::Received, "info nodes 100 string 1000"),
+        ];
+        let actual = [
+            entry(300, Direction::Sent, "go btime 1200 wtime 1000 byoyomi 0"),
+            entry(20, Direction::Received, "info nodes 109 string 1000"),
+            entry(30, Direction::Received, "bestmove resign"),
+        ];
+
+        let mismatches = Tolerance::default().diff(&expected, &actual);
+        assert_eq!(1, mismatches.len());
+        assert_eq!(1, mismatches[0].index);
+        assert_eq!(None, mismatches[0].expected);
+        assert_eq!(
+            "received line 1: expected nothing but got \"30 < bestmove resign\"",
+            mismatches[0].to_string()
+        );
+
+        let lenient = Tolerance {
+            time: None,
+            nodes: None,
+        };
+        assert_eq!(1, lenient.diff(&expected, &actual).len());
+        assert_eq!(3, Tolerance::exact().diff(&expected, &actual).len());
+    }
+}
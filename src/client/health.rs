@@ -0,0 +1,149 @@
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use super::engine::Engine;
+
+/// `HealthMonitor` periodically checks an idle engine with `isready` on a background thread.
+///
+/// A check is performed when the engine is not searching and has been idle for `interval`.
+/// If `readyok` is not received within `timeout`, the engine is flagged as unhealthy
+/// and `EngineEvent::Unhealthy` is notified to the handlers registered on the engine.
+/// The monitor stops when it is dropped.
+///
+/// # Examples
+/// ```no_run
+/// use std::sync::{Arc, Mutex};
+/// use std::time::Duration;
+/// use usi::{Engine, EngineEvent, HealthMonitor};
+///
+/// let mut engine = Engine::spawn("/path/to/usi_engine").unwrap();
+/// engine.on_event(|event| {
+///     if let EngineEvent::Unhealthy = event {
+///         eprintln!("the engine is not responding");
+///     }
+/// });
+///
+/// let engine = Arc::new(Mutex::new(engine));
+/// let monitor = HealthMonitor::spawn(engine.clone(), Duration::from_secs(30), Duration::from_secs(5));
+/// ```
+#[derive(Debug)]
+pub struct HealthMonitor {
+    stop: Option<Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl HealthMonitor {
+    pub fn spawn(engine: Arc<Mutex<Engine>>, interval: Duration, timeout: Duration) -> Self {
+        let (stop, receiver) = mpsc::channel();
+
+        let thread = thread::spawn(move || loop {
+            let wait = match engine.lock() {
+                Ok(engine) => interval.saturating_sub(engine.idle_time()),
+                Err(_) => break,
+            };
+            match receiver.recv_timeout(wait.max(Duration::from_millis(10))) {
+                Err(RecvTimeoutError::Timeout) => {}
+                _ => break,
+            }
+
+            let Ok(mut engine) = engine.lock() else { break };
+            if !engine.is_searching() && engine.idle_time() >= interval {
+                // Errors other than the failed check mean the engine is in use.
+                let _ = engine.check_health(timeout);
+            }
+        });
+
+        HealthMonitor {
+            stop: Some(stop),
+            thread: Some(thread),
+        }
+    }
+
+    /// Stops the monitor and waits for the background thread to finish.
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        drop(self.stop.take());
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for HealthMonitor {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::ToyEngine;
+    use crate::*;
+
+    #[test]
+    fn health() {
+        let mut engine = Engine::connect(InProcessTransport::spawn(
+            // The engine hangs after a game starts.
+            ToyEngine::new().new_game_delay(Duration::from_secs(1)),
+        ))
+        .unwrap();
+        assert!(engine.check_health(Duration::from_secs(1)).unwrap());
+
+        let unhealthy = Arc::new(Mutex::new(false));
+        let flag = unhealthy.clone();
+        engine.on_event(move |event| {
+            if let EngineEvent::Unhealthy = event {
+                *flag.lock().unwrap() = true;
+            }
+        });
+        engine.send(&GuiCommand::UsiNewGame).unwrap();
+
+        let engine = Arc::new(Mutex::new(engine));
+        let monitor = HealthMonitor::spawn(
+            engine.clone(),
+            Duration::from_millis(10),
+            Duration::from_millis(50),
+        );
+        while !*unhealthy.lock().unwrap() {
+            thread::sleep(Duration::from_millis(10));
+        }
+        monitor.stop();
+        assert!(!engine.lock().unwrap().is_healthy());
+    }
+
+    #[test]
+    fn restarted_while_searching() {
+        let mut count = 0;
+        let mut engine = Engine::connect_with(move || {
+            count += 1;
+            Ok(if count == 1 {
+                // Crashes during the search.
+                Box::new(IoTransport::new("usiok\nreadyok\n".as_bytes(), Vec::new()))
+            } else {
+                let toy = ToyEngine::new().bench(BenchReport::default());
+                Box::new(InProcessTransport::spawn(toy)) as Box<dyn Transport>
+            })
+        })
+        .unwrap();
+        engine.set_max_restarts(1).unwrap();
+
+        engine
+            .send(&GuiCommand::Go(ThinkParams::new().infinite()))
+            .unwrap();
+        assert!(matches!(
+            engine.check_health(Duration::from_secs(1)),
+            Err(Error::IllegalState { .. })
+        ));
+        assert!(matches!(engine.recv_event(), Ok(EngineEvent::Restarted)));
+
+        // The restarted engine is idle and healthy.
+        assert!(engine.check_health(Duration::from_secs(1)).unwrap());
+        assert!(engine.bench(&[]).is_ok());
+    }
+}
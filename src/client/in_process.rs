@@ -0,0 +1,141 @@
+use std::fmt;
+use std::io;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use super::transport::Transport;
+use crate::server::{Dispatcher, Responder, UsiEngine, DEFAULT_INFO_INTERVAL};
+
+/// `InProcessTransport` runs a `UsiEngine` on a thread of the current process
+/// and connects to it through channels instead of pipes.
+///
+/// # Examples
+///
+/// ```
+/// use usi::{Engine, InfoSender, InProcessTransport, Responder, StopToken, ThinkParams, UsiEngine};
+///
+/// struct ToyEngine;
+///
+/// impl UsiEngine for ToyEngine {
+///     fn name(&self) -> String {
+///         "Toy".to_string()
+///     }
+///
+///     fn author(&self) -> String {
+///         "usi-rs".to_string()
+///     }
+///
+///     fn go(&mut self, _params: ThinkParams, _responder: &Responder, _info: InfoSender, _stop: StopToken) {}
+/// }
+///
+/// let engine = Engine::connect(InProcessTransport::spawn(ToyEngine)).unwrap();
+/// assert_eq!("Toy", engine.info().name());
+/// ```
+pub struct InProcessTransport {
+    sender: Option<Sender<String>>,
+    receiver: Receiver<String>,
+    thread: Option<JoinHandle<()>>,
+    timeout: Option<Duration>,
+}
+
+impl fmt::Debug for InProcessTransport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("InProcessTransport").finish_non_exhaustive()
+    }
+}
+
+impl InProcessTransport {
+    /// Starts `engine` on a new thread.
+    /// The thread exits after `quit` is handled or the transport is closed,
+    /// calling `UsiEngine::quit` in either case.
+    pub fn spawn<E: UsiEngine>(mut engine: E) -> Self {
+        let (gui_sender, gui_receiver) = mpsc::channel::<String>();
+        let (engine_sender, engine_receiver) = mpsc::channel();
+
+        let thread = thread::spawn(move || {
+            let mut dispatcher =
+                Dispatcher::new(Responder::new(engine_sender), DEFAULT_INFO_INTERVAL);
+            for line in gui_receiver {
+                if dispatcher.dispatch(&mut engine, &line) {
+                    break;
+                }
+            }
+            engine.quit();
+        });
+
+        InProcessTransport {
+            sender: Some(gui_sender),
+            receiver: engine_receiver,
+            thread: Some(thread),
+            timeout: None,
+        }
+    }
+}
+
+impl Transport for InProcessTransport {
+    fn read_line(&mut self) -> io::Result<Option<String>> {
+        match self.timeout {
+            Some(timeout) => match self.receiver.recv_timeout(timeout) {
+                Ok(line) => Ok(Some(line)),
+                Err(RecvTimeoutError::Timeout) => Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    "no line received within the timeout",
+                )),
+                Err(RecvTimeoutError::Disconnected) => Ok(None),
+            },
+            None => Ok(self.receiver.recv().ok()),
+        }
+    }
+
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        self.sender
+            .as_ref()
+            .and_then(|s| s.send(line.to_string()).ok())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::BrokenPipe, "the engine has stopped"))
+    }
+
+    fn close(&mut self) -> io::Result<()> {
+        self.sender.take();
+        if let Some(thread) = self.thread.take() {
+            thread
+                .join()
+                .map_err(|_| io::Error::other("the engine thread panicked"))?;
+        }
+        Ok(())
+    }
+
+    fn set_read_timeout(&mut self, timeout: Option<Duration>) -> io::Result<()> {
+        self.timeout = timeout;
+        Ok(())
+    }
+}
+
+impl Drop for InProcessTransport {
+    fn drop(&mut self) {
+        // Detach the engine thread instead of blocking until it finishes.
+        self.sender.take();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::ToyEngine;
+    use crate::{BestMoveParams, Engine, EngineCommand, Error, GuiCommand, ThinkParams};
+
+    #[test]
+    fn it_works() {
+        let mut engine =
+            Engine::connect(InProcessTransport::spawn(ToyEngine::new().background())).unwrap();
+        engine.send(&GuiCommand::Go(ThinkParams::new())).unwrap();
+        assert_eq!(
+            Some(EngineCommand::BestMove(BestMoveParams::Resign)),
+            *engine.recv().unwrap().response()
+        );
+
+        engine.send(&GuiCommand::Quit).unwrap();
+        assert!(matches!(engine.recv(), Err(Error::EngineClosed)));
+        engine.close().unwrap();
+    }
+}
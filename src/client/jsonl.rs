@@ -0,0 +1,376 @@
+use std::io::{self, BufRead, Lines, Write};
+use std::time::Duration;
+
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+
+use super::transcript::{Direction, TranscriptEntry};
+use crate::error::Error;
+use crate::protocol::*;
+
+/// The name of the format written in the first line of JSONL transcripts.
+const FORMAT_NAME: &str = "usi-transcript";
+
+/// The version of the JSONL transcript format written by `JsonlTranscriptWriter`.
+///
+/// Readers accept transcripts up to this version, and ignore unknown fields,
+/// which are added without changing the version.
+pub const TRANSCRIPT_FORMAT_VERSION: u32 = 1;
+
+/// `JsonlTranscriptWriter` writes a transcript as JSON Lines.
+///
+/// The first line is the header `{"format":"usi-transcript","version":1}`, followed by
+/// an object per entry with the elapsed time in milliseconds, the direction (`sent` or
+/// `received`), the raw line, and the command parsed from it, which is `null` if the line
+/// can't be parsed. Commands have their name as `type` and their parameters as fields
+/// in alphabetical order, with times in milliseconds.
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+/// use usi::{Direction, JsonlTranscriptReader, JsonlTranscriptWriter, TranscriptEntry};
+///
+/// let entry = TranscriptEntry {
+///     elapsed: Duration::from_millis(1500),
+///     direction: Direction::Received,
+///     line: "bestmove 7g7f ponder 3c3d".to_string(),
+/// };
+/// let mut writer = JsonlTranscriptWriter::new(Vec::new());
+/// writer.write(&entry).unwrap();
+/// let jsonl = String::from_utf8(writer.into_inner()).unwrap();
+/// assert_eq!(
+///     "{\"format\":\"usi-transcript\",\"version\":1}\n\
+///      {\"elapsed_ms\":1500,\"direction\":\"received\",\"line\":\"bestmove 7g7f ponder 3c3d\",\
+///      \"command\":{\"move\":\"7g7f\",\"ponder\":\"3c3d\",\"type\":\"bestmove\"}}\n",
+///     jsonl
+/// );
+///
+/// let entries = JsonlTranscriptReader::new(jsonl.as_bytes()).unwrap();
+/// assert_eq!(vec![entry], entries.collect::<Result<Vec<_>, _>>().unwrap());
+/// ```
+#[derive(Debug)]
+pub struct JsonlTranscriptWriter<W: Write> {
+    writer: W,
+    started: bool,
+}
+
+impl<W: Write> JsonlTranscriptWriter<W> {
+    pub fn new(writer: W) -> Self {
+        JsonlTranscriptWriter {
+            writer,
+            started: false,
+        }
+    }
+
+    /// Writes `entry`, after the header if it is the first one.
+    pub fn write(&mut self, entry: &TranscriptEntry) -> io::Result<()> {
+        if !self.started {
+            writeln!(self.writer, "{}", header_line())?;
+            self.started = true;
+        }
+        writeln!(self.writer, "{}", entry_line(entry))
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// `JsonlTranscriptReader` reads the entries of a transcript written by
+/// `JsonlTranscriptWriter`, checking the version in the header.
+///
+/// The parsed commands are not read, since they are parsed again from the raw lines.
+#[derive(Debug)]
+pub struct JsonlTranscriptReader<R: BufRead> {
+    lines: Lines<R>,
+    version: u32,
+}
+
+impl<R: BufRead> JsonlTranscriptReader<R> {
+    /// Reads the header from `reader`. Returns `Error::ProtocolViolation` if it isn't
+    /// a transcript or its version is newer than `TRANSCRIPT_FORMAT_VERSION`.
+    pub fn new(reader: R) -> Result<Self, Error> {
+        let mut lines = reader.lines();
+        let header = lines.find(|line| !matches!(line, Ok(line) if line.trim().is_empty()));
+        let version = match header {
+            Some(line) => parse_header(&line?)?,
+            None => TRANSCRIPT_FORMAT_VERSION,
+        };
+        Ok(JsonlTranscriptReader { lines, version })
+    }
+
+    /// Returns the version of the transcript.
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+}
+
+impl<R: BufRead> Iterator for JsonlTranscriptReader<R> {
+    type Item = Result<TranscriptEntry, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.lines.next()? {
+                Ok(line) if line.trim().is_empty() => continue,
+                Ok(line) => return Some(parse_entry(&line)),
+                Err(e) => return Some(Err(e.into())),
+            }
+        }
+    }
+}
+
+pub(crate) fn header_line() -> String {
+    json!({"format": FORMAT_NAME, "version": TRANSCRIPT_FORMAT_VERSION}).to_string()
+}
+
+/// Returns the version of a transcript from its header.
+pub(crate) fn parse_header(line: &str) -> Result<u32, Error> {
+    let header: Value = serde_json::from_str(line)?;
+    if header["format"] != FORMAT_NAME {
+        return Err(Error::ProtocolViolation {
+            reason: "not a USI transcript",
+        });
+    }
+    match header["version"].as_u64() {
+        Some(version) if version <= u64::from(TRANSCRIPT_FORMAT_VERSION) => Ok(version as u32),
+        _ => Err(Error::ProtocolViolation {
+            reason: "unsupported transcript version",
+        }),
+    }
+}
+
+pub(crate) fn entry_line(entry: &TranscriptEntry) -> String {
+    let (direction, command) = match entry.direction {
+        Direction::Sent => (
+            "sent",
+            GuiCommand::parse(&entry.line)
+                .ok()
+                .map(|c| gui_command_json(&c)),
+        ),
+        Direction::Received => (
+            "received",
+            EngineCommand::parse(&entry.line)
+                .ok()
+                .map(|c| engine_command_json(&c)),
+        ),
+    };
+    let entry = JsonlEntry {
+        elapsed_ms: entry.elapsed.as_millis() as u64,
+        direction,
+        line: &entry.line,
+        command,
+    };
+    serde_json::to_string(&entry).unwrap()
+}
+
+/// An entry of a JSONL transcript, whose fields are written in this order.
+#[derive(Serialize)]
+struct JsonlEntry<'a> {
+    elapsed_ms: u64,
+    direction: &'a str,
+    line: &'a str,
+    command: Option<Value>,
+}
+
+pub(crate) fn parse_entry(line: &str) -> Result<TranscriptEntry, Error> {
+    let entry: Value = serde_json::from_str(line)?;
+    let direction = match entry["direction"].as_str() {
+        Some("sent") => Direction::Sent,
+        Some("received") => Direction::Received,
+        _ => return Err(Error::IllegalSyntax),
+    };
+    Ok(TranscriptEntry {
+        elapsed: Duration::from_millis(entry["elapsed_ms"].as_u64().ok_or(Error::IllegalSyntax)?),
+        direction,
+        line: entry["line"]
+            .as_str()
+            .ok_or(Error::IllegalSyntax)?
+            .to_string(),
+    })
+}
+
+fn millis(time: Option<Duration>) -> Value {
+    json!(time.map(|t| t.as_millis() as u64))
+}
+
+/// Returns `command` as an object with its name as `type`.
+pub(crate) fn gui_command_json(command: &GuiCommand) -> Value {
+    match command {
+        GuiCommand::Bench(args) => json!({"type": "bench", "args": args}),
+        GuiCommand::GameOver(kind) => json!({"type": "gameover", "result": kind.to_string()}),
+        GuiCommand::Go(params) => {
+            let mate = match params.get_mate() {
+                Some(MateParam::Timeout(t)) => json!(t.as_millis() as u64),
+                Some(MateParam::Infinite) => json!("infinite"),
+                None => Value::Null,
+            };
+            json!({
+                "type": "go",
+                "ponder": params.is_ponder(),
+                "btime": millis(params.get_btime()),
+                "wtime": millis(params.get_wtime()),
+                "byoyomi": millis(params.get_byoyomi()),
+                "binc": millis(params.get_binc()),
+                "winc": millis(params.get_winc()),
+                "infinite": params.is_infinite(),
+                "mate": mate,
+                "depth": params.get_depth(),
+            })
+        }
+        GuiCommand::IsReady => json!({"type": "isready"}),
+        GuiCommand::Ponderhit => json!({"type": "ponderhit"}),
+        GuiCommand::Position(position) => json!({"type": "position", "position": position}),
+        GuiCommand::SetOption(params) => {
+            json!({"type": "setoption", "name": params.name, "value": params.value})
+        }
+        GuiCommand::Stop => json!({"type": "stop"}),
+        GuiCommand::Usi => json!({"type": "usi"}),
+        GuiCommand::UsiNewGame => json!({"type": "usinewgame"}),
+        GuiCommand::Quit => json!({"type": "quit"}),
+    }
+}
+
+/// Returns `command` as an object with its name as `type`.
+pub(crate) fn engine_command_json(command: &EngineCommand) -> Value {
+    match command {
+        EngineCommand::Id(IdParams::Name(name)) => json!({"type": "id", "name": name}),
+        EngineCommand::Id(IdParams::Author(author)) => json!({"type": "id", "author": author}),
+        EngineCommand::BestMove(params) => {
+            let (mv, ponder) = match params {
+                BestMoveParams::MakeMove(mv, ponder) => (mv.as_str(), ponder.as_deref()),
+                BestMoveParams::Resign => ("resign", None),
+                BestMoveParams::Win => ("win", None),
+            };
+            json!({"type": "bestmove", "move": mv, "ponder": ponder})
+        }
+        EngineCommand::Checkmate(CheckmateParams::Mate(moves)) => {
+            json!({"type": "checkmate", "moves": moves})
+        }
+        EngineCommand::Checkmate(params) => {
+            let result = match params {
+                CheckmateParams::NoMate => "nomate",
+                CheckmateParams::NotImplemented => "notimplemented",
+                _ => "timeout",
+            };
+            json!({"type": "checkmate", "result": result})
+        }
+        EngineCommand::Info(params) => {
+            let mut info = Map::new();
+            info.insert("type".to_string(), json!("info"));
+            info.extend(info_json(params));
+            Value::Object(info)
+        }
+        EngineCommand::Option(params) => json!({
+            "type": "option",
+            "name": params.name,
+            "option_type": params.value.to_string(),
+        }),
+        EngineCommand::ReadyOk => json!({"type": "readyok"}),
+        EngineCommand::UsiOk => json!({"type": "usiok"}),
+        EngineCommand::Unknown => Value::Null,
+    }
+}
+
+/// Returns the fields of `info`, e.g. `{"depth": 10, "score": {"cp": 30}, "pv": ["7g7f"]}`.
+pub(crate) fn info_json(params: &[InfoParams]) -> Map<String, Value> {
+    let mut info = Map::new();
+    for param in params {
+        let (key, value) = match param {
+            InfoParams::CurrMove(mv) => ("currmove", json!(mv)),
+            InfoParams::Depth(depth, seldepth) => {
+                if let Some(seldepth) = seldepth {
+                    info.insert("seldepth".to_string(), json!(seldepth));
+                }
+                ("depth", json!(depth))
+            }
+            InfoParams::HashFull(n) => ("hashfull", json!(n)),
+            InfoParams::MultiPv(n) => ("multipv", json!(n)),
+            InfoParams::Nodes(n) => ("nodes", json!(n)),
+            InfoParams::Nps(n) => ("nps", json!(n)),
+            InfoParams::Pv(moves) => ("pv", json!(moves)),
+            InfoParams::Score(value, kind) => ("score", score_json(*value, *kind)),
+            InfoParams::Text(text) => ("string", json!(text)),
+            InfoParams::Time(time) => ("time", json!(time.as_millis() as u64)),
+        };
+        info.insert(key.to_string(), value);
+    }
+    info
+}
+
+fn score_json(value: i32, kind: ScoreKind) -> Value {
+    let (key, bound) = match kind {
+        ScoreKind::CpExact => ("cp", None),
+        ScoreKind::CpLowerbound => ("cp", Some("lowerbound")),
+        ScoreKind::CpUpperbound => ("cp", Some("upperbound")),
+        ScoreKind::MateExact | ScoreKind::MateSignOnly => ("mate", None),
+        ScoreKind::MateLowerbound => ("mate", Some("lowerbound")),
+        ScoreKind::MateUpperbound => ("mate", Some("upperbound")),
+    };
+    let mut score = json!({ key: value });
+    if let Some(bound) = bound {
+        score["bound"] = json!(bound);
+    }
+    score
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(direction: Direction, line: &str) -> TranscriptEntry {
+        TranscriptEntry {
+            elapsed: Duration::from_millis(3),
+            direction,
+            line: line.to_string(),
+        }
+    }
+
+    #[test]
+    fn commands() {
+        let line = entry_line(&entry(
+            Direction::Sent,
+            "go btime 1000 wtime 2000 byoyomi 500",
+        ));
+        let value: Value = serde_json::from_str(&line).unwrap();
+        assert_eq!("go", value["command"]["type"]);
+        assert_eq!(1000, value["command"]["btime"]);
+        assert_eq!(Value::Null, value["command"]["binc"]);
+
+        let line = entry_line(&entry(
+            Direction::Received,
+            "info depth 8 seldepth 10 score mate -3 pv 7g7f",
+        ));
+        let value: Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(
+            json!({"type": "info", "depth": 8, "seldepth": 10, "score": {"mate": -3}, "pv": ["7g7f"]}),
+            value["command"]
+        );
+
+        let line = entry_line(&entry(Direction::Received, "hello"));
+        let value: Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(Value::Null, value["command"]);
+        assert_eq!(
+            entry(Direction::Received, "hello"),
+            parse_entry(&line).unwrap()
+        );
+    }
+
+    #[test]
+    fn header() {
+        let reader = JsonlTranscriptReader::new(
+            "\n{\"format\":\"usi-transcript\",\"version\":1}\n\n".as_bytes(),
+        )
+        .unwrap();
+        assert_eq!(1, reader.version());
+        assert_eq!(0, reader.count());
+
+        assert!(JsonlTranscriptReader::new(
+            "{\"format\":\"usi-transcript\",\"version\":2}".as_bytes()
+        )
+        .is_err());
+        assert!(
+            JsonlTranscriptReader::new("{\"format\":\"other\",\"version\":1}".as_bytes()).is_err()
+        );
+        assert!(JsonlTranscriptReader::new("0 > usi".as_bytes()).is_err());
+    }
+}
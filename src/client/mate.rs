@@ -0,0 +1,148 @@
+use std::time::Instant;
+
+use super::engine::Engine;
+use crate::error::{Error, TimeoutPhase};
+use crate::protocol::*;
+
+/// The result of `Engine::solve_mate`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum MateResult {
+    /// The moves of the mate, starting with the move of the side to move.
+    Mate(Vec<String>),
+    /// The engine proved there is no mate.
+    NoMate,
+    /// The engine couldn't find a mate within the time limit.
+    Timeout,
+    /// The engine doesn't support `go mate`.
+    Unsupported,
+}
+
+impl Engine {
+    /// Searches a mate in `position` with `go mate`, and waits for `checkmate`.
+    ///
+    /// `position` is the SFEN optionally followed by `moves` as in `GuiCommand::Position`.
+    /// `MateResult::Unsupported` is returned without searching once the engine answered
+    /// `checkmate notimplemented`, as well as when it answers `bestmove` instead of `checkmate`.
+    ///
+    /// If the engine doesn't answer within the time limit and the grace period of
+    /// `Timeouts::bestmove`, `stop` is sent; `Error::Timeout` is returned if `checkmate`
+    /// is still not received after another grace period. Returns `Error::IllegalState`
+    /// while searching.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use std::time::Duration;
+    /// use usi::{Engine, MateParam, MateResult};
+    ///
+    /// let mut engine = Engine::spawn("/path/to/usi_engine").unwrap();
+    /// let sfen = "7nl/7k1/9/7SP/9/9/9/9/9 b G 1";
+    /// match engine.solve_mate(sfen, MateParam::Timeout(Duration::from_secs(10))).unwrap() {
+    ///     MateResult::Mate(moves) => println!("mate: {}", moves.join(" ")),
+    ///     result => println!("{:?}", result),
+    /// }
+    /// ```
+    pub fn solve_mate(&mut self, position: &str, limit: MateParam) -> Result<MateResult, Error> {
+        if self.supports_mate_search() == Some(false) {
+            return Ok(MateResult::Unsupported);
+        }
+        if self.is_searching() {
+            return Err(Error::IllegalState {
+                reason: "the engine is searching",
+            });
+        }
+        self.send(&GuiCommand::Position(position.to_string()))?;
+        self.send(&GuiCommand::Go(ThinkParams::new().mate(limit.clone())))?;
+
+        let grace = self.timeouts().bestmove;
+        let mut deadline = match (limit, grace) {
+            (MateParam::Timeout(t), Some(grace)) => Some(Instant::now() + t + grace),
+            _ => None,
+        };
+        let mut stopped = false;
+        loop {
+            let output = match deadline {
+                Some(deadline) => {
+                    let remaining = deadline.saturating_duration_since(Instant::now());
+                    if remaining.is_zero() {
+                        None
+                    } else {
+                        self.recv_timeout(remaining)?
+                    }
+                }
+                None => Some(self.recv()?),
+            };
+            let Some(output) = output else {
+                if stopped {
+                    return Err(Error::Timeout {
+                        phase: TimeoutPhase::BestMove,
+                    });
+                }
+                self.send(&GuiCommand::Stop)?;
+                stopped = true;
+                deadline = grace.map(|grace| Instant::now() + grace);
+                continue;
+            };
+            match output.response() {
+                Some(EngineCommand::Checkmate(params)) => {
+                    return Ok(match params {
+                        CheckmateParams::Mate(moves) => MateResult::Mate(moves.clone()),
+                        CheckmateParams::NoMate => MateResult::NoMate,
+                        CheckmateParams::Timeout => MateResult::Timeout,
+                        CheckmateParams::NotImplemented => MateResult::Unsupported,
+                    })
+                }
+                Some(EngineCommand::BestMove(_)) => return Ok(MateResult::Unsupported),
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::IoTransport;
+
+    const SFEN: &str = "7nl/7k1/9/7SP/9/9/9/9/9 b G 1";
+
+    #[test]
+    fn solve_mate() {
+        let output = "usiok\nreadyok\n\
+                      info depth 3\ncheckmate G*2c 2b3a 2d3c+\n\
+                      bestmove resign\n\
+                      checkmate timeout\n\
+                      checkmate notimplemented\n";
+        let mut engine = Engine::connect(IoTransport::new(output.as_bytes(), Vec::new())).unwrap();
+
+        let moves = vec!["G*2c", "2b3a", "2d3c+"];
+        assert_eq!(
+            MateResult::Mate(moves.into_iter().map(String::from).collect()),
+            engine.solve_mate(SFEN, MateParam::Infinite).unwrap()
+        );
+        assert!(!engine.is_searching());
+        assert_eq!(Some(true), engine.supports_mate_search());
+
+        engine
+            .send(&GuiCommand::Go(ThinkParams::new().infinite()))
+            .unwrap();
+        assert!(matches!(
+            engine.solve_mate(SFEN, MateParam::Infinite),
+            Err(Error::IllegalState { .. })
+        ));
+        engine.recv().unwrap();
+
+        assert_eq!(
+            MateResult::Timeout,
+            engine.solve_mate(SFEN, MateParam::Infinite).unwrap()
+        );
+        assert_eq!(
+            MateResult::Unsupported,
+            engine.solve_mate(SFEN, MateParam::Infinite).unwrap()
+        );
+        // No more output is read once the engine is known not to support `go mate`.
+        assert_eq!(
+            MateResult::Unsupported,
+            engine.solve_mate(SFEN, MateParam::Infinite).unwrap()
+        );
+    }
+}
@@ -0,0 +1,309 @@
+use std::collections::VecDeque;
+use std::io;
+use std::thread;
+use std::time::Duration;
+
+use super::transport::Transport;
+use crate::protocol::GuiCommand;
+
+/// A command the mock engine expects, with the lines it replies.
+#[derive(Clone, Debug)]
+struct Expectation {
+    command: String,
+    prefix: bool,
+    replies: Vec<(Duration, String)>,
+}
+
+impl Expectation {
+    /// Returns `true` if `line` is the command, or with `prefix`, starts with it followed by
+    /// arguments.
+    fn matches(&self, line: &str) -> bool {
+        line.strip_prefix(self.command.as_str())
+            .is_some_and(|rest| rest.is_empty() || (self.prefix && rest.starts_with(' ')))
+    }
+}
+
+/// `MockEngine` is a `Transport` playing a scripted engine, to test code driving engines
+/// without engine binaries.
+///
+/// Commands are expected in order with `expect`, each replied with the lines added by `reply`
+/// and `reply_after`. An expected command matches the written line exactly, while one expected
+/// by `expect_prefix` matches it followed by arguments, so `expect_prefix("go")` matches
+/// `go btime 0 wtime 0 byoyomi 1000`.
+///
+/// Commands which aren't expected next are answered as by a real engine: `usi` with `id` and
+/// `option` lines and `usiok`, `isready` with `readyok`, and `setoption`, `usinewgame`,
+/// `gameover` and `ponderhit` are accepted silently. `stop` delivers the pending replies without
+/// their delays, and `quit` closes the connection. Any other command fails with
+/// `io::ErrorKind::InvalidData`, as does closing the transport before all the expected commands
+/// are written.
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+/// use usi::{BestMoveParams, Engine, GuiCommand, MockEngine, ThinkParams};
+///
+/// let mock = MockEngine::new("Mock")
+///     .expect("position startpos moves 7g7f")
+///     .expect_prefix("go")
+///     .reply("info depth 1 score cp 30 pv 3c3d")
+///     .reply_after(Duration::from_millis(10), "bestmove 3c3d");
+///
+/// let mut engine = Engine::connect(mock).unwrap();
+/// assert_eq!("Mock", engine.info().name());
+/// engine.send(&GuiCommand::parse("position startpos moves 7g7f").unwrap()).unwrap();
+/// let bestmove = engine.go(ThinkParams::new().byoyomi(Duration::from_secs(1))).unwrap().wait();
+/// assert_eq!(
+///     BestMoveParams::MakeMove("3c3d".to_string(), None),
+///     bestmove.unwrap()
+/// );
+/// engine.shutdown().unwrap();
+/// ```
+#[derive(Clone, Debug)]
+pub struct MockEngine {
+    name: String,
+    author: String,
+    options: Vec<String>,
+    expectations: VecDeque<Expectation>,
+    pending: VecDeque<(Duration, String)>,
+    timeout: Option<Duration>,
+    quit: bool,
+}
+
+impl MockEngine {
+    /// Creates a mock engine answering `usi` with `id name` of `name`.
+    pub fn new<S: Into<String>>(name: S) -> Self {
+        MockEngine {
+            name: name.into(),
+            author: String::from("usi-rs"),
+            options: Vec::new(),
+            expectations: VecDeque::new(),
+            pending: VecDeque::new(),
+            timeout: None,
+            quit: false,
+        }
+    }
+
+    /// Sets `id author`. Defaults to `usi-rs`.
+    #[must_use]
+    pub fn author<S: Into<String>>(mut self, author: S) -> Self {
+        self.author = author.into();
+        self
+    }
+
+    /// Adds an `option` line answering `usi`, e.g. `option name USI_Hash type spin default 256`.
+    #[must_use]
+    pub fn option<S: Into<String>>(mut self, line: S) -> Self {
+        self.options.push(line.into());
+        self
+    }
+
+    /// Expects `command` to be written next, exactly.
+    ///
+    /// Complete commands are normalized as `Engine` writes them, e.g. `position startpos`
+    /// as `position sfen`.
+    #[must_use]
+    pub fn expect<S: Into<String>>(self, command: S) -> Self {
+        self.push_expectation(command.into(), false)
+    }
+
+    /// Expects `command` to be written next, followed by any arguments.
+    #[must_use]
+    pub fn expect_prefix<S: Into<String>>(self, command: S) -> Self {
+        self.push_expectation(command.into(), true)
+    }
+
+    /// Replies `line` to the last expected command.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no command is expected yet.
+    #[must_use]
+    pub fn reply<S: Into<String>>(self, line: S) -> Self {
+        self.reply_after(Duration::ZERO, line)
+    }
+
+    /// Replies `line` to the last expected command, `delay` after the previous reply.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no command is expected yet.
+    #[must_use]
+    pub fn reply_after<S: Into<String>>(mut self, delay: Duration, line: S) -> Self {
+        self.expectations
+            .back_mut()
+            .expect("reply must follow expect")
+            .replies
+            .push((delay, line.into()));
+        self
+    }
+
+    /// Returns the number of the expected commands which haven't been written yet.
+    pub fn remaining(&self) -> usize {
+        self.expectations.len()
+    }
+
+    fn push_expectation(mut self, command: String, prefix: bool) -> Self {
+        let command = match GuiCommand::parse(&command) {
+            Ok(parsed) => parsed.to_string(),
+            Err(_) => command,
+        };
+        self.expectations.push_back(Expectation {
+            command,
+            prefix,
+            replies: Vec::new(),
+        });
+        self
+    }
+
+    fn push_replies<I: IntoIterator<Item = String>>(&mut self, lines: I) {
+        self.pending
+            .extend(lines.into_iter().map(|line| (Duration::ZERO, line)));
+    }
+}
+
+impl Transport for MockEngine {
+    fn read_line(&mut self) -> io::Result<Option<String>> {
+        if self.quit {
+            return Ok(None);
+        }
+        let Some((delay, _)) = self.pending.front_mut() else {
+            // Nothing is coming, as with an engine waiting for a command.
+            return match self.timeout {
+                Some(timeout) => {
+                    thread::sleep(timeout);
+                    Err(io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        "no line received within the timeout",
+                    ))
+                }
+                None => Ok(None),
+            };
+        };
+        match self.timeout {
+            Some(timeout) if timeout < *delay => {
+                thread::sleep(timeout);
+                *delay -= timeout;
+                Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    "no line received within the timeout",
+                ))
+            }
+            _ => {
+                thread::sleep(*delay);
+                Ok(self.pending.pop_front().map(|(_, line)| line))
+            }
+        }
+    }
+
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        if let Some(expectation) = self.expectations.front() {
+            if expectation.matches(line) {
+                let expectation = self.expectations.pop_front().unwrap();
+                self.pending.extend(expectation.replies);
+                return Ok(());
+            }
+        }
+
+        let command = line.split_whitespace().next().unwrap_or_default();
+        match command {
+            "usi" => {
+                let mut lines = vec![
+                    format!("id name {}", self.name),
+                    format!("id author {}", self.author),
+                ];
+                lines.extend(self.options.iter().cloned());
+                lines.push(String::from("usiok"));
+                self.push_replies(lines);
+            }
+            "isready" => self.push_replies([String::from("readyok")]),
+            "setoption" | "usinewgame" | "gameover" | "ponderhit" => {}
+            "stop" => {
+                for (delay, _) in &mut self.pending {
+                    *delay = Duration::ZERO;
+                }
+            }
+            "quit" => self.quit = true,
+            _ => {
+                let expected = self.expectations.front().map(|e| e.command.as_str());
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("expected {expected:?} but {line:?} was written"),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn close(&mut self) -> io::Result<()> {
+        self.quit = true;
+        match self.expectations.front() {
+            Some(expectation) => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "{:?} and {} more commands were expected but not written",
+                    expectation.command,
+                    self.expectations.len() - 1
+                ),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    fn set_read_timeout(&mut self, timeout: Option<Duration>) -> io::Result<()> {
+        self.timeout = timeout;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::Engine;
+    use crate::error::Error;
+    use crate::protocol::ThinkParams;
+
+    #[test]
+    fn unexpected() {
+        let mock = MockEngine::new("Mock")
+            .option("option name USI_Hash type spin default 256 min 1 max 1024")
+            .expect("position startpos");
+        let mut engine = Engine::connect(mock).unwrap();
+        assert!(engine.options().get("USI_Hash").is_some());
+        engine.set_option("USI_Hash", "512").unwrap();
+
+        // The position doesn't match with moves.
+        let position = GuiCommand::parse("position startpos moves 7g7f").unwrap();
+        assert!(matches!(engine.send(&position), Err(Error::EngineIo(_))));
+
+        let go = GuiCommand::Go(ThinkParams::new());
+        assert!(matches!(engine.send(&go), Err(Error::EngineIo(_))));
+        assert!(matches!(engine.close(), Err(Error::EngineIo(_))));
+    }
+
+    #[test]
+    fn delays() {
+        let mut mock = MockEngine::new("Mock")
+            .expect_prefix("go")
+            .reply_after(Duration::from_millis(50), "bestmove resign");
+        assert!(!mock.expectations[0].matches("gogo"));
+        mock.write_line("go infinite").unwrap();
+        assert_eq!(0, mock.remaining());
+
+        mock.set_read_timeout(Some(Duration::from_millis(10)))
+            .unwrap();
+        assert_eq!(
+            io::ErrorKind::TimedOut,
+            mock.read_line().unwrap_err().kind()
+        );
+        mock.write_line("stop").unwrap();
+        assert_eq!(
+            Some("bestmove resign".to_string()),
+            mock.read_line().unwrap()
+        );
+
+        mock.write_line("quit").unwrap();
+        assert_eq!(None, mock.read_line().unwrap());
+        mock.close().unwrap();
+    }
+}
@@ -0,0 +1,91 @@
+mod analyze;
+mod annotate;
+#[cfg(feature = "async")]
+mod async_engine;
+mod builder;
+mod coalesce;
+#[cfg(feature = "tokio")]
+mod codec;
+mod compare;
+#[cfg(feature = "serde")]
+mod config;
+mod conformance;
+mod continuous;
+mod encoding;
+mod engine;
+mod eval;
+mod framing;
+mod golden;
+mod health;
+mod in_process;
+mod jis0208;
+#[cfg(feature = "serde")]
+mod jsonl;
+mod mate;
+mod mock;
+mod options;
+mod parallel;
+mod ponder;
+mod pool;
+mod quirks;
+mod resources;
+#[cfg(feature = "async")]
+mod runtime;
+#[cfg(feature = "serde")]
+mod schema;
+mod search;
+mod socket;
+mod ssh;
+#[cfg(feature = "tracing")]
+mod trace;
+mod transcript;
+mod transport;
+#[cfg(feature = "websocket")]
+mod websocket;
+
+pub use self::analyze::{AnalysisLimits, AnalysisReport};
+pub use self::annotate::{AnnotatedGame, AnnotatedMove, GameAnnotator, MateWarning};
+#[cfg(feature = "async")]
+pub use self::async_engine::AsyncEngine;
+pub use self::builder::EngineBuilder;
+pub use self::coalesce::InfoCoalescer;
+#[cfg(feature = "tokio")]
+pub use self::codec::UsiCodec;
+pub use self::compare::{EngineComparison, PositionDiff};
+#[cfg(feature = "serde")]
+pub use self::config::EngineConfig;
+pub use self::conformance::{
+    ConformanceOutcome, ConformanceReport, ConformanceSuite, Scenario, ScenarioResult,
+};
+pub use self::continuous::ContinuousAnalysis;
+pub use self::encoding::Encoding;
+pub use self::engine::{Engine, EngineEvent, StallAction, Timeouts, Watchdog};
+pub use self::eval::EvalFileIssue;
+pub use self::framing::{Framing, Newline};
+pub use self::golden::{GoldenMismatch, GoldenMode, GoldenTranscript, Tolerance};
+pub use self::health::HealthMonitor;
+pub use self::in_process::InProcessTransport;
+#[cfg(feature = "serde")]
+pub use self::jsonl::{JsonlTranscriptReader, JsonlTranscriptWriter, TRANSCRIPT_FORMAT_VERSION};
+pub use self::mate::MateResult;
+pub use self::mock::MockEngine;
+pub use self::options::{EngineOption, EngineOptions, OptionChange};
+pub use self::ponder::PonderManager;
+pub use self::pool::{EnginePool, PooledEngine};
+pub use self::quirks::Quirks;
+pub use self::resources::{AutoConfig, SystemResources};
+#[cfg(feature = "async")]
+pub use self::runtime::*;
+pub use self::search::SearchHandle;
+pub use self::socket::TcpTransport;
+#[cfg(unix)]
+pub use self::socket::UnixTransport;
+pub use self::ssh::{SshTransport, SshTransportBuilder};
+#[cfg(test)]
+pub(crate) use self::transcript::SharedBuffer;
+pub use self::transcript::{
+    Direction, RecordingTransport, ReplayTransport, TranscriptEntry, TranscriptFormat,
+};
+pub use self::transport::{IoTransport, ProcessTransport, Transport};
+#[cfg(feature = "websocket")]
+pub use self::websocket::WebSocketBridge;
@@ -0,0 +1,448 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::error::Error;
+use crate::protocol::*;
+
+/// Represents an option declared by the engine with its current value.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EngineOption {
+    name: String,
+    kind: OptionKind,
+    value: Option<String>,
+}
+
+impl EngineOption {
+    /// Returns the option name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the type of the option with its default value and constraints.
+    pub fn kind(&self) -> &OptionKind {
+        &self.kind
+    }
+
+    /// Returns the default value declared by the engine.
+    pub fn default_value(&self) -> Option<String> {
+        match &self.kind {
+            OptionKind::Check { default } => default.map(|v| v.to_string()),
+            OptionKind::Spin { default, .. } => default.map(|v| v.to_string()),
+            OptionKind::Combo { default, .. }
+            | OptionKind::Button { default }
+            | OptionKind::String { default }
+            | OptionKind::Filename { default } => default.clone(),
+        }
+    }
+
+    /// Returns the current value, which is the last value sent by `setoption`
+    /// or the default value.
+    pub fn value(&self) -> Option<&str> {
+        self.value.as_deref()
+    }
+
+    /// Returns the current value of a filename option as a path.
+    /// Returns `None` for other types or an empty value.
+    pub fn path(&self) -> Option<PathBuf> {
+        match (&self.kind, self.value()) {
+            (OptionKind::Filename { .. }, Some(value)) if !value.is_empty() => {
+                Some(PathBuf::from(value))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the path of a filename option like `EngineOption::path`,
+    /// resolving a relative path against `working_dir`.
+    pub fn resolve_path<P: AsRef<Path>>(&self, working_dir: P) -> Option<PathBuf> {
+        self.path().map(|path| working_dir.as_ref().join(path))
+    }
+
+    /// Checks `value` against the type of the option and returns the normalized value.
+    ///
+    /// Check options accept `true` or `false`, spin options accept integers within the range,
+    /// combo options accept one of the choices, and buttons accept only an empty value.
+    /// Returns `Error::OptionOutOfRange` for integers outside the range of a spin option,
+    /// or `Error::InvalidOptionValue` otherwise.
+    pub fn validate(&self, value: &str) -> Result<String, Error> {
+        self.kind.parse_valid(&self.name, value)?;
+        Ok(match &self.kind {
+            OptionKind::Spin { .. } => value.trim().to_string(),
+            _ => value.to_string(),
+        })
+    }
+
+    /// Checks `value` like `EngineOption::validate`, except that integers outside the range
+    /// of a spin option are snapped to the minimum or maximum.
+    pub fn clamp(&self, value: &str) -> Result<String, Error> {
+        match (&self.kind, self.validate(value)) {
+            (OptionKind::Spin { min, max, .. }, Err(Error::OptionOutOfRange { given, .. })) => {
+                let given = min.map_or(given, |min| given.max(min));
+                Ok(max.map_or(given, |max| given.min(max)).to_string())
+            }
+            (_, res) => res,
+        }
+    }
+
+    /// Checks that the declared default fits the range or choices of the option,
+    /// like `OptionParams::validate_default`.
+    pub fn validate_default(&self) -> Result<(), Error> {
+        OptionParams {
+            name: self.name.clone(),
+            value: self.kind.clone(),
+        }
+        .validate_default()
+    }
+
+    /// Returns `true` if the value was changed by `setoption`.
+    pub fn is_modified(&self) -> bool {
+        self.value != self.default_value()
+    }
+}
+
+/// Represents a change of an option value, notified as `EngineEvent::OptionChanged`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OptionChange {
+    /// The option name.
+    pub name: String,
+    /// The value before the change.
+    pub old: Option<String>,
+    /// The value after the change, which is `None` for buttons.
+    pub new: Option<String>,
+    /// `true` if the engine re-declared the option with `new` as the default,
+    /// or `false` if the value was sent by `setoption`.
+    pub acknowledged: bool,
+}
+
+/// `EngineOptions` is a collection of the options declared by the engine
+/// in response to `usi` command, in the declared order.
+///
+/// # Examples
+/// ```no_run
+/// use usi::Engine;
+///
+/// let engine = Engine::spawn("/path/to/usi_engine").unwrap();
+/// for option in engine.options() {
+///     println!("{}: {:?} = {:?}", option.name(), option.kind(), option.value());
+/// }
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EngineOptions {
+    options: Vec<EngineOption>,
+    index: HashMap<String, usize>,
+}
+
+impl EngineOptions {
+    /// Returns the option named `name`.
+    pub fn get(&self, name: &str) -> Option<&EngineOption> {
+        self.index.get(name).map(|&i| &self.options[i])
+    }
+
+    /// Returns `true` if the engine declared the option named `name`.
+    pub fn contains(&self, name: &str) -> bool {
+        self.index.contains_key(name)
+    }
+
+    /// Returns the current value of the option named `name`.
+    pub fn value(&self, name: &str) -> Option<&str> {
+        self.get(name).and_then(EngineOption::value)
+    }
+
+    /// Returns the first option declared among `names`.
+    fn find(&self, names: &[&str]) -> Option<&EngineOption> {
+        names.iter().find_map(|name| self.get(name))
+    }
+
+    /// Returns the option of the hash size in MB, `USI_Hash` or `Hash`.
+    pub fn hash_option(&self) -> Option<&EngineOption> {
+        self.find(&["USI_Hash", "Hash"])
+    }
+
+    /// Returns the option enabling pondering, `USI_Ponder`.
+    pub fn ponder_option(&self) -> Option<&EngineOption> {
+        self.find(&["USI_Ponder"])
+    }
+
+    /// Returns the option of the number of PVs, `USI_MultiPV` or `MultiPV`.
+    pub fn multipv_option(&self) -> Option<&EngineOption> {
+        self.find(&["USI_MultiPV", "MultiPV"])
+    }
+
+    /// Returns the option of the number of search threads, `Threads`.
+    pub fn threads_option(&self) -> Option<&EngineOption> {
+        self.find(&["Threads"])
+    }
+
+    /// Returns the option of the directory of evaluation files, `EvalDir` or `Eval_Dir`.
+    pub fn eval_dir_option(&self) -> Option<&EngineOption> {
+        self.find(&["EvalDir", "Eval_Dir"])
+    }
+
+    /// Returns the option of the evaluation file, `EvalFile` or `DNN_Model`.
+    pub fn eval_file_option(&self) -> Option<&EngineOption> {
+        self.find(&["EvalFile", "DNN_Model"])
+    }
+
+    /// Returns the option of the directory of opening books, `BookDir`.
+    pub fn book_dir_option(&self) -> Option<&EngineOption> {
+        self.find(&["BookDir"])
+    }
+
+    /// Returns `true` if the engine declared `USI_Ponder`.
+    pub fn supports_ponder(&self) -> bool {
+        self.ponder_option().is_some()
+    }
+
+    /// Returns `true` if the engine can report multiple PVs.
+    pub fn supports_multipv(&self) -> bool {
+        self.multipv_option().is_some()
+    }
+
+    /// Returns an iterator over the options whose declared default is invalid.
+    ///
+    /// Such defaults are reported as the current value but the engine may reject them
+    /// when sent back by `setoption`.
+    pub fn invalid_defaults(&self) -> impl Iterator<Item = &EngineOption> {
+        self.iter()
+            .filter(|option| option.validate_default().is_err())
+    }
+
+    /// Returns the number of options.
+    pub fn len(&self) -> usize {
+        self.options.len()
+    }
+
+    /// Returns `true` if the engine declared no options.
+    pub fn is_empty(&self) -> bool {
+        self.options.is_empty()
+    }
+
+    /// Returns an iterator over the options in the declared order.
+    pub fn iter(&self) -> std::slice::Iter<'_, EngineOption> {
+        self.options.iter()
+    }
+
+    /// Records an `option` command. A redeclared option replaces the previous one,
+    /// keeping the value set by `setoption` if it is still valid.
+    /// Returns the change if the value changed.
+    pub(crate) fn declare(&mut self, params: &OptionParams) -> Option<OptionChange> {
+        let option = EngineOption {
+            name: params.name.clone(),
+            kind: params.value.clone(),
+            value: None,
+        };
+        let mut option = EngineOption {
+            value: option.default_value(),
+            ..option
+        };
+
+        match self.index.get(&params.name) {
+            Some(&i) => {
+                let old = &self.options[i];
+                if let Some(value) = old.value.as_deref().filter(|_| old.is_modified()) {
+                    if let Ok(value) = option.validate(value) {
+                        option.value = Some(value);
+                    }
+                }
+                let old = std::mem::replace(&mut self.options[i], option);
+                let new = &self.options[i].value;
+                (old.value != *new).then(|| OptionChange {
+                    name: params.name.clone(),
+                    old: old.value,
+                    new: new.clone(),
+                    acknowledged: true,
+                })
+            }
+            None => {
+                self.index.insert(params.name.clone(), self.options.len());
+                self.options.push(option);
+                None
+            }
+        }
+    }
+
+    /// Records the value sent by `setoption` command and returns the change,
+    /// which is `None` if the value is unchanged. Pressing a button is always a change.
+    /// Undeclared options are ignored.
+    pub(crate) fn set_value(&mut self, params: &SetOptionParams) -> Option<OptionChange> {
+        let option = &mut self.options[*self.index.get(&params.name)?];
+        let old = option.value.clone();
+        // Buttons have no state.
+        if !matches!(option.kind, OptionKind::Button { .. }) {
+            if old == params.value {
+                return None;
+            }
+            option.value = params.value.clone();
+        }
+        Some(OptionChange {
+            name: params.name.clone(),
+            old,
+            new: params.value.clone(),
+            acknowledged: false,
+        })
+    }
+}
+
+impl<'a> IntoIterator for &'a EngineOptions {
+    type Item = &'a EngineOption;
+    type IntoIter = std::slice::Iter<'a, EngineOption>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn options() {
+        let mut options = EngineOptions::default();
+        for line in [
+            "option name USI_Hash type spin default 256 min 1 max 4096",
+            "option name USI_Ponder type check default false",
+            "option name Style type combo default Normal var Solid var Normal var Risky",
+            "option name ClearHash type button",
+            "option name USI_Hash type spin default 16 min 1 max 1024",
+        ] {
+            match EngineCommand::parse(line).unwrap() {
+                EngineCommand::Option(params) => {
+                    options.declare(&params);
+                }
+                _ => unreachable!(),
+            }
+        }
+
+        assert_eq!(4, options.len());
+        assert_eq!(
+            vec!["USI_Hash", "USI_Ponder", "Style", "ClearHash"],
+            options.iter().map(|o| o.name()).collect::<Vec<_>>()
+        );
+        assert_eq!(Some("16"), options.value("USI_Hash"));
+        assert_eq!(Some("false"), options.value("USI_Ponder"));
+        assert_eq!(None, options.value("ClearHash"));
+        assert!(!options.contains("Threads"));
+        assert!(options.supports_ponder());
+        assert!(!options.supports_multipv());
+        assert_eq!(
+            Some("USI_Hash"),
+            options.hash_option().map(EngineOption::name)
+        );
+
+        assert_eq!(
+            Some(OptionChange {
+                name: "USI_Hash".to_string(),
+                old: Some("16".to_string()),
+                new: Some("512".to_string()),
+                acknowledged: false,
+            }),
+            options.set_value(&SetOptionParams::new("USI_Hash", "512"))
+        );
+        assert_eq!(
+            None,
+            options.set_value(&SetOptionParams::new("USI_Hash", "512"))
+        );
+        assert!(options
+            .set_value(&SetOptionParams::button("ClearHash"))
+            .is_some());
+        options.set_value(&SetOptionParams::new("Threads", "4"));
+        let hash = options.get("USI_Hash").unwrap();
+        assert_eq!(Some("512"), hash.value());
+        assert_eq!(Some("16".to_string()), hash.default_value());
+        assert!(hash.is_modified());
+        assert!(!options.get("ClearHash").unwrap().is_modified());
+        assert_eq!(4, options.len());
+
+        // The value set by `setoption` survives a redeclaration while it is valid.
+        let declare = |options: &mut EngineOptions, line: &str| match EngineCommand::parse(line) {
+            Ok(EngineCommand::Option(params)) => options.declare(&params),
+            _ => unreachable!(),
+        };
+        let line = "option name USI_Hash type spin default 32 min 1 max 1024";
+        assert_eq!(None, declare(&mut options, line));
+        assert_eq!(Some("512"), options.value("USI_Hash"));
+        let line = "option name USI_Hash type spin default 32 min 1 max 256";
+        assert_eq!(
+            Some(OptionChange {
+                name: "USI_Hash".to_string(),
+                old: Some("512".to_string()),
+                new: Some("32".to_string()),
+                acknowledged: true,
+            }),
+            declare(&mut options, line)
+        );
+    }
+
+    #[test]
+    fn validate() {
+        let mut options = EngineOptions::default();
+        for line in [
+            "option name USI_Hash type spin default 16 min 1 max 1024",
+            "option name USI_Ponder type check default false",
+            "option name ClearHash type button",
+            "option name BookFile type filename default <empty>",
+            "option name Style type combo default Aggressive var Solid var Normal",
+        ] {
+            match EngineCommand::parse(line).unwrap() {
+                EngineCommand::Option(params) => {
+                    options.declare(&params);
+                }
+                _ => unreachable!(),
+            }
+        }
+
+        let hash = options.get("USI_Hash").unwrap();
+        assert_eq!("256", hash.validate(" 256").unwrap());
+        assert!(matches!(
+            hash.validate("large"),
+            Err(Error::InvalidOptionValue { .. })
+        ));
+        for value in ["0", "1025"] {
+            assert!(matches!(
+                hash.validate(value),
+                Err(Error::OptionOutOfRange {
+                    min: 1,
+                    max: 1024,
+                    ..
+                })
+            ));
+        }
+        assert_eq!("1", hash.clamp("0").unwrap());
+        assert_eq!("1024", hash.clamp("1025").unwrap());
+        assert_eq!("16", hash.clamp("16").unwrap());
+        assert!(hash.clamp("large").is_err());
+
+        let ponder = options.get("USI_Ponder").unwrap();
+        assert!(ponder.validate("true").is_ok());
+        assert!(ponder.validate("yes").is_err());
+
+        let button = options.get("ClearHash").unwrap();
+        assert!(button.validate("").is_ok());
+        assert!(button.validate("now").is_err());
+
+        assert!(options.get("BookFile").unwrap().validate("book.db").is_ok());
+
+        let book = options.get("BookFile").unwrap();
+        assert_eq!(None, book.path());
+        options.set_value(&SetOptionParams::new("BookFile", "book/main.db"));
+        let book = options.get("BookFile").unwrap();
+        assert_eq!(Some(PathBuf::from("book/main.db")), book.path());
+        assert_eq!(
+            Some(Path::new("/opt/engine").join("book/main.db")),
+            book.resolve_path("/opt/engine")
+        );
+        assert_eq!(None, options.get("USI_Hash").unwrap().path());
+
+        let style = options.get("Style").unwrap();
+        assert!(style.validate("Normal").is_ok());
+        assert!(style.validate("Aggressive").is_err());
+        assert_eq!(
+            vec!["Style"],
+            options
+                .invalid_defaults()
+                .map(EngineOption::name)
+                .collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,188 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+use super::analyze::{AnalysisLimits, AnalysisReport};
+use super::annotate::{positions, start_sfen, AnnotatedGame, GameAnnotator};
+use super::pool::EnginePool;
+use crate::error::Error;
+
+impl EnginePool {
+    /// Analyzes `positions` concurrently with the engines of the pool, and returns the reports
+    /// in the order of `positions`.
+    ///
+    /// Each position is analyzed with `Engine::analyze` within `limits`. A worker thread is
+    /// started for each engine, and the workers take the next unanalyzed position as they finish,
+    /// so engines analyzing quick positions aren't left idle. On an error, the engine is
+    /// discarded, the remaining positions are skipped, and the error of the earliest failed
+    /// position is returned.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use usi::{AnalysisLimits, EngineBuilder, EnginePool};
+    ///
+    /// let pool = EnginePool::new(EngineBuilder::new("/path/to/usi_engine"), 4).unwrap();
+    /// let start = "lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL b - 1";
+    /// let positions = vec![start.to_string(), format!("{start} moves 7g7f")];
+    /// let reports = pool.analyze_all(&positions, AnalysisLimits::new().depth(16)).unwrap();
+    /// ```
+    pub fn analyze_all(
+        &self,
+        positions: &[String],
+        limits: AnalysisLimits,
+    ) -> Result<Vec<AnalysisReport>, Error> {
+        let next = AtomicUsize::new(0);
+        let failed = AtomicBool::new(false);
+        let results = Mutex::new((0..positions.len()).map(|_| None).collect::<Vec<_>>());
+        let worker = || {
+            let mut engine = match self.get() {
+                Ok(engine) => engine,
+                Err(e) => {
+                    failed.store(true, Ordering::Relaxed);
+                    return Some(e);
+                }
+            };
+            while !failed.load(Ordering::Relaxed) {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let Some(position) = positions.get(i) else {
+                    break;
+                };
+                let result = engine.analyze(position, limits);
+                if result.is_err() {
+                    failed.store(true, Ordering::Relaxed);
+                    engine.discard();
+                }
+                results.lock().unwrap()[i] = Some(result);
+            }
+            None
+        };
+
+        let workers = self.size().min(positions.len());
+        let pool_error = thread::scope(|s| {
+            let handles: Vec<_> = (0..workers).map(|_| s.spawn(worker)).collect();
+            handles
+                .into_iter()
+                .filter_map(|handle| handle.join().unwrap())
+                .next()
+        });
+
+        let mut reports = Vec::with_capacity(positions.len());
+        for result in results.into_inner().unwrap() {
+            match result {
+                Some(result) => reports.push(result?),
+                None => break,
+            }
+        }
+        match pool_error {
+            Some(e) if reports.len() < positions.len() => Err(e),
+            _ => Ok(reports),
+        }
+    }
+}
+
+impl GameAnnotator {
+    /// Annotates `moves` played from `start` like `GameAnnotator::annotate`, analyzing
+    /// the positions concurrently with the engines of `pool` by `EnginePool::analyze_all`.
+    pub fn annotate_parallel(
+        &self,
+        pool: &EnginePool,
+        start: &str,
+        moves: &[String],
+    ) -> Result<AnnotatedGame, Error> {
+        let start = start_sfen(start)?;
+        let reports = pool.analyze_all(&positions(&start, moves), self.limits())?;
+        Ok(self.build(&start, moves, reports))
+    }
+
+    /// Annotates a batch of games, each given as the SFEN of the initial position or `startpos`
+    /// and the moves. The positions of all the games are analyzed concurrently with the engines
+    /// of `pool`, so short games don't leave engines idle.
+    pub fn annotate_games(
+        &self,
+        pool: &EnginePool,
+        games: &[(String, Vec<String>)],
+    ) -> Result<Vec<AnnotatedGame>, Error> {
+        let games = games
+            .iter()
+            .map(|(start, moves)| Ok((start_sfen(start)?, moves)))
+            .collect::<Result<Vec<_>, Error>>()?;
+        let all: Vec<String> = games
+            .iter()
+            .flat_map(|(start, moves)| positions(start, moves))
+            .collect();
+        let mut reports = pool.analyze_all(&all, self.limits())?.into_iter();
+        Ok(games
+            .iter()
+            .map(|(start, moves)| {
+                let game = reports.by_ref().take(moves.len() + 1).collect();
+                self.build(start, moves, game)
+            })
+            .collect())
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::EngineBuilder;
+
+    const SFEN: &str = "lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL b - 1";
+
+    /// Spawns engines which report the last word of the position as the best move.
+    fn pool(size: usize) -> EnginePool {
+        let builder = EngineBuilder::new("sh").args([
+            "-c",
+            "while read l; do case $l in usi) echo usiok;; isready) echo readyok;; \
+             position*) p=$l;; go*) echo \"info depth 1 score cp 10 pv ${p##* }\"; \
+             echo \"bestmove ${p##* }\";; quit) exit;; esac; done",
+        ]);
+        EnginePool::new(builder, size).unwrap()
+    }
+
+    #[test]
+    fn analyze_all() {
+        let pool = pool(3);
+        let positions: Vec<String> = (0..10).map(|i| format!("{SFEN} moves m{i}")).collect();
+        let reports = pool
+            .analyze_all(&positions, AnalysisLimits::new().depth(1))
+            .unwrap();
+
+        assert_eq!(10, reports.len());
+        for (i, report) in reports.iter().enumerate() {
+            assert_eq!(positions[i], report.position);
+            assert_eq!(Some(format!("m{i}").as_str()), report.best_move());
+        }
+        assert_eq!(3, pool.idle_count());
+        assert!(pool
+            .analyze_all(&[], AnalysisLimits::new())
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn annotate_games() {
+        let pool = pool(2);
+        let games = vec![
+            (
+                SFEN.to_string(),
+                vec!["7g7f".to_string(), "3c3d".to_string()],
+            ),
+            ("startpos".to_string(), vec!["2g2f".to_string()]),
+        ];
+        let annotator = GameAnnotator::new(AnalysisLimits::new().depth(1));
+        let annotated = annotator.annotate_games(&pool, &games).unwrap();
+
+        assert_eq!(2, annotated.len());
+        assert_eq!(3, annotated[0].reports.len());
+        assert_eq!(2, annotated[1].reports.len());
+        assert_eq!(Some("2g2f"), annotated[1].reports[1].best_move());
+        assert_eq!(SFEN, annotated[1].start);
+        assert_eq!(
+            annotated[0].moves,
+            annotator
+                .annotate_parallel(&pool, SFEN, &games[0].1)
+                .unwrap()
+                .moves
+        );
+    }
+}
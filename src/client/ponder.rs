@@ -0,0 +1,185 @@
+use super::engine::Engine;
+use super::search::SearchHandle;
+use crate::error::Error;
+use crate::protocol::*;
+
+/// `PonderManager` sequences `go ponder`, `ponderhit` and `stop` commands while
+/// the engine thinks on the opponent's time.
+///
+/// Positions are the arguments of `position sfen` command, optionally followed by `moves`.
+///
+/// # Examples
+/// ```no_run
+/// use usi::{BestMoveParams, Engine, PonderManager, ThinkParams};
+///
+/// let mut engine = Engine::spawn("/path/to/usi_engine").unwrap();
+/// let mut ponder = PonderManager::new();
+///
+/// let position = "lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL b - 1 moves 7g7f";
+/// ponder.start(&mut engine, position, "3c3d", ThinkParams::new()).unwrap();
+///
+/// // When the opponent moves, the engine continues or restarts the search as necessary.
+/// let bestmove = ponder
+///     .opponent_moved(&mut engine, "8c8d", ThinkParams::new())
+///     .unwrap()
+///     .wait()
+///     .unwrap();
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct PonderManager {
+    pondering: Option<Pondering>,
+}
+
+#[derive(Clone, Debug)]
+struct Pondering {
+    position: String,
+    expected: String,
+}
+
+impl PonderManager {
+    pub fn new() -> Self {
+        PonderManager::default()
+    }
+
+    /// Returns `true` if the engine is pondering.
+    pub fn is_pondering(&self) -> bool {
+        self.pondering.is_some()
+    }
+
+    /// Returns the opponent's move the engine is pondering on.
+    pub fn expected_move(&self) -> Option<&str> {
+        self.pondering.as_ref().map(|p| p.expected.as_str())
+    }
+
+    /// Starts pondering on `ponder_move` played in `position`, which is the position
+    /// after the engine's move. `params` must not include `ponder`; it is added here.
+    ///
+    /// Returns `Error::IllegalState` if the engine is already pondering.
+    pub fn start(
+        &mut self,
+        engine: &mut Engine,
+        position: &str,
+        ponder_move: &str,
+        params: ThinkParams,
+    ) -> Result<(), Error> {
+        if self.pondering.is_some() {
+            return Err(Error::IllegalState {
+                reason: "the engine is already pondering",
+            });
+        }
+
+        engine.send(&GuiCommand::Position(append_move(position, ponder_move)))?;
+        engine.send(&GuiCommand::Go(params.ponder()))?;
+        self.pondering = Some(Pondering {
+            position: position.to_string(),
+            expected: ponder_move.to_string(),
+        });
+        Ok(())
+    }
+
+    /// Notifies the move played by the opponent and returns the search for the engine's reply.
+    ///
+    /// If the engine was pondering on `mv`, `ponderhit` is sent and the ponder search continues.
+    /// Otherwise, the ponder search is stopped, its `bestmove` is discarded, and a new search
+    /// is started with `params`.
+    ///
+    /// Returns `Error::IllegalState` if the engine is not pondering.
+    pub fn opponent_moved<'a>(
+        &mut self,
+        engine: &'a mut Engine,
+        mv: &str,
+        params: ThinkParams,
+    ) -> Result<SearchHandle<'a>, Error> {
+        let pondering = self.pondering.take().ok_or(Error::IllegalState {
+            reason: "the engine is not pondering",
+        })?;
+
+        if pondering.expected == mv {
+            engine.send(&GuiCommand::Ponderhit)?;
+            return Ok(SearchHandle::new(engine));
+        }
+
+        // The result of the ponder search is stale.
+        engine.stop()?;
+        engine.send(&GuiCommand::Position(append_move(&pondering.position, mv)))?;
+        engine.go(params)
+    }
+
+    /// Stops pondering and discards its result, e.g. when the game is over.
+    pub fn cancel(&mut self, engine: &mut Engine) -> Result<(), Error> {
+        if self.pondering.take().is_some() {
+            engine.stop()?;
+        }
+        Ok(())
+    }
+}
+
+fn append_move(position: &str, mv: &str) -> String {
+    if position.split_whitespace().any(|t| t == "moves") {
+        format!("{position} {mv}")
+    } else {
+        format!("{position} moves {mv}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::SharedBuffer;
+    use crate::{IoTransport, Transport};
+
+    #[test]
+    fn ponder() {
+        let output = "usiok\nreadyok\nbestmove 2g2f\nbestmove 2f2e\nbestmove 6i7h\n";
+        let written = SharedBuffer::default();
+        let buf = written.clone();
+        let mut engine = Engine::connect_with(move || {
+            Ok(Box::new(IoTransport::new(output.as_bytes(), buf.clone())) as Box<dyn Transport>)
+        })
+        .unwrap();
+        written.0.lock().unwrap().clear();
+
+        let mut ponder = PonderManager::new();
+        ponder
+            .start(
+                &mut engine,
+                "startpos-sfen moves 7g7f",
+                "3c3d",
+                ThinkParams::new(),
+            )
+            .unwrap();
+        assert_eq!(Some("3c3d"), ponder.expected_move());
+        assert!(matches!(
+            ponder.start(&mut engine, "startpos-sfen", "3c3d", ThinkParams::new()),
+            Err(Error::IllegalState { .. })
+        ));
+
+        // Ponder miss discards `bestmove 2g2f`.
+        let search = ponder
+            .opponent_moved(&mut engine, "8c8d", ThinkParams::new())
+            .unwrap();
+        assert_eq!(
+            BestMoveParams::MakeMove("2f2e".to_string(), None),
+            search.wait().unwrap()
+        );
+        assert!(!ponder.is_pondering());
+
+        ponder
+            .start(&mut engine, "startpos-sfen", "7a6b", ThinkParams::new())
+            .unwrap();
+        let search = ponder
+            .opponent_moved(&mut engine, "7a6b", ThinkParams::new())
+            .unwrap();
+        assert_eq!(
+            BestMoveParams::MakeMove("6i7h".to_string(), None),
+            search.wait().unwrap()
+        );
+
+        assert_eq!(
+            "position sfen startpos-sfen moves 7g7f 3c3d\ngo ponder\nstop\n\
+             position sfen startpos-sfen moves 7g7f 8c8d\ngo\n\
+             position sfen startpos-sfen moves 7a6b\ngo ponder\nponderhit\n",
+            std::str::from_utf8(&written.0.lock().unwrap()).unwrap()
+        );
+    }
+}
@@ -0,0 +1,217 @@
+use std::fmt;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Condvar, Mutex};
+
+use super::builder::EngineBuilder;
+use super::engine::Engine;
+use crate::error::Error;
+
+/// `EnginePool` manages engines spawned with the same configuration.
+///
+/// Idle engines are checked with `isready` when they are handed out, and engines which
+/// don't respond are replaced by newly spawned ones. A search left running by the previous
+/// borrower is stopped and its output is discarded beforehand. The pool can be shared between threads.
+///
+/// # Examples
+/// ```no_run
+/// use usi::{EngineBuilder, EnginePool, ThinkParams};
+///
+/// let pool = EnginePool::new(EngineBuilder::new("/path/to/usi_engine"), 4).unwrap();
+/// std::thread::scope(|s| {
+///     for _ in 0..8 {
+///         s.spawn(|| {
+///             let mut engine = pool.get().unwrap();
+///             let bestmove = engine.go(ThinkParams::new()).unwrap().wait().unwrap();
+///         });
+///     }
+/// });
+/// ```
+pub struct EnginePool {
+    builder: EngineBuilder,
+    size: usize,
+    // Idle slots. `None` is a slot whose engine needs to be respawned.
+    idle: Mutex<Vec<Option<Engine>>>,
+    available: Condvar,
+}
+
+impl fmt::Debug for EnginePool {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("EnginePool")
+            .field("builder", &self.builder)
+            .field("size", &self.size)
+            .finish_non_exhaustive()
+    }
+}
+
+impl EnginePool {
+    /// Spawns `size` engines configured by `builder`.
+    pub fn new(builder: EngineBuilder, size: usize) -> Result<Self, Error> {
+        let idle = (0..size)
+            .map(|_| builder.spawn().map(Some))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(EnginePool {
+            builder,
+            size,
+            idle: Mutex::new(idle),
+            available: Condvar::new(),
+        })
+    }
+
+    /// Returns the number of engines managed by the pool.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Returns the number of idle engines.
+    pub fn idle_count(&self) -> usize {
+        self.idle.lock().unwrap().len()
+    }
+
+    /// Blocks until an engine is idle and hands it out.
+    /// The engine is returned to the pool when the guard is dropped.
+    pub fn get(&self) -> Result<PooledEngine<'_>, Error> {
+        let mut idle = self.idle.lock().unwrap();
+        loop {
+            if let Some(slot) = idle.pop() {
+                drop(idle);
+                return self.check_out(slot);
+            }
+            idle = self.available.wait(idle).unwrap();
+        }
+    }
+
+    /// Hands out an idle engine if any.
+    pub fn try_get(&self) -> Option<Result<PooledEngine<'_>, Error>> {
+        let slot = self.idle.lock().unwrap().pop()?;
+        Some(self.check_out(slot))
+    }
+
+    fn check_out(&self, slot: Option<Engine>) -> Result<PooledEngine<'_>, Error> {
+        let engine = match slot.map(|mut engine| reset(&mut engine).map(|_| engine)) {
+            Some(Ok(engine)) => engine,
+            _ => match self.builder.spawn() {
+                Ok(engine) => engine,
+                Err(e) => {
+                    self.check_in(None);
+                    return Err(e);
+                }
+            },
+        };
+
+        Ok(PooledEngine {
+            pool: self,
+            engine: Some(engine),
+            discard: false,
+        })
+    }
+
+    fn check_in(&self, slot: Option<Engine>) {
+        self.idle.lock().unwrap().push(slot);
+        self.available.notify_one();
+    }
+}
+
+/// Stops a search left running, waiting for its `bestmove`, and waits for `readyok`.
+fn reset(engine: &mut Engine) -> Result<(), Error> {
+    if engine.is_searching() {
+        engine.stop()?;
+    }
+    engine.prepare()
+}
+
+/// `PooledEngine` is an engine handed out by `EnginePool`.
+#[derive(Debug)]
+pub struct PooledEngine<'a> {
+    pool: &'a EnginePool,
+    engine: Option<Engine>,
+    discard: bool,
+}
+
+impl PooledEngine<'_> {
+    /// Marks the engine as broken, so it is replaced by a new engine.
+    pub fn discard(&mut self) {
+        self.discard = true;
+    }
+}
+
+impl Deref for PooledEngine<'_> {
+    type Target = Engine;
+
+    fn deref(&self) -> &Engine {
+        self.engine.as_ref().unwrap()
+    }
+}
+
+impl DerefMut for PooledEngine<'_> {
+    fn deref_mut(&mut self) -> &mut Engine {
+        self.engine.as_mut().unwrap()
+    }
+}
+
+impl Drop for PooledEngine<'_> {
+    fn drop(&mut self) {
+        let engine = self.engine.take();
+        if self.discard {
+            drop(engine);
+            self.pool.check_in(None);
+        } else {
+            self.pool.check_in(engine);
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pool() {
+        let builder = EngineBuilder::new("sh").args([
+            "-c",
+            "while read l; do case $l in usi) echo usiok;; isready) echo readyok;; \
+             quit) exit;; esac; done",
+        ]);
+        let pool = EnginePool::new(builder, 2).unwrap();
+        assert_eq!(2, pool.size());
+
+        let first = pool.get().unwrap();
+        let mut second = pool.get().unwrap();
+        assert!(pool.try_get().is_none());
+
+        second.discard();
+        drop(second);
+        assert_eq!(1, pool.idle_count());
+        drop(first);
+
+        std::thread::scope(|s| {
+            for _ in 0..4 {
+                s.spawn(|| {
+                    let mut engine = pool.get().unwrap();
+                    engine.prepare().unwrap();
+                });
+            }
+        });
+        assert_eq!(2, pool.idle_count());
+    }
+
+    #[test]
+    fn stop_left_search() {
+        let builder = EngineBuilder::new("sh").args([
+            "-c",
+            "while read l; do case $l in usi) echo usiok;; isready) echo readyok;; \
+             stop) echo 'bestmove 7g7f';; quit) exit;; esac; done",
+        ]);
+        let pool = EnginePool::new(builder, 1).unwrap();
+
+        let mut engine = pool.get().unwrap();
+        engine
+            .send(&crate::GuiCommand::Go(crate::ThinkParams::new().infinite()))
+            .unwrap();
+        drop(engine);
+
+        let mut engine = pool.get().unwrap();
+        assert!(!engine.is_searching());
+        engine.prepare().unwrap();
+    }
+}
@@ -0,0 +1,170 @@
+use std::time::Duration;
+
+use crate::protocol::*;
+
+/// `Quirks` describes how an engine deviates from the standard protocol and option names.
+///
+/// Quirks are keyed on the prefix of the `id name` of the engine. They rename standard
+/// options to the spelling of the engine, set options the engine requires for GUI play,
+/// and work around protocol deviations, such as engines taking minutes to send `readyok`.
+/// `Quirks::detect` looks up the built-in presets; custom quirks are applied with
+/// `Engine::set_quirks`.
+///
+/// # Examples
+/// ```no_run
+/// use std::time::Duration;
+/// use usi::{Engine, Quirks};
+///
+/// let mut engine = Engine::spawn("/path/to/usi_engine").unwrap();
+/// let quirks = Quirks::new("MyEngine")
+///     .alias("Threads", "NumThreads")
+///     .default_option("Logging", "false")
+///     .readyok_timeout(Duration::from_secs(300));
+/// engine.set_quirks(quirks).unwrap();
+///
+/// // Sent as `setoption name NumThreads value 4`.
+/// engine.set_option("Threads", "4").unwrap();
+/// ```
+#[derive(Clone, Debug)]
+pub struct Quirks {
+    name_prefix: String,
+    aliases: Vec<(String, String)>,
+    defaults: Vec<SetOptionParams>,
+    readyok_timeout: Option<Duration>,
+    rewrite_line: Option<fn(&str) -> Option<String>>,
+}
+
+impl Quirks {
+    /// Creates empty quirks for engines whose `id name` starts with `name_prefix`.
+    pub fn new<S: Into<String>>(name_prefix: S) -> Self {
+        Quirks {
+            name_prefix: name_prefix.into(),
+            aliases: Vec::new(),
+            defaults: Vec::new(),
+            readyok_timeout: None,
+            rewrite_line: None,
+        }
+    }
+
+    /// Returns the built-in presets for YaneuraOu, dlshogi, Gikou and Apery.
+    pub fn builtin() -> Vec<Quirks> {
+        vec![
+            Quirks::new("YaneuraOu").alias("USI_MultiPV", "MultiPV"),
+            // Building the inference engine of a model may take minutes on the first run.
+            Quirks::new("dlshogi")
+                .alias("Threads", "UCT_Threads")
+                .alias("EvalFile", "DNN_Model")
+                .readyok_timeout(Duration::from_secs(600)),
+            Quirks::new("Gikou").alias("USI_MultiPV", "MultiPV"),
+            Quirks::new("Apery")
+                .alias("USI_MultiPV", "MultiPV")
+                .alias("EvalDir", "Eval_Dir"),
+        ]
+    }
+
+    /// Returns the built-in preset matching the `id name` of an engine.
+    pub fn detect(engine_name: &str) -> Option<Quirks> {
+        Quirks::builtin()
+            .into_iter()
+            .find(|quirks| quirks.matches(engine_name))
+    }
+
+    /// Adds `engine_name` as the spelling of the standard option `name`.
+    ///
+    /// Aliases apply only if the engine declares `engine_name` but not `name`.
+    #[must_use]
+    pub fn alias<S: Into<String>, T: Into<String>>(mut self, name: S, engine_name: T) -> Self {
+        self.aliases.push((name.into(), engine_name.into()));
+        self
+    }
+
+    /// Adds an option value sent when the quirks are applied, if the engine declares the option.
+    #[must_use]
+    pub fn default_option<S: Into<String>, V: Into<String>>(mut self, name: S, value: V) -> Self {
+        self.defaults.push(SetOptionParams::new(name, value));
+        self
+    }
+
+    /// Sets the time limit of waiting for `readyok` the engine needs.
+    #[must_use]
+    pub fn readyok_timeout(mut self, timeout: Duration) -> Self {
+        self.readyok_timeout = Some(timeout);
+        self
+    }
+
+    /// Sets a function rewriting each line received from the engine before it is parsed.
+    /// Lines are dropped if the function returns `None`.
+    #[must_use]
+    pub fn rewrite_line(mut self, rewrite: fn(&str) -> Option<String>) -> Self {
+        self.rewrite_line = Some(rewrite);
+        self
+    }
+
+    /// Returns `true` if the quirks apply to the engine named `engine_name`.
+    pub fn matches(&self, engine_name: &str) -> bool {
+        engine_name.starts_with(&self.name_prefix)
+    }
+
+    /// Returns the spellings of the standard option `name` used by the engine.
+    pub fn aliases_of<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> {
+        self.aliases
+            .iter()
+            .filter(move |(standard, _)| standard == name)
+            .map(|(_, engine_name)| engine_name.as_str())
+    }
+
+    /// Returns the option values sent when the quirks are applied.
+    pub fn defaults(&self) -> &[SetOptionParams] {
+        &self.defaults
+    }
+
+    /// Returns the time limit of waiting for `readyok` the engine needs.
+    pub fn readyok(&self) -> Option<Duration> {
+        self.readyok_timeout
+    }
+
+    /// Rewrites a line received from the engine.
+    pub(crate) fn rewrite(&self, line: String) -> Option<String> {
+        match self.rewrite_line {
+            Some(rewrite) => rewrite(&line),
+            None => Some(line),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::ToyEngine;
+    use crate::{Engine, InProcessTransport, OptionRegistry};
+
+    #[test]
+    fn quirks() {
+        let toy = ToyEngine::new().name("dlshogi 2024").options(
+            OptionRegistry::new()
+                .spin("UCT_Threads", 2, 1, 256)
+                .check("Draw_Ponder", true),
+        );
+        let mut engine = Engine::connect(InProcessTransport::spawn(toy)).unwrap();
+        let quirks = Quirks::detect(engine.info().name()).unwrap();
+        assert!(Quirks::detect("Unknown").is_none());
+        assert_eq!(
+            vec!["UCT_Threads"],
+            quirks.aliases_of("Threads").collect::<Vec<_>>()
+        );
+
+        let quirks = quirks
+            .default_option("Draw_Ponder", "false")
+            .default_option("Missing", "1")
+            .rewrite_line(|line| (!line.starts_with("info string")).then(|| line.to_string()));
+        engine.set_quirks(quirks).unwrap();
+        assert_eq!(Some(Duration::from_secs(600)), engine.timeouts().readyok);
+        assert_eq!(Some("false"), engine.options().value("Draw_Ponder"));
+
+        engine.set_option("Threads", "4").unwrap();
+        assert_eq!(Some("4"), engine.options().value("UCT_Threads"));
+        engine.set_option("UCT_Threads", "8").unwrap();
+        assert_eq!(Some("8"), engine.options().value("UCT_Threads"));
+        assert!(engine.set_option("Hash", "4").is_err());
+    }
+}
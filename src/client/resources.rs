@@ -0,0 +1,220 @@
+use std::thread;
+
+use super::engine::Engine;
+use super::options::{EngineOption, EngineOptions};
+use crate::error::Error;
+use crate::protocol::*;
+
+/// Memory and CPU cores available on this machine.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SystemResources {
+    /// Available memory in MB, which is unknown on platforms other than Linux.
+    pub available_memory_mb: Option<u64>,
+    /// The number of CPU cores, or hardware threads.
+    pub cpu_cores: usize,
+}
+
+impl SystemResources {
+    /// Inspects the resources of this machine.
+    pub fn detect() -> Self {
+        SystemResources {
+            available_memory_mb: available_memory_mb(),
+            cpu_cores: thread::available_parallelism().map_or(1, |n| n.get()),
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn available_memory_mb() -> Option<u64> {
+    let meminfo = std::fs::read_to_string("/proc/meminfo").ok()?;
+    meminfo.lines().find_map(|line| {
+        let kb = line
+            .strip_prefix("MemAvailable:")?
+            .trim()
+            .strip_suffix("kB")?;
+        kb.trim().parse::<u64>().ok().map(|kb| kb / 1024)
+    })
+}
+
+#[cfg(not(target_os = "linux"))]
+fn available_memory_mb() -> Option<u64> {
+    None
+}
+
+/// `AutoConfig` proposes `USI_Hash` and `Threads` values from the system resources,
+/// instead of the small defaults engines usually declare.
+///
+/// The hash takes half of the available memory rounded down to a power of two,
+/// and every CPU core is used for threads, within the caps and the ranges declared
+/// by the engine.
+///
+/// # Examples
+/// ```no_run
+/// use usi::{AutoConfig, Engine};
+///
+/// let mut engine = Engine::spawn("/path/to/usi_engine").unwrap();
+/// let applied = AutoConfig::new()
+///     .max_hash_mb(4096)
+///     .max_threads(8)
+///     .apply(&mut engine)
+///     .unwrap();
+/// for params in applied {
+///     println!("{}", params.value.unwrap_or_default());
+/// }
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AutoConfig {
+    max_hash_mb: Option<u32>,
+    max_threads: Option<u32>,
+    memory_fraction: f64,
+    reserved_cores: usize,
+}
+
+impl Default for AutoConfig {
+    fn default() -> Self {
+        AutoConfig {
+            max_hash_mb: None,
+            max_threads: None,
+            memory_fraction: 0.5,
+            reserved_cores: 0,
+        }
+    }
+}
+
+impl AutoConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Caps the hash size in MB.
+    #[must_use]
+    pub fn max_hash_mb(mut self, mb: u32) -> Self {
+        self.max_hash_mb = Some(mb);
+        self
+    }
+
+    /// Caps the number of threads.
+    #[must_use]
+    pub fn max_threads(mut self, threads: u32) -> Self {
+        self.max_threads = Some(threads);
+        self
+    }
+
+    /// Sets the fraction of the available memory used for the hash. Defaults to 0.5.
+    #[must_use]
+    pub fn memory_fraction(mut self, fraction: f64) -> Self {
+        self.memory_fraction = fraction.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Sets the number of CPU cores left for other processes, e.g. a second engine
+    /// or the GUI. Defaults to 0.
+    #[must_use]
+    pub fn reserved_cores(mut self, cores: usize) -> Self {
+        self.reserved_cores = cores;
+        self
+    }
+
+    /// Returns the hash size in MB and the number of threads for `resources`,
+    /// before fitting them to the ranges of the options.
+    pub fn plan(&self, resources: &SystemResources) -> (Option<u32>, u32) {
+        let hash = resources.available_memory_mb.map(|mb| {
+            let mb = (mb as f64 * self.memory_fraction) as u64;
+            let mb = u32::try_from(mb).unwrap_or(u32::MAX);
+            let mb = self.max_hash_mb.map_or(mb, |max| mb.min(max));
+            // Engines allocate hash tables in powers of two.
+            match mb {
+                0 => 1,
+                mb => 1 << mb.ilog2(),
+            }
+        });
+        let cores = resources
+            .cpu_cores
+            .saturating_sub(self.reserved_cores)
+            .max(1);
+        let threads = u32::try_from(cores).unwrap_or(u32::MAX);
+        let threads = self.max_threads.map_or(threads, |max| threads.min(max));
+        (hash, threads.max(1))
+    }
+
+    /// Returns `setoption` parameters for the hash and thread options declared in `options`,
+    /// clamped to their ranges. The hash is skipped if the available memory is unknown.
+    pub fn propose(
+        &self,
+        resources: &SystemResources,
+        options: &EngineOptions,
+    ) -> Vec<SetOptionParams> {
+        let (hash, threads) = self.plan(resources);
+        let mut proposal = Vec::new();
+        let mut push = |option: Option<&EngineOption>, value: Option<u32>| {
+            if let (Some(option), Some(value)) = (option, value) {
+                if let Ok(value) = option.clamp(&value.to_string()) {
+                    proposal.push(SetOptionParams::new(option.name(), value));
+                }
+            }
+        };
+        push(options.hash_option(), hash);
+        push(options.threads_option(), Some(threads));
+        proposal
+    }
+
+    /// Detects the system resources and sends the proposed values to `engine`.
+    /// Returns the values sent.
+    pub fn apply(&self, engine: &mut Engine) -> Result<Vec<SetOptionParams>, Error> {
+        let proposal = self.propose(&SystemResources::detect(), engine.options());
+        for params in &proposal {
+            engine.set_option(&params.name, params.value.as_deref().unwrap_or_default())?;
+        }
+        Ok(proposal)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn propose() {
+        let mut options = EngineOptions::default();
+        for line in [
+            "option name USI_Hash type spin default 16 min 1 max 8192",
+            "option name Threads type spin default 1 min 1 max 4",
+        ] {
+            if let EngineCommand::Option(params) = EngineCommand::parse(line).unwrap() {
+                options.declare(&params);
+            }
+        }
+
+        let resources = SystemResources {
+            available_memory_mb: Some(12000),
+            cpu_cores: 16,
+        };
+        assert_eq!((Some(4096), 16), AutoConfig::new().plan(&resources));
+        assert_eq!(
+            (Some(1024), 15),
+            AutoConfig::new()
+                .max_hash_mb(2000)
+                .reserved_cores(1)
+                .plan(&resources)
+        );
+        assert_eq!(
+            vec![
+                SetOptionParams::new("USI_Hash", "8192"),
+                SetOptionParams::new("Threads", "4"),
+            ],
+            AutoConfig::new()
+                .memory_fraction(1.0)
+                .propose(&resources, &options)
+        );
+
+        let unknown = SystemResources {
+            available_memory_mb: None,
+            cpu_cores: 2,
+        };
+        assert_eq!(
+            vec![SetOptionParams::new("Threads", "2")],
+            AutoConfig::new().propose(&unknown, &options)
+        );
+        assert!(SystemResources::detect().cpu_cores >= 1);
+    }
+}
@@ -0,0 +1,107 @@
+use serde_json::{json, Map, Value};
+
+use super::options::{EngineOption, EngineOptions};
+use crate::protocol::*;
+
+impl EngineOptions {
+    /// Describes the options as a JSON Schema of an object, so that a settings form can be
+    /// rendered from it.
+    ///
+    /// Each property has the declared default, the range of spin options and the choices
+    /// of combo options. The USI type is kept in the `x-usi-type` keyword to pick widgets,
+    /// e.g. a file picker for `filename`. Buttons are `null` properties.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use usi::Engine;
+    ///
+    /// let engine = Engine::spawn("/path/to/usi_engine").unwrap();
+    /// let schema = engine.options().to_json_schema();
+    /// println!("{}", schema["properties"]["USI_Hash"]["maximum"]);
+    /// ```
+    pub fn to_json_schema(&self) -> Value {
+        let properties: Map<String, Value> = self
+            .iter()
+            .map(|option| (option.name().to_string(), property(option)))
+            .collect();
+        json!({
+            "$schema": "https://json-schema.org/draft/2020-12/schema",
+            "type": "object",
+            "properties": properties,
+            "additionalProperties": false,
+        })
+    }
+}
+
+fn property(option: &EngineOption) -> Value {
+    let (usi_type, mut schema) = match option.kind() {
+        OptionKind::Check { default } => {
+            ("check", json!({ "type": "boolean", "default": default }))
+        }
+        OptionKind::Spin { default, min, max } => (
+            "spin",
+            json!({ "type": "integer", "default": default, "minimum": min, "maximum": max }),
+        ),
+        OptionKind::Combo { default, vars } => (
+            "combo",
+            json!({ "type": "string", "default": default, "enum": vars }),
+        ),
+        OptionKind::Button { .. } => ("button", json!({ "type": "null" })),
+        OptionKind::String { default } => {
+            ("string", json!({ "type": "string", "default": default }))
+        }
+        OptionKind::Filename { default } => {
+            ("filename", json!({ "type": "string", "default": default }))
+        }
+    };
+    let map = schema.as_object_mut().expect("schema is an object");
+    // Absent defaults and bounds are omitted rather than null.
+    map.retain(|_, v| !v.is_null());
+    map.insert("title".to_string(), json!(option.name()));
+    map.insert("x-usi-type".to_string(), json!(usi_type));
+    schema
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn to_json_schema() {
+        let mut options = EngineOptions::default();
+        for line in [
+            "option name USI_Hash type spin default 256 min 1 max 4096",
+            "option name USI_Ponder type check default false",
+            "option name Style type combo default Normal var Solid var Normal",
+            "option name ClearHash type button",
+            "option name EvalDir type filename default eval",
+        ] {
+            if let EngineCommand::Option(params) = EngineCommand::parse(line).unwrap() {
+                options.declare(&params);
+            }
+        }
+
+        let schema = options.to_json_schema();
+        assert_eq!(json!(false), schema["additionalProperties"]);
+        let properties = &schema["properties"];
+        assert_eq!(
+            json!({
+                "title": "USI_Hash",
+                "type": "integer",
+                "default": 256,
+                "minimum": 1,
+                "maximum": 4096,
+                "x-usi-type": "spin",
+            }),
+            properties["USI_Hash"]
+        );
+        assert_eq!(json!("boolean"), properties["USI_Ponder"]["type"]);
+        assert_eq!(json!(["Solid", "Normal"]), properties["Style"]["enum"]);
+        assert_eq!(
+            json!({ "title": "ClearHash", "type": "null", "x-usi-type": "button" }),
+            properties["ClearHash"]
+        );
+        assert_eq!(json!("filename"), properties["EvalDir"]["x-usi-type"]);
+        assert_eq!(json!("eval"), properties["EvalDir"]["default"]);
+    }
+}
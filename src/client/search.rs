@@ -0,0 +1,249 @@
+use std::time::{Duration, Instant};
+
+use super::engine::{Engine, EngineEvent};
+use crate::error::Error;
+use crate::process::EngineOutput;
+use crate::protocol::*;
+
+/// `SearchHandle` tracks a search started by `Engine::go` until `bestmove` is received.
+///
+/// The handle borrows the engine, so the search must be finished by `SearchHandle::wait`
+/// or `SearchHandle::stop` before other commands are sent. Iterating the handle yields
+/// the parameters of `info` commands until `bestmove` is received.
+///
+/// If the engine is restarted during the search, the search is lost and
+/// `Error::EngineClosed` is returned.
+///
+/// Dropping an unfinished handle sends `stop` and discards the output up to `bestmove`,
+/// waiting no longer than the `bestmove` timeout, so that the engine is idle afterwards.
+///
+/// # Examples
+/// ```no_run
+/// use usi::{Engine, ThinkParams};
+///
+/// let mut engine = Engine::spawn("/path/to/usi_engine").unwrap();
+/// let mut search = engine.go(ThinkParams::new().infinite()).unwrap();
+/// for info in search.by_ref().take(10) {
+///     println!("{:?}", info.unwrap());
+/// }
+/// let bestmove = search.stop().unwrap();
+/// ```
+#[derive(Debug)]
+pub struct SearchHandle<'a> {
+    engine: &'a mut Engine,
+    bestmove: Option<BestMoveParams>,
+    done: bool,
+}
+
+impl<'a> SearchHandle<'a> {
+    pub(crate) fn new(engine: &'a mut Engine) -> Self {
+        SearchHandle {
+            engine,
+            bestmove: None,
+            done: false,
+        }
+    }
+
+    /// Blocks until the next `info` command is received.
+    /// Returns `None` once `bestmove` is received.
+    pub fn next_info(&mut self) -> Result<Option<Vec<InfoParams>>, Error> {
+        while !self.done {
+            let output = match self.engine.recv_event() {
+                Ok(EngineEvent::Output(output)) => output,
+                Ok(EngineEvent::Restarted) => {
+                    self.done = true;
+                    return Err(Error::EngineClosed);
+                }
+                Ok(
+                    EngineEvent::Unhealthy
+                    | EngineEvent::SearchStalled
+                    | EngineEvent::OptionChanged(_),
+                ) => continue,
+                Err(e) => {
+                    self.done = true;
+                    return Err(e);
+                }
+            };
+            if let Some(params) = self.accept(&output) {
+                return Ok(Some(params));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Waits up to `timeout` for the next `info` command, e.g. to check for cancellation
+    /// while the engine is quiet. Returns `None` if nothing was received in time or once
+    /// `bestmove` is received, which `SearchHandle::bestmove` tells apart.
+    /// Transports without timeout support block until the next line.
+    pub fn next_info_timeout(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<Option<Vec<InfoParams>>, Error> {
+        let deadline = Instant::now() + timeout;
+        while !self.done {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                break;
+            }
+            let output = match self.engine.recv_timeout(remaining) {
+                Ok(Some(output)) => output,
+                Ok(None) => break,
+                Err(e) => {
+                    self.done = true;
+                    return Err(e);
+                }
+            };
+            if let Some(params) = self.accept(&output) {
+                return Ok(Some(params));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Returns the parameters of `info`, recording `bestmove` which ends the search.
+    fn accept(&mut self, output: &EngineOutput) -> Option<Vec<InfoParams>> {
+        match output.response() {
+            Some(EngineCommand::Info(params)) => return Some(params.clone()),
+            Some(EngineCommand::BestMove(params)) => {
+                self.bestmove = Some(params.clone());
+                self.done = true;
+            }
+            _ => {}
+        }
+        None
+    }
+
+    /// Sends `ponderhit`, which continues the ponder search as a normal search.
+    pub fn ponderhit(&mut self) -> Result<(), Error> {
+        if self.done {
+            return Err(Error::IllegalState {
+                reason: "the search has finished",
+            });
+        }
+        self.engine.send(&GuiCommand::Ponderhit)
+    }
+
+    /// Returns `bestmove` if the search has finished.
+    pub fn bestmove(&self) -> Option<&BestMoveParams> {
+        self.bestmove.as_ref()
+    }
+
+    /// Blocks until `bestmove` is received. `info` commands are discarded.
+    pub fn wait(mut self) -> Result<BestMoveParams, Error> {
+        while self.next_info()?.is_some() {}
+        self.bestmove.take().ok_or(Error::EngineClosed)
+    }
+
+    /// Sends `stop` command unless the search has finished, and waits for `bestmove`.
+    pub fn stop(mut self) -> Result<BestMoveParams, Error> {
+        match self.bestmove.take() {
+            Some(params) => Ok(params),
+            None if self.done => Err(Error::EngineClosed),
+            None => {
+                self.done = true;
+                self.engine.stop()
+            }
+        }
+    }
+}
+
+impl Drop for SearchHandle<'_> {
+    fn drop(&mut self) {
+        if !self.done {
+            let _ = self.engine.stop();
+        }
+    }
+}
+
+impl Iterator for SearchHandle<'_> {
+    type Item = Result<Vec<InfoParams>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_info().transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_util::ToyEngine;
+    use crate::*;
+    use std::time::Duration;
+
+    #[test]
+    fn search() {
+        let output = "usiok\nreadyok\ninfo depth 1 score cp 30\n\
+                      info depth 2 score cp 10\nbestmove 7g7f\n\
+                      info depth 1\nbestmove 2g2f\nbestmove 3g3f\n";
+        let mut engine = Engine::connect(IoTransport::new(output.as_bytes(), Vec::new())).unwrap();
+
+        let search = engine.go(ThinkParams::new().infinite()).unwrap();
+        assert_eq!(2, search.count());
+
+        let search = engine.go(ThinkParams::new().infinite()).unwrap();
+        assert_eq!(
+            BestMoveParams::MakeMove("2g2f".to_string(), None),
+            search.wait().unwrap()
+        );
+
+        let mut search = engine.go(ThinkParams::new().infinite()).unwrap();
+        assert!(search.next_info().unwrap().is_none());
+        assert_eq!(
+            Some(&BestMoveParams::MakeMove("3g3f".to_string(), None)),
+            search.bestmove()
+        );
+        assert!(search.stop().is_ok());
+
+        let search = engine.go(ThinkParams::new().infinite()).unwrap();
+        assert!(matches!(search.wait(), Err(Error::EngineClosed)));
+    }
+
+    #[test]
+    fn drop_unfinished() {
+        let output = "usiok
+readyok
+info depth 1
+info depth 2
+bestmove 7g7f
+                      info depth 1
+bestmove 2g2f
+";
+        let mut engine = Engine::connect(IoTransport::new(output.as_bytes(), Vec::new())).unwrap();
+
+        let mut search = engine.go(ThinkParams::new().infinite()).unwrap();
+        assert!(search.next_info().unwrap().is_some());
+        drop(search);
+
+        // The output of the dropped search is not mixed into the next one.
+        let search = engine.go(ThinkParams::new().infinite()).unwrap();
+        assert_eq!(
+            BestMoveParams::MakeMove("2g2f".to_string(), None),
+            search.wait().unwrap()
+        );
+    }
+
+    #[test]
+    fn ponderhit() {
+        let toy = ToyEngine::new()
+            .info(vec![InfoParams::Depth(1, None)])
+            .bestmove("7g7f", None);
+        let mut engine = Engine::connect(InProcessTransport::spawn(toy)).unwrap();
+
+        let mut search = engine.go(ThinkParams::new().ponder()).unwrap();
+        let timeout = Duration::from_secs(1);
+        assert!(search.next_info_timeout(timeout).unwrap().is_some());
+        // The engine is quiet until `ponderhit`.
+        let quiet = search.next_info_timeout(Duration::from_millis(10)).unwrap();
+        assert!(quiet.is_none() && search.bestmove().is_none());
+
+        search.ponderhit().unwrap();
+        assert!(search.next_info_timeout(timeout).unwrap().is_none());
+        assert_eq!(
+            Some(&BestMoveParams::MakeMove("7g7f".to_string(), None)),
+            search.bestmove()
+        );
+        assert!(matches!(
+            search.ponderhit(),
+            Err(Error::IllegalState { .. })
+        ));
+    }
+}
@@ -0,0 +1,209 @@
+use std::io::{self, BufReader};
+use std::net::{Shutdown, TcpStream, ToSocketAddrs};
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+#[cfg(unix)]
+use std::path::Path;
+use std::time::Duration;
+
+use super::encoding::Encoding;
+use super::framing::Framing;
+use super::transport::{IoTransport, Transport};
+
+/// `TcpTransport` communicates with a USI engine served over a TCP connection.
+///
+/// # Examples
+/// ```no_run
+/// use usi::{Engine, TcpTransport};
+///
+/// let transport = TcpTransport::connect("analysis-server:4081").unwrap();
+/// let engine = Engine::connect(transport).unwrap();
+/// println!("connected to {}", engine.info().name());
+/// ```
+#[derive(Debug)]
+pub struct TcpTransport {
+    stream: TcpStream,
+    io: IoTransport<BufReader<TcpStream>, TcpStream>,
+}
+
+impl TcpTransport {
+    /// Opens a TCP connection to the engine server.
+    pub fn connect<A: ToSocketAddrs>(addr: A) -> io::Result<Self> {
+        TcpTransport::from_stream(TcpStream::connect(addr)?)
+    }
+
+    /// Creates a transport from an established TCP connection.
+    pub fn from_stream(stream: TcpStream) -> io::Result<Self> {
+        let reader = BufReader::new(stream.try_clone()?);
+        let writer = stream.try_clone()?;
+
+        Ok(TcpTransport {
+            stream,
+            io: IoTransport::new(reader, writer),
+        })
+    }
+
+    /// Returns the underlying TCP stream.
+    pub fn stream(&self) -> &TcpStream {
+        &self.stream
+    }
+}
+
+impl Transport for TcpTransport {
+    fn read_line(&mut self) -> io::Result<Option<String>> {
+        self.io.read_line()
+    }
+
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        self.io.write_line(line)
+    }
+
+    fn close(&mut self) -> io::Result<()> {
+        self.stream.shutdown(Shutdown::Both)
+    }
+
+    fn set_read_timeout(&mut self, timeout: Option<Duration>) -> io::Result<()> {
+        self.stream.set_read_timeout(timeout)
+    }
+
+    fn set_encoding(&mut self, encoding: Encoding) -> io::Result<()> {
+        self.io.set_encoding(encoding)
+    }
+
+    fn set_framing(&mut self, framing: Framing) -> io::Result<()> {
+        self.io.set_framing(framing)
+    }
+}
+
+/// `UnixTransport` communicates with a USI engine served over a Unix domain socket.
+#[cfg(unix)]
+#[derive(Debug)]
+pub struct UnixTransport {
+    stream: UnixStream,
+    io: IoTransport<BufReader<UnixStream>, UnixStream>,
+}
+
+#[cfg(unix)]
+impl UnixTransport {
+    /// Connects to the engine server listening on the socket at `path`.
+    pub fn connect<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        UnixTransport::from_stream(UnixStream::connect(path)?)
+    }
+
+    /// Creates a transport from an established Unix domain socket connection.
+    pub fn from_stream(stream: UnixStream) -> io::Result<Self> {
+        let reader = BufReader::new(stream.try_clone()?);
+        let writer = stream.try_clone()?;
+
+        Ok(UnixTransport {
+            stream,
+            io: IoTransport::new(reader, writer),
+        })
+    }
+
+    /// Returns the underlying Unix domain socket stream.
+    pub fn stream(&self) -> &UnixStream {
+        &self.stream
+    }
+}
+
+#[cfg(unix)]
+impl Transport for UnixTransport {
+    fn read_line(&mut self) -> io::Result<Option<String>> {
+        self.io.read_line()
+    }
+
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        self.io.write_line(line)
+    }
+
+    fn close(&mut self) -> io::Result<()> {
+        self.stream.shutdown(Shutdown::Both)
+    }
+
+    fn set_read_timeout(&mut self, timeout: Option<Duration>) -> io::Result<()> {
+        self.stream.set_read_timeout(timeout)
+    }
+
+    fn set_encoding(&mut self, encoding: Encoding) -> io::Result<()> {
+        self.io.set_encoding(encoding)
+    }
+
+    fn set_framing(&mut self, framing: Framing) -> io::Result<()> {
+        self.io.set_framing(framing)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Engine, Error};
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    fn read_line<S: Read>(stream: &mut S) -> String {
+        let mut line = Vec::new();
+        let mut byte = [0u8];
+        while stream.read(&mut byte).unwrap() == 1 && byte[0] != b'\n' {
+            line.push(byte[0]);
+        }
+        String::from_utf8(line).unwrap()
+    }
+
+    fn serve<S: Read + Write>(mut stream: S) {
+        assert_eq!("usi", read_line(&mut stream));
+        stream.write_all(b"id name Remote\nusiok\n").unwrap();
+        assert_eq!("isready", read_line(&mut stream));
+        stream.write_all(b"readyok\n").unwrap();
+    }
+
+    #[test]
+    fn tcp_timeout() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut transport = TcpTransport::connect(addr).unwrap();
+        let (mut server, _) = listener.accept().unwrap();
+
+        transport
+            .set_read_timeout(Some(Duration::from_millis(10)))
+            .unwrap();
+        server.write_all(b"usi").unwrap();
+        assert_eq!(
+            io::ErrorKind::TimedOut,
+            transport.read_line().unwrap_err().kind()
+        );
+        server.write_all(b"ok\n").unwrap();
+        assert_eq!(Some("usiok".to_string()), transport.read_line().unwrap());
+    }
+
+    #[test]
+    fn tcp() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = thread::spawn(move || serve(listener.accept().unwrap().0));
+
+        let mut engine = Engine::connect(TcpTransport::connect(addr).unwrap()).unwrap();
+        assert_eq!("Remote", engine.info().name());
+        server.join().unwrap();
+
+        assert!(matches!(engine.recv(), Err(Error::EngineClosed)));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unix() {
+        use std::os::unix::net::UnixListener;
+
+        let path = std::env::temp_dir().join(format!("usi-rs-test-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+        let server = thread::spawn(move || serve(listener.accept().unwrap().0));
+
+        let engine = Engine::connect(UnixTransport::connect(&path).unwrap()).unwrap();
+        assert_eq!("Remote", engine.info().name());
+        server.join().unwrap();
+        engine.close().unwrap();
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,357 @@
+use std::ffi::OsString;
+use std::io::{self, BufReader, Write};
+use std::path::PathBuf;
+use std::process::{Child, ChildStdin, Command, ExitStatus, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::encoding::{encode_line, Encoding};
+use super::framing::Framing;
+use super::transport::{wait_exit, LineReceiver, StderrCapture, Transport};
+
+/// `SshTransportBuilder` configures how a USI engine is launched on a remote machine over SSH.
+///
+/// The `ssh` client installed on the local machine is used, so host aliases,
+/// agents and keys configured in `~/.ssh/config` are available.
+///
+/// # Examples
+/// ```no_run
+/// use usi::{Engine, SshTransportBuilder};
+///
+/// let transport = SshTransportBuilder::new("analysis-server")
+///     .user("shogi")
+///     .current_dir("/opt/engines/YaneuraOu")
+///     .spawn("./YaneuraOu-by-gcc")
+///     .unwrap();
+/// let engine = Engine::connect(transport).unwrap();
+/// ```
+#[derive(Clone, Debug)]
+pub struct SshTransportBuilder {
+    program: OsString,
+    host: String,
+    user: Option<String>,
+    port: Option<u16>,
+    identity_file: Option<PathBuf>,
+    options: Vec<String>,
+    current_dir: Option<String>,
+    args: Vec<String>,
+    grace_period: Duration,
+}
+
+impl SshTransportBuilder {
+    pub fn new<S: Into<String>>(host: S) -> Self {
+        SshTransportBuilder {
+            program: "ssh".into(),
+            host: host.into(),
+            user: None,
+            port: None,
+            identity_file: None,
+            options: Vec::new(),
+            current_dir: None,
+            args: Vec::new(),
+            grace_period: Duration::from_secs(3),
+        }
+    }
+
+    /// Sets the path of the `ssh` client. Defaults to `ssh`.
+    #[must_use]
+    pub fn program<S: Into<OsString>>(mut self, program: S) -> Self {
+        self.program = program.into();
+        self
+    }
+
+    #[must_use]
+    pub fn user<S: Into<String>>(mut self, user: S) -> Self {
+        self.user = Some(user.into());
+        self
+    }
+
+    #[must_use]
+    pub fn port(mut self, port: u16) -> Self {
+        self.port = Some(port);
+        self
+    }
+
+    #[must_use]
+    pub fn identity_file<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.identity_file = Some(path.into());
+        self
+    }
+
+    /// Adds an option passed to `ssh` with `-o`, e.g. `ConnectTimeout=10`.
+    #[must_use]
+    pub fn option<S: Into<String>>(mut self, option: S) -> Self {
+        self.options.push(option.into());
+        self
+    }
+
+    /// Sets the remote directory the engine is launched from.
+    #[must_use]
+    pub fn current_dir<S: Into<String>>(mut self, dir: S) -> Self {
+        self.current_dir = Some(dir.into());
+        self
+    }
+
+    /// Adds a command-line argument passed to the remote engine.
+    #[must_use]
+    pub fn arg<S: Into<String>>(mut self, arg: S) -> Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    /// Sets how long to wait for the remote engine to exit after `quit` before the
+    /// connection is killed. Defaults to 3 seconds.
+    #[must_use]
+    pub fn grace_period(mut self, grace_period: Duration) -> Self {
+        self.grace_period = grace_period;
+        self
+    }
+
+    /// Launches `engine_path` on the remote host.
+    ///
+    /// Fails with `io::ErrorKind::InvalidInput` if the host or the user starts with `-`,
+    /// which `ssh` would take as an option.
+    pub fn spawn<S: AsRef<str>>(&self, engine_path: S) -> io::Result<SshTransport> {
+        self.validate()?;
+        let mut process = self
+            .command(engine_path.as_ref())
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        let stdin = process.stdin.take();
+        let stdout = process.stdout.take();
+        let (stdin, stdout) = stdin
+            .zip(stdout)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::BrokenPipe, "failed to open ssh stdio"))?;
+
+        let stderr = process.stderr.take().map(StderrCapture::spawn);
+
+        Ok(SshTransport {
+            process,
+            reader: LineReceiver::spawn(BufReader::new(stdout)),
+            stdin: Some(stdin),
+            stderr,
+            eof: false,
+            grace_period: self.grace_period,
+            newline: Framing::default().newline.as_str(),
+        })
+    }
+
+    fn validate(&self) -> io::Result<()> {
+        let user = self.user.as_deref().unwrap_or_default();
+        if self.host.is_empty() || self.host.starts_with('-') || user.starts_with('-') {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid ssh destination {:?}", self.host),
+            ));
+        }
+        Ok(())
+    }
+
+    fn command(&self, engine_path: &str) -> Command {
+        let mut cmd = Command::new(&self.program);
+        // Disable pseudo-terminal allocation so the protocol stream is passed through as is.
+        cmd.arg("-T");
+        if let Some(port) = self.port {
+            cmd.arg("-p").arg(port.to_string());
+        }
+        if let Some(path) = &self.identity_file {
+            cmd.arg("-i").arg(path);
+        }
+        for opt in &self.options {
+            cmd.arg("-o").arg(opt);
+        }
+        // Ends the options so the destination is never parsed as one.
+        cmd.arg("--");
+        match &self.user {
+            Some(user) => cmd.arg(format!("{user}@{}", self.host)),
+            None => cmd.arg(&self.host),
+        };
+
+        let mut remote = String::new();
+        if let Some(dir) = &self.current_dir {
+            remote.push_str(&format!("cd {} && ", quote(dir)));
+        }
+        // `exec` lets the engine receive EOF of stdin directly when the connection closes.
+        remote.push_str("exec ");
+        remote.push_str(&quote(engine_path));
+        for arg in &self.args {
+            remote.push(' ');
+            remote.push_str(&quote(arg));
+        }
+        cmd.arg(remote);
+
+        cmd
+    }
+}
+
+/// `SshTransport` communicates with a USI engine launched on a remote machine over SSH.
+///
+/// On `close()` or drop, `quit` is sent and the connection is closed so the remote engine
+/// terminates. The local `ssh` process is killed if it doesn't exit within the grace period.
+#[derive(Debug)]
+pub struct SshTransport {
+    process: Child,
+    reader: LineReceiver,
+    stdin: Option<ChildStdin>,
+    stderr: Option<StderrCapture>,
+    eof: bool,
+    grace_period: Duration,
+    newline: &'static str,
+}
+
+impl SshTransport {
+    /// Returns the local `ssh` process.
+    pub fn process(&mut self) -> &mut Child {
+        &mut self.process
+    }
+
+    /// Sets a handler called on the background thread with each line written to stderr,
+    /// which includes both the remote engine's stderr and errors of `ssh` itself.
+    pub fn set_stderr_handler<F: FnMut(&str) + Send + 'static>(&mut self, handler: F) {
+        if let Some(stderr) = &self.stderr {
+            stderr.set_handler(Box::new(handler));
+        }
+    }
+}
+
+impl Transport for SshTransport {
+    fn read_line(&mut self) -> io::Result<Option<String>> {
+        let res = self.reader.read_line();
+        if let Ok(None) = res {
+            self.eof = true;
+        }
+        res
+    }
+
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        let stdin = self
+            .stdin
+            .as_mut()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotConnected, "connection closed"))?;
+        stdin.write_all(&encode_line(self.reader.encoding(), line)?)?;
+        stdin.write_all(self.newline.as_bytes())?;
+        stdin.flush()
+    }
+
+    fn close(&mut self) -> io::Result<()> {
+        if let Some(mut stdin) = self.stdin.take() {
+            // The engine may have exited already, so the failure is not an error here.
+            let _ = stdin
+                .write_all(format!("quit{}", self.newline).as_bytes())
+                .and_then(|_| stdin.flush());
+        }
+
+        let deadline = Instant::now() + self.grace_period;
+        while self.process.try_wait()?.is_none() {
+            if Instant::now() >= deadline {
+                self.process.kill()?;
+                self.process.wait()?;
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        Ok(())
+    }
+
+    fn set_read_timeout(&mut self, timeout: Option<Duration>) -> io::Result<()> {
+        self.reader.set_timeout(timeout);
+        Ok(())
+    }
+
+    fn set_encoding(&mut self, encoding: Encoding) -> io::Result<()> {
+        self.reader.set_encoding(encoding);
+        Ok(())
+    }
+
+    fn set_framing(&mut self, framing: Framing) -> io::Result<()> {
+        self.reader.set_framing(framing);
+        self.newline = framing.newline.as_str();
+        Ok(())
+    }
+
+    fn stderr_lines(&mut self) -> Option<Vec<String>> {
+        let eof = self.eof;
+        self.stderr.as_ref().map(|stderr| stderr.lines(eof))
+    }
+
+    fn exit_status(&mut self) -> Option<ExitStatus> {
+        wait_exit(&mut self.process, self.eof)
+    }
+}
+
+impl Drop for SshTransport {
+    fn drop(&mut self) {
+        let _ = self.close();
+    }
+}
+
+/// Quotes `s` for a POSIX shell on the remote host.
+fn quote(s: &str) -> String {
+    if !s.is_empty()
+        && s.chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./=:@%+,".contains(c))
+    {
+        return s.to_string();
+    }
+    format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn command() {
+        let builder = SshTransportBuilder::new("example.com")
+            .user("shogi")
+            .port(2222)
+            .identity_file("/home/shogi/.ssh/id_ed25519")
+            .option("BatchMode=yes")
+            .current_dir("/opt/my engines")
+            .arg("--threads=4");
+        let cmd = builder.command("./engine");
+
+        assert_eq!("ssh", cmd.get_program());
+        let args: Vec<_> = cmd.get_args().map(|a| a.to_str().unwrap()).collect();
+        assert_eq!(
+            vec![
+                "-T",
+                "-p",
+                "2222",
+                "-i",
+                "/home/shogi/.ssh/id_ed25519",
+                "-o",
+                "BatchMode=yes",
+                "--",
+                "shogi@example.com",
+                "cd '/opt/my engines' && exec ./engine --threads=4",
+            ],
+            args
+        );
+    }
+
+    #[test]
+    fn destination() {
+        let err = SshTransportBuilder::new("-oProxyCommand=touch /tmp/pwned")
+            .spawn("./engine")
+            .unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidInput, err.kind());
+
+        let err = SshTransportBuilder::new("example.com")
+            .user("-oProxyCommand=touch /tmp/pwned")
+            .spawn("./engine")
+            .unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidInput, err.kind());
+        assert!(SshTransportBuilder::new("example.com").validate().is_ok());
+    }
+
+    #[test]
+    fn quoting() {
+        assert_eq!("./engine", quote("./engine"));
+        assert_eq!("''", quote(""));
+        assert_eq!(r"'it'\''s'", quote("it's"));
+    }
+}
@@ -0,0 +1,24 @@
+use tracing::Level;
+
+/// Logs a line sent to or received from the engine named `engine`.
+/// `direction` is either `send` or `recv`.
+pub(crate) fn traffic(level: Level, direction: &'static str, engine: &str, line: &str) {
+    // The level of `tracing::event!` must be a constant.
+    macro_rules! traffic {
+        ($level:expr) => {
+            tracing::event!(target: "usi", $level, direction, engine, line)
+        };
+    }
+
+    if level == Level::ERROR {
+        traffic!(Level::ERROR)
+    } else if level == Level::WARN {
+        traffic!(Level::WARN)
+    } else if level == Level::INFO {
+        traffic!(Level::INFO)
+    } else if level == Level::DEBUG {
+        traffic!(Level::DEBUG)
+    } else {
+        traffic!(Level::TRACE)
+    }
+}
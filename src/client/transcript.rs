@@ -0,0 +1,409 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::process::ExitStatus;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use super::encoding::Encoding;
+use super::framing::Framing;
+use super::transport::Transport;
+use crate::error::Error;
+
+/// Represents a direction of a line in a transcript.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Direction {
+    /// Sent from the GUI to the engine, written as `>`.
+    Sent,
+    /// Received by the GUI from the engine, written as `<`.
+    Received,
+}
+
+/// Represents the format of a transcript written by `RecordingTransport`.
+///
+/// `Jsonl` only exists with `serde` feature, so the enum is non-exhaustive.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum TranscriptFormat {
+    /// A line per entry as written by `TranscriptEntry`'s `Display`.
+    #[default]
+    Text,
+    /// JSON Lines as written by `JsonlTranscriptWriter`.
+    #[cfg(feature = "serde")]
+    Jsonl,
+}
+
+/// Represents a line in a transcript.
+///
+/// An entry is written as the elapsed time in milliseconds, the direction and the line.
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+/// use usi::{Direction, TranscriptEntry};
+///
+/// let entry: TranscriptEntry = "1500 < bestmove 7g7f".parse().unwrap();
+/// assert_eq!(Duration::from_millis(1500), entry.elapsed);
+/// assert_eq!(Direction::Received, entry.direction);
+/// assert_eq!("bestmove 7g7f", entry.line);
+/// assert_eq!("1500 < bestmove 7g7f", entry.to_string());
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TranscriptEntry {
+    pub elapsed: Duration,
+    pub direction: Direction,
+    pub line: String,
+}
+
+impl fmt::Display for TranscriptEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let direction = match self.direction {
+            Direction::Sent => '>',
+            Direction::Received => '<',
+        };
+        write!(f, "{} {}", self.elapsed.as_millis(), direction)?;
+        // An empty line is written without the trailing space.
+        if !self.line.is_empty() {
+            write!(f, " {}", self.line)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for TranscriptEntry {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        let (elapsed, rest) = s.split_once(' ').ok_or(Error::IllegalSyntax)?;
+        let direction = match rest.get(..1) {
+            Some(">") => Direction::Sent,
+            Some("<") => Direction::Received,
+            _ => return Err(Error::IllegalSyntax),
+        };
+        // An empty line is written without the trailing space.
+        let line = rest.get(2..).unwrap_or_default();
+
+        Ok(TranscriptEntry {
+            elapsed: Duration::from_millis(elapsed.parse()?),
+            direction,
+            line: line.to_string(),
+        })
+    }
+}
+
+/// `RecordingTransport<T, W>` wraps a transport and writes a transcript of the traffic to `W`.
+///
+/// Each entry is flushed immediately, so the transcript is complete even if the process crashes.
+/// The transcript is written in `TranscriptFormat::Text` unless set by `RecordingTransport::format`.
+///
+/// # Examples
+/// ```no_run
+/// use usi::{Engine, ProcessTransport, RecordingTransport};
+///
+/// let transport = ProcessTransport::spawn("/path/to/usi_engine").unwrap();
+/// let transport = RecordingTransport::create(transport, "session.log").unwrap();
+/// let engine = Engine::connect(transport).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct RecordingTransport<T: Transport, W: Write + Send> {
+    inner: T,
+    writer: W,
+    start: Instant,
+    format: TranscriptFormat,
+    started: bool,
+}
+
+impl<T: Transport, W: Write + Send> RecordingTransport<T, W> {
+    pub fn new(inner: T, writer: W) -> Self {
+        RecordingTransport {
+            inner,
+            writer,
+            start: Instant::now(),
+            format: TranscriptFormat::Text,
+            started: false,
+        }
+    }
+
+    /// Sets the format of the transcript.
+    #[must_use]
+    pub fn format(mut self, format: TranscriptFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Consumes the transport, returning the wrapped transport and the writer.
+    pub fn into_inner(self) -> (T, W) {
+        (self.inner, self.writer)
+    }
+
+    fn record(&mut self, direction: Direction, line: &str) -> io::Result<()> {
+        let entry = TranscriptEntry {
+            elapsed: self.start.elapsed(),
+            direction,
+            line: line.to_string(),
+        };
+        match self.format {
+            TranscriptFormat::Text => writeln!(self.writer, "{}", entry)?,
+            #[cfg(feature = "serde")]
+            TranscriptFormat::Jsonl => {
+                if !self.started {
+                    writeln!(self.writer, "{}", super::jsonl::header_line())?;
+                }
+                writeln!(self.writer, "{}", super::jsonl::entry_line(&entry))?;
+            }
+        }
+        self.started = true;
+        self.writer.flush()
+    }
+}
+
+/// A transcript buffer shared between its owner and `RecordingTransport`s writing into it.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct SharedBuffer(pub(crate) Arc<Mutex<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<T: Transport> RecordingTransport<T, BufWriter<File>> {
+    /// Creates a transcript file at `path`, truncating an existing file.
+    pub fn create<P: AsRef<Path>>(inner: T, path: P) -> io::Result<Self> {
+        Ok(RecordingTransport::new(
+            inner,
+            BufWriter::new(File::create(path)?),
+        ))
+    }
+}
+
+impl<T: Transport, W: Write + Send> Transport for RecordingTransport<T, W> {
+    fn read_line(&mut self) -> io::Result<Option<String>> {
+        let line = self.inner.read_line()?;
+        if let Some(line) = &line {
+            self.record(Direction::Received, line)?;
+        }
+        Ok(line)
+    }
+
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        self.record(Direction::Sent, line)?;
+        self.inner.write_line(line)
+    }
+
+    fn close(&mut self) -> io::Result<()> {
+        self.inner.close()
+    }
+
+    fn set_read_timeout(&mut self, timeout: Option<Duration>) -> io::Result<()> {
+        self.inner.set_read_timeout(timeout)
+    }
+
+    fn set_encoding(&mut self, encoding: Encoding) -> io::Result<()> {
+        self.inner.set_encoding(encoding)
+    }
+
+    fn set_framing(&mut self, framing: Framing) -> io::Result<()> {
+        self.inner.set_framing(framing)
+    }
+
+    fn stderr_lines(&mut self) -> Option<Vec<String>> {
+        self.inner.stderr_lines()
+    }
+
+    fn exit_status(&mut self) -> Option<ExitStatus> {
+        self.inner.exit_status()
+    }
+}
+
+/// Reads the entries of a transcript in either format from `reader`.
+pub(crate) fn read_entries<R: BufRead>(reader: R) -> io::Result<Vec<TranscriptEntry>> {
+    let mut lines = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        if !line.is_empty() {
+            lines.push(line);
+        }
+    }
+    let entries: Result<Vec<TranscriptEntry>, Error> = match lines.split_first() {
+        #[cfg(feature = "serde")]
+        Some((header, lines)) if header.starts_with('{') => super::jsonl::parse_header(header)
+            .and_then(|_| lines.iter().map(|l| super::jsonl::parse_entry(l)).collect()),
+        _ => lines.iter().map(|l| l.parse()).collect(),
+    };
+    entries.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// `ReplayTransport` plays back the lines received in a transcript.
+///
+/// Transcripts in `TranscriptFormat::Jsonl` are detected by their header with `serde` feature.
+///
+/// Lines written to the transport are discarded by default. In strict mode, they are
+/// compared with the lines sent in the transcript and a mismatch fails with
+/// `io::ErrorKind::InvalidData`.
+///
+/// # Examples
+/// ```
+/// use usi::{Engine, ReplayTransport};
+///
+/// let transcript = "0 > usi\n5 < id name Toy\n5 < usiok\n6 > isready\n8 < readyok\n";
+/// let transport = ReplayTransport::from_reader(transcript.as_bytes()).unwrap().strict(true);
+/// let engine = Engine::connect(transport).unwrap();
+/// assert_eq!("Toy", engine.info().name());
+/// ```
+#[derive(Clone, Debug)]
+pub struct ReplayTransport {
+    sent: VecDeque<String>,
+    received: VecDeque<String>,
+    strict: bool,
+}
+
+impl ReplayTransport {
+    /// Reads a transcript from `reader`.
+    pub fn from_reader<R: BufRead>(reader: R) -> io::Result<Self> {
+        let entries = read_entries(reader)?;
+
+        let mut sent = VecDeque::new();
+        let mut received = VecDeque::new();
+        for entry in entries {
+            match entry.direction {
+                Direction::Sent => sent.push_back(entry.line),
+                Direction::Received => received.push_back(entry.line),
+            }
+        }
+
+        Ok(ReplayTransport {
+            sent,
+            received,
+            strict: false,
+        })
+    }
+
+    /// Reads a transcript file at `path`.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        ReplayTransport::from_reader(BufReader::new(File::open(path)?))
+    }
+
+    /// Sets whether written lines are verified against the transcript.
+    #[must_use]
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+}
+
+impl Transport for ReplayTransport {
+    fn read_line(&mut self) -> io::Result<Option<String>> {
+        Ok(self.received.pop_front())
+    }
+
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        let expected = self.sent.pop_front();
+        if self.strict && expected.as_deref() != Some(line) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("expected {expected:?} but {line:?} was written"),
+            ));
+        }
+        Ok(())
+    }
+
+    fn close(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn set_read_timeout(&mut self, _timeout: Option<Duration>) -> io::Result<()> {
+        // Recorded lines are always available.
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::*;
+
+    #[test]
+    fn record_and_replay() {
+        let output = "id name Toy \nusiok\n";
+        let mut transport =
+            RecordingTransport::new(IoTransport::new(output.as_bytes(), Vec::new()), Vec::new());
+        transport.write_line("usi").unwrap();
+        transport.read_line().unwrap();
+        transport.write_line("").unwrap();
+        let (_, transcript) = transport.into_inner();
+        let transcript = String::from_utf8(transcript).unwrap();
+        let entries = transcript
+            .lines()
+            .map(|l| l.parse::<TranscriptEntry>().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                (Direction::Sent, "usi"),
+                (Direction::Received, "id name Toy "),
+                (Direction::Sent, "")
+            ],
+            entries
+                .iter()
+                .map(|e| (e.direction, e.line.as_str()))
+                .collect::<Vec<_>>()
+        );
+
+        let transcript = "0 > usi\n1 < id name Toy\n1 < usiok\n2 > isready\n3 < readyok\n\
+                          4 > go\n10 < bestmove 7g7f\n";
+        let mut engine = Engine::connect(
+            ReplayTransport::from_reader(transcript.as_bytes())
+                .unwrap()
+                .strict(true),
+        )
+        .unwrap();
+        assert!(engine.go(ThinkParams::new()).unwrap().wait().is_ok());
+        assert!(engine.send(&GuiCommand::Stop).is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn jsonl() {
+        let mut transport = RecordingTransport::new(
+            IoTransport::new("usiok\n".as_bytes(), Vec::new()),
+            Vec::new(),
+        )
+        .format(TranscriptFormat::Jsonl);
+        transport.write_line("usi").unwrap();
+        transport.read_line().unwrap();
+        let (_, transcript) = transport.into_inner();
+        let entries = JsonlTranscriptReader::new(transcript.as_slice())
+            .unwrap()
+            .map(|e| e.unwrap())
+            .map(|e| (e.direction, e.line))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                (Direction::Sent, "usi".to_string()),
+                (Direction::Received, "usiok".to_string())
+            ],
+            entries
+        );
+
+        let transcript = "{\"format\":\"usi-transcript\",\"version\":1}\n\
+            {\"elapsed_ms\":0,\"direction\":\"sent\",\"line\":\"usi\",\"command\":null}\n\
+            {\"elapsed_ms\":1,\"direction\":\"received\",\"line\":\"id name Toy\"}\n\
+            {\"elapsed_ms\":1,\"direction\":\"received\",\"line\":\"usiok\"}\n\
+            {\"elapsed_ms\":2,\"direction\":\"sent\",\"line\":\"isready\"}\n\
+            {\"elapsed_ms\":3,\"direction\":\"received\",\"line\":\"readyok\"}\n";
+        let engine =
+            Engine::connect(ReplayTransport::from_reader(transcript.as_bytes()).unwrap()).unwrap();
+        assert_eq!("Toy", engine.info().name());
+
+        let transcript = "{\"format\":\"usi-transcript\",\"version\":99}\n";
+        assert!(ReplayTransport::from_reader(transcript.as_bytes()).is_err());
+    }
+}
@@ -0,0 +1,581 @@
+use std::collections::VecDeque;
+use std::ffi::OsStr;
+use std::fmt;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::process::{Child, ChildStdin, Command, ExitStatus, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::encoding::{encode_line, Encoding, LineDecoder};
+use super::framing::Framing;
+
+/// `Transport` is a line-oriented connection to a USI engine.
+///
+/// `Engine` talks to the engine only through this trait, so engines running in a subprocess,
+/// behind a socket, or inside the same process can be driven with the same client code.
+pub trait Transport: Send {
+    /// Blocks until the next line is received and returns it without the line terminator.
+    /// Returns `Ok(None)` when the connection is closed.
+    fn read_line(&mut self) -> io::Result<Option<String>>;
+
+    /// Writes a line and flushes it to the engine.
+    /// `line` must not contain a line terminator.
+    fn write_line(&mut self, line: &str) -> io::Result<()>;
+
+    /// Closes the connection and releases the resources.
+    fn close(&mut self) -> io::Result<()>;
+
+    /// Sets the timeout of `read_line`.
+    /// When no line is received within `timeout`, `read_line` fails with `io::ErrorKind::TimedOut`
+    /// and a later call can still receive the line. `None` blocks indefinitely.
+    ///
+    /// Returns `io::ErrorKind::Unsupported` if the transport cannot time out.
+    fn set_read_timeout(&mut self, timeout: Option<Duration>) -> io::Result<()> {
+        let _ = timeout;
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "the transport doesn't support read timeouts",
+        ))
+    }
+
+    /// Sets the encoding of the lines exchanged with the engine. Received lines are transcoded
+    /// to UTF-8, and sent lines are encoded back.
+    ///
+    /// Returns `io::ErrorKind::Unsupported` if the transport doesn't receive raw bytes.
+    fn set_encoding(&mut self, encoding: Encoding) -> io::Result<()> {
+        let _ = encoding;
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "the transport doesn't support encodings",
+        ))
+    }
+
+    /// Sets how lines are split and terminated.
+    ///
+    /// Returns `io::ErrorKind::Unsupported` if the transport doesn't frame lines itself.
+    fn set_framing(&mut self, framing: Framing) -> io::Result<()> {
+        let _ = framing;
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "the transport doesn't support framing",
+        ))
+    }
+
+    /// Returns the latest lines the engine wrote to stderr,
+    /// or `None` if the transport doesn't capture stderr.
+    fn stderr_lines(&mut self) -> Option<Vec<String>> {
+        None
+    }
+
+    /// Returns the exit status of the engine process if it has exited,
+    /// or `None` if it is still running or the transport is not backed by a process.
+    fn exit_status(&mut self) -> Option<ExitStatus> {
+        None
+    }
+}
+
+impl<T: Transport + ?Sized> Transport for Box<T> {
+    fn read_line(&mut self) -> io::Result<Option<String>> {
+        (**self).read_line()
+    }
+
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        (**self).write_line(line)
+    }
+
+    fn close(&mut self) -> io::Result<()> {
+        (**self).close()
+    }
+
+    fn set_read_timeout(&mut self, timeout: Option<Duration>) -> io::Result<()> {
+        (**self).set_read_timeout(timeout)
+    }
+
+    fn set_encoding(&mut self, encoding: Encoding) -> io::Result<()> {
+        (**self).set_encoding(encoding)
+    }
+
+    fn set_framing(&mut self, framing: Framing) -> io::Result<()> {
+        (**self).set_framing(framing)
+    }
+
+    fn stderr_lines(&mut self) -> Option<Vec<String>> {
+        (**self).stderr_lines()
+    }
+
+    fn exit_status(&mut self) -> Option<ExitStatus> {
+        (**self).exit_status()
+    }
+}
+
+/// `IoTransport<R, W>` is a `Transport` over a pair of a reader and a writer.
+///
+/// # Examples
+///
+/// ```
+/// use usi::{IoTransport, Transport};
+///
+/// let mut transport = IoTransport::new("usiok\r\nreadyok\n".as_bytes(), Vec::new());
+/// transport.write_line("usi").unwrap();
+/// assert_eq!(Some("usiok".to_string()), transport.read_line().unwrap());
+/// assert_eq!(Some("readyok".to_string()), transport.read_line().unwrap());
+/// assert_eq!(None, transport.read_line().unwrap());
+/// assert_eq!(b"usi\n", &transport.writer()[..]);
+/// ```
+#[derive(Debug)]
+pub struct IoTransport<R: BufRead, W: Write> {
+    reader: R,
+    writer: W,
+    pending: Vec<u8>,
+    decoder: LineDecoder,
+    newline: &'static str,
+}
+
+impl<R: BufRead, W: Write> IoTransport<R, W> {
+    pub fn new(reader: R, writer: W) -> Self {
+        IoTransport {
+            reader,
+            writer,
+            pending: Vec::new(),
+            decoder: LineDecoder::default(),
+            newline: Framing::default().newline.as_str(),
+        }
+    }
+
+    /// Returns the underlying reader.
+    pub fn reader(&self) -> &R {
+        &self.reader
+    }
+
+    /// Returns the underlying writer.
+    pub fn writer(&self) -> &W {
+        &self.writer
+    }
+
+    /// Consumes the transport, returning the underlying reader and writer.
+    pub fn into_inner(self) -> (R, W) {
+        (self.reader, self.writer)
+    }
+
+    /// Writes an encoded line and the line terminator.
+    fn write_raw_line(&mut self, line: &[u8]) -> io::Result<()> {
+        self.writer.write_all(line)?;
+        self.writer.write_all(self.newline.as_bytes())?;
+        self.writer.flush()
+    }
+
+    /// Reads the next line with the line terminator, but without decoding it.
+    fn read_raw_line(&mut self) -> io::Result<Option<Vec<u8>>> {
+        // A partial line read before an error, e.g. a socket timeout, is kept in `pending`
+        // and completed on the next call.
+        match self.reader.read_until(b'\n', &mut self.pending) {
+            Ok(0) if self.pending.is_empty() => Ok(None),
+            Ok(_) => Ok(Some(std::mem::take(&mut self.pending))),
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                Err(io::Error::new(io::ErrorKind::TimedOut, e))
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
+
+impl<R: BufRead + Send, W: Write + Send> Transport for IoTransport<R, W> {
+    fn read_line(&mut self) -> io::Result<Option<String>> {
+        match self.read_raw_line()? {
+            Some(line) => self.decoder.decode(line).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        let line = encode_line(self.decoder.encoding(), line)?;
+        self.write_raw_line(&line)
+    }
+
+    fn close(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+
+    fn set_encoding(&mut self, encoding: Encoding) -> io::Result<()> {
+        self.decoder.set_encoding(encoding);
+        Ok(())
+    }
+
+    fn set_framing(&mut self, framing: Framing) -> io::Result<()> {
+        self.decoder.set_framing(framing);
+        self.newline = framing.newline.as_str();
+        Ok(())
+    }
+}
+
+/// `LineReceiver` reads lines on a background thread, so reading can time out.
+///
+/// Lines are decoded on the receiving side, so the encoding can be changed at any time.
+#[derive(Debug)]
+pub(crate) struct LineReceiver {
+    receiver: Receiver<io::Result<Vec<u8>>>,
+    timeout: Option<Duration>,
+    decoder: LineDecoder,
+}
+
+impl LineReceiver {
+    /// Spawns a thread which reads `reader` until EOF or an error.
+    pub(crate) fn spawn<R: BufRead + Send + 'static>(reader: R) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let mut io = IoTransport::new(reader, io::sink());
+
+        thread::spawn(move || loop {
+            match io.read_raw_line() {
+                Ok(Some(line)) => {
+                    if sender.send(Ok(line)).is_err() {
+                        break;
+                    }
+                }
+                Ok(None) => break,
+                Err(e) => {
+                    let _ = sender.send(Err(e));
+                    break;
+                }
+            }
+        });
+
+        LineReceiver {
+            receiver,
+            timeout: None,
+            decoder: LineDecoder::default(),
+        }
+    }
+
+    pub(crate) fn read_line(&mut self) -> io::Result<Option<String>> {
+        let res = match self.timeout {
+            Some(timeout) => match self.receiver.recv_timeout(timeout) {
+                Ok(res) => res,
+                Err(RecvTimeoutError::Timeout) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        "no line received within the timeout",
+                    ))
+                }
+                Err(RecvTimeoutError::Disconnected) => return Ok(None),
+            },
+            None => match self.receiver.recv() {
+                Ok(res) => res,
+                Err(_) => return Ok(None),
+            },
+        };
+        self.decoder.decode(res?).map(Some)
+    }
+
+    pub(crate) fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    pub(crate) fn set_encoding(&mut self, encoding: Encoding) {
+        self.decoder.set_encoding(encoding);
+    }
+
+    /// Returns the encoding of the lines, which is `Auto` until it is detected.
+    pub(crate) fn encoding(&self) -> Encoding {
+        self.decoder.encoding()
+    }
+
+    pub(crate) fn set_framing(&mut self, framing: Framing) {
+        self.decoder.set_framing(framing);
+    }
+}
+
+/// Default buffer capacity of engine stdio, the same as `std::io::BufReader`.
+pub(crate) const DEFAULT_BUF_SIZE: usize = 8 * 1024;
+
+/// Number of the latest stderr lines kept by `StderrCapture`.
+const STDERR_LINES: usize = 20;
+
+type StderrHandler = Box<dyn FnMut(&str) + Send>;
+
+/// Reads stderr of an engine on a background thread and keeps the latest lines.
+pub(crate) struct StderrCapture {
+    lines: Arc<Mutex<VecDeque<String>>>,
+    handler: Arc<Mutex<Option<StderrHandler>>>,
+    done: Receiver<()>,
+}
+
+impl fmt::Debug for StderrCapture {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("StderrCapture")
+            .field("lines", &self.lines)
+            .finish_non_exhaustive()
+    }
+}
+
+impl StderrCapture {
+    pub(crate) fn spawn<R: Read + Send + 'static>(stderr: R) -> Self {
+        let lines = Arc::new(Mutex::new(VecDeque::new()));
+        let handler: Arc<Mutex<Option<StderrHandler>>> = Arc::new(Mutex::new(None));
+        let (sender, done) = mpsc::channel();
+
+        let (buf, h) = (lines.clone(), handler.clone());
+        thread::spawn(move || {
+            let mut reader = BufReader::new(stderr);
+            let mut raw = Vec::new();
+            loop {
+                raw.clear();
+                match reader.read_until(b'\n', &mut raw) {
+                    Ok(0) | Err(_) => break,
+                    Ok(_) => {}
+                }
+                // Diagnostics are kept even if they are not valid UTF-8.
+                let line = String::from_utf8_lossy(&raw);
+                let line = line.trim_end_matches(['\r', '\n']);
+
+                if let Some(handler) = h.lock().unwrap().as_mut() {
+                    handler(line);
+                }
+                let mut buf = buf.lock().unwrap();
+                if buf.len() == STDERR_LINES {
+                    buf.pop_front();
+                }
+                buf.push_back(line.to_string());
+            }
+            drop(sender);
+        });
+
+        StderrCapture {
+            lines,
+            handler,
+            done,
+        }
+    }
+
+    pub(crate) fn set_handler(&self, handler: StderrHandler) {
+        *self.handler.lock().unwrap() = Some(handler);
+    }
+
+    /// Returns the latest lines.
+    /// If `wait` is `true`, waits shortly for the engine to finish writing to stderr.
+    pub(crate) fn lines(&self, wait: bool) -> Vec<String> {
+        if wait {
+            let _ = self.done.recv_timeout(Duration::from_millis(100));
+        }
+        self.lines.lock().unwrap().iter().cloned().collect()
+    }
+}
+
+/// Returns the exit status of `process`.
+/// If `eof` is set, the process is given a moment to exit as stdout is already closed.
+pub(crate) fn wait_exit(process: &mut Child, eof: bool) -> Option<ExitStatus> {
+    let deadline = Instant::now() + Duration::from_millis(100);
+    loop {
+        match process.try_wait() {
+            Ok(None) if eof && Instant::now() < deadline => thread::sleep(Duration::from_millis(5)),
+            Ok(status) => return status,
+            Err(_) => return None,
+        }
+    }
+}
+
+/// `ProcessTransport` communicates with a USI engine process through its stdin and stdout.
+///
+/// If stderr of the process is piped, the latest lines are captured and can be
+/// retrieved by `Transport::stderr_lines`.
+///
+/// Lines are written with the terminator of the current platform, see `Framing`.
+///
+/// Closing or dropping the transport kills the process if it is still running
+/// and reaps it, so no zombie processes are left behind.
+pub struct ProcessTransport {
+    process: Child,
+    reader: LineReceiver,
+    writer: IoTransport<io::Empty, BufWriter<ChildStdin>>,
+    stderr: Option<StderrCapture>,
+    eof: bool,
+    closed: bool,
+}
+
+impl fmt::Debug for ProcessTransport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ProcessTransport")
+            .field("process", &self.process)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ProcessTransport {
+    /// Spawns a new process of the USI engine.
+    pub fn spawn<P: AsRef<OsStr>>(engine_path: P) -> io::Result<Self> {
+        let process = Command::new(engine_path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        ProcessTransport::from_process(process)
+    }
+
+    /// Creates a transport from an already spawned process.
+    /// Both stdin and stdout of `process` must be piped. stderr is captured if it is piped.
+    pub fn from_process(process: Child) -> io::Result<Self> {
+        ProcessTransport::with_capacity(process, DEFAULT_BUF_SIZE, DEFAULT_BUF_SIZE)
+    }
+
+    /// Creates a transport from an already spawned process with the specified
+    /// buffer capacities of stdout and stdin.
+    pub fn with_capacity(
+        mut process: Child,
+        read_capacity: usize,
+        write_capacity: usize,
+    ) -> io::Result<Self> {
+        let (stdin, stdout) = match (process.stdin.take(), process.stdout.take()) {
+            (Some(stdin), Some(stdout)) => (stdin, stdout),
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "stdin and stdout of the engine process must be piped",
+                ))
+            }
+        };
+
+        let stderr = process.stderr.take().map(StderrCapture::spawn);
+
+        let mut transport = ProcessTransport {
+            process,
+            reader: LineReceiver::spawn(BufReader::with_capacity(read_capacity, stdout)),
+            writer: IoTransport::new(io::empty(), BufWriter::with_capacity(write_capacity, stdin)),
+            stderr,
+            eof: false,
+            closed: false,
+        };
+        transport.set_framing(Framing::native())?;
+        Ok(transport)
+    }
+
+    /// Returns the underlying engine process.
+    pub fn process(&mut self) -> &mut Child {
+        &mut self.process
+    }
+
+    /// Sets a handler called on the background thread with each line the engine writes to stderr.
+    /// Does nothing if stderr is not captured.
+    pub fn set_stderr_handler<F: FnMut(&str) + Send + 'static>(&mut self, handler: F) {
+        if let Some(stderr) = &self.stderr {
+            stderr.set_handler(Box::new(handler));
+        }
+    }
+}
+
+impl Transport for ProcessTransport {
+    fn read_line(&mut self) -> io::Result<Option<String>> {
+        let res = self.reader.read_line();
+        if let Ok(None) = res {
+            self.eof = true;
+        }
+        res
+    }
+
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        // The writer doesn't read, so the encoding detected by the reader is used.
+        let line = encode_line(self.reader.encoding(), line)?;
+        self.writer.write_raw_line(&line)
+    }
+
+    fn close(&mut self) -> io::Result<()> {
+        if self.closed {
+            return Ok(());
+        }
+        self.closed = true;
+
+        if self.process.try_wait()?.is_none() {
+            self.process.kill()?;
+        }
+        self.process.wait()?;
+        Ok(())
+    }
+
+    fn set_read_timeout(&mut self, timeout: Option<Duration>) -> io::Result<()> {
+        self.reader.set_timeout(timeout);
+        Ok(())
+    }
+
+    fn set_encoding(&mut self, encoding: Encoding) -> io::Result<()> {
+        self.reader.set_encoding(encoding);
+        Ok(())
+    }
+
+    fn set_framing(&mut self, framing: Framing) -> io::Result<()> {
+        self.reader.set_framing(framing);
+        self.writer.set_framing(framing)
+    }
+
+    fn stderr_lines(&mut self) -> Option<Vec<String>> {
+        // Once stdout is closed, the last words of the engine are likely on their way.
+        let eof = self.eof;
+        self.stderr.as_ref().map(|stderr| stderr.lines(eof))
+    }
+
+    fn exit_status(&mut self) -> Option<ExitStatus> {
+        wait_exit(&mut self.process, self.eof)
+    }
+}
+
+impl Drop for ProcessTransport {
+    fn drop(&mut self) {
+        let _ = self.close();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line_receiver() {
+        let (reader, mut writer) = io::pipe().unwrap();
+        let mut receiver = LineReceiver::spawn(BufReader::new(reader));
+        receiver.set_timeout(Some(Duration::from_millis(10)));
+
+        writer.write_all(b"usi").unwrap();
+        assert_eq!(
+            io::ErrorKind::TimedOut,
+            receiver.read_line().unwrap_err().kind()
+        );
+
+        writer.write_all(b"ok\r\n").unwrap();
+        receiver.set_timeout(None);
+        assert_eq!(Some("usiok".to_string()), receiver.read_line().unwrap());
+
+        drop(writer);
+        assert_eq!(None, receiver.read_line().unwrap());
+    }
+
+    #[test]
+    fn encode_written_lines() {
+        // "option name 定跡" in Shift_JIS.
+        let output = b"option name \x92\xe8\x90\xd5 type string default a\n";
+        let mut transport = IoTransport::new(&output[..], Vec::new());
+        transport.write_line("usi").unwrap();
+        transport.read_line().unwrap();
+        transport.write_line("setoption name 定跡 value b").unwrap();
+        assert_eq!(
+            b"usi\nsetoption name \x92\xe8\x90\xd5 value b\n",
+            &transport.writer()[..]
+        );
+
+        let mut transport = IoTransport::new(io::empty(), Vec::new());
+        transport.write_line("setoption name 定跡").unwrap();
+        transport.set_encoding(Encoding::EucJp).unwrap();
+        transport.write_line("setoption name 定跡").unwrap();
+        assert_eq!(
+            io::ErrorKind::InvalidInput,
+            transport.write_line("🐟").unwrap_err().kind()
+        );
+        assert_eq!(
+            [
+                "setoption name 定跡\n".as_bytes(),
+                b"setoption name \xc4\xea\xc0\xd7\n"
+            ]
+            .concat(),
+            transport.writer()[..]
+        );
+    }
+}
@@ -0,0 +1,850 @@
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use super::engine::Engine;
+use super::jsonl::info_json;
+use crate::board::Board;
+use crate::error::Error;
+use crate::protocol::*;
+
+/// The GUID appended to the key of the opening handshake, defined by RFC 6455.
+const WEBSOCKET_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// The maximum size of a message received from a client.
+const MAX_MESSAGE_SIZE: usize = 1 << 20;
+
+/// The maximum size of the header of the opening handshake.
+const MAX_HEADER_SIZE: u64 = 8 << 10;
+
+/// How long a client is waited for to complete the opening handshake.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+const OPCODE_CONTINUATION: u8 = 0x0;
+const OPCODE_TEXT: u8 = 0x1;
+const OPCODE_CLOSE: u8 = 0x8;
+const OPCODE_PING: u8 = 0x9;
+const OPCODE_PONG: u8 = 0xa;
+
+/// `WebSocketBridge` exposes an engine to browser-based GUIs over WebSocket,
+/// with JSON-RPC 2.0 messages.
+///
+/// Clients call the methods below, which are translated into USI commands.
+/// Every method returns `null` once the command is sent, except `engine_info`.
+/// Notifications, i.e. requests without `id`, are handled without a response.
+///
+/// * `engine_info`: returns `{"name": ..., "author": ...}`.
+/// * `set_option`: `{"name": "USI_Hash", "value": "256"}`, where `value` is omitted for buttons.
+/// * `new_game`: waits for `readyok`, and sends `usinewgame`. It fails with the error code
+///   -32000 while searching, as the `bestmove` would be lost.
+/// * `position`: `{"sfen": ..., "moves": ["7g7f", ...]}`, where `sfen` defaults to
+///   the initial position.
+/// * `go`: `{"btime": 60000, "wtime": 60000, "byoyomi": 10000, "binc": ..., "winc": ...,
+///   "depth": ..., "infinite": false, "ponder": false}` with times in milliseconds,
+///   all optional.
+/// * `stop`, `ponderhit`.
+/// * `game_over`: `{"result": "win"}`, which is `win`, `lose` or `draw`.
+///
+/// The outputs of the engine are sent as notifications: `info` with the parsed fields,
+/// e.g. `{"depth": 10, "score": {"cp": 30}, "pv": ["7g7f"]}`, `bestmove` with
+/// `{"move": "7g7f", "ponder": "3c3d"}` where `move` may be `resign` or `win`, and `output`
+/// with `{"line": ...}` for the other lines.
+///
+/// Strings containing line breaks, and positions which are not valid SFEN, are rejected
+/// with the error code -32602, so that clients can't send arbitrary commands to the engine.
+/// Browsers are rejected as well unless their origin is allowed with
+/// `WebSocketBridge::allow_origin`, so that other web pages can't drive the engine.
+/// A search left running by a client is stopped when the client disconnects.
+///
+/// The engine needs a transport supporting `Transport::set_read_timeout`, such as
+/// `ProcessTransport`, so that requests are handled while the engine is idle.
+///
+/// # Examples
+/// ```no_run
+/// use usi::{Engine, WebSocketBridge};
+///
+/// let mut engine = Engine::spawn("/path/to/usi_engine").unwrap();
+/// let bridge = WebSocketBridge::bind("127.0.0.1:8080")
+///     .unwrap()
+///     .allow_origin("http://localhost:3000");
+/// // In the page served from http://localhost:3000:
+/// //   ws = new WebSocket("ws://127.0.0.1:8080");
+/// //   ws.send(JSON.stringify({jsonrpc: "2.0", id: 1, method: "go", params: {byoyomi: 1000}}));
+/// bridge.serve(&mut engine).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct WebSocketBridge {
+    listener: TcpListener,
+    poll_interval: Duration,
+    allowed_origins: Vec<String>,
+}
+
+impl WebSocketBridge {
+    /// Listens for WebSocket connections on `addr`.
+    pub fn bind<A: ToSocketAddrs>(addr: A) -> Result<Self, Error> {
+        Ok(WebSocketBridge {
+            listener: TcpListener::bind(addr)?,
+            poll_interval: Duration::from_millis(10),
+            allowed_origins: Vec::new(),
+        })
+    }
+
+    /// Returns the address the bridge listens on.
+    pub fn local_addr(&self) -> Result<SocketAddr, Error> {
+        Ok(self.listener.local_addr()?)
+    }
+
+    /// Sets how long the engine is waited for before checking for requests.
+    /// Defaults to 10 milliseconds.
+    #[must_use]
+    pub fn poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// Allows browsers on `origin`, e.g. `http://localhost:3000`, to connect.
+    /// No origin is allowed by default, while clients sending no `Origin` header are.
+    #[must_use]
+    pub fn allow_origin<S: Into<String>>(mut self, origin: S) -> Self {
+        self.allowed_origins.push(origin.into());
+        self
+    }
+
+    /// Serves clients one at a time, until an error occurs with the engine.
+    /// Errors of a connection close only the connection.
+    pub fn serve(&self, engine: &mut Engine) -> Result<(), Error> {
+        loop {
+            if let Err(e @ (Error::EngineClosed | Error::EngineExited(_))) = self.serve_one(engine)
+            {
+                return Err(e);
+            }
+        }
+    }
+
+    /// Accepts a connection, and serves `engine` to it until the client disconnects.
+    /// The search is stopped if the client leaves it running.
+    pub fn serve_one(&self, engine: &mut Engine) -> Result<(), Error> {
+        let (stream, _) = self.listener.accept()?;
+        stream.set_nodelay(true)?;
+        // An idle client must not block the clients waiting behind it.
+        stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+        let mut reader = BufReader::new(stream.try_clone()?);
+        handshake(&mut reader, &stream, &self.allowed_origins)?;
+        stream.set_read_timeout(None)?;
+        let writer = Arc::new(Mutex::new(stream));
+        let requests = spawn_reader(reader, Arc::clone(&writer));
+
+        let result = Connection {
+            engine,
+            writer: &writer,
+        }
+        .run(&requests, self.poll_interval);
+        let _ = writer.lock().unwrap().shutdown(std::net::Shutdown::Both);
+        stop_search(engine, result)
+    }
+}
+
+/// Stops a search left running by a connection which ended with `result`, even with an error,
+/// as the next client would receive its `bestmove` otherwise. Returns the error of `result` first.
+fn stop_search(engine: &mut Engine, result: Result<(), Error>) -> Result<(), Error> {
+    let stopped = if engine.is_searching() {
+        engine.stop().map(drop)
+    } else {
+        Ok(())
+    };
+    result.and(stopped)
+}
+
+struct Connection<'a> {
+    engine: &'a mut Engine,
+    writer: &'a Mutex<TcpStream>,
+}
+
+impl Connection<'_> {
+    fn run(&mut self, requests: &Receiver<String>, poll_interval: Duration) -> Result<(), Error> {
+        loop {
+            loop {
+                match requests.try_recv() {
+                    Ok(text) => self.handle(&text)?,
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => return Ok(()),
+                }
+            }
+            if let Some(output) = self.engine.recv_timeout(poll_interval)? {
+                let (method, params) = notification(output.response(), output.raw_str());
+                self.send(&json!({"jsonrpc": "2.0", "method": method, "params": params}))?;
+            }
+        }
+    }
+
+    fn handle(&mut self, text: &str) -> Result<(), Error> {
+        let request = match serde_json::from_str::<Request>(text) {
+            Ok(request) => request,
+            Err(e) => return self.send(&error_response(Value::Null, -32700, &e.to_string())),
+        };
+        let result = self.call(&request.method, request.params);
+        let Some(id) = request.id else {
+            // Notifications are never answered, even with errors.
+            return match result {
+                Err(CallError::Engine(e)) => Err(e),
+                _ => Ok(()),
+            };
+        };
+        let response = match result {
+            Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+            Err(CallError::UnknownMethod) => error_response(id, -32601, "method not found"),
+            Err(CallError::InvalidParams(e)) => error_response(id, -32602, &e.to_string()),
+            Err(CallError::Rejected(e)) => error_response(id, -32000, &e.to_string()),
+            Err(CallError::Engine(e)) => {
+                let response = error_response(id, -32000, &e.to_string());
+                self.send(&response)?;
+                return Err(e);
+            }
+        };
+        self.send(&response)
+    }
+
+    fn call(&mut self, method: &str, params: Value) -> Result<Value, CallError> {
+        let command = match method {
+            "engine_info" => {
+                let info = self.engine.info();
+                return Ok(json!({"name": info.name(), "author": info.author()}));
+            }
+            "set_option" => {
+                let params: SetOptionRequest = parse_params(params)?;
+                check_line(&params.name)?;
+                if let Some(value) = &params.value {
+                    check_line(value)?;
+                }
+                GuiCommand::SetOption(SetOptionParams {
+                    name: params.name,
+                    value: params.value,
+                })
+            }
+            "new_game" => {
+                if self.engine.is_searching() {
+                    return Err(CallError::Rejected(Error::IllegalState {
+                        reason: "the engine is searching",
+                    }));
+                }
+                self.engine.prepare()?;
+                GuiCommand::UsiNewGame
+            }
+            "position" => {
+                let params: PositionRequest = parse_params(params)?;
+                let sfen = params.sfen.as_deref().unwrap_or(STARTPOS_SFEN);
+                check_line(sfen)?;
+                for mv in &params.moves {
+                    check_line(mv)?;
+                }
+                let position = crate::csa::position(sfen, &params.moves);
+                Board::from_position(&position).map_err(invalid_params)?;
+                GuiCommand::Position(position)
+            }
+            "go" => GuiCommand::Go(parse_params::<GoRequest>(params)?.into()),
+            "stop" => GuiCommand::Stop,
+            "ponderhit" => GuiCommand::Ponderhit,
+            "game_over" => {
+                let params: GameOverRequest = parse_params(params)?;
+                GuiCommand::GameOver(match params.result.as_str() {
+                    "win" => GameOverKind::Win,
+                    "lose" => GameOverKind::Lose,
+                    "draw" => GameOverKind::Draw,
+                    _ => return Err(invalid_params(Error::IllegalSyntax)),
+                })
+            }
+            _ => return Err(CallError::UnknownMethod),
+        };
+        // The command must be read back as is, e.g. a name can't contain ` value `.
+        if GuiCommand::parse(&command.to_string()).ok().as_ref() != Some(&command) {
+            return Err(invalid_params(Error::IllegalSyntax));
+        }
+        self.engine.send(&command)?;
+        Ok(Value::Null)
+    }
+
+    fn send(&self, message: &Value) -> Result<(), Error> {
+        let mut writer = self.writer.lock().unwrap();
+        write_frame(&mut *writer, OPCODE_TEXT, message.to_string().as_bytes())?;
+        Ok(())
+    }
+}
+
+enum CallError {
+    UnknownMethod,
+    InvalidParams(String),
+    /// The call is not allowed now, which leaves the connection open.
+    Rejected(Error),
+    Engine(Error),
+}
+
+impl From<Error> for CallError {
+    fn from(e: Error) -> Self {
+        CallError::Engine(e)
+    }
+}
+
+#[derive(Deserialize)]
+struct Request {
+    id: Option<Value>,
+    method: String,
+    #[serde(default)]
+    params: Value,
+}
+
+#[derive(Deserialize)]
+struct SetOptionRequest {
+    name: String,
+    value: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct PositionRequest {
+    sfen: Option<String>,
+    #[serde(default)]
+    moves: Vec<String>,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct GoRequest {
+    btime: Option<u64>,
+    wtime: Option<u64>,
+    byoyomi: Option<u64>,
+    binc: Option<u64>,
+    winc: Option<u64>,
+    depth: Option<i32>,
+    infinite: bool,
+    ponder: bool,
+}
+
+impl From<GoRequest> for ThinkParams {
+    fn from(go: GoRequest) -> Self {
+        let mut params = ThinkParams::new();
+        let millis = Duration::from_millis;
+        if let Some(t) = go.btime {
+            params = params.btime(millis(t));
+        }
+        if let Some(t) = go.wtime {
+            params = params.wtime(millis(t));
+        }
+        if let Some(t) = go.byoyomi {
+            params = params.byoyomi(millis(t));
+        }
+        if let Some(t) = go.binc {
+            params = params.binc(millis(t));
+        }
+        if let Some(t) = go.winc {
+            params = params.winc(millis(t));
+        }
+        if let Some(depth) = go.depth {
+            params = params.depth(depth);
+        }
+        if go.infinite {
+            params = params.infinite();
+        }
+        if go.ponder {
+            params = params.ponder();
+        }
+        params
+    }
+}
+
+#[derive(Deserialize)]
+struct GameOverRequest {
+    result: String,
+}
+
+fn parse_params<T: for<'de> Deserialize<'de>>(params: Value) -> Result<T, CallError> {
+    // Methods without required parameters can be called without `params`.
+    let params = if params.is_null() { json!({}) } else { params };
+    serde_json::from_value(params).map_err(|e| CallError::InvalidParams(e.to_string()))
+}
+
+fn invalid_params(e: Error) -> CallError {
+    CallError::InvalidParams(e.to_string())
+}
+
+/// Rejects line breaks, which would end the command sent to the engine.
+fn check_line(s: &str) -> Result<(), CallError> {
+    if s.contains(['\r', '\n']) {
+        return Err(invalid_params(Error::IllegalSyntax));
+    }
+    Ok(())
+}
+
+fn error_response(id: Value, code: i32, message: &str) -> Value {
+    json!({"jsonrpc": "2.0", "id": id, "error": {"code": code, "message": message}})
+}
+
+/// Returns the method and the parameters of the notification of an engine output.
+fn notification(command: &Option<EngineCommand>, line: &str) -> (&'static str, Value) {
+    match command {
+        Some(EngineCommand::Info(params)) => ("info", Value::Object(info_json(params))),
+        Some(EngineCommand::BestMove(BestMoveParams::MakeMove(mv, ponder))) => {
+            ("bestmove", json!({"move": mv, "ponder": ponder}))
+        }
+        Some(EngineCommand::BestMove(BestMoveParams::Resign)) => {
+            ("bestmove", json!({"move": "resign", "ponder": null}))
+        }
+        Some(EngineCommand::BestMove(BestMoveParams::Win)) => {
+            ("bestmove", json!({"move": "win", "ponder": null}))
+        }
+        _ => ("output", json!({"line": line})),
+    }
+}
+
+/// Performs the opening handshake of a client connection.
+/// Clients sending an `Origin` header are accepted only from `allowed_origins`.
+fn handshake<R: BufRead, W: Write>(
+    reader: &mut R,
+    mut writer: W,
+    allowed_origins: &[String],
+) -> Result<(), Error> {
+    let mut reader = reader.take(MAX_HEADER_SIZE);
+    let mut key = None;
+    let mut origin = None;
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        if !line.ends_with('\n') {
+            if reader.limit() == 0 {
+                writer.write_all(
+                    b"HTTP/1.1 431 Request Header Fields Too Large\r\nContent-Length: 0\r\n\r\n",
+                )?;
+                return Err(Error::IllegalSyntax);
+            }
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("sec-websocket-key") {
+                key = Some(value.trim().to_string());
+            } else if name.eq_ignore_ascii_case("origin") {
+                origin = Some(value.trim().to_string());
+            }
+        }
+    }
+    if origin.is_some_and(|origin| !allowed_origins.contains(&origin)) {
+        writer.write_all(b"HTTP/1.1 403 Forbidden\r\nContent-Length: 0\r\n\r\n")?;
+        return Err(io::Error::from(io::ErrorKind::PermissionDenied).into());
+    }
+    let Some(key) = key else {
+        writer.write_all(b"HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\n\r\n")?;
+        return Err(Error::IllegalSyntax);
+    };
+    let accept = base64(&sha1(format!("{key}{WEBSOCKET_GUID}").as_bytes()));
+    write!(
+        writer,
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+         Sec-WebSocket-Accept: {accept}\r\n\r\n"
+    )?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Reads messages on a new thread, answering pings and closes, and sends text messages
+/// through the returned channel, which is disconnected when the connection is closed.
+fn spawn_reader<R>(mut reader: R, writer: Arc<Mutex<TcpStream>>) -> Receiver<String>
+where
+    R: Read + Send + 'static,
+{
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let mut message = Vec::new();
+        while let Ok((fin, opcode, payload)) = read_frame(&mut reader) {
+            match opcode {
+                OPCODE_TEXT | OPCODE_CONTINUATION => {
+                    message.extend_from_slice(&payload);
+                    if message.len() > MAX_MESSAGE_SIZE {
+                        break;
+                    }
+                    if fin {
+                        let text = String::from_utf8_lossy(&message).into_owned();
+                        message.clear();
+                        if sender.send(text).is_err() {
+                            break;
+                        }
+                    }
+                }
+                OPCODE_PING => {
+                    let mut writer = writer.lock().unwrap();
+                    if write_frame(&mut *writer, OPCODE_PONG, &payload).is_err() {
+                        break;
+                    }
+                }
+                OPCODE_CLOSE => {
+                    let _ = write_frame(&mut *writer.lock().unwrap(), OPCODE_CLOSE, &payload);
+                    break;
+                }
+                _ => {}
+            }
+        }
+    });
+    receiver
+}
+
+/// Reads a frame from a client, and returns whether it is final, the opcode, and the payload.
+fn read_frame<R: Read>(reader: &mut R) -> io::Result<(bool, u8, Vec<u8>)> {
+    let mut header = [0; 2];
+    reader.read_exact(&mut header)?;
+    let fin = header[0] & 0x80 != 0;
+    let opcode = header[0] & 0x0f;
+    let masked = header[1] & 0x80 != 0;
+    let len = match header[1] & 0x7f {
+        126 => {
+            let mut len = [0; 2];
+            reader.read_exact(&mut len)?;
+            u64::from(u16::from_be_bytes(len))
+        }
+        127 => {
+            let mut len = [0; 8];
+            reader.read_exact(&mut len)?;
+            u64::from_be_bytes(len)
+        }
+        len => u64::from(len),
+    };
+    if len > MAX_MESSAGE_SIZE as u64 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "the frame is too large",
+        ));
+    }
+    let mut mask = [0; 4];
+    if masked {
+        reader.read_exact(&mut mask)?;
+    }
+    let mut payload = vec![0; len as usize];
+    reader.read_exact(&mut payload)?;
+    for (i, byte) in payload.iter_mut().enumerate() {
+        *byte ^= mask[i % 4];
+    }
+    Ok((fin, opcode, payload))
+}
+
+/// Writes an unmasked final frame, as sent by servers.
+fn write_frame<W: Write>(writer: &mut W, opcode: u8, payload: &[u8]) -> io::Result<()> {
+    let mut frame = vec![0x80 | opcode];
+    match payload.len() {
+        len @ 0..=125 => frame.push(len as u8),
+        len @ 126..=0xffff => {
+            frame.push(126);
+            frame.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            frame.push(127);
+            frame.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+    frame.extend_from_slice(payload);
+    writer.write_all(&frame)?;
+    writer.flush()
+}
+
+/// Computes the SHA-1 digest, which the opening handshake requires.
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476, 0xc3d2e1f0];
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&(data.len() as u64 * 8).to_be_bytes());
+
+    for chunk in message.chunks(64) {
+        let mut w = [0u32; 80];
+        for (i, word) in chunk.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, &w) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5a827999),
+                20..=39 => (b ^ c ^ d, 0x6ed9eba1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8f1bbcdc),
+                _ => (b ^ c ^ d, 0xca62c1d6),
+            };
+            let temp = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(w);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+        for (h, v) in h.iter_mut().zip([a, b, c, d, e]) {
+            *h = h.wrapping_add(v);
+        }
+    }
+
+    let mut digest = [0; 20];
+    for (i, h) in h.iter().enumerate() {
+        digest[i * 4..i * 4 + 4].copy_from_slice(&h.to_be_bytes());
+    }
+    digest
+}
+
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::new();
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::ToyEngine;
+    use crate::InProcessTransport;
+
+    fn toy_engine() -> ToyEngine {
+        ToyEngine::new()
+            .info(vec![
+                InfoParams::Depth(1, None),
+                InfoParams::Score(30, ScoreKind::CpExact),
+            ])
+            .bestmove("7g7f", None)
+    }
+
+    /// Sends a masked text frame as clients do.
+    fn send_text(stream: &mut TcpStream, text: &str) {
+        let mask = [1, 2, 3, 4];
+        let mut frame = vec![0x81, 0x80 | text.len() as u8];
+        frame.extend_from_slice(&mask);
+        frame.extend(text.bytes().enumerate().map(|(i, b)| b ^ mask[i % 4]));
+        stream.write_all(&frame).unwrap();
+    }
+
+    /// Connects to `addr` and completes the opening handshake.
+    fn open(addr: SocketAddr) -> TcpStream {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        write!(
+            stream,
+            "GET / HTTP/1.1\r\nHost: localhost\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+             Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\r\n"
+        )
+        .unwrap();
+        let mut response = Vec::new();
+        while !response.ends_with(b"\r\n\r\n") {
+            let mut byte = [0];
+            stream.read_exact(&mut byte).unwrap();
+            response.push(byte[0]);
+        }
+        let response = String::from_utf8(response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 101"));
+        assert!(response.contains("Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo="));
+        stream
+    }
+
+    fn recv_json(stream: &mut TcpStream) -> Value {
+        let (_, opcode, payload) = read_frame(stream).unwrap();
+        assert_eq!(OPCODE_TEXT, opcode);
+        serde_json::from_slice(&payload).unwrap()
+    }
+
+    #[test]
+    fn digest() {
+        // The example of RFC 6455.
+        assert_eq!(
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=",
+            base64(&sha1(
+                b"dGhlIHNhbXBsZSBub25jZQ==258EAFA5-E914-47DA-95CA-C5AB0DC85B11"
+            ))
+        );
+        assert_eq!("YQ==", base64(b"a"));
+        assert_eq!("YWI=", base64(b"ab"));
+    }
+
+    #[test]
+    fn bridge() {
+        let bridge = WebSocketBridge::bind("127.0.0.1:0").unwrap();
+        let addr = bridge.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let mut engine = Engine::connect(InProcessTransport::spawn(toy_engine())).unwrap();
+            bridge.serve_one(&mut engine)
+        });
+
+        let mut stream = open(addr);
+
+        send_text(
+            &mut stream,
+            r#"{"jsonrpc":"2.0","id":1,"method":"engine_info"}"#,
+        );
+        assert_eq!(
+            json!({"jsonrpc": "2.0", "id": 1, "result": {"name": "Toy", "author": "usi-rs"}}),
+            recv_json(&mut stream)
+        );
+
+        send_text(&mut stream, r#"{"jsonrpc":"2.0","id":2,"method":"fly"}"#);
+        assert_eq!(-32601, recv_json(&mut stream)["error"]["code"]);
+        send_text(
+            &mut stream,
+            r#"{"jsonrpc":"2.0","id":3,"method":"position","params":{"moves":1}}"#,
+        );
+        assert_eq!(-32602, recv_json(&mut stream)["error"]["code"]);
+        for request in [
+            r#"{"jsonrpc":"2.0","id":3,"method":"position","params":{"sfen":"x\nquit"}}"#,
+            r#"{"jsonrpc":"2.0","id":3,"method":"position","params":{"moves":["7g7f quit"]}}"#,
+            r#"{"jsonrpc":"2.0","id":3,"method":"position","params":{"sfen":"9/9 b - 1"}}"#,
+            r#"{"jsonrpc":"2.0","id":3,"method":"set_option","params":{"name":"a\rquit"}}"#,
+            r#"{"jsonrpc":"2.0","id":3,"method":"set_option","params":{"name":"a value b"}}"#,
+        ] {
+            send_text(&mut stream, request);
+            assert_eq!(-32602, recv_json(&mut stream)["error"]["code"], "{request}");
+        }
+
+        // Notifications are not answered.
+        send_text(&mut stream, r#"{"jsonrpc":"2.0","method":"stop"}"#);
+        send_text(&mut stream, r#"{"jsonrpc":"2.0","method":"fly"}"#);
+
+        send_text(
+            &mut stream,
+            r#"{"jsonrpc":"2.0","id":4,"method":"position","params":{"moves":["2g2f"]}}"#,
+        );
+        assert_eq!(
+            json!({"jsonrpc": "2.0", "id": 4, "result": null}),
+            recv_json(&mut stream)
+        );
+        send_text(
+            &mut stream,
+            r#"{"jsonrpc":"2.0","id":5,"method":"go","params":{"byoyomi":1000}}"#,
+        );
+        assert_eq!(json!(5), recv_json(&mut stream)["id"]);
+
+        let info = recv_json(&mut stream);
+        assert_eq!("info", info["method"]);
+        assert_eq!(json!({"depth": 1, "score": {"cp": 30}}), info["params"]);
+        let bestmove = recv_json(&mut stream);
+        assert_eq!("bestmove", bestmove["method"]);
+        assert_eq!("7g7f", bestmove["params"]["move"]);
+
+        stream.write_all(&[0x88, 0x80, 0, 0, 0, 0]).unwrap();
+        assert_eq!(OPCODE_CLOSE, read_frame(&mut stream).unwrap().1);
+        server.join().unwrap().unwrap();
+    }
+
+    #[test]
+    fn handshake_checks() {
+        let request = "GET / HTTP/1.1\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n";
+
+        let mut output = Vec::new();
+        let input = format!("{request}Origin: http://localhost:3000\r\n\r\n");
+        assert!(handshake(&mut input.as_bytes(), &mut output, &[]).is_err());
+        assert!(output.starts_with(b"HTTP/1.1 403"));
+
+        output.clear();
+        let allowed = ["http://localhost:3000".to_string()];
+        handshake(&mut input.as_bytes(), &mut output, &allowed).unwrap();
+        assert!(output.starts_with(b"HTTP/1.1 101"));
+
+        output.clear();
+        let input = format!("{request}X-Padding: {}\r\n\r\n", "a".repeat(10000));
+        assert!(handshake(&mut input.as_bytes(), &mut output, &[]).is_err());
+        assert!(output.starts_with(b"HTTP/1.1 431"));
+
+        output.clear();
+        assert!(handshake(&mut request.as_bytes(), &mut output, &[]).is_err());
+        assert!(output.is_empty());
+    }
+
+    #[test]
+    fn stop_on_disconnect() {
+        let bridge = WebSocketBridge::bind("127.0.0.1:0").unwrap();
+        let addr = bridge.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let mut engine =
+                Engine::connect(InProcessTransport::spawn(ToyEngine::new().wait())).unwrap();
+            bridge.serve_one(&mut engine).map(|()| engine)
+        });
+
+        let mut stream = open(addr);
+        send_text(
+            &mut stream,
+            r#"{"jsonrpc":"2.0","id":1,"method":"go","params":{"infinite":true}}"#,
+        );
+        assert_eq!(json!(1), recv_json(&mut stream)["id"]);
+        drop(stream);
+
+        let engine = server.join().unwrap().unwrap();
+        assert!(!engine.is_searching());
+    }
+
+    #[test]
+    fn new_game_while_searching() {
+        let bridge = WebSocketBridge::bind("127.0.0.1:0").unwrap();
+        let addr = bridge.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let mut engine =
+                Engine::connect(InProcessTransport::spawn(ToyEngine::new().wait())).unwrap();
+            bridge.serve_one(&mut engine)
+        });
+
+        let mut stream = open(addr);
+        send_text(
+            &mut stream,
+            r#"{"jsonrpc":"2.0","id":1,"method":"go","params":{"infinite":true}}"#,
+        );
+        assert_eq!(json!(1), recv_json(&mut stream)["id"]);
+        send_text(
+            &mut stream,
+            r#"{"jsonrpc":"2.0","id":2,"method":"new_game"}"#,
+        );
+        let response = recv_json(&mut stream);
+        assert_eq!(json!(2), response["id"]);
+        assert_eq!(-32000, response["error"]["code"]);
+
+        // The search is still delivered.
+        send_text(&mut stream, r#"{"jsonrpc":"2.0","id":3,"method":"stop"}"#);
+        assert_eq!(json!(3), recv_json(&mut stream)["id"]);
+        let bestmove = recv_json(&mut stream);
+        assert_eq!(json!("bestmove"), bestmove["method"]);
+        assert_eq!(json!("resign"), bestmove["params"]["move"]);
+        drop(stream);
+        server.join().unwrap().unwrap();
+    }
+
+    #[test]
+    fn stop_search_after_error() {
+        let mut engine =
+            Engine::connect(InProcessTransport::spawn(ToyEngine::new().wait())).unwrap();
+        engine
+            .send(&GuiCommand::Go(ThinkParams::new().infinite()))
+            .unwrap();
+        let result = stop_search(
+            &mut engine,
+            Err(Error::EngineIo(io::ErrorKind::BrokenPipe.into())),
+        );
+        assert!(matches!(result, Err(Error::EngineIo(_))));
+        assert!(!engine.is_searching());
+    }
+}
@@ -0,0 +1,152 @@
+use core::fmt;
+use core::time::Duration;
+
+use crate::protocol::ThinkParams;
+
+/// Represents a side of the game. Black moves first.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Color {
+    Black,
+    White,
+}
+
+impl Color {
+    /// Returns the opponent side.
+    pub fn flip(self) -> Color {
+        match self {
+            Color::Black => Color::White,
+            Color::White => Color::Black,
+        }
+    }
+}
+
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Color::Black => write!(f, "black"),
+            Color::White => write!(f, "white"),
+        }
+    }
+}
+
+/// Represents a time control of a game, applied to both sides.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use usi::{GuiCommand, TimeControl};
+///
+/// let tc = TimeControl::Byoyomi {
+///     main: Duration::from_secs(600),
+///     byoyomi: Duration::from_secs(30),
+///     periods: 1,
+/// };
+/// let params = tc.think_params(Duration::from_secs(540), Duration::from_secs(600));
+/// assert_eq!(
+///     "go btime 540000 wtime 600000 byoyomi 30000",
+///     GuiCommand::Go(params).to_string()
+/// );
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum TimeControl {
+    /// Main time followed by a fixed time for every move once it runs out.
+    ///
+    /// With more than one period, a move overrunning byoyomi uses up a period
+    /// and continues in the next one. The game is lost when no period is left.
+    Byoyomi {
+        main: Duration,
+        byoyomi: Duration,
+        periods: u32,
+    },
+    /// Main time with an increment added after every move (Fischer).
+    Increment { main: Duration, increment: Duration },
+    /// Main time only. The game is lost when it runs out.
+    SuddenDeath { main: Duration },
+    /// A fixed time for every move, without main time.
+    PerMove(Duration),
+    /// No time limit. The search continues until `stop` is sent.
+    Unlimited,
+}
+
+impl TimeControl {
+    /// Returns the main time each side starts with.
+    pub fn main_time(&self) -> Duration {
+        match *self {
+            TimeControl::Byoyomi { main, .. }
+            | TimeControl::Increment { main, .. }
+            | TimeControl::SuddenDeath { main } => main,
+            TimeControl::PerMove(_) | TimeControl::Unlimited => Duration::ZERO,
+        }
+    }
+
+    /// Returns the parameters of `go` for the remaining main times of black and white.
+    pub fn think_params(&self, btime: Duration, wtime: Duration) -> ThinkParams {
+        let params = ThinkParams::new();
+        match *self {
+            TimeControl::Byoyomi { byoyomi, .. } => {
+                params.btime(btime).wtime(wtime).byoyomi(byoyomi)
+            }
+            TimeControl::Increment { increment, .. } => params
+                .btime(btime)
+                .wtime(wtime)
+                .binc(increment)
+                .winc(increment),
+            // Some engines expect either byoyomi or increments.
+            TimeControl::SuddenDeath { .. } => {
+                params.btime(btime).wtime(wtime).byoyomi(Duration::ZERO)
+            }
+            TimeControl::PerMove(t) => params
+                .btime(Duration::ZERO)
+                .wtime(Duration::ZERO)
+                .byoyomi(t),
+            TimeControl::Unlimited => params.infinite(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::GuiCommand;
+    use alloc::string::ToString;
+
+    #[test]
+    fn think_params() {
+        let b = Duration::from_secs(100);
+        let w = Duration::from_secs(200);
+        let cases = [
+            (
+                "go btime 100000 wtime 200000 byoyomi 10000",
+                TimeControl::Byoyomi {
+                    main: Duration::from_secs(600),
+                    byoyomi: Duration::from_secs(10),
+                    periods: 1,
+                },
+            ),
+            (
+                "go btime 100000 wtime 200000 binc 5000 winc 5000",
+                TimeControl::Increment {
+                    main: Duration::from_secs(600),
+                    increment: Duration::from_secs(5),
+                },
+            ),
+            (
+                "go btime 100000 wtime 200000 byoyomi 0",
+                TimeControl::SuddenDeath {
+                    main: Duration::from_secs(600),
+                },
+            ),
+            (
+                "go btime 0 wtime 0 byoyomi 3000",
+                TimeControl::PerMove(Duration::from_secs(3)),
+            ),
+            ("go infinite", TimeControl::Unlimited),
+        ];
+
+        for (expected, tc) in cases {
+            assert_eq!(expected, GuiCommand::Go(tc.think_params(b, w)).to_string());
+        }
+        assert_eq!(Color::White, Color::Black.flip());
+    }
+}
@@ -0,0 +1,175 @@
+use core::fmt;
+use core::time::Duration;
+
+use super::control::{Color, TimeControl};
+use super::game::GameClock;
+
+/// Represents a style of displaying clocks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ClockStyle {
+    /// The conventional Japanese form, e.g. `00:05:32 + 30秒`.
+    Japanese,
+    /// A compact form, e.g. `5:32/byoyomi 30s`.
+    Compact,
+}
+
+/// Displays the clock of a side, returned by `GameClock::display`.
+#[derive(Clone, Copy, Debug)]
+pub struct ClockDisplay<'a> {
+    clock: &'a GameClock,
+    color: Color,
+    style: ClockStyle,
+}
+
+impl GameClock {
+    /// Returns an object displaying the clock of `color` in `style`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use usi::{ClockStyle, Color, GameClock, TimeControl};
+    ///
+    /// let mut clock = GameClock::new(TimeControl::Byoyomi {
+    ///     main: Duration::from_secs(600),
+    ///     byoyomi: Duration::from_secs(30),
+    ///     periods: 1,
+    /// });
+    /// clock.record_move(Duration::from_secs(268));
+    /// assert_eq!(
+    ///     "00:05:32 + 30秒",
+    ///     clock.display(Color::Black, ClockStyle::Japanese).to_string()
+    /// );
+    /// assert_eq!(
+    ///     "5:32/byoyomi 30s",
+    ///     clock.display(Color::Black, ClockStyle::Compact).to_string()
+    /// );
+    /// ```
+    pub fn display(&self, color: Color, style: ClockStyle) -> ClockDisplay<'_> {
+        ClockDisplay {
+            clock: self,
+            color,
+            style,
+        }
+    }
+}
+
+impl fmt::Display for ClockDisplay<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let remaining = self.clock.remaining(self.color);
+        let periods = self.clock.periods_left(self.color);
+        match self.style {
+            ClockStyle::Japanese => match *self.clock.control() {
+                TimeControl::Byoyomi { byoyomi, .. } => {
+                    write!(f, "{} + {}秒", Hms(remaining), byoyomi.as_secs())?;
+                    if periods > 1 {
+                        write!(f, "×{periods}")?;
+                    }
+                    Ok(())
+                }
+                TimeControl::Increment { increment, .. } => {
+                    write!(f, "{} + 1手{}秒加算", Hms(remaining), increment.as_secs())
+                }
+                TimeControl::SuddenDeath { .. } => write!(f, "{}", Hms(remaining)),
+                TimeControl::PerMove(t) => write!(f, "1手{}秒", t.as_secs()),
+                TimeControl::Unlimited => write!(f, "無制限"),
+            },
+            ClockStyle::Compact => match *self.clock.control() {
+                TimeControl::Byoyomi { byoyomi, .. } => {
+                    write!(f, "{}/byoyomi {}s", Ms(remaining), byoyomi.as_secs())?;
+                    if periods > 1 {
+                        write!(f, " x{periods}")?;
+                    }
+                    Ok(())
+                }
+                TimeControl::Increment { increment, .. } => {
+                    write!(f, "{}/+{}s", Ms(remaining), increment.as_secs())
+                }
+                TimeControl::SuddenDeath { .. } => write!(f, "{}", Ms(remaining)),
+                TimeControl::PerMove(t) => write!(f, "{}s/move", t.as_secs()),
+                TimeControl::Unlimited => write!(f, "unlimited"),
+            },
+        }
+    }
+}
+
+/// Formats a duration as `HH:MM:SS`.
+struct Hms(Duration);
+
+impl fmt::Display for Hms {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let secs = self.0.as_secs();
+        write!(
+            f,
+            "{:02}:{:02}:{:02}",
+            secs / 3600,
+            secs / 60 % 60,
+            secs % 60
+        )
+    }
+}
+
+/// Formats a duration as `M:SS`, or `H:MM:SS` from an hour.
+struct Ms(Duration);
+
+impl fmt::Display for Ms {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let secs = self.0.as_secs();
+        if secs >= 3600 {
+            write!(f, "{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+        } else {
+            write!(f, "{}:{:02}", secs / 60, secs % 60)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn display() {
+        let secs = Duration::from_secs;
+        let cases = [
+            (
+                TimeControl::Byoyomi {
+                    main: secs(3600),
+                    byoyomi: secs(60),
+                    periods: 3,
+                },
+                "01:00:00 + 60秒×3",
+                "1:00:00/byoyomi 60s x3",
+            ),
+            (
+                TimeControl::Increment {
+                    main: secs(300),
+                    increment: secs(10),
+                },
+                "00:05:00 + 1手10秒加算",
+                "5:00/+10s",
+            ),
+            (
+                TimeControl::SuddenDeath { main: secs(59) },
+                "00:00:59",
+                "0:59",
+            ),
+            (TimeControl::PerMove(secs(30)), "1手30秒", "30s/move"),
+            (TimeControl::Unlimited, "無制限", "unlimited"),
+        ];
+
+        for (control, japanese, compact) in cases {
+            let clock = GameClock::new(control);
+            assert_eq!(
+                japanese,
+                clock
+                    .display(Color::White, ClockStyle::Japanese)
+                    .to_string()
+            );
+            assert_eq!(
+                compact,
+                clock.display(Color::White, ClockStyle::Compact).to_string()
+            );
+        }
+    }
+}
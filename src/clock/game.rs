@@ -0,0 +1,269 @@
+use core::time::Duration;
+
+use super::control::{Color, TimeControl};
+use crate::protocol::ThinkParams;
+
+/// `GameClock` tracks the clocks of both sides during a game.
+///
+/// Each move is recorded with the time the side to move spent on it. Main time is consumed
+/// first, then byoyomi, and increments are added after each move.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use usi::{Color, GameClock, TimeControl};
+///
+/// let mut clock = GameClock::new(TimeControl::Byoyomi {
+///     main: Duration::from_secs(10),
+///     byoyomi: Duration::from_secs(5),
+///     periods: 1,
+/// });
+/// clock.record_move(Duration::from_secs(12));
+/// assert_eq!(Duration::ZERO, clock.remaining(Color::Black));
+/// assert!(clock.in_byoyomi(Color::Black));
+/// assert_eq!(Color::White, clock.side_to_move());
+/// assert_eq!(None, clock.flagged());
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct GameClock {
+    control: TimeControl,
+    remaining: [Duration; 2],
+    periods: [u32; 2],
+    side_to_move: Color,
+    ply: u32,
+    flagged: Option<Color>,
+}
+
+impl GameClock {
+    /// Creates a clock at the start of a game with black to move.
+    pub fn new(control: TimeControl) -> Self {
+        GameClock::with_side_to_move(control, Color::Black)
+    }
+
+    /// Creates a clock at the start of a game with `side` to move, e.g. for handicap games.
+    pub fn with_side_to_move(control: TimeControl, side: Color) -> Self {
+        let main = control.main_time();
+        let periods = match control {
+            TimeControl::Byoyomi { periods, .. } => periods,
+            _ => 0,
+        };
+        GameClock {
+            control,
+            remaining: [main, main],
+            periods: [periods, periods],
+            side_to_move: side,
+            ply: 0,
+            flagged: None,
+        }
+    }
+
+    /// Returns the time control.
+    pub fn control(&self) -> &TimeControl {
+        &self.control
+    }
+
+    /// Returns the remaining main time of `color`.
+    pub fn remaining(&self, color: Color) -> Duration {
+        self.remaining[index(color)]
+    }
+
+    /// Returns the number of byoyomi periods left to `color`.
+    pub fn periods_left(&self, color: Color) -> u32 {
+        self.periods[index(color)]
+    }
+
+    /// Returns the time `color` can spend on the next move before running out of time,
+    /// or `None` if the time is unlimited.
+    pub fn allotted(&self, color: Color) -> Option<Duration> {
+        let remaining = self.remaining(color);
+        match self.control {
+            TimeControl::Byoyomi { byoyomi, .. } => {
+                Some(remaining + byoyomi * self.periods_left(color))
+            }
+            TimeControl::Increment { .. } | TimeControl::SuddenDeath { .. } => Some(remaining),
+            TimeControl::PerMove(t) => Some(t),
+            TimeControl::Unlimited => None,
+        }
+    }
+
+    /// Returns `true` if `color` used up the main time and moves within byoyomi.
+    pub fn in_byoyomi(&self, color: Color) -> bool {
+        matches!(self.control, TimeControl::Byoyomi { .. }) && self.remaining(color).is_zero()
+    }
+
+    /// Returns the side to move.
+    pub fn side_to_move(&self) -> Color {
+        self.side_to_move
+    }
+
+    /// Returns the number of moves recorded.
+    pub fn ply(&self) -> u32 {
+        self.ply
+    }
+
+    /// Returns the side which ran out of time, if any.
+    /// The flag is kept once it fell, as the game should be adjudicated.
+    pub fn flagged(&self) -> Option<Color> {
+        self.flagged
+    }
+
+    /// Records a move of the side to move which took `elapsed`, and passes the turn.
+    /// Returns `false` if the side ran out of time.
+    pub fn record_move(&mut self, elapsed: Duration) -> bool {
+        let side = self.side_to_move;
+        let remaining = &mut self.remaining[index(side)];
+        let in_time = match self.control {
+            TimeControl::Byoyomi { byoyomi, .. } => {
+                let over = elapsed.saturating_sub(*remaining);
+                *remaining = remaining.saturating_sub(elapsed);
+                let periods = &mut self.periods[index(side)];
+                // A period is used up by every full byoyomi exceeded.
+                let used = if over.is_zero() {
+                    0
+                } else if byoyomi.is_zero() {
+                    u32::MAX
+                } else {
+                    let used = (over.as_nanos() - 1) / byoyomi.as_nanos();
+                    u32::try_from(used).unwrap_or(u32::MAX)
+                };
+                let in_time = used < *periods;
+                *periods = periods.saturating_sub(used);
+                in_time
+            }
+            TimeControl::Increment { increment, .. } => {
+                let in_time = elapsed <= *remaining;
+                *remaining = remaining.saturating_sub(elapsed);
+                if in_time {
+                    *remaining += increment;
+                }
+                in_time
+            }
+            TimeControl::SuddenDeath { .. } => {
+                let in_time = elapsed <= *remaining;
+                *remaining = remaining.saturating_sub(elapsed);
+                in_time
+            }
+            TimeControl::PerMove(t) => elapsed <= t,
+            TimeControl::Unlimited => true,
+        };
+
+        if !in_time && self.flagged.is_none() {
+            self.flagged = Some(side);
+        }
+        self.side_to_move = side.flip();
+        self.ply += 1;
+        in_time
+    }
+}
+
+impl ThinkParams {
+    /// Creates the parameters of `go` for `side` from the state of `clock`.
+    ///
+    /// Byoyomi is sent for byoyomi and per-move time controls, including byoyomi-only games
+    /// with no main time, and `binc`/`winc` for increment time controls.
+    /// If `side` is not to move, the parameters are for pondering on the opponent's time.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use usi::{Color, GameClock, GuiCommand, ThinkParams, TimeControl};
+    ///
+    /// let clock = GameClock::new(TimeControl::Byoyomi {
+    ///     main: Duration::ZERO,
+    ///     byoyomi: Duration::from_secs(10),
+    ///     periods: 1,
+    /// });
+    /// let params = ThinkParams::from_clock(&clock, Color::Black);
+    /// assert_eq!(
+    ///     "go btime 0 wtime 0 byoyomi 10000",
+    ///     GuiCommand::Go(params).to_string()
+    /// );
+    /// ```
+    pub fn from_clock(clock: &GameClock, side: Color) -> ThinkParams {
+        let params = clock
+            .control()
+            .think_params(clock.remaining(Color::Black), clock.remaining(Color::White));
+        if side == clock.side_to_move() {
+            params
+        } else {
+            params.ponder()
+        }
+    }
+}
+
+fn index(color: Color) -> usize {
+    match color {
+        Color::Black => 0,
+        Color::White => 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_move() {
+        let secs = Duration::from_secs;
+        let mut clock = GameClock::new(TimeControl::Increment {
+            main: secs(60),
+            increment: secs(10),
+        });
+        assert!(clock.record_move(secs(30)));
+        assert!(clock.record_move(secs(5)));
+        assert_eq!(secs(40), clock.remaining(Color::Black));
+        assert_eq!(secs(65), clock.remaining(Color::White));
+        assert_eq!(
+            ThinkParams::new()
+                .ponder()
+                .btime(secs(40))
+                .wtime(secs(65))
+                .binc(secs(10))
+                .winc(secs(10)),
+            ThinkParams::from_clock(&clock, Color::White)
+        );
+        assert!(!clock.record_move(secs(41)));
+        assert_eq!(Some(Color::Black), clock.flagged());
+        assert_eq!(3, clock.ply());
+
+        let mut clock = GameClock::new(TimeControl::Byoyomi {
+            main: secs(10),
+            byoyomi: secs(5),
+            periods: 1,
+        });
+        assert!(clock.record_move(secs(15)));
+        assert!(clock.record_move(secs(3)));
+        assert!(!clock.in_byoyomi(Color::White));
+        assert!(!clock.record_move(secs(6)));
+        assert_eq!(Some(Color::Black), clock.flagged());
+
+        let mut clock = GameClock::new(TimeControl::Byoyomi {
+            main: Duration::ZERO,
+            byoyomi: secs(30),
+            periods: 3,
+        });
+        assert_eq!(Some(secs(90)), clock.allotted(Color::Black));
+        assert!(clock.record_move(secs(30)));
+        assert_eq!(3, clock.periods_left(Color::Black));
+        assert!(clock.record_move(secs(61)));
+        assert_eq!(1, clock.periods_left(Color::White));
+        assert!(clock.record_move(secs(31)));
+        assert_eq!(2, clock.periods_left(Color::Black));
+        assert!(!clock.record_move(secs(31)));
+        assert_eq!(0, clock.periods_left(Color::White));
+        assert_eq!(Some(Color::White), clock.flagged());
+
+        let mut clock = GameClock::with_side_to_move(TimeControl::PerMove(secs(3)), Color::White);
+        assert_eq!(
+            ThinkParams::new()
+                .btime(Duration::ZERO)
+                .wtime(Duration::ZERO)
+                .byoyomi(secs(3)),
+            ThinkParams::from_clock(&clock, Color::White)
+        );
+        assert!(!clock.record_move(secs(4)));
+        assert_eq!(Some(Color::White), clock.flagged());
+    }
+}
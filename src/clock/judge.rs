@@ -0,0 +1,108 @@
+use core::time::Duration;
+#[cfg(feature = "std")]
+use std::time::Instant;
+
+/// Represents whether a move was made in time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TimeVerdict {
+    /// The move was made within the allotted time.
+    InTime,
+    /// The move exceeded the allotted time by `over`, which is attributed to lag.
+    Lagged { over: Duration },
+    /// The move exceeded the allotted time and the lag allowance by `over`.
+    TimedOut { over: Duration },
+}
+
+impl TimeVerdict {
+    /// Returns `true` if the side should lose on time.
+    pub fn is_timeout(&self) -> bool {
+        matches!(self, TimeVerdict::TimedOut { .. })
+    }
+}
+
+/// `TimeJudge` decides whether an engine exceeded its time,
+/// allowing for communication and process scheduling delays.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use usi::{TimeJudge, TimeVerdict};
+///
+/// let judge = TimeJudge::new(Duration::from_millis(200));
+/// let allotted = Duration::from_secs(10);
+/// assert_eq!(
+///     TimeVerdict::Lagged { over: Duration::from_millis(150) },
+///     judge.judge_elapsed(allotted, Duration::from_millis(10150))
+/// );
+/// assert!(judge.judge_elapsed(allotted, Duration::from_secs(11)).is_timeout());
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct TimeJudge {
+    grace: Duration,
+}
+
+impl TimeJudge {
+    /// Creates a judge allowing `grace` over the allotted time.
+    pub fn new(grace: Duration) -> Self {
+        TimeJudge { grace }
+    }
+
+    /// Returns the lag allowance.
+    pub fn grace(&self) -> Duration {
+        self.grace
+    }
+
+    /// Judges a move which took `elapsed` against `allotted`.
+    pub fn judge_elapsed(&self, allotted: Duration, elapsed: Duration) -> TimeVerdict {
+        let over = elapsed.saturating_sub(allotted);
+        if over.is_zero() {
+            TimeVerdict::InTime
+        } else if over <= self.grace {
+            TimeVerdict::Lagged { over }
+        } else {
+            TimeVerdict::TimedOut {
+                over: over - self.grace,
+            }
+        }
+    }
+
+    /// Judges a move with `allotted` time, for which `go` was sent at `go_sent`
+    /// and `bestmove` was received at `bestmove_received`.
+    #[cfg(feature = "std")]
+    pub fn judge(
+        &self,
+        allotted: Duration,
+        go_sent: Instant,
+        bestmove_received: Instant,
+    ) -> TimeVerdict {
+        self.judge_elapsed(
+            allotted,
+            bestmove_received.saturating_duration_since(go_sent),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn judge_elapsed() {
+        let judge = TimeJudge::new(Duration::from_millis(100));
+        let allotted = Duration::from_secs(1);
+        let ms = Duration::from_millis;
+        assert_eq!(TimeVerdict::InTime, judge.judge_elapsed(allotted, ms(1000)));
+        assert_eq!(
+            TimeVerdict::Lagged { over: ms(100) },
+            judge.judge_elapsed(allotted, ms(1100))
+        );
+        assert_eq!(
+            TimeVerdict::TimedOut { over: ms(1) },
+            judge.judge_elapsed(allotted, ms(1101))
+        );
+        assert!(TimeJudge::default()
+            .judge_elapsed(allotted, ms(1001))
+            .is_timeout());
+    }
+}
@@ -0,0 +1,156 @@
+use core::time::Duration;
+
+use super::control::TimeControl;
+
+/// The default number of plies expected in a game.
+const DEFAULT_EXPECTED_PLIES: u32 = 160;
+
+/// The minimum number of moves the remaining time is divided into.
+const MIN_MOVES_LEFT: u32 = 10;
+
+/// Represents a recommended time budget for a move.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TimeBudget {
+    /// The time after which no new iteration should be started.
+    pub soft: Duration,
+    /// The time at which the search must stop.
+    pub hard: Duration,
+}
+
+/// `TimeManager` recommends how long to think on a move under a `TimeControl`.
+///
+/// The remaining main time is spread over the moves expected to be left in the game.
+/// The hard limit allows a few times the soft limit, but never the time to run out.
+///
+/// # Examples
+///
+/// ```
+/// use std::time::Duration;
+/// use usi::{TimeControl, TimeManager};
+///
+/// let manager = TimeManager::new(TimeControl::Byoyomi {
+///     main: Duration::from_secs(600),
+///     byoyomi: Duration::from_secs(10),
+///     periods: 1,
+/// });
+/// let budget = manager.budget(Duration::from_secs(600), 1).unwrap();
+/// assert!(budget.soft > Duration::from_secs(10));
+/// assert!(budget.hard <= Duration::from_secs(610));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TimeManager {
+    control: TimeControl,
+    expected_plies: u32,
+    margin: Duration,
+}
+
+impl TimeManager {
+    /// Creates a manager for `control`.
+    pub fn new(control: TimeControl) -> Self {
+        TimeManager {
+            control,
+            expected_plies: DEFAULT_EXPECTED_PLIES,
+            margin: Duration::from_millis(100),
+        }
+    }
+
+    /// Sets the number of plies expected in a game. Defaults to 160.
+    #[must_use]
+    pub fn expected_plies(mut self, plies: u32) -> Self {
+        self.expected_plies = plies;
+        self
+    }
+
+    /// Sets the time kept in reserve for communication delays. Defaults to 100 ms.
+    #[must_use]
+    pub fn margin(mut self, margin: Duration) -> Self {
+        self.margin = margin;
+        self
+    }
+
+    /// Returns the time control.
+    pub fn control(&self) -> &TimeControl {
+        &self.control
+    }
+
+    /// Returns the budget for the move at `ply` (starting from 1) with `remaining` main time
+    /// of the side to move. Returns `None` if the time is unlimited.
+    pub fn budget(&self, remaining: Duration, ply: u32) -> Option<TimeBudget> {
+        let moves_left = (self.expected_plies.saturating_sub(ply) / 2).max(MIN_MOVES_LEFT);
+        let share = remaining / moves_left;
+
+        let (soft, available) = match self.control {
+            TimeControl::Byoyomi { byoyomi, .. } => (share + byoyomi, remaining + byoyomi),
+            // The increment is added after the move, so it can't be spent on this one.
+            TimeControl::Increment { increment, .. } => (share + increment * 3 / 4, remaining),
+            TimeControl::SuddenDeath { .. } => (share, remaining),
+            TimeControl::PerMove(t) => (t, t),
+            TimeControl::Unlimited => return None,
+        };
+
+        let available = available.saturating_sub(self.margin);
+        let hard = (soft * 4).min(available);
+        Some(TimeBudget {
+            soft: soft.min(hard),
+            hard,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn budget() {
+        let secs = Duration::from_secs;
+        let byoyomi = TimeManager::new(TimeControl::Byoyomi {
+            main: secs(600),
+            byoyomi: secs(10),
+            periods: 1,
+        })
+        .expected_plies(161);
+        assert_eq!(
+            Some(TimeBudget {
+                soft: secs(17) + Duration::from_millis(500),
+                hard: secs(70),
+            }),
+            byoyomi.budget(secs(600), 1)
+        );
+        // Main time is gone, so only byoyomi is left.
+        assert_eq!(
+            Some(TimeBudget {
+                soft: secs(10) - Duration::from_millis(100),
+                hard: secs(10) - Duration::from_millis(100),
+            }),
+            byoyomi.budget(Duration::ZERO, 100)
+        );
+
+        let increment = TimeManager::new(TimeControl::Increment {
+            main: secs(300),
+            increment: secs(4),
+        })
+        .margin(Duration::ZERO);
+        // The budget never exceeds the remaining time.
+        assert_eq!(
+            Some(TimeBudget {
+                soft: secs(1),
+                hard: secs(1),
+            }),
+            increment.budget(secs(1), 150)
+        );
+
+        let per_move = TimeManager::new(TimeControl::PerMove(secs(3))).margin(Duration::ZERO);
+        assert_eq!(
+            Some(TimeBudget {
+                soft: secs(3),
+                hard: secs(3),
+            }),
+            per_move.budget(Duration::ZERO, 1)
+        );
+        assert_eq!(
+            None,
+            TimeManager::new(TimeControl::Unlimited).budget(secs(1), 1)
+        );
+    }
+}
@@ -0,0 +1,11 @@
+mod control;
+mod format;
+mod game;
+mod judge;
+mod manager;
+
+pub use self::control::{Color, TimeControl};
+pub use self::format::{ClockDisplay, ClockStyle};
+pub use self::game::GameClock;
+pub use self::judge::{TimeJudge, TimeVerdict};
+pub use self::manager::{TimeBudget, TimeManager};
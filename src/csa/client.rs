@@ -0,0 +1,507 @@
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::summary::{parse_move_line, position, GameSummary};
+use crate::analysis::{InfoAggregator, SearchSnapshot};
+use crate::board::{Board, Move};
+use crate::clock::GameClock;
+use crate::error::Error;
+use crate::protocol::*;
+use crate::record::{GameEnding, GameRecord, RecordedMove};
+use crate::Engine;
+
+/// The reason a game on a CSA server ended, sent as `#RESIGN`, `#TIME_UP` and so on.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum CsaGameEnd {
+    Resign,
+    TimeUp,
+    IllegalMove,
+    Sennichite,
+    OuteSennichite,
+    /// A win declared by the entering king rule.
+    Jishogi,
+    MaxMoves,
+    /// The game was interrupted by the server.
+    Chudan,
+    /// Another reason, without the leading `#`.
+    Other(String),
+}
+
+/// The result of a game on a CSA server, from the client's side.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CsaOutcome {
+    Win,
+    Lose,
+    Draw,
+    /// The game was aborted without a result.
+    Censored,
+}
+
+/// The result of `CsaClient::play`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct CsaGameResult {
+    pub outcome: CsaOutcome,
+    /// The reason the game ended, if the server sent one.
+    pub reason: Option<CsaGameEnd>,
+    /// All the moves of the game in USI notation, including the moves of `GameSummary::moves`.
+    pub moves: Vec<String>,
+    /// The time spent on each move of `moves`.
+    pub elapsed: Vec<Duration>,
+}
+
+impl CsaGameResult {
+    /// Converts the game of `summary` into a `GameRecord` to export it as KIF or KI2.
+    pub fn to_record(&self, summary: &GameSummary) -> GameRecord {
+        let mut record = GameRecord::new(&summary.start);
+        record.black_name = Some(summary.black_name.clone());
+        record.white_name = Some(summary.white_name.clone());
+        record.moves = self
+            .moves
+            .iter()
+            .zip(&self.elapsed)
+            .map(|(mv, &elapsed)| RecordedMove::new(mv).time(elapsed))
+            .collect();
+        record.ending = match self.reason {
+            Some(CsaGameEnd::Resign) => Some(GameEnding::Resign),
+            Some(CsaGameEnd::TimeUp) => Some(GameEnding::TimeUp),
+            Some(CsaGameEnd::IllegalMove | CsaGameEnd::OuteSennichite) => {
+                Some(GameEnding::IllegalMove)
+            }
+            Some(CsaGameEnd::Sennichite) => Some(GameEnding::Sennichite),
+            Some(CsaGameEnd::Jishogi) => Some(GameEnding::DeclareWin),
+            Some(CsaGameEnd::MaxMoves) => Some(GameEnding::Jishogi),
+            Some(CsaGameEnd::Chudan) => Some(GameEnding::Interrupted),
+            Some(CsaGameEnd::Other(_)) | None => None,
+        };
+        record
+    }
+}
+
+/// The interval of the empty lines `CsaClient::connect` sends to keep the connection alive.
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// The writer to the server, shared with the keep-alive thread.
+struct ServerWriter {
+    inner: Box<dyn Write + Send>,
+    last_write: Instant,
+}
+
+impl ServerWriter {
+    fn write_line(&mut self, line: &str) -> io::Result<()> {
+        self.inner.write_all(line.as_bytes())?;
+        self.inner.write_all(b"\n")?;
+        self.inner.flush()?;
+        self.last_write = Instant::now();
+        Ok(())
+    }
+}
+
+/// `CsaClient` connects to a server of the CSA network protocol, such as shogi-server,
+/// and plays games with a USI engine.
+///
+/// The client logs in, waits for a game offer, agrees to it, and then relays the moves between
+/// the server and the engine until the game ends. The moves are converted between the CSA and
+/// USI notations, and the clocks are tracked from the times reported by the server to send
+/// `btime`, `wtime` and `byoyomi` or the increments to the engine. The engine doesn't ponder.
+///
+/// Servers drop idle connections, so an empty line is sent when nothing was sent for a while,
+/// as set by `CsaClient::keep_alive`.
+///
+/// # Examples
+/// ```no_run
+/// use usi::{CsaClient, Engine};
+///
+/// let mut engine = Engine::spawn("/path/to/usi_engine").unwrap();
+/// let mut client = CsaClient::connect("localhost:4081").unwrap();
+/// client.login("my_engine", "password").unwrap();
+///
+/// let summary = client.wait_game().unwrap();
+/// if client.agree(&summary).unwrap() {
+///     let result = client.play(&mut engine, &summary).unwrap();
+///     println!("{:?} by {:?} in {} moves", result.outcome, result.reason, result.moves.len());
+/// }
+/// client.logout().unwrap();
+/// ```
+pub struct CsaClient {
+    reader: Box<dyn BufRead + Send>,
+    writer: Arc<Mutex<ServerWriter>>,
+    name: Option<String>,
+}
+
+impl std::fmt::Debug for CsaClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.debug_struct("CsaClient")
+            .field("name", &self.name)
+            .finish_non_exhaustive()
+    }
+}
+
+impl CsaClient {
+    /// Connects to a CSA server over TCP, keeping the connection alive every 30 seconds.
+    pub fn connect<A: ToSocketAddrs>(addr: A) -> Result<Self, Error> {
+        let stream = TcpStream::connect(addr)?;
+        stream.set_nodelay(true)?;
+        Ok(CsaClient::new(stream.try_clone()?, stream).keep_alive(KEEP_ALIVE_INTERVAL))
+    }
+
+    /// Creates a client communicating with the server through `reader` and `writer`.
+    pub fn new<R, W>(reader: R, writer: W) -> Self
+    where
+        R: Read + Send + 'static,
+        W: Write + Send + 'static,
+    {
+        CsaClient {
+            reader: Box::new(BufReader::new(reader)),
+            writer: Arc::new(Mutex::new(ServerWriter {
+                inner: Box::new(writer),
+                last_write: Instant::now(),
+            })),
+            name: None,
+        }
+    }
+
+    /// Sends an empty line whenever nothing was sent for `interval`, from a background thread
+    /// which ends when the client is dropped or the connection fails.
+    #[must_use]
+    pub fn keep_alive(self, interval: Duration) -> Self {
+        let writer = Arc::downgrade(&self.writer);
+        thread::spawn(move || loop {
+            let Some(writer) = writer.upgrade() else {
+                return;
+            };
+            let idle = writer.lock().unwrap().last_write.elapsed();
+            let wait = match interval.checked_sub(idle) {
+                Some(wait) if !wait.is_zero() => wait,
+                _ => {
+                    if writer.lock().unwrap().write_line("").is_err() {
+                        return;
+                    }
+                    interval
+                }
+            };
+            drop(writer);
+            thread::sleep(wait);
+        });
+        self
+    }
+
+    /// Returns the name the client logged in with.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Logs in with `LOGIN`. Returns `Error::ServerRejected` if the server refuses the login.
+    pub fn login(&mut self, name: &str, password: &str) -> Result<(), Error> {
+        self.send(&format!("LOGIN {name} {password}"))?;
+        let line = self.recv()?;
+        if line == format!("LOGIN:{name} OK") {
+            self.name = Some(name.to_string());
+            Ok(())
+        } else {
+            Err(Error::ServerRejected { reason: line })
+        }
+    }
+
+    /// Logs out with `LOGOUT`, and waits for the server to confirm it.
+    pub fn logout(&mut self) -> Result<(), Error> {
+        self.send("LOGOUT")?;
+        while self.recv()? != "LOGOUT:completed" {}
+        self.name = None;
+        Ok(())
+    }
+
+    /// Waits for a game to be offered, and returns its summary.
+    pub fn wait_game(&mut self) -> Result<GameSummary, Error> {
+        while self.recv()? != "BEGIN Game_Summary" {}
+        let mut text = String::from("BEGIN Game_Summary\n");
+        loop {
+            let line = self.recv()?;
+            text.push_str(&line);
+            text.push('\n');
+            if line == "END Game_Summary" {
+                return GameSummary::parse(&text);
+            }
+        }
+    }
+
+    /// Agrees to the game of `summary`. Returns `false` if the opponent rejected it.
+    pub fn agree(&mut self, summary: &GameSummary) -> Result<bool, Error> {
+        self.send(&format!("AGREE {}", summary.game_id))?;
+        loop {
+            let line = self.recv()?;
+            if line.starts_with("START:") {
+                return Ok(true);
+            }
+            if line.starts_with("REJECT:") {
+                return Ok(false);
+            }
+        }
+    }
+
+    /// Rejects the game of `summary`.
+    pub fn reject(&mut self, summary: &GameSummary) -> Result<(), Error> {
+        self.send(&format!("REJECT {}", summary.game_id))?;
+        while !self.recv()?.starts_with("REJECT:") {}
+        Ok(())
+    }
+
+    /// Plays the game of `summary` with `engine` after `CsaClient::agree`, and returns the result.
+    ///
+    /// `usinewgame` is sent before the game, and `gameover` after it, where a censored game
+    /// is a draw. Returns `Error::IllegalMove` if a move of the engine or the server
+    /// doesn't match the position.
+    pub fn play(
+        &mut self,
+        engine: &mut Engine,
+        summary: &GameSummary,
+    ) -> Result<CsaGameResult, Error> {
+        self.play_with(engine, summary, |_, _| None)
+    }
+
+    /// Plays the game like `CsaClient::play`, appending the string returned by `comment`
+    /// to the engine's moves after a comma. `comment` is called with the position before
+    /// the move and the aggregated `info` output of the search.
+    pub(crate) fn play_with<F>(
+        &mut self,
+        engine: &mut Engine,
+        summary: &GameSummary,
+        mut comment: F,
+    ) -> Result<CsaGameResult, Error>
+    where
+        F: FnMut(&Board, &SearchSnapshot) -> Option<String>,
+    {
+        let mut board = Board::from_sfen(&summary.start)?;
+        let mut clock =
+            GameClock::with_side_to_move(summary.time_control.clone(), board.side_to_move());
+        let mut moves = Vec::new();
+        let mut times = Vec::new();
+        for (mv, elapsed) in summary.moves.iter().zip(&summary.elapsed) {
+            board.make_move(&mv.parse()?)?;
+            clock.record_move(*elapsed);
+            moves.push(mv.clone());
+            times.push(*elapsed);
+        }
+
+        engine.send(&GuiCommand::UsiNewGame)?;
+        let mut reason = None;
+        let outcome = 'game: loop {
+            if board.side_to_move() == summary.my_color {
+                engine.send(&GuiCommand::Position(position(&summary.start, &moves)))?;
+                let mut search = engine.go(ThinkParams::from_clock(&clock, summary.my_color))?;
+                let mut aggregator = InfoAggregator::new();
+                while let Some(info) = search.next_info()? {
+                    aggregator.push(&info);
+                }
+                let line = match search.wait()? {
+                    BestMoveParams::MakeMove(mv, _) => {
+                        let mut line = board.to_csa_move(&mv.parse()?)?;
+                        if let Some(comment) = comment(&board, aggregator.snapshot()) {
+                            line.push(',');
+                            line.push_str(&comment);
+                        }
+                        line
+                    }
+                    BestMoveParams::Resign => "%TORYO".to_string(),
+                    BestMoveParams::Win => "%KACHI".to_string(),
+                };
+                self.send(&line)?;
+            }
+
+            loop {
+                match self.recv_event(&board, summary.time_unit)? {
+                    ServerEvent::Move(mv, elapsed) if reason.is_none() => {
+                        board.make_move(&mv)?;
+                        clock.record_move(elapsed);
+                        moves.push(mv.to_string());
+                        times.push(elapsed);
+                        break;
+                    }
+                    ServerEvent::Move(..) => {
+                        return Err(Error::ProtocolViolation {
+                            reason: "the server sent a move after the game ended",
+                        })
+                    }
+                    // The result follows the reason.
+                    ServerEvent::End(end) => reason = Some(end),
+                    ServerEvent::Result(outcome) => break 'game outcome,
+                }
+            }
+        };
+
+        let kind = match outcome {
+            CsaOutcome::Win => GameOverKind::Win,
+            CsaOutcome::Lose => GameOverKind::Lose,
+            CsaOutcome::Draw | CsaOutcome::Censored => GameOverKind::Draw,
+        };
+        engine.send(&GuiCommand::GameOver(kind))?;
+        Ok(CsaGameResult {
+            outcome,
+            reason,
+            moves,
+            elapsed: times,
+        })
+    }
+
+    /// Reads lines until a move or a special line starting with `#` is received.
+    fn recv_event(&mut self, board: &Board, unit: Duration) -> Result<ServerEvent, Error> {
+        loop {
+            let line = self.recv()?;
+            if let Some(special) = line.strip_prefix('#') {
+                return Ok(parse_special(special));
+            }
+            if line.starts_with(['+', '-']) {
+                let (mv, time) = parse_move_line(&line)?;
+                let mv = board.parse_csa_move(mv)?;
+                return Ok(ServerEvent::Move(mv, unit * time.unwrap_or(0)));
+            }
+            // Echoes of `%TORYO` and so on are followed by the result.
+        }
+    }
+
+    fn send(&mut self, line: &str) -> Result<(), Error> {
+        self.writer.lock().unwrap().write_line(line)?;
+        Ok(())
+    }
+
+    /// Reads a line, skipping the empty lines sent to keep the connection alive.
+    fn recv(&mut self) -> Result<String, Error> {
+        loop {
+            let mut line = String::new();
+            if self.reader.read_line(&mut line)? == 0 {
+                return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+            }
+            let line = line.trim_end();
+            if !line.is_empty() {
+                return Ok(line.to_string());
+            }
+        }
+    }
+}
+
+enum ServerEvent {
+    Move(Move, Duration),
+    End(CsaGameEnd),
+    Result(CsaOutcome),
+}
+
+fn parse_special(s: &str) -> ServerEvent {
+    ServerEvent::End(match s {
+        "WIN" => return ServerEvent::Result(CsaOutcome::Win),
+        "LOSE" => return ServerEvent::Result(CsaOutcome::Lose),
+        "DRAW" => return ServerEvent::Result(CsaOutcome::Draw),
+        "CENSORED" => return ServerEvent::Result(CsaOutcome::Censored),
+        "RESIGN" => CsaGameEnd::Resign,
+        "TIME_UP" => CsaGameEnd::TimeUp,
+        "ILLEGAL_MOVE" => CsaGameEnd::IllegalMove,
+        "SENNICHITE" => CsaGameEnd::Sennichite,
+        "OUTE_SENNICHITE" => CsaGameEnd::OuteSennichite,
+        "JISHOGI" => CsaGameEnd::Jishogi,
+        "MAX_MOVES" => CsaGameEnd::MaxMoves,
+        "CHUDAN" => CsaGameEnd::Chudan,
+        other => CsaGameEnd::Other(other.to_string()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::SharedBuffer;
+    use crate::IoTransport;
+
+    const SUMMARY: &str = "BEGIN Game_Summary\n\
+                           Game_ID:g1\n\
+                           Name+:engine\n\
+                           Name-:human\n\
+                           Your_Turn:+\n\
+                           BEGIN Time\n\
+                           Total_Time:60\n\
+                           Byoyomi:10\n\
+                           END Time\n\
+                           BEGIN Position\n\
+                           PI\n\
+                           +\n\
+                           END Position\n\
+                           END Game_Summary\n";
+
+    #[test]
+    fn play() {
+        let server = format!(
+            "LOGIN:engine OK\n{SUMMARY}START:g1\n\
+             +7776FU,T5\n\n-3334FU,T2\n\
+             %TORYO,T1\n#RESIGN\n#LOSE\nLOGOUT:completed\n"
+        );
+        let written = SharedBuffer::default();
+        let mut client = CsaClient::new(io::Cursor::new(server), written.clone());
+        let output = "usiok\nreadyok\n\
+                      info depth 1 score cp 50 pv 7g7f\nbestmove 7g7f\n\
+                      bestmove resign\n";
+        let engine_written = SharedBuffer::default();
+        let mut engine =
+            Engine::connect(IoTransport::new(output.as_bytes(), engine_written.clone())).unwrap();
+
+        client.login("engine", "pass").unwrap();
+        assert_eq!(Some("engine"), client.name());
+        let summary = client.wait_game().unwrap();
+        assert!(client.agree(&summary).unwrap());
+        let result = client.play(&mut engine, &summary).unwrap();
+        client.logout().unwrap();
+
+        assert_eq!(CsaOutcome::Lose, result.outcome);
+        assert_eq!(Some(CsaGameEnd::Resign), result.reason);
+        assert_eq!(vec!["7g7f", "3c3d"], result.moves);
+        assert_eq!(
+            vec![Duration::from_secs(5), Duration::from_secs(2)],
+            result.elapsed
+        );
+        let record = result.to_record(&summary);
+        assert_eq!(Some("human".to_string()), record.white_name);
+        assert!(record
+            .to_kif()
+            .unwrap()
+            .ends_with("   3 投了\nまで2手で後手の勝ち\n"));
+        assert_eq!(
+            vec![
+                "LOGIN engine pass",
+                "AGREE g1",
+                "+7776FU",
+                "%TORYO",
+                "LOGOUT"
+            ],
+            written.lines()
+        );
+
+        let sent = engine_written.lines();
+        assert!(sent.contains(&"usinewgame".to_string()));
+        // 5 seconds were spent from 60 seconds of the main time.
+        assert!(sent.contains(&"go btime 55000 wtime 58000 byoyomi 10000".to_string()));
+        assert_eq!(Some(&"gameover lose".to_string()), sent.last());
+    }
+
+    #[test]
+    fn login_rejected() {
+        let mut client = CsaClient::new(io::Cursor::new("LOGIN:incorrect\n"), io::sink());
+        assert!(matches!(
+            client.login("engine", "wrong"),
+            Err(Error::ServerRejected { .. })
+        ));
+        assert!(client.recv().is_err());
+    }
+
+    #[test]
+    fn keep_alive() {
+        let written = SharedBuffer::default();
+        let mut client =
+            CsaClient::new(io::empty(), written.clone()).keep_alive(Duration::from_millis(20));
+        client.send("LOGIN engine pass").unwrap();
+        thread::sleep(Duration::from_millis(70));
+        drop(client);
+
+        let lines = written.lines();
+        assert_eq!("LOGIN engine pass", lines[0]);
+        assert!(lines.len() >= 2);
+        assert!(lines[1..].iter().all(String::is_empty));
+    }
+}
@@ -0,0 +1,571 @@
+use std::io::{self, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::thread;
+use std::time::Duration;
+
+use super::client::{CsaClient, CsaGameResult, CsaOutcome};
+use super::record::csa_score;
+use super::summary::GameSummary;
+use crate::analysis::SearchSnapshot;
+use crate::board::Board;
+use crate::error::Error;
+use crate::Engine;
+
+/// The host name of Floodgate.
+pub const FLOODGATE_HOST: &str = "wdoor.c.u-tokyo.ac.jp";
+
+/// The port of the CSA server of Floodgate.
+pub const FLOODGATE_PORT: u16 = 4081;
+
+/// The timeout of connecting to and reading from the web server of kifu.
+const HTTP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The maximum number of redirects followed by `Floodgate::fetch_kifu`.
+const MAX_REDIRECTS: usize = 5;
+
+/// The longest wait before retrying a failed connection.
+const MAX_RETRY_INTERVAL: Duration = Duration::from_secs(300);
+
+/// A room of Floodgate, which is selected by the game name in the password of `LOGIN`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum FloodgateRoom {
+    /// `floodgate-300-10F`: 5 minutes with an increment of 10 seconds.
+    Standard,
+    /// `floodgate-600-10F`: 10 minutes with an increment of 10 seconds.
+    Long,
+    /// Another game name.
+    Other(String),
+}
+
+impl FloodgateRoom {
+    /// Returns the game name of the room, e.g. `floodgate-300-10F`.
+    pub fn game_name(&self) -> &str {
+        match self {
+            FloodgateRoom::Standard => "floodgate-300-10F",
+            FloodgateRoom::Long => "floodgate-600-10F",
+            FloodgateRoom::Other(name) => name,
+        }
+    }
+
+    /// Returns the password of `LOGIN` to enter the room, e.g. `floodgate-300-10F,trip`
+    /// where `trip` is the password identifying the player.
+    pub fn login_password(&self, trip: &str) -> String {
+        format!("{},{trip}", self.game_name())
+    }
+}
+
+/// A game played on Floodgate.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct FloodgateGame {
+    pub summary: GameSummary,
+    pub result: CsaGameResult,
+}
+
+impl FloodgateGame {
+    /// Returns the URL of the kifu of the game. See `Floodgate::kifu_url`.
+    pub fn kifu_url(&self) -> Option<String> {
+        Floodgate::kifu_url(&self.summary.game_id)
+    }
+
+    /// Downloads the kifu of the game in the CSA format. See `Floodgate::fetch_kifu`.
+    pub fn fetch_kifu(&self) -> Result<String, Error> {
+        Floodgate::fetch_kifu(&self.summary.game_id)
+    }
+}
+
+/// The number of games won, lost and drawn by `Floodgate::run`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct FloodgateStats {
+    pub wins: u32,
+    pub losses: u32,
+    /// Draws, including censored games.
+    pub draws: u32,
+}
+
+impl FloodgateStats {
+    fn record(&mut self, outcome: CsaOutcome) {
+        match outcome {
+            CsaOutcome::Win => self.wins += 1,
+            CsaOutcome::Lose => self.losses += 1,
+            CsaOutcome::Draw | CsaOutcome::Censored => self.draws += 1,
+        }
+    }
+}
+
+/// `Floodgate` plays games on Floodgate, the online shogi server for computer players,
+/// with a USI engine.
+///
+/// The client connects and logs in again for every game, and logs out after it.
+/// When the connection fails, e.g. the server is restarting, `Floodgate::run` logs in again
+/// after waiting for an interval which doubles with each consecutive failure.
+/// The engine's evaluation and principal variation are sent with each move as a comment
+/// such as `'* 30 -3334FU +2726FU`, with the score from black's side, which is shown
+/// on the Floodgate pages.
+///
+/// # Examples
+/// ```no_run
+/// use usi::{Engine, Floodgate, FloodgateRoom};
+///
+/// let mut engine = Engine::spawn("/path/to/usi_engine").unwrap();
+/// let floodgate = Floodgate::new("my_engine", "trip").room(FloodgateRoom::Long);
+///
+/// let stats = floodgate
+///     .run(&mut engine, 10, |game| {
+///         println!("{:?} {:?}", game.result.outcome, game.kifu_url());
+///     })
+///     .unwrap();
+/// println!("+{} -{} ={}", stats.wins, stats.losses, stats.draws);
+/// ```
+#[derive(Clone, Debug)]
+pub struct Floodgate {
+    addr: String,
+    name: String,
+    trip: String,
+    room: FloodgateRoom,
+    comments: bool,
+    max_retries: u32,
+    retry_interval: Duration,
+}
+
+impl Floodgate {
+    /// Creates a client logging in as `name` with the password `trip`.
+    pub fn new(name: &str, trip: &str) -> Self {
+        Floodgate {
+            addr: format!("{FLOODGATE_HOST}:{FLOODGATE_PORT}"),
+            name: name.to_string(),
+            trip: trip.to_string(),
+            room: FloodgateRoom::Standard,
+            comments: true,
+            max_retries: 5,
+            retry_interval: Duration::from_secs(10),
+        }
+    }
+
+    /// Sets the room to play in. Defaults to `FloodgateRoom::Standard`.
+    #[must_use]
+    pub fn room(mut self, room: FloodgateRoom) -> Self {
+        self.room = room;
+        self
+    }
+
+    /// Sets the address of the server, e.g. to connect to a local shogi-server.
+    #[must_use]
+    pub fn addr(mut self, addr: &str) -> Self {
+        self.addr = addr.to_string();
+        self
+    }
+
+    /// Sets whether the evaluation is sent with each move. Defaults to `true`.
+    #[must_use]
+    pub fn comments(mut self, comments: bool) -> Self {
+        self.comments = comments;
+        self
+    }
+
+    /// Sets how many times in a row `run` logs in again after the connection fails.
+    /// Defaults to 5.
+    #[must_use]
+    pub fn max_retries(mut self, retries: u32) -> Self {
+        self.max_retries = retries;
+        self
+    }
+
+    /// Sets the wait before the first retry, which doubles up to 5 minutes with each
+    /// consecutive failure. Defaults to 10 seconds.
+    #[must_use]
+    pub fn retry_interval(mut self, interval: Duration) -> Self {
+        self.retry_interval = interval;
+        self
+    }
+
+    /// Connects, logs in, and plays a game with `engine`. Games rejected by the opponent
+    /// are skipped.
+    pub fn play(&self, engine: &mut Engine) -> Result<FloodgateGame, Error> {
+        let mut client = CsaClient::connect(self.addr.as_str())?;
+        client.login(&self.name, &self.room.login_password(&self.trip))?;
+        let summary = loop {
+            let summary = client.wait_game()?;
+            if client.agree(&summary)? {
+                break summary;
+            }
+        };
+        let result = if self.comments {
+            client.play_with(engine, &summary, floodgate_comment)?
+        } else {
+            client.play(engine, &summary)?
+        };
+        // The server may close the connection after the game without confirming the logout.
+        let _ = client.logout();
+        Ok(FloodgateGame { summary, result })
+    }
+
+    /// Plays `games` games in a row, calling `on_game` after each game, and returns
+    /// the number of wins, losses and draws.
+    ///
+    /// Connection errors are retried as set by `max_retries`, and a game cut off by one
+    /// is not counted. Other errors, e.g. of the engine, end the run.
+    pub fn run<F>(
+        &self,
+        engine: &mut Engine,
+        games: u32,
+        mut on_game: F,
+    ) -> Result<FloodgateStats, Error>
+    where
+        F: FnMut(&FloodgateGame),
+    {
+        let mut stats = FloodgateStats::default();
+        let mut played = 0;
+        let mut failures = 0;
+        while played < games {
+            match self.play(engine) {
+                Ok(game) => {
+                    played += 1;
+                    failures = 0;
+                    stats.record(game.result.outcome);
+                    on_game(&game);
+                }
+                Err(Error::EngineIo(_)) if failures < self.max_retries => {
+                    // The engine may be left searching by a game cut off.
+                    if engine.is_searching() {
+                        engine.stop()?;
+                    }
+                    thread::sleep(
+                        self.retry_interval
+                            .saturating_mul(2u32.saturating_pow(failures))
+                            .min(MAX_RETRY_INTERVAL),
+                    );
+                    failures += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(stats)
+    }
+
+    /// Returns the URL of the kifu of a Floodgate game from its ID,
+    /// e.g. `wdoor+floodgate-300-10F+a+b+20240101090000`, or `None` if the ID doesn't end
+    /// with the start time.
+    ///
+    /// # Examples
+    /// ```
+    /// assert_eq!(
+    ///     Some("http://wdoor.c.u-tokyo.ac.jp/shogi/x/2024/01/01/wdoor+floodgate-300-10F+a+b+20240101090000.csa"),
+    ///     usi::Floodgate::kifu_url("wdoor+floodgate-300-10F+a+b+20240101090000").as_deref()
+    /// );
+    /// ```
+    pub fn kifu_url(game_id: &str) -> Option<String> {
+        let time = game_id.rsplit('+').next()?;
+        if time.len() != 14 || !time.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        Some(format!(
+            "http://{FLOODGATE_HOST}/shogi/x/{}/{}/{}/{game_id}.csa",
+            &time[..4],
+            &time[4..6],
+            &time[6..8]
+        ))
+    }
+
+    /// Downloads the kifu of a Floodgate game in the CSA format.
+    ///
+    /// Redirects to `http` URLs are followed. Returns `Error::ServerRejected` with the status
+    /// line if the kifu isn't found, and `Error::EngineIo` if the server doesn't respond
+    /// within 30 seconds.
+    pub fn fetch_kifu(game_id: &str) -> Result<String, Error> {
+        let url = Floodgate::kifu_url(game_id).ok_or(Error::IllegalSyntax)?;
+        fetch(url)
+    }
+}
+
+/// Downloads the body at an `http` URL, following redirects.
+fn fetch(mut url: String) -> Result<String, Error> {
+    for _ in 0..=MAX_REDIRECTS {
+        match http_get(&url)? {
+            HttpResponse::Ok(body) => return Ok(body),
+            HttpResponse::Redirect(location) => url = location,
+        }
+    }
+    Err(Error::ServerRejected {
+        reason: "too many redirects".to_string(),
+    })
+}
+
+/// A successful response to `GET`.
+#[derive(Debug, PartialEq, Eq)]
+enum HttpResponse {
+    Ok(String),
+    /// A redirect to the absolute URL.
+    Redirect(String),
+}
+
+/// Sends `GET` to an `http` URL, and returns the response.
+fn http_get(url: &str) -> Result<HttpResponse, Error> {
+    let rest = url.strip_prefix("http://").ok_or(Error::ServerRejected {
+        reason: format!("unsupported URL {url}"),
+    })?;
+    let (host, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+    let addr = if host.contains(':') {
+        host.to_string()
+    } else {
+        format!("{host}:80")
+    };
+
+    let mut last_error = io::Error::from(io::ErrorKind::NotFound);
+    let mut stream = None;
+    for addr in addr.to_socket_addrs()? {
+        match TcpStream::connect_timeout(&addr, HTTP_TIMEOUT) {
+            Ok(s) => {
+                stream = Some(s);
+                break;
+            }
+            Err(e) => last_error = e,
+        }
+    }
+    let mut stream = stream.ok_or(last_error)?;
+    stream.set_read_timeout(Some(HTTP_TIMEOUT))?;
+    stream.set_write_timeout(Some(HTTP_TIMEOUT))?;
+    write!(
+        stream,
+        "GET {} HTTP/1.0\r\nHost: {host}\r\nConnection: close\r\n\r\n",
+        if path.is_empty() { "/" } else { path }
+    )?;
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response)?;
+    parse_http_response(&response, host)
+}
+
+/// Parses an HTTP response from `host`. Returns `Error::ServerRejected` unless the status is
+/// 200 or a redirect.
+fn parse_http_response(response: &[u8], host: &str) -> Result<HttpResponse, Error> {
+    let response = String::from_utf8_lossy(response);
+    let (head, body) = response
+        .split_once("\r\n\r\n")
+        .ok_or(Error::IllegalSyntax)?;
+    let mut lines = head.lines();
+    let status = lines.next().unwrap_or_default();
+    match status.split(' ').nth(1) {
+        Some("200") => return Ok(HttpResponse::Ok(body.to_string())),
+        Some("301" | "302" | "303" | "307" | "308") => {
+            let location = lines.find_map(|line| {
+                let (name, value) = line.split_once(':')?;
+                name.eq_ignore_ascii_case("location").then(|| value.trim())
+            });
+            match location {
+                Some(location) if location.starts_with('/') => {
+                    return Ok(HttpResponse::Redirect(format!("http://{host}{location}")))
+                }
+                Some(location) => return Ok(HttpResponse::Redirect(location.to_string())),
+                None => {}
+            }
+        }
+        _ => {}
+    }
+    Err(Error::ServerRejected {
+        reason: status.to_string(),
+    })
+}
+
+/// Formats the score and the principal variation of the search as a Floodgate comment.
+fn floodgate_comment(board: &Board, snapshot: &SearchSnapshot) -> Option<String> {
+    let score = snapshot.best_score()?;
+    let mut comment = format!("'* {}", csa_score(score, board.side_to_move()));
+    let mut board = board.clone();
+    // The first move of the principal variation is the move sent with the comment.
+    for (i, mv) in snapshot.best_moves().iter().enumerate() {
+        let Ok(mv) = mv.parse() else { break };
+        let Ok(csa) = board.to_csa_move(&mv) else {
+            break;
+        };
+        if board.make_move(&mv).is_err() {
+            break;
+        }
+        if i > 0 {
+            comment.push(' ');
+            comment.push_str(&csa);
+        }
+    }
+    Some(comment)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::InfoAggregator;
+    use crate::protocol::*;
+    use crate::IoTransport;
+    use std::io::{BufRead, BufReader};
+    use std::net::TcpListener;
+    use std::thread;
+
+    #[test]
+    fn room() {
+        assert_eq!(
+            "floodgate-300-10F,trip",
+            FloodgateRoom::Standard.login_password("trip")
+        );
+        assert_eq!(
+            "floodgate-3600-0",
+            FloodgateRoom::Other("floodgate-3600-0".to_string()).game_name()
+        );
+    }
+
+    #[test]
+    fn kifu() {
+        assert_eq!(None, Floodgate::kifu_url("wdoor+floodgate-300-10F+a+b"));
+        let host = FLOODGATE_HOST;
+        assert_eq!(
+            HttpResponse::Ok("P1\n".to_string()),
+            parse_http_response(
+                b"HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\n\r\nP1\n",
+                host
+            )
+            .unwrap()
+        );
+        assert!(matches!(
+            parse_http_response(b"HTTP/1.1 404 Not Found\r\n\r\n", host),
+            Err(Error::ServerRejected { .. })
+        ));
+        assert_eq!(
+            HttpResponse::Redirect(format!("http://{host}/shogi/x/a.csa")),
+            parse_http_response(
+                b"HTTP/1.1 301 Moved Permanently\r\nlocation: /shogi/x/a.csa\r\n\r\n",
+                host
+            )
+            .unwrap()
+        );
+        assert_eq!(
+            HttpResponse::Redirect("http://example.com/a.csa".to_string()),
+            parse_http_response(
+                b"HTTP/1.1 302 Found\r\nLocation: http://example.com/a.csa\r\n\r\n",
+                host
+            )
+            .unwrap()
+        );
+        assert!(matches!(
+            parse_http_response(b"HTTP/1.1 302 Found\r\n\r\n", host),
+            Err(Error::ServerRejected { .. })
+        ));
+    }
+
+    #[test]
+    fn fetch_redirected() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let mut requests = Vec::new();
+            for response in [
+                "HTTP/1.1 302 Found\r\nLocation: /new.csa\r\n\r\n",
+                "HTTP/1.1 200 OK\r\n\r\nP1\n",
+            ] {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut lines = BufReader::new(&stream).lines();
+                requests.push(lines.next().unwrap().unwrap());
+                // Reads the rest of the header.
+                for line in lines {
+                    if line.unwrap().is_empty() {
+                        break;
+                    }
+                }
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+            requests
+        });
+
+        assert_eq!("P1\n", fetch(format!("http://{addr}/old.csa")).unwrap());
+        assert_eq!(
+            vec!["GET /old.csa HTTP/1.0", "GET /new.csa HTTP/1.0"],
+            server.join().unwrap()
+        );
+    }
+
+    #[test]
+    fn comment() {
+        let mut aggregator = InfoAggregator::new();
+        aggregator.push(&[
+            InfoParams::Score(30, ScoreKind::CpExact),
+            InfoParams::Pv(vec![
+                "3c3d".to_string(),
+                "2g2f".to_string(),
+                "8c8d".to_string(),
+            ]),
+        ]);
+        let board = Board::from_position(&format!("{} moves 7g7f", crate::protocol::STARTPOS_SFEN))
+            .unwrap();
+        assert_eq!(
+            Some("'* -30 +2726FU -8384FU".to_string()),
+            floodgate_comment(&board, aggregator.snapshot())
+        );
+        assert_eq!(None, floodgate_comment(&board, &SearchSnapshot::default()));
+    }
+
+    #[test]
+    fn run() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        let server = thread::spawn(move || {
+            let mut received = Vec::new();
+            // The first connection is lost before logging in.
+            drop(listener.accept().unwrap());
+            for (id, turn, result) in [("g1", "+", "#WIN"), ("g2", "-", "#LOSE")] {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                write!(
+                    stream,
+                    "LOGIN:engine OK\n\
+                     BEGIN Game_Summary\nGame_ID:{id}\nYour_Turn:{turn}\n\
+                     BEGIN Position\nPI\n+\nEND Position\nEND Game_Summary\n\
+                     START:{id}\n"
+                )
+                .unwrap();
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 0 {
+                    let sent = line.trim_end().to_string();
+                    line.clear();
+                    if sent.starts_with('+') || sent.starts_with('%') {
+                        write!(
+                            stream,
+                            "{}\n#RESIGN\n{result}\n",
+                            sent.split(',').next().unwrap()
+                        )
+                        .unwrap();
+                    } else if sent == "LOGOUT" {
+                        writeln!(stream, "LOGOUT:completed").unwrap();
+                    } else if sent.starts_with("AGREE") && turn == "-" {
+                        writeln!(stream, "+7776FU,T1").unwrap();
+                    }
+                    received.push(sent);
+                    if received.last().is_some_and(|l| l == "LOGOUT") {
+                        break;
+                    }
+                }
+            }
+            received
+        });
+
+        let output = "usiok\nreadyok\n\
+                      info depth 1 score cp 50 pv 7g7f 3c3d\nbestmove 7g7f\n\
+                      bestmove resign\n";
+        let mut engine = Engine::connect(IoTransport::new(output.as_bytes(), Vec::new())).unwrap();
+        let mut outcomes = Vec::new();
+        let stats = Floodgate::new("engine", "trip")
+            .addr(&addr)
+            .retry_interval(Duration::from_millis(10))
+            .run(&mut engine, 2, |game| outcomes.push(game.result.outcome))
+            .unwrap();
+
+        assert_eq!(vec![CsaOutcome::Win, CsaOutcome::Lose], outcomes);
+        assert_eq!(
+            FloodgateStats {
+                wins: 1,
+                losses: 1,
+                draws: 0
+            },
+            stats
+        );
+        let received = server.join().unwrap();
+        assert_eq!("LOGIN engine floodgate-300-10F,trip", received[0]);
+        assert_eq!("+7776FU,'* 50 -3334FU", received[2]);
+        assert_eq!("LOGIN engine floodgate-300-10F,trip", received[4]);
+        assert!(received.contains(&"%TORYO".to_string()));
+    }
+}
@@ -0,0 +1,16 @@
+#[cfg(all(feature = "std", not(target_family = "wasm")))]
+mod client;
+#[cfg(all(feature = "std", not(target_family = "wasm")))]
+mod floodgate;
+mod notation;
+mod record;
+mod summary;
+
+#[cfg(all(feature = "std", not(target_family = "wasm")))]
+pub use self::client::{CsaClient, CsaGameEnd, CsaGameResult, CsaOutcome};
+#[cfg(all(feature = "std", not(target_family = "wasm")))]
+pub use self::floodgate::{
+    Floodgate, FloodgateGame, FloodgateRoom, FloodgateStats, FLOODGATE_HOST, FLOODGATE_PORT,
+};
+pub(crate) use self::summary::position;
+pub use self::summary::GameSummary;
@@ -0,0 +1,296 @@
+use alloc::format;
+use alloc::string::{String, ToString};
+use core::fmt::Write;
+
+use crate::board::{Board, Move, Piece, PieceKind, Square};
+use crate::clock::Color;
+use crate::error::Error;
+
+/// The number of pieces of each kind in a game, in the order of `PieceKind::HAND`.
+const TOTAL: [u8; 7] = [2, 2, 4, 4, 4, 4, 18];
+
+impl Board {
+    /// Parses a position in the CSA format.
+    ///
+    /// The position is given by `PI`, optionally followed by the pieces removed for handicap
+    /// games, or by the rows `P1` to `P9`, and the pieces of `P+` and `P-` including `00AL`.
+    /// The line `+` or `-` sets the side to move. Other lines, such as comments and moves,
+    /// are ignored.
+    ///
+    /// # Examples
+    /// ```
+    /// use usi::Board;
+    ///
+    /// let board = Board::from_csa("PI82HI22KA\n-\n").unwrap();
+    /// assert_eq!(
+    ///     "lnsgkgsnl/9/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL w - 1",
+    ///     board.to_sfen()
+    /// );
+    /// ```
+    pub fn from_csa(csa: &str) -> Result<Self, Error> {
+        let mut board = Board::empty();
+        for line in csa.lines().map(str::trim_end) {
+            if let Some(removed) = line.strip_prefix("PI") {
+                board = Board::startpos();
+                for (sq, kind) in pieces(removed)? {
+                    let sq = sq.ok_or(Error::IllegalSyntax)?;
+                    if board.piece_at(sq).map(|piece| piece.kind) != Some(kind) {
+                        return Err(Error::IllegalSyntax);
+                    }
+                    board.set_piece(sq, None);
+                }
+            } else if let Some(pieces) = line.strip_prefix("P+") {
+                place(&mut board, Color::Black, pieces)?;
+            } else if let Some(pieces) = line.strip_prefix("P-") {
+                place(&mut board, Color::White, pieces)?;
+            } else if let Some(row) = line.strip_prefix('P') {
+                let rank = row
+                    .get(..1)
+                    .and_then(|rank| rank.parse().ok())
+                    .filter(|rank| (1..=9).contains(rank))
+                    .ok_or(Error::IllegalSyntax)?;
+                let cells = &row[1..];
+                for (i, file) in (1..=9).rev().enumerate() {
+                    let cell = cells.get(i * 3..i * 3 + 3).unwrap_or(" * ");
+                    let sq = Square::new(file, rank).unwrap();
+                    board.set_piece(sq, parse_piece(cell)?);
+                }
+            } else if line == "+" {
+                board.set_side_to_move(Color::Black);
+            } else if line == "-" {
+                board.set_side_to_move(Color::White);
+            }
+        }
+        Ok(board)
+    }
+
+    /// Returns the position in the CSA format: the rows `P1` to `P9`, the pieces in hand,
+    /// and the side to move, each line terminated by a line break.
+    pub fn to_csa(&self) -> String {
+        let mut csa = String::new();
+        for rank in 1..=9 {
+            let _ = write!(csa, "P{rank}");
+            for file in (1..=9).rev() {
+                match self.piece_at(Square::new(file, rank).unwrap()) {
+                    Some(piece) => csa.push_str(&csa_piece(piece)),
+                    None => csa.push_str(" * "),
+                }
+            }
+            csa.push('\n');
+        }
+        for color in [Color::Black, Color::White] {
+            let mut hand = String::new();
+            for kind in PieceKind::HAND {
+                for _ in 0..self.hand(color, kind) {
+                    let _ = write!(hand, "00{}", kind.csa());
+                }
+            }
+            if !hand.is_empty() {
+                let _ = writeln!(csa, "P{}{hand}", sign(color));
+            }
+        }
+        let _ = writeln!(csa, "{}", sign(self.side_to_move()));
+        csa
+    }
+
+    /// Converts `mv` by the side to move into the CSA format, e.g. `+7776FU`.
+    pub fn to_csa_move(&self, mv: &Move) -> Result<String, Error> {
+        let piece = self.moved_piece(mv)?;
+        let from = match *mv {
+            Move::Normal { from, .. } => format!("{}{}", from.file(), from.rank()),
+            Move::Drop { .. } => String::from("00"),
+        };
+        let to = mv.to();
+        Ok(format!(
+            "{}{from}{}{}{}",
+            sign(piece.color),
+            to.file(),
+            to.rank(),
+            piece.kind.csa()
+        ))
+    }
+
+    /// Parses a move in the CSA format by the side to move, e.g. `+7776FU`.
+    ///
+    /// Returns `Error::IllegalMove` if the move is not by the side to move,
+    /// or doesn't match the piece on the board.
+    pub fn parse_csa_move(&self, csa: &str) -> Result<Move, Error> {
+        if csa.len() != 7 || !csa.is_ascii() {
+            return Err(Error::IllegalSyntax);
+        }
+        let color = match &csa[..1] {
+            "+" => Color::Black,
+            "-" => Color::White,
+            _ => return Err(Error::IllegalSyntax),
+        };
+        let from = parse_square(&csa[1..3])?;
+        let to = parse_square(&csa[3..5])?.ok_or(Error::IllegalSyntax)?;
+        let kind = parse_kind(&csa[5..])?;
+        let illegal = || Error::IllegalMove(csa.to_string());
+        if color != self.side_to_move() {
+            return Err(illegal());
+        }
+
+        let mv = match from {
+            None => Move::Drop { to, kind },
+            Some(from) => {
+                let moved = self.piece_at(from).ok_or_else(illegal)?.kind;
+                Move::Normal {
+                    from,
+                    to,
+                    promote: moved != kind,
+                }
+            }
+        };
+        if self.moved_piece(&mv)?.kind != kind {
+            return Err(illegal());
+        }
+        Ok(mv)
+    }
+}
+
+/// Returns the sign of `color` in the CSA format.
+pub(crate) fn sign(color: Color) -> char {
+    match color {
+        Color::Black => '+',
+        Color::White => '-',
+    }
+}
+
+fn csa_piece(piece: Piece) -> String {
+    format!("{}{}", sign(piece.color), piece.kind.csa())
+}
+
+fn parse_piece(cell: &str) -> Result<Option<Piece>, Error> {
+    let color = match cell.get(..1) {
+        Some("+") => Color::Black,
+        Some("-") => Color::White,
+        _ if cell.trim() == "*" => return Ok(None),
+        _ => return Err(Error::IllegalSyntax),
+    };
+    Ok(Some(Piece::new(color, parse_kind(&cell[1..])?)))
+}
+
+fn parse_kind(s: &str) -> Result<PieceKind, Error> {
+    PieceKind::from_csa(s).ok_or(Error::IllegalSyntax)
+}
+
+/// Parses a square such as `77`, or `00` for a piece in hand.
+fn parse_square(s: &str) -> Result<Option<Square>, Error> {
+    match s.as_bytes() {
+        b"00" => Ok(None),
+        &[file @ b'1'..=b'9', rank @ b'1'..=b'9'] => Ok(Square::new(file - b'0', rank - b'0')),
+        _ => Err(Error::IllegalSyntax),
+    }
+}
+
+/// Parses the pieces of `P+`, `P-` and `PI` such as `5546FU00KI`.
+/// `AL` is returned as the king, which can't be in hand otherwise.
+fn pieces(s: &str) -> Result<alloc::vec::Vec<(Option<Square>, PieceKind)>, Error> {
+    if !s.is_ascii() || !s.len().is_multiple_of(4) {
+        return Err(Error::IllegalSyntax);
+    }
+    (0..s.len())
+        .step_by(4)
+        .map(|i| {
+            let sq = parse_square(&s[i..i + 2])?;
+            let kind = match &s[i + 2..i + 4] {
+                "AL" if sq.is_none() => PieceKind::King,
+                kind => parse_kind(kind)?,
+            };
+            Ok((sq, kind))
+        })
+        .collect()
+}
+
+/// Places the pieces of a `P+` or `P-` line.
+fn place(board: &mut Board, color: Color, s: &str) -> Result<(), Error> {
+    for (sq, kind) in pieces(s)? {
+        match (sq, kind) {
+            (Some(sq), kind) => board.set_piece(sq, Some(Piece::new(color, kind))),
+            // `00AL` gives all the remaining pieces.
+            (None, PieceKind::King) => {
+                for (kind, total) in PieceKind::HAND.into_iter().zip(TOTAL) {
+                    let on_board = (1..=9)
+                        .flat_map(|file| (1..=9).map(move |rank| Square::new(file, rank).unwrap()))
+                        .filter_map(|sq| board.piece_at(sq))
+                        .filter(|piece| piece.kind.unpromote() == kind)
+                        .count() as u8;
+                    let in_hand = board.hand(Color::Black, kind) + board.hand(Color::White, kind);
+                    let rest = total.saturating_sub(on_board + in_hand);
+                    board.set_hand(color, kind, board.hand(color, kind) + rest);
+                }
+            }
+            (None, kind) if kind.is_promoted() => return Err(Error::IllegalSyntax),
+            (None, kind) => board.set_hand(color, kind, board.hand(color, kind) + 1),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::STARTPOS_SFEN;
+
+    const STARTPOS_CSA: &str = "P1-KY-KE-GI-KI-OU-KI-GI-KE-KY\n\
+                                P2 * -HI *  *  *  *  * -KA * \n\
+                                P3-FU-FU-FU-FU-FU-FU-FU-FU-FU\n\
+                                P4 *  *  *  *  *  *  *  *  * \n\
+                                P5 *  *  *  *  *  *  *  *  * \n\
+                                P6 *  *  *  *  *  *  *  *  * \n\
+                                P7+FU+FU+FU+FU+FU+FU+FU+FU+FU\n\
+                                P8 * +KA *  *  *  *  * +HI * \n\
+                                P9+KY+KE+GI+KI+OU+KI+GI+KE+KY\n\
+                                +\n";
+
+    #[test]
+    fn position() {
+        assert_eq!(STARTPOS_CSA, Board::startpos().to_csa());
+        assert_eq!(Board::startpos(), Board::from_csa(STARTPOS_CSA).unwrap());
+        assert_eq!(
+            Board::startpos(),
+            Board::from_csa("'comment\nPI\n+\n").unwrap()
+        );
+
+        let board = Board::from_csa("P1-OU\nP+00KA\nP-00AL\n+\n").unwrap();
+        assert_eq!("k8/9/9/9/9/9/9/9/9 b B2rb4g4s4n4l18p 1", board.to_sfen());
+        assert_eq!(board, Board::from_csa(&board.to_csa()).unwrap());
+
+        assert!(Board::from_csa("PI55FU").is_err());
+        assert!(Board::from_csa("P0").is_err());
+        assert!(Board::from_csa("P1-XX").is_err());
+    }
+
+    #[test]
+    fn moves() {
+        let mut board = Board::from_sfen(STARTPOS_SFEN).unwrap();
+        for (usi, csa) in [
+            ("7g7f", "+7776FU"),
+            ("3c3d", "-3334FU"),
+            ("8h2b+", "+8822UM"),
+            ("3a2b", "-3122GI"),
+            ("B*4e", "+0045KA"),
+        ] {
+            let mv: Move = usi.parse().unwrap();
+            assert_eq!(csa, board.to_csa_move(&mv).unwrap());
+            assert_eq!(mv, board.parse_csa_move(csa).unwrap());
+            board.make_move(&mv).unwrap();
+        }
+
+        // The move of the opponent.
+        assert!(matches!(
+            board.parse_csa_move("+2726FU"),
+            Err(Error::IllegalMove(_))
+        ));
+        // The piece doesn't match the board.
+        assert!(matches!(
+            board.parse_csa_move("-4132GI"),
+            Err(Error::IllegalMove(_))
+        ));
+        assert!(matches!(
+            board.parse_csa_move("-4132K"),
+            Err(Error::IllegalSyntax)
+        ));
+    }
+}
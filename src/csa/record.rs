@@ -0,0 +1,314 @@
+use alloc::string::{String, ToString};
+use core::fmt::Write;
+use core::time::Duration;
+
+use crate::analysis::Score;
+use crate::board::Board;
+use crate::clock::Color;
+use crate::error::Error;
+use crate::protocol::*;
+use crate::record::{GameEnding, GameRecord, RecordedMove};
+
+/// The score written in comments for mates, from which the number of plies is subtracted.
+const MATE_SCORE: i32 = 100_000;
+
+/// Mate scores are those within this number of plies from `MATE_SCORE`.
+const MAX_MATE_PLIES: i32 = 1_000;
+
+impl GameRecord {
+    /// Parses a game record in the CSA file format.
+    ///
+    /// The names of `N+` and `N-` are read as the player names, and other information lines
+    /// such as `$EVENT:` as headers without the `$`. The time of `T` lines is read in seconds.
+    /// Comments starting with `'*` or `'**` followed by a number, as written by Floodgate
+    /// and shogi GUIs, are read as the score of the move from black's side, and other
+    /// comments after a move as its comment. Only the first game of a file is read.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::time::Duration;
+    /// use usi::{GameEnding, GameRecord};
+    ///
+    /// let record = GameRecord::from_csa("\
+    /// V2.2
+    /// N+alice
+    /// N-bob
+    /// $EVENT:test
+    /// PI
+    /// +
+    /// +7776FU,T3
+    /// '* 30 -3334FU
+    /// -3334FU
+    /// T5
+    /// %TORYO
+    /// ").unwrap();
+    ///
+    /// assert_eq!(Some("alice"), record.black_name.as_deref());
+    /// assert_eq!(vec!["7g7f", "3c3d"], record.usi_moves());
+    /// assert_eq!(Some(Duration::from_secs(5)), record.moves[1].time);
+    /// assert_eq!(Some(30), record.moves[0].score.map(|score| score.value));
+    /// assert_eq!(Some(GameEnding::Resign), record.ending);
+    /// ```
+    pub fn from_csa(csa: &str) -> Result<Self, Error> {
+        let lines = csa.lines().map(str::trim_end);
+        let mut record = GameRecord::new("");
+        let mut position = String::new();
+        let mut board = None;
+
+        for line in lines {
+            if line == "/" {
+                break;
+            }
+            // Several statements can be written on a line, separated by commas.
+            let statements = if line.starts_with('\'') {
+                alloc::vec![line]
+            } else {
+                line.split(',').collect()
+            };
+            for statement in statements {
+                let moved = board.is_some();
+                if let Some(name) = statement.strip_prefix("N+") {
+                    record.black_name = Some(name.to_string());
+                } else if let Some(name) = statement.strip_prefix("N-") {
+                    record.white_name = Some(name.to_string());
+                } else if let Some(info) = statement.strip_prefix('$') {
+                    if let Some((key, value)) = info.split_once(':') {
+                        record.headers.push((key.to_string(), value.to_string()));
+                    }
+                } else if statement.starts_with('P') || (!moved && matches!(statement, "+" | "-")) {
+                    position.push_str(statement);
+                    position.push('\n');
+                } else if statement.starts_with(['+', '-']) {
+                    let board = match &mut board {
+                        Some(board) => board,
+                        None => board.insert(start(&mut record, &position)?),
+                    };
+                    let mv = board.parse_csa_move(statement)?;
+                    board.make_move(&mv)?;
+                    record.moves.push(RecordedMove::new(&mv.to_string()));
+                } else if let Some(time) = statement.strip_prefix('T') {
+                    let time = parse_seconds(time)?;
+                    if let Some(last) = record.moves.last_mut() {
+                        last.time = Some(time);
+                    }
+                } else if let Some(special) = statement.strip_prefix('%') {
+                    record.ending = parse_ending(special);
+                } else if let Some(comment) = statement.strip_prefix('\'') {
+                    let side = board.as_ref().map(|board| board.side_to_move().flip());
+                    if let (Some(last), Some(side)) = (record.moves.last_mut(), side) {
+                        read_comment(last, comment, side);
+                    }
+                }
+            }
+        }
+
+        if board.is_none() {
+            start(&mut record, &position)?;
+        }
+        Ok(record)
+    }
+
+    /// Exports the record in the CSA file format, version 2.2.
+    ///
+    /// Headers are written as information lines with `$`, the time of each move in seconds,
+    /// and the score as a comment starting with `'*` from black's side. Mates are written
+    /// as 100000 minus the number of plies.
+    pub fn to_csa(&self) -> Result<String, Error> {
+        let mut board = Board::from_sfen(&self.start)?;
+        let mut csa = String::from("V2.2\n");
+        if let Some(name) = &self.black_name {
+            let _ = writeln!(csa, "N+{name}");
+        }
+        if let Some(name) = &self.white_name {
+            let _ = writeln!(csa, "N-{name}");
+        }
+        for (key, value) in &self.headers {
+            let _ = writeln!(csa, "${key}:{value}");
+        }
+        if board == Board::startpos() {
+            csa.push_str("PI\n+\n");
+        } else {
+            csa.push_str(&board.to_csa());
+        }
+
+        for recorded in &self.moves {
+            let mv = recorded.mv.parse()?;
+            let side = board.side_to_move();
+            let _ = writeln!(csa, "{}", board.to_csa_move(&mv)?);
+            if let Some(time) = recorded.time {
+                let _ = writeln!(csa, "T{}", time.as_secs());
+            }
+            if let Some(score) = recorded.score {
+                let _ = writeln!(csa, "'* {}", csa_score(score, side));
+            }
+            if let Some(comment) = &recorded.comment {
+                for line in comment.lines() {
+                    let _ = writeln!(csa, "'{line}");
+                }
+            }
+            board.make_move(&mv)?;
+        }
+
+        if let Some(ending) = self.ending {
+            let special = match ending {
+                GameEnding::Resign => "TORYO",
+                GameEnding::Mate => "TSUMI",
+                GameEnding::TimeUp => "TIME_UP",
+                GameEnding::IllegalMove => "ILLEGAL_MOVE",
+                GameEnding::Sennichite => "SENNICHITE",
+                GameEnding::Jishogi => "JISHOGI",
+                GameEnding::DeclareWin => "KACHI",
+                GameEnding::Interrupted => "CHUDAN",
+            };
+            let _ = writeln!(csa, "%{special}");
+        }
+        Ok(csa)
+    }
+}
+
+/// Returns the score of `side` as the number written in CSA comments, from black's side.
+pub(crate) fn csa_score(score: Score, side: Color) -> i32 {
+    let value = match score.mate_distance() {
+        Some(plies) if plies > 0 => MATE_SCORE - plies,
+        Some(plies) => -MATE_SCORE - plies,
+        None if score.is_winning_mate() => MATE_SCORE,
+        None if score.is_mate() => -MATE_SCORE,
+        None => score.value,
+    };
+    match side {
+        Color::Black => value,
+        Color::White => -value,
+    }
+}
+
+/// Returns the score of `side` from a number written in CSA comments.
+fn parse_csa_score(value: i32, side: Color) -> Score {
+    let value = match side {
+        Color::Black => value,
+        Color::White => -value,
+    };
+    if value.abs() > MATE_SCORE - MAX_MATE_PLIES {
+        Score::new(
+            value.signum() * (MATE_SCORE - value.abs()),
+            ScoreKind::MateExact,
+        )
+    } else {
+        Score::new(value, ScoreKind::CpExact)
+    }
+}
+
+/// Sets the SFEN of the initial position of `record`, and returns the board.
+fn start(record: &mut GameRecord, position: &str) -> Result<Board, Error> {
+    let board = Board::from_csa(position)?;
+    record.start = board.to_sfen();
+    Ok(board)
+}
+
+/// Reads a comment after the move `mv` by `side`.
+fn read_comment(mv: &mut RecordedMove, comment: &str, side: Color) {
+    let score = comment
+        .strip_prefix("**")
+        .or_else(|| comment.strip_prefix('*'))
+        .and_then(|rest| rest.split_whitespace().next())
+        .and_then(|value| value.parse().ok());
+    if let Some(value) = score {
+        mv.score = Some(parse_csa_score(value, side));
+        return;
+    }
+    match &mut mv.comment {
+        Some(text) => {
+            text.push('\n');
+            text.push_str(comment);
+        }
+        None => mv.comment = Some(comment.to_string()),
+    }
+}
+
+/// Parses the seconds of a `T` line, which may have a fraction since version 3.0.
+fn parse_seconds(s: &str) -> Result<Duration, Error> {
+    let (secs, fraction) = s.split_once('.').unwrap_or((s, ""));
+    let mut time = Duration::from_secs(secs.parse()?);
+    if !fraction.is_empty() {
+        // Up to milliseconds.
+        if fraction.len() > 3 || !fraction.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(Error::IllegalSyntax);
+        }
+        let scale = 10u32.pow(3 - fraction.len() as u32);
+        time += Duration::from_millis(u64::from(fraction.parse::<u32>()? * scale));
+    }
+    Ok(time)
+}
+
+fn parse_ending(s: &str) -> Option<GameEnding> {
+    Some(match s {
+        "TORYO" => GameEnding::Resign,
+        "TSUMI" => GameEnding::Mate,
+        "TIME_UP" => GameEnding::TimeUp,
+        "ILLEGAL_MOVE" | "+ILLEGAL_ACTION" | "-ILLEGAL_ACTION" => GameEnding::IllegalMove,
+        "SENNICHITE" => GameEnding::Sennichite,
+        "JISHOGI" | "HIKIWAKE" | "MAX_MOVES" => GameEnding::Jishogi,
+        "KACHI" => GameEnding::DeclareWin,
+        "CHUDAN" => GameEnding::Interrupted,
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::STARTPOS_SFEN;
+    use alloc::vec;
+
+    #[test]
+    fn round_trip() {
+        let mut record = GameRecord::new(STARTPOS_SFEN);
+        record.black_name = Some("alice".into());
+        record.headers.push(("EVENT".into(), "test".into()));
+        record.moves.push(
+            RecordedMove::new("7g7f")
+                .time(Duration::from_secs(3))
+                .score(Score::new(5, ScoreKind::MateExact)),
+        );
+        record.moves.push(
+            RecordedMove::new("3c3d")
+                .score(Score::new(40, ScoreKind::CpExact))
+                .comment("first\nsecond"),
+        );
+        record.ending = Some(GameEnding::Interrupted);
+
+        let csa = record.to_csa().unwrap();
+        assert_eq!(
+            "V2.2\nN+alice\n$EVENT:test\nPI\n+\n\
+             +7776FU\nT3\n'* 99995\n\
+             -3334FU\n'* -40\n'first\n'second\n%CHUDAN\n",
+            csa
+        );
+        assert_eq!(record, GameRecord::from_csa(&csa).unwrap());
+    }
+
+    #[test]
+    fn from_csa() {
+        let record = GameRecord::from_csa(
+            "'comment before the game\n\
+             P1 *  *  *  * -OU\nP9 *  *  *  * +OU\nP+00KI\n-\n\
+             -5152OU,T1.5\n+0058KI,T10\n%KACHI\n/\n+5958OU\n",
+        )
+        .unwrap();
+        assert_eq!(
+            "4k4/9/9/9/9/9/9/9/4K4 w G 1",
+            Board::from_sfen(&record.start).unwrap().to_sfen()
+        );
+        assert_eq!(vec!["5a5b", "G*5h"], record.usi_moves());
+        assert_eq!(Some(Duration::from_millis(1500)), record.moves[0].time);
+        assert_eq!(Some(GameEnding::DeclareWin), record.ending);
+
+        // Without moves.
+        let record = GameRecord::from_csa("PI\n+\n").unwrap();
+        assert_eq!(STARTPOS_SFEN, record.start);
+
+        assert!(GameRecord::from_csa("PI\n+\n-3334FU\n").is_err());
+        assert!(GameRecord::from_csa("PI\n+\n+7776FU\nTx\n").is_err());
+        assert!(GameRecord::from_csa("PI\n+\n+7776FU\nT1.55é\n").is_err());
+        assert!(GameRecord::from_csa("PI\n+\n+7776FU\nT1.5555\n").is_err());
+    }
+}
@@ -0,0 +1,322 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::time::Duration;
+
+use crate::board::Board;
+use crate::clock::{Color, TimeControl};
+use crate::error::Error;
+
+/// The summary of a game offered by a CSA server, sent between `BEGIN Game_Summary`
+/// and `END Game_Summary`.
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+/// use usi::{Color, GameSummary, TimeControl};
+///
+/// let summary = GameSummary::parse("\
+/// BEGIN Game_Summary
+/// Protocol_Version:1.2
+/// Game_ID:20240101-001
+/// Name+:alice
+/// Name-:bob
+/// Your_Turn:-
+/// BEGIN Time
+/// Time_Unit:1sec
+/// Total_Time:600
+/// Byoyomi:10
+/// END Time
+/// BEGIN Position
+/// PI
+/// +
+/// +7776FU,T3
+/// END Position
+/// END Game_Summary
+/// ").unwrap();
+///
+/// assert_eq!("20240101-001", summary.game_id);
+/// assert_eq!(Color::White, summary.my_color);
+/// assert_eq!(vec!["7g7f"], summary.moves);
+/// assert_eq!(
+///     TimeControl::Byoyomi {
+///         main: Duration::from_secs(600),
+///         byoyomi: Duration::from_secs(10),
+///         periods: 1,
+///     },
+///     summary.time_control
+/// );
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct GameSummary {
+    pub game_id: String,
+    /// The name of the black player.
+    pub black_name: String,
+    /// The name of the white player.
+    pub white_name: String,
+    /// The side the client plays.
+    pub my_color: Color,
+    pub time_control: TimeControl,
+    /// The unit of the times sent by the server, such as the time spent on each move.
+    pub time_unit: Duration,
+    /// The maximum number of moves, after which the game is a draw.
+    pub max_moves: Option<u32>,
+    /// The SFEN of the initial position.
+    pub start: String,
+    /// The moves already played in USI notation, e.g. when a game is resumed.
+    pub moves: Vec<String>,
+    /// The time spent on each move of `moves`.
+    pub elapsed: Vec<Duration>,
+}
+
+impl GameSummary {
+    /// Parses a game summary from the lines sent by the server.
+    ///
+    /// Lines outside of the summary are ignored. The times of `BEGIN Time+` and `BEGIN Time-`
+    /// blocks for different time controls of the players are read as the time control
+    /// of the client's side.
+    pub fn parse(text: &str) -> Result<Self, Error> {
+        let mut game_id = None;
+        let mut names = [String::new(), String::new()];
+        let mut my_color = None;
+        let mut max_moves = None;
+        let mut time = TimeFields::default();
+        let mut position = String::new();
+        let mut move_lines = Vec::new();
+
+        let mut block = Vec::new();
+        for line in text.lines().map(str::trim_end) {
+            if let Some(name) = line.strip_prefix("BEGIN ") {
+                block.push(name);
+                continue;
+            }
+            if line.starts_with("END ") {
+                block.pop();
+                continue;
+            }
+            match block.as_slice() {
+                ["Game_Summary"] => {
+                    let Some((key, value)) = line.split_once(':') else {
+                        continue;
+                    };
+                    match key {
+                        "Game_ID" => game_id = Some(value.to_string()),
+                        "Name+" => names[0] = value.to_string(),
+                        "Name-" => names[1] = value.to_string(),
+                        "Your_Turn" => my_color = Some(parse_color(value)?),
+                        "Max_Moves" => max_moves = Some(value.parse()?).filter(|&n| n > 0),
+                        _ => {}
+                    }
+                }
+                ["Game_Summary", "Time"] => time.read(line)?,
+                ["Game_Summary", side @ ("Time+" | "Time-")] => {
+                    let color = parse_color(&side[4..])?;
+                    if my_color.is_none_or(|my_color| my_color == color) {
+                        time.read(line)?;
+                    }
+                }
+                ["Game_Summary", "Position"] => {
+                    if line.starts_with(['+', '-']) && line.len() > 1 {
+                        move_lines.push(line);
+                    } else {
+                        position.push_str(line);
+                        position.push('\n');
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let mut board = Board::from_csa(&position)?;
+        let start = board.to_sfen();
+        let mut moves = Vec::new();
+        let mut elapsed = Vec::new();
+        for line in move_lines {
+            let (mv, t) = parse_move_line(line)?;
+            let mv = board.parse_csa_move(mv)?;
+            board.make_move(&mv)?;
+            moves.push(mv.to_string());
+            elapsed.push(time.unit * t.unwrap_or(0));
+        }
+
+        Ok(GameSummary {
+            game_id: game_id.ok_or(Error::IllegalSyntax)?,
+            black_name: core::mem::take(&mut names[0]),
+            white_name: core::mem::take(&mut names[1]),
+            my_color: my_color.ok_or(Error::IllegalSyntax)?,
+            time_control: time.control(),
+            time_unit: time.unit,
+            max_moves,
+            start,
+            moves,
+            elapsed,
+        })
+    }
+
+    /// Returns the name of the player of `color`.
+    pub fn name(&self, color: Color) -> &str {
+        match color {
+            Color::Black => &self.black_name,
+            Color::White => &self.white_name,
+        }
+    }
+
+    /// Returns the position after `moves` as in `GuiCommand::Position`.
+    pub fn position(&self) -> String {
+        position(&self.start, &self.moves)
+    }
+}
+
+/// Returns the SFEN followed by `moves` as in `GuiCommand::Position`.
+pub(crate) fn position(start: &str, moves: &[String]) -> String {
+    if moves.is_empty() {
+        start.to_string()
+    } else {
+        alloc::format!("{} moves {}", start, moves.join(" "))
+    }
+}
+
+/// Splits a move line such as `+7776FU,T3` into the move and the time in units.
+pub(crate) fn parse_move_line(line: &str) -> Result<(&str, Option<u32>), Error> {
+    let mut fields = line.split(',');
+    let mv = fields.next().unwrap_or_default();
+    let time = fields
+        .find_map(|field| field.strip_prefix('T'))
+        .map(str::parse)
+        .transpose()?;
+    Ok((mv, time))
+}
+
+fn parse_color(s: &str) -> Result<Color, Error> {
+    match s {
+        "+" => Ok(Color::Black),
+        "-" => Ok(Color::White),
+        _ => Err(Error::IllegalSyntax),
+    }
+}
+
+struct TimeFields {
+    unit: Duration,
+    total: u32,
+    byoyomi: u32,
+    increment: u32,
+}
+
+impl Default for TimeFields {
+    fn default() -> Self {
+        TimeFields {
+            unit: Duration::from_secs(1),
+            total: 0,
+            byoyomi: 0,
+            increment: 0,
+        }
+    }
+}
+
+impl TimeFields {
+    fn read(&mut self, line: &str) -> Result<(), Error> {
+        let Some((key, value)) = line.split_once(':') else {
+            return Ok(());
+        };
+        match key {
+            "Time_Unit" => {
+                let split = value
+                    .find(|c: char| !c.is_ascii_digit())
+                    .unwrap_or(value.len());
+                let count: u64 = match &value[..split] {
+                    "" => 1,
+                    count => count.parse()?,
+                };
+                self.unit = match &value[split..] {
+                    "msec" => Duration::from_millis(count),
+                    "sec" => Duration::from_secs(count),
+                    "min" => Duration::from_secs(count * 60),
+                    _ => return Err(Error::IllegalSyntax),
+                };
+            }
+            "Total_Time" => self.total = value.parse()?,
+            "Byoyomi" => self.byoyomi = value.parse()?,
+            "Increment" => self.increment = value.parse()?,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn control(&self) -> TimeControl {
+        let main = self.unit * self.total;
+        match (self.byoyomi, self.increment) {
+            (_, increment) if increment > 0 => TimeControl::Increment {
+                main,
+                increment: self.unit * increment,
+            },
+            (byoyomi, _) if byoyomi > 0 => TimeControl::Byoyomi {
+                main,
+                byoyomi: self.unit * byoyomi,
+                periods: 1,
+            },
+            _ if self.total > 0 => TimeControl::SuddenDeath { main },
+            _ => TimeControl::Unlimited,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn parse() {
+        let text = "BEGIN Game_Summary\n\
+                    Game_ID:wdoor+floodgate-300-10F+a+b+20240101000000\n\
+                    Name+:a\n\
+                    Name-:b\n\
+                    Your_Turn:+\n\
+                    Max_Moves:256\n\
+                    BEGIN Time\n\
+                    Time_Unit:100msec\n\
+                    Total_Time:3000\n\
+                    Increment:100\n\
+                    END Time\n\
+                    BEGIN Position\n\
+                    P1-KY-KE-GI-KI-OU-KI-GI-KE-KY\n\
+                    P2 * -HI *  *  *  *  * -KA * \n\
+                    P3-FU-FU-FU-FU-FU-FU-FU-FU-FU\n\
+                    P4 *  *  *  *  *  *  *  *  * \n\
+                    P5 *  *  *  *  *  *  *  *  * \n\
+                    P6 *  *  *  *  *  *  *  *  * \n\
+                    P7+FU+FU+FU+FU+FU+FU+FU+FU+FU\n\
+                    P8 * +KA *  *  *  *  * +HI * \n\
+                    P9+KY+KE+GI+KI+OU+KI+GI+KE+KY\n\
+                    +\n\
+                    +2726FU,T12\n\
+                    -3334FU,T6\n\
+                    END Position\n\
+                    END Game_Summary\n";
+        let summary = GameSummary::parse(text).unwrap();
+
+        assert_eq!(Color::Black, summary.my_color);
+        assert_eq!("b", summary.name(Color::White));
+        assert_eq!(Some(256), summary.max_moves);
+        assert_eq!(
+            TimeControl::Increment {
+                main: Duration::from_secs(300),
+                increment: Duration::from_secs(10),
+            },
+            summary.time_control
+        );
+        assert_eq!(vec!["2g2f", "3c3d"], summary.moves);
+        assert_eq!(Duration::from_millis(1200), summary.elapsed[0]);
+        assert_eq!(
+            alloc::format!("{} moves 2g2f 3c3d", crate::protocol::STARTPOS_SFEN),
+            summary.position()
+        );
+
+        assert!(GameSummary::parse("BEGIN Game_Summary\nGame_ID:x\nEND Game_Summary\n").is_err());
+    }
+
+    #[test]
+    fn move_line() {
+        assert_eq!(("+7776FU", Some(3)), parse_move_line("+7776FU,T3").unwrap());
+        assert_eq!(("-3334FU", None), parse_move_line("-3334FU").unwrap());
+    }
+}
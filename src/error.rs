@@ -1,19 +1,116 @@
+use alloc::boxed::Box;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
 use thiserror::Error;
 
+/// Represents a phase of the communication which timed out.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TimeoutPhase {
+    /// Waiting for `usiok` after `usi`.
+    UsiOk,
+    /// Waiting for `readyok` after `isready`.
+    ReadyOk,
+    /// Waiting for `bestmove` after `stop`.
+    BestMove,
+}
+
+impl fmt::Display for TimeoutPhase {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TimeoutPhase::UsiOk => write!(f, "usiok"),
+            TimeoutPhase::ReadyOk => write!(f, "readyok"),
+            TimeoutPhase::BestMove => write!(f, "bestmove"),
+        }
+    }
+}
+
+/// Diagnostics collected when the engine process exits unexpectedly.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EngineCrashReport {
+    /// The exit code of the process, if it exited normally.
+    pub exit_code: Option<i32>,
+    /// The signal which terminated the process, if any.
+    pub signal: Option<i32>,
+    /// The latest lines the engine wrote to stderr.
+    pub stderr: Vec<String>,
+    /// The latest protocol lines exchanged with the engine, oldest first.
+    /// Lines sent to the engine are prefixed with `> ` and lines received with `< `.
+    pub protocol: Vec<String>,
+}
+
+impl fmt::Display for EngineCrashReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.exit_code, self.signal) {
+            (Some(code), _) => write!(f, "exit code {}", code),
+            (None, Some(signal)) => write!(f, "signal {}", signal),
+            (None, None) => write!(f, "unknown status"),
+        }
+    }
+}
+
+/// The error type of this crate.
+///
+/// Variants depending on optional features, such as `EngineIo` and `Json`, only exist when the
+/// features are enabled, so the enum is non-exhaustive.
 #[derive(Error, Debug)]
+#[non_exhaustive]
 pub enum Error {
     #[error("illegal USI command syntax")]
     IllegalSyntax,
 
     #[error("illegal USI command syntax")]
-    IllegalNumberFormat(#[from] std::num::ParseIntError),
+    IllegalNumberFormat(#[from] core::num::ParseIntError),
 
     #[error("the engine already started listening")]
     IllegalOperation,
 
+    #[error("the operation is not allowed because {reason}")]
+    IllegalState { reason: &'static str },
+
+    #[error("invalid argument: {reason}")]
+    InvalidArgument { reason: &'static str },
+
+    #[error("illegal move {0}")]
+    IllegalMove(String),
+
+    #[error("the engine closed the connection")]
+    EngineClosed,
+
+    #[error("the engine process exited with {0}")]
+    EngineExited(EngineCrashReport),
+
+    #[error("the engine has no option named {name}")]
+    UnknownOption { name: String },
+
+    #[error("invalid value {value:?} for option {name}")]
+    InvalidOptionValue { name: String, value: String },
+
+    #[error("value {given} for option {name} is out of range [{min}, {max}]")]
+    OptionOutOfRange {
+        name: String,
+        min: i32,
+        max: i32,
+        given: i32,
+    },
+
+    #[error("the engine violated the protocol: {reason}")]
+    ProtocolViolation { reason: &'static str },
+
+    #[error("timed out waiting for {phase}")]
+    Timeout { phase: TimeoutPhase },
+
+    #[error("the server rejected the request: {reason}")]
+    ServerRejected { reason: String },
+
+    #[cfg(feature = "std")]
     #[error("IO error occurred when communicating with the engine")]
     EngineIo(#[from] std::io::Error),
 
+    #[cfg(feature = "serde")]
+    #[error("failed to serialize or deserialize JSON")]
+    Json(#[from] serde_json::Error),
+
     #[error("An error occurred inside the external handler")]
-    HandlerError(#[from] Box<dyn std::error::Error + Send + Sync>),
+    HandlerError(#[from] Box<dyn core::error::Error + Send + Sync>),
 }
@@ -0,0 +1,845 @@
+//! A C ABI for parsing commands and driving engines, declared in `include/usi.h`.
+//!
+//! Objects are passed as opaque handles created by `usi_*_parse` or `usi_engine_spawn`,
+//! and freed with the matching `usi_*_free` function. Functions which can fail return
+//! `UsiStatus`, write their results through output pointers, and set the message returned by
+//! `usi_last_error` on the calling thread. Strings are UTF-8 and NUL-terminated; those
+//! returned through `char **` are owned by the caller and freed with `usi_string_free`, and
+//! those returned as `const char *` are borrowed from the handle.
+//!
+//! Build the library with `cargo rustc --release --features ffi --crate-type cdylib`,
+//! or `staticlib` to link it statically.
+//!
+//! ```c
+//! #include "usi.h"
+//!
+//! UsiEngine *engine;
+//! if (usi_engine_spawn("/path/to/usi_engine", &engine) != USI_OK) {
+//!     fprintf(stderr, "%s\n", usi_last_error());
+//!     return 1;
+//! }
+//! usi_engine_new_game(engine);
+//! usi_engine_position(engine, "startpos moves 7g7f");
+//!
+//! UsiGoParams params = usi_go_params_default();
+//! params.byoyomi_ms = 1000;
+//! UsiCommand *bestmove;
+//! usi_engine_go(engine, &params, NULL, NULL, &bestmove);
+//! char *mv, *ponder;
+//! usi_command_bestmove(bestmove, &mv, &ponder);
+//! printf("%s\n", mv);
+//! usi_string_free(mv);
+//! usi_string_free(ponder);
+//! usi_command_free(bestmove);
+//! usi_engine_free(engine);
+//! ```
+use std::cell::RefCell;
+use std::ffi::{c_char, c_void, CStr, CString};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::time::Duration;
+
+use crate::client::Engine;
+use crate::error::Error;
+use crate::protocol::*;
+
+/// The result of a function.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum UsiStatus {
+    Ok = 0,
+    /// A required pointer was NULL.
+    NullPointer = 1,
+    /// A string was not valid UTF-8.
+    InvalidUtf8 = 2,
+    /// A command or a position could not be parsed.
+    IllegalSyntax = 3,
+    /// The requested value is absent, e.g. the depth of an `info` command without one.
+    NotFound = 4,
+    /// An option was unknown, or its value was invalid.
+    InvalidOption = 5,
+    /// The engine closed the connection or exited.
+    EngineClosed = 6,
+    /// Waiting for the engine timed out.
+    Timeout = 7,
+    /// An I/O error occurred.
+    Io = 8,
+    /// The library panicked. The handles passed may be left in an unspecified state.
+    Panic = 9,
+    Other = 10,
+}
+
+/// The kind of a command sent by an engine.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum UsiCommandKind {
+    Id = 0,
+    BestMove = 1,
+    Checkmate = 2,
+    Info = 3,
+    Option = 4,
+    ReadyOk = 5,
+    UsiOk = 6,
+    Unknown = 7,
+}
+
+/// A numeric field of an `info` command.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum UsiInfoField {
+    Depth = 0,
+    SelDepth = 1,
+    Time = 2,
+    Nodes = 3,
+    Nps = 4,
+    HashFull = 5,
+    MultiPv = 6,
+    ScoreCp = 7,
+    ScoreMate = 8,
+}
+
+impl TryFrom<i32> for UsiInfoField {
+    type Error = Error;
+
+    fn try_from(field: i32) -> Result<Self, Error> {
+        Ok(match field {
+            0 => UsiInfoField::Depth,
+            1 => UsiInfoField::SelDepth,
+            2 => UsiInfoField::Time,
+            3 => UsiInfoField::Nodes,
+            4 => UsiInfoField::Nps,
+            5 => UsiInfoField::HashFull,
+            6 => UsiInfoField::MultiPv,
+            7 => UsiInfoField::ScoreCp,
+            8 => UsiInfoField::ScoreMate,
+            _ => {
+                return Err(Error::InvalidArgument {
+                    reason: "the info field is unknown",
+                })
+            }
+        })
+    }
+}
+
+/// The time control and limits of `usi_engine_go`. Negative values are unset.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct UsiGoParams {
+    pub btime_ms: i64,
+    pub wtime_ms: i64,
+    pub byoyomi_ms: i64,
+    pub binc_ms: i64,
+    pub winc_ms: i64,
+    pub depth: i32,
+}
+
+/// A command sent by an engine, with the line it was parsed from.
+#[derive(Debug)]
+pub struct UsiCommand {
+    raw: CString,
+    command: Option<EngineCommand>,
+}
+
+/// An engine with its name and author.
+#[derive(Debug)]
+pub struct UsiEngine {
+    engine: Engine,
+    name: CString,
+    author: CString,
+}
+
+/// Called by `usi_engine_go` with each `info` command, which is freed after the call.
+pub type UsiInfoCallback = Option<unsafe extern "C" fn(info: *const UsiCommand, data: *mut c_void)>;
+
+thread_local! {
+    static LAST_ERROR: RefCell<CString> = RefCell::new(CString::default());
+}
+
+struct Failure(UsiStatus, String);
+
+impl From<Error> for Failure {
+    fn from(e: Error) -> Self {
+        let status = match e {
+            Error::IllegalSyntax | Error::IllegalNumberFormat(_) => UsiStatus::IllegalSyntax,
+            Error::UnknownOption { .. }
+            | Error::InvalidOptionValue { .. }
+            | Error::OptionOutOfRange { .. } => UsiStatus::InvalidOption,
+            Error::EngineClosed | Error::EngineExited(_) => UsiStatus::EngineClosed,
+            Error::Timeout { .. } => UsiStatus::Timeout,
+            Error::EngineIo(_) => UsiStatus::Io,
+            _ => UsiStatus::Other,
+        };
+        Failure(status, e.to_string())
+    }
+}
+
+fn failure(status: UsiStatus, message: &str) -> Failure {
+    Failure(status, message.to_string())
+}
+
+/// Runs `f`, catching panics, and records the error message.
+fn run<F: FnOnce() -> Result<(), Failure>>(f: F) -> UsiStatus {
+    let result = panic::catch_unwind(AssertUnwindSafe(f))
+        .unwrap_or_else(|_| Err(failure(UsiStatus::Panic, "the library panicked")));
+    let (status, message) = match result {
+        Ok(()) => (UsiStatus::Ok, String::new()),
+        Err(Failure(status, message)) => (status, message),
+    };
+    LAST_ERROR.with(|last| {
+        *last.borrow_mut() = CString::new(message).unwrap_or_default();
+    });
+    status
+}
+
+unsafe fn read_str<'a>(s: *const c_char) -> Result<&'a str, Failure> {
+    if s.is_null() {
+        return Err(failure(UsiStatus::NullPointer, "a string is NULL"));
+    }
+    CStr::from_ptr(s)
+        .to_str()
+        .map_err(|_| failure(UsiStatus::InvalidUtf8, "a string is not valid UTF-8"))
+}
+
+unsafe fn handle<'a, T>(p: *mut T) -> Result<&'a mut T, Failure> {
+    p.as_mut()
+        .ok_or_else(|| failure(UsiStatus::NullPointer, "a handle is NULL"))
+}
+
+unsafe fn borrow<'a, T>(p: *const T) -> Result<&'a T, Failure> {
+    p.as_ref()
+        .ok_or_else(|| failure(UsiStatus::NullPointer, "a handle is NULL"))
+}
+
+/// Writes the value made by `value` to `out`, which is made only if `out` isn't NULL
+/// so that nothing is leaked.
+unsafe fn write<T, F: FnOnce() -> T>(out: *mut T, value: F) -> Result<(), Failure> {
+    if out.is_null() {
+        return Err(failure(UsiStatus::NullPointer, "an output pointer is NULL"));
+    }
+    out.write(value());
+    Ok(())
+}
+
+fn to_c_string(s: &str) -> *mut c_char {
+    // Lines are read without NUL, which can't be represented.
+    CString::new(s.replace('\0', "")).unwrap().into_raw()
+}
+
+fn new_command(raw: &str, command: Option<EngineCommand>) -> *mut UsiCommand {
+    Box::into_raw(Box::new(UsiCommand {
+        raw: CString::new(raw.replace('\0', "")).unwrap(),
+        command,
+    }))
+}
+
+/// Returns the error message of the last function called on this thread,
+/// or an empty string if it succeeded. The string is valid until the next call.
+#[no_mangle]
+pub extern "C" fn usi_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ptr())
+}
+
+/// Frees a string returned by the library.
+///
+/// # Safety
+/// `s` must be NULL or a string returned through `char **` which is not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn usi_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+/// Parses a line sent by an engine. Lines which aren't commands are parsed
+/// as `USI_COMMAND_UNKNOWN`.
+///
+/// # Safety
+/// `line` must be a valid string, and `out` a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn usi_command_parse(
+    line: *const c_char,
+    out: *mut *mut UsiCommand,
+) -> UsiStatus {
+    run(|| {
+        let line = read_str(line)?;
+        let command = match EngineCommand::parse(line)? {
+            EngineCommand::Unknown => None,
+            command => Some(command),
+        };
+        write(out, || new_command(line, command))
+    })
+}
+
+/// Frees a command.
+///
+/// # Safety
+/// `command` must be NULL or a command which is not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn usi_command_free(command: *mut UsiCommand) {
+    if !command.is_null() {
+        drop(Box::from_raw(command));
+    }
+}
+
+/// Returns the kind of `command`, or `USI_COMMAND_UNKNOWN` if it is NULL.
+///
+/// # Safety
+/// `command` must be NULL or a valid command.
+#[no_mangle]
+pub unsafe extern "C" fn usi_command_kind(command: *const UsiCommand) -> UsiCommandKind {
+    match command
+        .as_ref()
+        .and_then(|command| command.command.as_ref())
+    {
+        Some(EngineCommand::Id(_)) => UsiCommandKind::Id,
+        Some(EngineCommand::BestMove(_)) => UsiCommandKind::BestMove,
+        Some(EngineCommand::Checkmate(_)) => UsiCommandKind::Checkmate,
+        Some(EngineCommand::Info(_)) => UsiCommandKind::Info,
+        Some(EngineCommand::Option(_)) => UsiCommandKind::Option,
+        Some(EngineCommand::ReadyOk) => UsiCommandKind::ReadyOk,
+        Some(EngineCommand::UsiOk) => UsiCommandKind::UsiOk,
+        Some(EngineCommand::Unknown) | None => UsiCommandKind::Unknown,
+    }
+}
+
+/// Returns the line `command` was parsed from, which is valid until it is freed.
+///
+/// # Safety
+/// `command` must be a valid command.
+#[no_mangle]
+pub unsafe extern "C" fn usi_command_raw(command: *const UsiCommand) -> *const c_char {
+    match command.as_ref() {
+        Some(command) => command.raw.as_ptr(),
+        None => ptr::null(),
+    }
+}
+
+/// Writes the move of a `bestmove` command, which is `resign` or `win` for those,
+/// and the ponder move, which is NULL if absent.
+///
+/// # Safety
+/// `command` must be a valid command, and `mv` and `ponder` valid pointers.
+#[no_mangle]
+pub unsafe extern "C" fn usi_command_bestmove(
+    command: *const UsiCommand,
+    mv: *mut *mut c_char,
+    ponder: *mut *mut c_char,
+) -> UsiStatus {
+    run(|| {
+        let command = borrow(command)?;
+        let (best, ponder_move) = match &command.command {
+            Some(EngineCommand::BestMove(BestMoveParams::MakeMove(mv, ponder))) => {
+                (mv.as_str(), ponder.as_deref())
+            }
+            Some(EngineCommand::BestMove(BestMoveParams::Resign)) => ("resign", None),
+            Some(EngineCommand::BestMove(BestMoveParams::Win)) => ("win", None),
+            _ => return Err(failure(UsiStatus::NotFound, "not a bestmove command")),
+        };
+        if mv.is_null() || ponder.is_null() {
+            return Err(failure(UsiStatus::NullPointer, "an output pointer is NULL"));
+        }
+        write(mv, || to_c_string(best))?;
+        write(ponder, || ponder_move.map_or(ptr::null_mut(), to_c_string))
+    })
+}
+
+/// Writes a numeric field of an `info` command, with the time in milliseconds.
+/// `field` is one of `UsiInfoField`, and `USI_OTHER` is returned for other values.
+/// Returns `USI_NOT_FOUND` if the field is absent, or the command isn't `info`.
+///
+/// # Safety
+/// `command` must be a valid command, and `out` a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn usi_command_info(
+    command: *const UsiCommand,
+    field: i32,
+    out: *mut i64,
+) -> UsiStatus {
+    run(|| {
+        let command = borrow(command)?;
+        // Taken as an integer, as an out of range value is undefined behavior for an enum.
+        let field = UsiInfoField::try_from(field)?;
+        let Some(EngineCommand::Info(params)) = &command.command else {
+            return Err(failure(UsiStatus::NotFound, "not an info command"));
+        };
+        let value = params.iter().find_map(|param| match (field, param) {
+            (UsiInfoField::Depth, InfoParams::Depth(depth, _)) => Some(i64::from(*depth)),
+            (UsiInfoField::SelDepth, InfoParams::Depth(_, seldepth)) => seldepth.map(i64::from),
+            (UsiInfoField::Time, InfoParams::Time(time)) => Some(time.as_millis() as i64),
+            (UsiInfoField::Nodes, InfoParams::Nodes(n)) => i64::try_from(*n).ok(),
+            (UsiInfoField::Nps, InfoParams::Nps(n)) => i64::try_from(*n).ok(),
+            (UsiInfoField::HashFull, InfoParams::HashFull(n)) => Some(i64::from(*n)),
+            (UsiInfoField::MultiPv, InfoParams::MultiPv(n)) => Some(i64::from(*n)),
+            (UsiInfoField::ScoreCp, InfoParams::Score(value, kind)) if !is_mate(*kind) => {
+                Some(i64::from(*value))
+            }
+            (UsiInfoField::ScoreMate, InfoParams::Score(value, kind)) if is_mate(*kind) => {
+                Some(i64::from(*value))
+            }
+            _ => None,
+        });
+        match value {
+            Some(value) => write(out, || value),
+            None => Err(failure(UsiStatus::NotFound, "the field is absent")),
+        }
+    })
+}
+
+fn is_mate(kind: ScoreKind) -> bool {
+    matches!(
+        kind,
+        ScoreKind::MateExact
+            | ScoreKind::MateSignOnly
+            | ScoreKind::MateLowerbound
+            | ScoreKind::MateUpperbound
+    )
+}
+
+/// Writes the PV of an `info` command as moves separated by spaces.
+///
+/// # Safety
+/// `command` must be a valid command, and `out` a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn usi_command_info_pv(
+    command: *const UsiCommand,
+    out: *mut *mut c_char,
+) -> UsiStatus {
+    run(|| {
+        let command = borrow(command)?;
+        let pv = match &command.command {
+            Some(EngineCommand::Info(params)) => params.iter().find_map(|param| match param {
+                InfoParams::Pv(moves) => Some(moves.join(" ")),
+                _ => None,
+            }),
+            _ => None,
+        };
+        match pv {
+            Some(pv) => write(out, || to_c_string(&pv)),
+            None => Err(failure(UsiStatus::NotFound, "the PV is absent")),
+        }
+    })
+}
+
+/// Parses a command sent to engines, and writes it in the canonical form.
+///
+/// # Safety
+/// `line` must be a valid string, and `out` a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn usi_gui_command_normalize(
+    line: *const c_char,
+    out: *mut *mut c_char,
+) -> UsiStatus {
+    run(|| {
+        let command = GuiCommand::parse(read_str(line)?)?;
+        write(out, || to_c_string(&command.to_string()))
+    })
+}
+
+/// Returns the parameters of `usi_engine_go` without any limit.
+#[no_mangle]
+pub extern "C" fn usi_go_params_default() -> UsiGoParams {
+    UsiGoParams {
+        btime_ms: -1,
+        wtime_ms: -1,
+        byoyomi_ms: -1,
+        binc_ms: -1,
+        winc_ms: -1,
+        depth: -1,
+    }
+}
+
+impl UsiGoParams {
+    /// Returns `true` if any of the time control and the depth is set.
+    fn has_limit(&self) -> bool {
+        [
+            self.btime_ms,
+            self.wtime_ms,
+            self.byoyomi_ms,
+            self.binc_ms,
+            self.winc_ms,
+            i64::from(self.depth),
+        ]
+        .iter()
+        .any(|&value| value >= 0)
+    }
+}
+
+impl From<&UsiGoParams> for ThinkParams {
+    fn from(go: &UsiGoParams) -> Self {
+        let millis = |ms: i64| u64::try_from(ms).ok().map(Duration::from_millis);
+        let mut params = ThinkParams::new();
+        if let Some(t) = millis(go.btime_ms) {
+            params = params.btime(t);
+        }
+        if let Some(t) = millis(go.wtime_ms) {
+            params = params.wtime(t);
+        }
+        if let Some(t) = millis(go.byoyomi_ms) {
+            params = params.byoyomi(t);
+        }
+        if let Some(t) = millis(go.binc_ms) {
+            params = params.binc(t);
+        }
+        if let Some(t) = millis(go.winc_ms) {
+            params = params.winc(t);
+        }
+        if go.depth >= 0 {
+            params = params.depth(go.depth);
+        }
+        params
+    }
+}
+
+impl UsiEngine {
+    fn new(engine: Engine) -> Self {
+        let name = CString::new(engine.info().name().replace('\0', "")).unwrap();
+        let author = CString::new(engine.info().author().replace('\0', "")).unwrap();
+        UsiEngine {
+            engine,
+            name,
+            author,
+        }
+    }
+}
+
+/// Spawns the engine at `path`, sends `usi` and `isready`, and waits for `usiok` and `readyok`.
+///
+/// # Safety
+/// `path` must be a valid string, and `out` a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn usi_engine_spawn(
+    path: *const c_char,
+    out: *mut *mut UsiEngine,
+) -> UsiStatus {
+    run(|| {
+        let path = read_str(path)?;
+        if out.is_null() {
+            return Err(failure(UsiStatus::NullPointer, "an output pointer is NULL"));
+        }
+        let engine = Engine::spawn(path)?;
+        write(out, || Box::into_raw(Box::new(UsiEngine::new(engine))))
+    })
+}
+
+/// Sends `quit` to the engine, waits for it to exit, and frees it.
+///
+/// # Safety
+/// `engine` must be NULL or an engine which is not freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn usi_engine_free(engine: *mut UsiEngine) {
+    if !engine.is_null() {
+        let engine = Box::from_raw(engine);
+        let _ = panic::catch_unwind(AssertUnwindSafe(|| engine.engine.shutdown()));
+    }
+}
+
+/// Returns the name of the engine from `id name`, which is valid until it is freed.
+///
+/// # Safety
+/// `engine` must be a valid engine.
+#[no_mangle]
+pub unsafe extern "C" fn usi_engine_name(engine: *const UsiEngine) -> *const c_char {
+    engine
+        .as_ref()
+        .map_or(ptr::null(), |engine| engine.name.as_ptr())
+}
+
+/// Returns the author of the engine from `id author`, which is valid until it is freed.
+///
+/// # Safety
+/// `engine` must be a valid engine.
+#[no_mangle]
+pub unsafe extern "C" fn usi_engine_author(engine: *const UsiEngine) -> *const c_char {
+    engine
+        .as_ref()
+        .map_or(ptr::null(), |engine| engine.author.as_ptr())
+}
+
+/// Sets the option `name` to `value`, checking it against the declared options.
+///
+/// # Safety
+/// `engine` must be a valid engine, and `name` and `value` valid strings.
+#[no_mangle]
+pub unsafe extern "C" fn usi_engine_set_option(
+    engine: *mut UsiEngine,
+    name: *const c_char,
+    value: *const c_char,
+) -> UsiStatus {
+    run(|| {
+        let engine = handle(engine)?;
+        Ok(engine
+            .engine
+            .set_option(read_str(name)?, read_str(value)?)?)
+    })
+}
+
+/// Waits for `readyok`, and sends `usinewgame`.
+///
+/// # Safety
+/// `engine` must be a valid engine.
+#[no_mangle]
+pub unsafe extern "C" fn usi_engine_new_game(engine: *mut UsiEngine) -> UsiStatus {
+    run(|| {
+        let engine = &mut handle(engine)?.engine;
+        engine.prepare()?;
+        Ok(engine.send(&GuiCommand::UsiNewGame)?)
+    })
+}
+
+/// Sends `position` with a SFEN or `startpos`, optionally followed by `moves`.
+///
+/// # Safety
+/// `engine` must be a valid engine, and `position` a valid string.
+#[no_mangle]
+pub unsafe extern "C" fn usi_engine_position(
+    engine: *mut UsiEngine,
+    position: *const c_char,
+) -> UsiStatus {
+    run(|| {
+        let engine = handle(engine)?;
+        let command = GuiCommand::parse(&format!("position {}", read_str(position)?))?;
+        Ok(engine.engine.send(&command)?)
+    })
+}
+
+/// Sends a command line to the engine. Returns `USI_ILLEGAL_SYNTAX` if it isn't a command.
+///
+/// # Safety
+/// `engine` must be a valid engine, and `line` a valid string.
+#[no_mangle]
+pub unsafe extern "C" fn usi_engine_send(engine: *mut UsiEngine, line: *const c_char) -> UsiStatus {
+    run(|| {
+        let engine = handle(engine)?;
+        let command = GuiCommand::parse(read_str(line)?)?;
+        Ok(engine.engine.send(&command)?)
+    })
+}
+
+/// Waits for a line from the engine, and writes it as a command.
+///
+/// # Safety
+/// `engine` must be a valid engine, and `out` a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn usi_engine_recv(
+    engine: *mut UsiEngine,
+    out: *mut *mut UsiCommand,
+) -> UsiStatus {
+    run(|| {
+        let engine = handle(engine)?;
+        if out.is_null() {
+            return Err(failure(UsiStatus::NullPointer, "an output pointer is NULL"));
+        }
+        let output = engine.engine.recv()?;
+        write(out, || {
+            new_command(output.raw_str(), output.response().clone())
+        })
+    })
+}
+
+/// Sends `go` with `params`, and blocks until `bestmove`, which is written to `out`.
+/// `callback` is called with each `info` command and `data` unless it is NULL.
+///
+/// As the search can't be stopped, `params` must set a time control or a depth;
+/// `USI_OTHER` is returned otherwise.
+///
+/// # Safety
+/// `engine` must be a valid engine, `params` NULL or valid, and `out` a valid pointer.
+/// `callback` must not call functions with `engine`.
+#[no_mangle]
+pub unsafe extern "C" fn usi_engine_go(
+    engine: *mut UsiEngine,
+    params: *const UsiGoParams,
+    callback: UsiInfoCallback,
+    data: *mut c_void,
+    out: *mut *mut UsiCommand,
+) -> UsiStatus {
+    run(|| {
+        let engine = handle(engine)?;
+        if out.is_null() {
+            return Err(failure(UsiStatus::NullPointer, "an output pointer is NULL"));
+        }
+        let params = match params.as_ref() {
+            Some(params) if params.has_limit() => ThinkParams::from(params),
+            _ => {
+                return Err(failure(
+                    UsiStatus::Other,
+                    "go needs a time control or a depth",
+                ))
+            }
+        };
+        let mut search = engine.engine.go(params)?;
+        while let Some(info) = search.next_info()? {
+            if let Some(callback) = callback {
+                let command = EngineCommand::Info(info);
+                let info = UsiCommand {
+                    raw: CString::new(command.to_string().replace('\0', "")).unwrap(),
+                    command: Some(command),
+                };
+                callback(&info, data);
+            }
+        }
+        let bestmove = EngineCommand::BestMove(search.wait()?);
+        write(out, || new_command(&bestmove.to_string(), Some(bestmove)))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::ToyEngine;
+    use crate::InProcessTransport;
+
+    fn toy_engine() -> ToyEngine {
+        ToyEngine::new()
+            .info(vec![InfoParams::Depth(3, None), InfoParams::Nodes(100)])
+            .bestmove("7g7f", Some("3c3d"))
+    }
+
+    fn c(s: &str) -> CString {
+        CString::new(s).unwrap()
+    }
+
+    unsafe fn take(s: *mut c_char) -> String {
+        let owned = CStr::from_ptr(s).to_str().unwrap().to_string();
+        usi_string_free(s);
+        owned
+    }
+
+    #[test]
+    fn parse() {
+        unsafe {
+            let mut command = ptr::null_mut();
+            let line = c("info depth 10 seldepth 12 score mate 5 pv 7g7f 3c3d");
+            assert_eq!(
+                UsiStatus::Ok,
+                usi_command_parse(line.as_ptr(), &mut command)
+            );
+            assert_eq!(UsiCommandKind::Info, usi_command_kind(command));
+
+            let mut value = 0;
+            assert_eq!(
+                UsiStatus::Ok,
+                usi_command_info(command, UsiInfoField::SelDepth as i32, &mut value)
+            );
+            assert_eq!(12, value);
+            assert_eq!(
+                UsiStatus::Ok,
+                usi_command_info(command, UsiInfoField::ScoreMate as i32, &mut value)
+            );
+            assert_eq!(5, value);
+            assert_eq!(
+                UsiStatus::NotFound,
+                usi_command_info(command, UsiInfoField::ScoreCp as i32, &mut value)
+            );
+            assert_eq!(
+                "the field is absent",
+                CStr::from_ptr(usi_last_error()).to_str().unwrap()
+            );
+            assert_eq!(UsiStatus::Other, usi_command_info(command, 9, &mut value));
+
+            let mut pv = ptr::null_mut();
+            assert_eq!(UsiStatus::Ok, usi_command_info_pv(command, &mut pv));
+            assert_eq!("7g7f 3c3d", take(pv));
+            usi_command_free(command);
+
+            let line = c("bestmove resign");
+            assert_eq!(
+                UsiStatus::Ok,
+                usi_command_parse(line.as_ptr(), &mut command)
+            );
+            let (mut mv, mut ponder) = (ptr::null_mut(), ptr::null_mut());
+            assert_eq!(
+                UsiStatus::Ok,
+                usi_command_bestmove(command, &mut mv, &mut ponder)
+            );
+            assert_eq!("resign", take(mv));
+            assert!(ponder.is_null());
+            assert_eq!(
+                "bestmove resign",
+                CStr::from_ptr(usi_command_raw(command)).to_str().unwrap()
+            );
+            usi_command_free(command);
+
+            let line = c("bestmove");
+            assert_eq!(
+                UsiStatus::IllegalSyntax,
+                usi_command_parse(line.as_ptr(), &mut command)
+            );
+            assert_eq!(
+                UsiStatus::NullPointer,
+                usi_command_parse(ptr::null(), &mut command)
+            );
+
+            let mut normalized = ptr::null_mut();
+            let line = c("go  btime 1000   wtime 2000");
+            assert_eq!(
+                UsiStatus::Ok,
+                usi_gui_command_normalize(line.as_ptr(), &mut normalized)
+            );
+            assert_eq!("go btime 1000 wtime 2000", take(normalized));
+        }
+    }
+
+    unsafe extern "C" fn count_info(info: *const UsiCommand, data: *mut c_void) {
+        let mut depth = 0;
+        assert_eq!(
+            UsiStatus::Ok,
+            usi_command_info(info, UsiInfoField::Depth as i32, &mut depth)
+        );
+        *data.cast::<i64>() += depth;
+    }
+
+    #[test]
+    fn engine() {
+        unsafe {
+            let engine = Engine::connect(InProcessTransport::spawn(toy_engine())).unwrap();
+            let engine = Box::into_raw(Box::new(UsiEngine::new(engine)));
+            assert_eq!(
+                "Toy",
+                CStr::from_ptr(usi_engine_name(engine)).to_str().unwrap()
+            );
+            assert_eq!(UsiStatus::Ok, usi_engine_new_game(engine));
+            let position = c("startpos moves 7g7f");
+            assert_eq!(
+                UsiStatus::Ok,
+                usi_engine_position(engine, position.as_ptr())
+            );
+            let position = c("nowhere");
+            assert_eq!(
+                UsiStatus::IllegalSyntax,
+                usi_engine_position(engine, position.as_ptr())
+            );
+
+            let mut params = usi_go_params_default();
+            let mut bestmove = ptr::null_mut();
+            for params in [ptr::null(), &params as *const UsiGoParams] {
+                assert_eq!(
+                    UsiStatus::Other,
+                    usi_engine_go(engine, params, None, ptr::null_mut(), &mut bestmove)
+                );
+            }
+            params.byoyomi_ms = 1000;
+            let mut depths = 0i64;
+            assert_eq!(
+                UsiStatus::Ok,
+                usi_engine_go(
+                    engine,
+                    &params,
+                    Some(count_info),
+                    (&mut depths as *mut i64).cast(),
+                    &mut bestmove
+                )
+            );
+            assert_eq!(3, depths);
+            let (mut mv, mut ponder) = (ptr::null_mut(), ptr::null_mut());
+            assert_eq!(
+                UsiStatus::Ok,
+                usi_command_bestmove(bestmove, &mut mv, &mut ponder)
+            );
+            assert_eq!("7g7f", take(mv));
+            assert_eq!("3c3d", take(ponder));
+            usi_command_free(bestmove);
+            usi_engine_free(engine);
+        }
+    }
+}
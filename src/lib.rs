@@ -6,6 +6,7 @@
 //! # Data types representing commands defined in USI protocol.
 //!
 //! `GuiCommand` and `EngineCommand` represents input/output commands defined in the protocol.
+//! They can also be translated from and to related protocols such as UCI through `Dialect`.
 //!
 //! # Examples
 //!
@@ -34,9 +35,13 @@
 //! `UsiEngineHandler` can be used to spawn the USI engine process.
 //! You can send `GuiCommand`s and receive `EngineCommand`.
 //!
+//! `Engine` is a client which performs the `usi`/`isready` handshake when spawned,
+//! so it is ready to start a new game once created.
+//!
 //! # Examples
-//! ```no_run
-//! use usi::{BestMoveParams, Error, EngineCommand, GuiCommand, UsiEngineHandler};
+#![cfg_attr(feature = "std", doc = "```no_run")]
+#![cfg_attr(not(feature = "std"), doc = "```ignore")]
+//! use usi::{BestMoveParams, Error, EngineCommand, GuiCommand, SetOptionParams, UsiEngineHandler};
 //!
 //! let mut handler = UsiEngineHandler::spawn("/path/to/usi_engine", "/path/to/working_dir").unwrap();
 //!
@@ -45,7 +50,7 @@
 //! assert_eq!("engine name", info.name());
 //!
 //! // Set options.
-//! handler.send_command(&GuiCommand::SetOption("USI_Ponder".to_string(), Some("true".to_string()))).unwrap();
+//! handler.send_command(&GuiCommand::SetOption(SetOptionParams::new("USI_Ponder", "true"))).unwrap();
 //! handler.prepare().unwrap();
 //! handler.send_command(&GuiCommand::UsiNewGame).unwrap();
 //!
@@ -66,10 +71,65 @@
 //! }).unwrap();
 //! handler.send_command(&GuiCommand::Usi).unwrap();
 //! ```
+//!
+//! # Implementing a USI engine
+//!
+//! `EngineServer` runs a `UsiEngine` implementation over stdin and stdout,
+//! dispatching parsed `GuiCommand`s to it.
+//!
+//! # Features
+//!
+//! * `std` (enabled by default): engine process handling.
+//!   Without it, only the protocol types and parsers are available and the crate builds with `alloc` only.
+//! * `tokio`: `AsyncEngine`, an asynchronous engine client running on tokio.
+//! * `async-std`, `smol`: `AsyncEngine` running on async-std or smol, spawned by `AsyncEngine::spawn_on`.
+//! * `tracing`: logs every line sent to and received from engines as `tracing` events
+//!   with the `usi` target, at the level set by `Engine::set_trace_level`.
+//! * `serde`: `EngineConfig`, which saves and loads engine option settings as JSON,
+//!   `EngineOptions::to_json_schema` to render settings forms, and JSON export of analysis data
+//!   such as `ScoreHistory` and `AnalysisReport`, and transcripts as JSON Lines
+//!   with `TranscriptFormat::Jsonl`.
+//! * `ffi`: the `ffi` module with a C ABI for parsing commands and driving engines,
+//!   declared in `include/usi.h`.
+//!
+//! # WebAssembly
+//!
+//! The crate builds for `wasm32-unknown-unknown`, where engine processes, sockets
+//! and `EngineServer` are unavailable. The protocol types and parsers, the board,
+//! CSA and record formats, and the analysis types are the same as on native targets,
+//! so web GUIs can exchange them with a native backend, e.g. as JSON with `serde`.
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+mod analysis;
+mod board;
+#[cfg(all(feature = "std", not(target_family = "wasm")))]
+mod client;
+mod clock;
+mod csa;
 mod error;
+#[cfg(all(feature = "ffi", not(target_family = "wasm")))]
+pub mod ffi;
+#[cfg(all(feature = "std", not(target_family = "wasm")))]
 mod process;
 mod protocol;
+mod record;
+#[cfg(all(feature = "std", not(target_family = "wasm")))]
+mod server;
+#[cfg(all(test, feature = "std", not(target_family = "wasm")))]
+mod test_util;
 
+pub use self::analysis::*;
+pub use self::board::*;
+#[cfg(all(feature = "std", not(target_family = "wasm")))]
+pub use self::client::*;
+pub use self::clock::*;
+pub use self::csa::*;
 pub use self::error::*;
+#[cfg(all(feature = "std", not(target_family = "wasm")))]
 pub use self::process::*;
 pub use self::protocol::*;
+pub use self::record::*;
+#[cfg(all(feature = "std", not(target_family = "wasm")))]
+pub use self::server::*;
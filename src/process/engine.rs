@@ -14,6 +14,7 @@ use crate::protocol::*;
 #[derive(Clone, Debug, Default)]
 pub struct EngineInfo {
     name: String,
+    author: String,
     options: HashMap<String, String>,
 }
 
@@ -23,17 +24,58 @@ impl EngineInfo {
         &self.name
     }
 
+    /// Returns an engine author.
+    pub fn author(&self) -> &str {
+        &self.author
+    }
+
     /// Returns available engine options.
     pub fn options(&self) -> &HashMap<String, String> {
         &self.options
     }
+
+    /// Records `id` and `option` commands sent in response to `usi` command.
+    pub(crate) fn update(&mut self, command: &EngineCommand) {
+        match command {
+            EngineCommand::Id(IdParams::Name(name)) => {
+                self.name = name.to_string();
+            }
+            EngineCommand::Id(IdParams::Author(author)) => {
+                self.author = author.to_string();
+            }
+            EngineCommand::Option(OptionParams {
+                ref name,
+                ref value,
+            }) => {
+                self.options.insert(
+                    name.to_string(),
+                    match value {
+                        OptionKind::Check { default: Some(f) } => {
+                            if *f { "true" } else { "false" }.to_string()
+                        }
+                        OptionKind::Spin {
+                            default: Some(n), ..
+                        } => n.to_string(),
+                        OptionKind::Combo {
+                            default: Some(s), ..
+                        } => s.to_string(),
+                        OptionKind::Button { default: Some(s) } => s.to_string(),
+                        OptionKind::String { default: Some(s) } => s.to_string(),
+                        OptionKind::Filename { default: Some(s) } => s.to_string(),
+                        _ => String::new(),
+                    },
+                );
+            }
+            _ => {}
+        }
+    }
 }
 
 /// `UsiEngineHandler` provides a type-safe interface to the USI engine process.
 ///
 /// # Examples
 /// ```no_run
-/// use usi::{BestMoveParams, Error, EngineCommand, GuiCommand, UsiEngineHandler};
+/// use usi::{BestMoveParams, Error, EngineCommand, GuiCommand, SetOptionParams, UsiEngineHandler};
 ///
 /// let mut handler = UsiEngineHandler::spawn("/path/to/usi_engine", "/path/to/working_dir").unwrap();
 ///
@@ -42,7 +84,7 @@ impl EngineInfo {
 /// assert_eq!("engine name", info.name());
 ///
 /// // Set options and prepare the engine.
-/// handler.send_command(&GuiCommand::SetOption("USI_Ponder".to_string(), Some("true".to_string()))).unwrap();
+/// handler.send_command(&GuiCommand::SetOption(SetOptionParams::new("USI_Ponder", "true"))).unwrap();
 /// handler.prepare().unwrap();
 /// handler.send_command(&GuiCommand::UsiNewGame).unwrap();
 ///
@@ -72,7 +114,10 @@ pub struct UsiEngineHandler {
 
 impl Drop for UsiEngineHandler {
     fn drop(&mut self) {
-        self.kill().unwrap();
+        // The engine may have exited already, in which case sending `quit` fails.
+        let _ = self.writer.send(&GuiCommand::Quit);
+        let _ = self.process.kill();
+        let _ = self.process.wait();
     }
 }
 impl UsiEngineHandler {
@@ -113,34 +158,9 @@ impl UsiEngineHandler {
         loop {
             let output = reader.next_command()?;
             match output.response() {
-                Some(EngineCommand::Id(IdParams::Name(name))) => {
-                    info.name = name.to_string();
-                }
-                Some(EngineCommand::Option(OptionParams {
-                    ref name,
-                    ref value,
-                })) => {
-                    info.options.insert(
-                        name.to_string(),
-                        match value {
-                            OptionKind::Check { default: Some(f) } => {
-                                if *f { "true" } else { "false" }.to_string()
-                            }
-                            OptionKind::Spin {
-                                default: Some(n), ..
-                            } => n.to_string(),
-                            OptionKind::Combo {
-                                default: Some(s), ..
-                            } => s.to_string(),
-                            OptionKind::Button { default: Some(s) } => s.to_string(),
-                            OptionKind::String { default: Some(s) } => s.to_string(),
-                            OptionKind::Filename { default: Some(s) } => s.to_string(),
-                            _ => String::new(),
-                        },
-                    );
-                }
                 Some(EngineCommand::UsiOk) => break,
-                _ => {}
+                Some(cmd) => info.update(cmd),
+                None => {}
             }
         }
 
@@ -176,6 +196,7 @@ impl UsiEngineHandler {
     pub fn kill(&mut self) -> Result<(), Error> {
         self.writer.send(&GuiCommand::Quit)?;
         self.process.kill()?;
+        self.process.wait()?;
         Ok(())
     }
 
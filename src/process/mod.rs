@@ -4,4 +4,6 @@ mod writer;
 
 pub use self::engine::{EngineInfo, UsiEngineHandler};
 pub use self::reader::{EngineCommandReader, EngineOutput};
-pub use self::writer::GuiCommandWriter;
+pub use self::writer::{
+    write_command, write_engine_command, CommandWriter, FlushPolicy, GuiCommandWriter,
+};
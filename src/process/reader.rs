@@ -5,7 +5,7 @@ use crate::error::Error;
 use crate::EngineCommand;
 
 /// A struct to represent each output produced from a USI engine process.
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub struct EngineOutput {
     response: Option<EngineCommand>,
     raw_str: String,
@@ -13,6 +13,14 @@ pub struct EngineOutput {
 }
 
 impl EngineOutput {
+    pub(crate) fn new(response: Option<EngineCommand>, raw_str: String) -> Self {
+        EngineOutput {
+            response,
+            raw_str,
+            timestamp: Instant::now(),
+        }
+    }
+
     pub fn response(&self) -> &Option<EngineCommand> {
         &self.response
     }
@@ -55,11 +63,7 @@ impl<R: BufRead> EngineCommandReader<R> {
         loop {
             let bytes_read = self.receive.read_line(&mut buf)?;
             if bytes_read == 0 {
-                return Ok(EngineOutput {
-                    response: None,
-                    raw_str: buf,
-                    timestamp: Instant::now(),
-                });
+                return Ok(EngineOutput::new(None, buf));
             }
 
             if !buf.trim().is_empty() {
@@ -69,11 +73,7 @@ impl<R: BufRead> EngineCommandReader<R> {
         }
 
         let res = EngineCommand::parse(&buf)?;
-        Ok(EngineOutput {
-            response: Some(res),
-            raw_str: buf,
-            timestamp: Instant::now(),
-        })
+        Ok(EngineOutput::new(Some(res), buf))
     }
 }
 
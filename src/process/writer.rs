@@ -1,45 +1,181 @@
-use std::io::Write;
+use std::io::{BufWriter, Write};
 
 use crate::error::Error;
-use crate::GuiCommand;
+use crate::{CheckmateParams, EngineCommand, GuiCommand};
+
+/// Writes a `GuiCommand` followed by a newline directly into `writer`.
+///
+/// The command is formatted into the writer without an intermediate `String`.
+/// `writer` is not flushed; call `flush()` once the pending commands should reach the engine.
+///
+/// # Examples
+///
+/// ```
+/// use std::io::{BufWriter, Write};
+/// use usi::{write_command, GuiCommand};
+///
+/// let mut buf: Vec<u8> = Vec::new();
+/// write_command(&mut buf, &GuiCommand::Usi).unwrap();
+/// write_command(&mut buf, &GuiCommand::IsReady).unwrap();
+/// buf.flush().unwrap();
+/// assert_eq!("usi\nisready\n", std::str::from_utf8(&buf).unwrap());
+/// ```
+pub fn write_command<W: Write + ?Sized>(writer: &mut W, command: &GuiCommand) -> Result<(), Error> {
+    writeln!(writer, "{command}")?;
+    Ok(())
+}
+
+/// Writes an `EngineCommand` followed by a newline directly into `writer`.
+///
+/// `EngineCommand::Unknown` doesn't retain the original text, so nothing is written for it.
+/// Returns `Error::IllegalSyntax` for `checkmate` without moves, which no parser accepts.
+/// `writer` is not flushed; call `flush()` once the pending commands should reach the GUI.
+pub fn write_engine_command<W: Write + ?Sized>(
+    writer: &mut W,
+    command: &EngineCommand,
+) -> Result<(), Error> {
+    match *command {
+        EngineCommand::Unknown => return Ok(()),
+        EngineCommand::Checkmate(CheckmateParams::Mate(ref moves)) if moves.is_empty() => {
+            return Err(Error::IllegalSyntax);
+        }
+        _ => {}
+    }
+    writeln!(writer, "{command}")?;
+    Ok(())
+}
 
 /// `GuiCommandWriter<W>` converts `GuiCommand`s and writes strings into the writer.
 ///
+/// Each command is buffered and flushed as a whole, so a pipe receives one write per command.
+///
 /// # Examples
 ///
 /// ```
-/// use usi::{GuiCommand, GuiCommandWriter};
+/// use usi::{GuiCommand, GuiCommandWriter, SetOptionParams};
 ///
 /// let mut buf: Vec<u8> = Vec::new();
 /// let mut writer = GuiCommandWriter::new(&mut buf);
 /// writer.send(&GuiCommand::Usi).unwrap();
 /// writer.send(&GuiCommand::IsReady).unwrap();
-/// writer.send(&GuiCommand::SetOption("key".to_string(), Some("val".to_string()))).unwrap();
+/// writer.send(&GuiCommand::SetOption(SetOptionParams::new("key", "val"))).unwrap();
+/// drop(writer);
 /// assert_eq!("usi\nisready\nsetoption name key value val\n", std::str::from_utf8(&buf).unwrap());
 ///```
 ///
 #[derive(Debug)]
 pub struct GuiCommandWriter<W: Write> {
-    writer: W,
+    writer: BufWriter<W>,
 }
 
 impl<W: Write> GuiCommandWriter<W> {
     pub fn new(writer: W) -> Self {
-        GuiCommandWriter { writer }
+        GuiCommandWriter {
+            writer: BufWriter::new(writer),
+        }
     }
 
     pub fn send(&mut self, command: &GuiCommand) -> Result<(), Error> {
-        let s = format!("{command}\n");
-        self.writer.write_all(s.as_bytes())?;
+        write_command(&mut self.writer, command)?;
         self.writer.flush()?;
 
         Ok(())
     }
 }
 
+/// Determines when `CommandWriter` flushes queued commands.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum FlushPolicy {
+    /// Flushes as soon as a command other than `setoption`, `position` or `usinewgame` is queued.
+    #[default]
+    Interactive,
+    /// Flushes only when `flush()` is called or the writer is dropped.
+    Manual,
+}
+
+impl FlushPolicy {
+    fn should_flush(&self, command: &GuiCommand) -> bool {
+        match *self {
+            FlushPolicy::Interactive => !matches!(
+                *command,
+                GuiCommand::SetOption(..) | GuiCommand::Position(_) | GuiCommand::UsiNewGame
+            ),
+            FlushPolicy::Manual => false,
+        }
+    }
+}
+
+/// `CommandWriter<W>` queues `GuiCommand`s and writes them into the writer in a batch.
+///
+/// With the default `FlushPolicy::Interactive`, commands like `setoption` are buffered
+/// until a command the engine reacts to, such as `isready`, `go` or `stop`, is sent.
+///
+/// # Examples
+///
+/// ```
+/// use usi::{CommandWriter, GuiCommand, SetOptionParams};
+///
+/// let mut buf: Vec<u8> = Vec::new();
+/// {
+///     let mut writer = CommandWriter::new(&mut buf);
+///     writer.send(&GuiCommand::SetOption(SetOptionParams::new("USI_Hash", "256"))).unwrap();
+///     writer.send(&GuiCommand::SetOption(SetOptionParams::new("USI_Ponder", "true"))).unwrap();
+///     writer.send(&GuiCommand::IsReady).unwrap();
+/// }
+/// assert_eq!(
+///     "setoption name USI_Hash value 256\nsetoption name USI_Ponder value true\nisready\n",
+///     std::str::from_utf8(&buf).unwrap()
+/// );
+///```
+///
+#[derive(Debug)]
+pub struct CommandWriter<W: Write> {
+    writer: BufWriter<W>,
+    policy: FlushPolicy,
+}
+
+impl<W: Write> CommandWriter<W> {
+    pub fn new(writer: W) -> Self {
+        CommandWriter::with_policy(writer, FlushPolicy::default())
+    }
+
+    pub fn with_policy(writer: W, policy: FlushPolicy) -> Self {
+        CommandWriter {
+            writer: BufWriter::new(writer),
+            policy,
+        }
+    }
+
+    /// Queues a command, flushing the queue if required by the flush policy.
+    pub fn send(&mut self, command: &GuiCommand) -> Result<(), Error> {
+        write_command(&mut self.writer, command)?;
+        if self.policy.should_flush(command) {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Writes all queued commands into the underlying writer.
+    pub fn flush(&mut self) -> Result<(), Error> {
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// Returns the current flush policy.
+    pub fn policy(&self) -> FlushPolicy {
+        self.policy
+    }
+
+    /// Changes the flush policy.
+    pub fn set_policy(&mut self, policy: FlushPolicy) {
+        self.policy = policy;
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::SetOptionParams;
 
     #[test]
     fn it_works() {
@@ -52,14 +188,70 @@ mod tests {
             .send(&GuiCommand::IsReady)
             .expect("failed to write to the buffer");
         writer
-            .send(&GuiCommand::SetOption(
-                "key".to_string(),
-                Some("val".to_string()),
-            ))
+            .send(&GuiCommand::SetOption(SetOptionParams::new("key", "val")))
             .expect("failed to write to the buffer");
+        drop(writer);
         assert_eq!(
             "usi\nisready\nsetoption name key value val\n",
             std::str::from_utf8(&buf).unwrap()
         );
     }
+
+    #[test]
+    fn batched() {
+        let mut buf: Vec<u8> = Vec::new();
+        let mut writer = CommandWriter::new(&mut buf);
+        writer
+            .send(&GuiCommand::SetOption(SetOptionParams::new("a", "1")))
+            .unwrap();
+        writer
+            .send(&GuiCommand::SetOption(SetOptionParams::button("b")))
+            .unwrap();
+        assert!(writer.writer.get_ref().is_empty());
+
+        writer.send(&GuiCommand::Stop).unwrap();
+        assert_eq!(
+            "setoption name a value 1\nsetoption name b\nstop\n",
+            std::str::from_utf8(writer.writer.get_ref()).unwrap()
+        );
+
+        writer.set_policy(FlushPolicy::Manual);
+        writer.send(&GuiCommand::IsReady).unwrap();
+        assert_eq!(
+            "setoption name a value 1\nsetoption name b\nstop\n",
+            std::str::from_utf8(writer.writer.get_ref()).unwrap()
+        );
+        writer.flush().unwrap();
+        drop(writer);
+        assert_eq!(
+            "setoption name a value 1\nsetoption name b\nstop\nisready\n",
+            std::str::from_utf8(&buf).unwrap()
+        );
+    }
+
+    #[test]
+    fn write_engine_commands() {
+        let mut buf: Vec<u8> = Vec::new();
+        write_engine_command(&mut buf, &EngineCommand::UsiOk).unwrap();
+        write_engine_command(
+            &mut buf,
+            &EngineCommand::BestMove(crate::BestMoveParams::MakeMove("7g7f".to_string(), None)),
+        )
+        .unwrap();
+        assert_eq!("usiok\nbestmove 7g7f\n", std::str::from_utf8(&buf).unwrap());
+    }
+
+    #[test]
+    fn write_unserializable_engine_commands() {
+        let mut buf: Vec<u8> = Vec::new();
+        write_engine_command(&mut buf, &EngineCommand::Unknown).unwrap();
+        assert!(matches!(
+            write_engine_command(
+                &mut buf,
+                &EngineCommand::Checkmate(CheckmateParams::Mate(vec![]))
+            ),
+            Err(Error::IllegalSyntax)
+        ));
+        assert!(buf.is_empty());
+    }
 }
@@ -0,0 +1,112 @@
+use core::fmt;
+use core::time::Duration;
+
+/// Represents the result of the nonstandard `bench` command.
+///
+/// Engines report the result in free text, e.g.
+///
+/// ```text
+/// Total time (ms) : 5000
+/// Nodes searched  : 10000000
+/// Nodes/second    : 2000000
+/// ```
+///
+/// which is also the format written by `EngineServer`.
+/// `info` commands with `nodes`, `nps` and `time` are recognized as well.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BenchReport {
+    /// The total number of nodes searched.
+    pub nodes: Option<u64>,
+    /// The number of nodes searched per second.
+    pub nps: Option<u64>,
+    /// The total time of the benchmark.
+    pub time: Option<Duration>,
+}
+
+impl BenchReport {
+    /// Updates the report with a line of the engine output.
+    pub fn push_line(&mut self, line: &str) {
+        let mut tokens = line.split_whitespace();
+        if tokens.next() == Some("info") {
+            while let Some(token) = tokens.next() {
+                let value = tokens.clone().next().and_then(|v| v.parse().ok());
+                match token {
+                    "nodes" => self.nodes = value.or(self.nodes),
+                    "nps" => self.nps = value.or(self.nps),
+                    "time" => self.time = value.map(Duration::from_millis).or(self.time),
+                    // The rest of the line is text.
+                    "string" | "pv" => break,
+                    _ => {}
+                }
+            }
+            return;
+        }
+
+        let Some((key, value)) = line.split_once(':') else {
+            return;
+        };
+        let key = key.trim().to_ascii_lowercase();
+        let Ok(value) = value.trim().parse::<u64>() else {
+            return;
+        };
+        if key.starts_with("nodes") && (key.ends_with("second") || key.ends_with("nps")) {
+            self.nps = Some(value);
+        } else if key.starts_with("nodes") {
+            self.nodes = Some(value);
+        } else if key.starts_with("total time") {
+            self.time = Some(Duration::from_millis(value));
+        }
+    }
+}
+
+impl fmt::Display for BenchReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut separator = "";
+        if let Some(time) = self.time {
+            write!(f, "Total time (ms) : {}", time.as_millis())?;
+            separator = "\n";
+        }
+        if let Some(nodes) = self.nodes {
+            write!(f, "{separator}Nodes searched  : {}", nodes)?;
+            separator = "\n";
+        }
+        if let Some(nps) = self.nps {
+            write!(f, "{separator}Nodes/second    : {}", nps)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    #[test]
+    fn push_line() {
+        let mut report = BenchReport::default();
+        for line in [
+            "info depth 10 nodes 100 nps 50 time 2 pv 7g7f",
+            "===========================",
+            "Total time (ms) : 5000",
+            "Nodes searched  : 10000000",
+            "Nodes_searched/second : 2000000",
+            "info string nodes 1",
+        ] {
+            report.push_line(line);
+        }
+
+        let expected = BenchReport {
+            nodes: Some(10000000),
+            nps: Some(2000000),
+            time: Some(Duration::from_secs(5)),
+        };
+        assert_eq!(expected, report);
+
+        let mut parsed = BenchReport::default();
+        for line in expected.to_string().lines() {
+            parsed.push_line(line);
+        }
+        assert_eq!(expected, parsed);
+    }
+}
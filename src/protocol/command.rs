@@ -1,4 +1,7 @@
-use std::time::Duration;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+use core::time::Duration;
 
 use super::parser::EngineCommandParser;
 use crate::error::Error;
@@ -29,6 +32,155 @@ pub enum OptionKind {
     },
 }
 
+impl fmt::Display for OptionKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            OptionKind::Check { default } => {
+                write!(f, "check")?;
+                if let Some(v) = default {
+                    write!(f, " default {v}")?;
+                }
+            }
+            OptionKind::Spin { default, min, max } => {
+                write!(f, "spin")?;
+                if let Some(v) = default {
+                    write!(f, " default {v}")?;
+                }
+                if let Some(v) = min {
+                    write!(f, " min {v}")?;
+                }
+                if let Some(v) = max {
+                    write!(f, " max {v}")?;
+                }
+            }
+            OptionKind::Combo {
+                ref default,
+                ref vars,
+            } => {
+                write!(f, "combo")?;
+                if let Some(v) = default {
+                    write!(f, " default {}", FormatDefault(v))?;
+                }
+                for v in vars {
+                    write!(f, " var {v}")?;
+                }
+            }
+            OptionKind::Button { ref default } => {
+                write!(f, "button")?;
+                if let Some(v) = default {
+                    write!(f, " default {}", FormatDefault(v))?;
+                }
+            }
+            OptionKind::String { ref default } => {
+                write!(f, "string")?;
+                if let Some(v) = default {
+                    write!(f, " default {}", FormatDefault(v))?;
+                }
+            }
+            OptionKind::Filename { ref default } => {
+                write!(f, "filename")?;
+                if let Some(v) = default {
+                    write!(f, " default {}", FormatDefault(v))?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl OptionKind {
+    /// Checks that `value` has the type of the option and fits its range or choices.
+    ///
+    /// Returns `Error::OptionOutOfRange` for spin values outside the range, or
+    /// `Error::InvalidOptionValue` otherwise, without the option name.
+    /// `OptionParams::validate` includes the name.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use usi::{OptionKind, OptionValue};
+    ///
+    /// let kind = OptionKind::Spin { default: Some(1), min: Some(1), max: Some(8) };
+    /// assert!(kind.validate(&OptionValue::Int(4)).is_ok());
+    /// assert!(kind.validate(&OptionValue::Int(16)).is_err());
+    /// assert!(kind.validate(&OptionValue::Bool(true)).is_err());
+    /// ```
+    pub fn validate(&self, value: &OptionValue) -> Result<(), Error> {
+        self.validate_named("", value)
+    }
+
+    /// Parses `raw`, the value sent by `setoption`, as a value of the option type
+    /// without checking the range or choices. Returns `None` if it doesn't have the type.
+    ///
+    /// Check options take `true` or `false`, and buttons take no value.
+    pub fn parse_value(&self, raw: &str) -> Option<OptionValue> {
+        Some(match self {
+            OptionKind::Check { .. } => OptionValue::Bool(raw.parse().ok()?),
+            OptionKind::Spin { .. } => OptionValue::Int(raw.trim().parse().ok()?),
+            OptionKind::Combo { .. } => OptionValue::ComboChoice(raw.into()),
+            OptionKind::String { .. } | OptionKind::Filename { .. } => {
+                OptionValue::String(raw.into())
+            }
+            OptionKind::Button { .. } if raw.is_empty() => OptionValue::Button,
+            OptionKind::Button { .. } => return None,
+        })
+    }
+
+    /// Parses `raw` like `OptionKind::parse_value` and validates it, reporting errors
+    /// with `name`.
+    #[cfg(feature = "std")]
+    pub(crate) fn parse_valid(&self, name: &str, raw: &str) -> Result<OptionValue, Error> {
+        let value = self
+            .parse_value(raw)
+            .ok_or_else(|| Error::InvalidOptionValue {
+                name: name.into(),
+                value: raw.into(),
+            })?;
+        self.validate_named(name, &value)?;
+        Ok(value)
+    }
+
+    fn validate_named(&self, name: &str, value: &OptionValue) -> Result<(), Error> {
+        let valid = match (self, value) {
+            (OptionKind::Check { .. }, OptionValue::Bool(_)) => true,
+            (OptionKind::Spin { min, max, .. }, OptionValue::Int(n)) => {
+                return spin_in_range(name, *min, *max, *n);
+            }
+            (OptionKind::Combo { vars, .. }, OptionValue::ComboChoice(v)) => vars.contains(v),
+            (OptionKind::String { .. } | OptionKind::Filename { .. }, OptionValue::String(_)) => {
+                true
+            }
+            (OptionKind::Button { .. }, OptionValue::Button) => true,
+            _ => false,
+        };
+        if !valid {
+            return Err(Error::InvalidOptionValue {
+                name: name.into(),
+                value: value.to_string(),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Returns `Error::OptionOutOfRange` unless `given` is within `min` and `max`.
+pub(crate) fn spin_in_range(
+    name: &str,
+    min: Option<i32>,
+    max: Option<i32>,
+    given: i32,
+) -> Result<(), Error> {
+    if min.is_none_or(|min| min <= given) && max.is_none_or(|max| given <= max) {
+        return Ok(());
+    }
+    Err(Error::OptionOutOfRange {
+        name: name.into(),
+        min: min.unwrap_or(i32::MIN),
+        max: max.unwrap_or(i32::MAX),
+        given,
+    })
+}
+
 /// Represents parameters of "option" command.
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct OptionParams {
@@ -36,8 +188,65 @@ pub struct OptionParams {
     pub value: OptionKind,
 }
 
-/// Represents a kind of "score" parameter value in "info" command.
+impl OptionParams {
+    /// Checks `value` against the option like `OptionKind::validate`.
+    pub fn validate(&self, value: &OptionValue) -> Result<(), Error> {
+        self.value.validate_named(&self.name, value)
+    }
+
+    /// Checks that the declared default fits the range or choices of the option.
+    ///
+    /// Some engines declare a combo default which is none of the `var` entries,
+    /// or a spin default outside the range. Options without a default are valid.
+    pub fn validate_default(&self) -> Result<(), Error> {
+        let default = match &self.value {
+            OptionKind::Spin {
+                default: Some(v), ..
+            } => OptionValue::Int(*v),
+            OptionKind::Combo {
+                default: Some(v), ..
+            } => OptionValue::ComboChoice(v.clone()),
+            _ => return Ok(()),
+        };
+        self.validate(&default)
+    }
+}
+
+impl fmt::Display for OptionParams {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "name {} type {}", self.name, self.value)
+    }
+}
+
+/// Represents a typed value of an option.
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum OptionValue {
+    /// A value of a check option.
+    Bool(bool),
+    /// A value of a spin option.
+    Int(i32),
+    /// A value of a string or filename option.
+    String(String),
+    /// A choice of a combo option.
+    ComboChoice(String),
+    /// A press of a button option, which has no value.
+    Button,
+}
+
+impl fmt::Display for OptionValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            OptionValue::Bool(v) => write!(f, "{v}"),
+            OptionValue::Int(v) => write!(f, "{v}"),
+            OptionValue::String(ref v) | OptionValue::ComboChoice(ref v) => write!(f, "{v}"),
+            OptionValue::Button => Ok(()),
+        }
+    }
+}
+
+/// Represents a kind of "score" parameter value in "info" command.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ScoreKind {
     CpExact,
     CpLowerbound,
@@ -55,14 +264,81 @@ pub enum InfoParams {
     Depth(i32, Option<i32>),
     HashFull(i32),
     MultiPv(i32),
-    Nodes(i32),
-    Nps(i32),
+    Nodes(u64),
+    Nps(u64),
     Pv(Vec<String>),
     Score(i32, ScoreKind),
     Text(String),
     Time(Duration),
 }
 
+impl fmt::Display for InfoParams {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            InfoParams::CurrMove(ref m) => write!(f, "currmove {m}"),
+            InfoParams::Depth(d, None) => write!(f, "depth {d}"),
+            InfoParams::Depth(d, Some(sd)) => write!(f, "depth {d} seldepth {sd}"),
+            InfoParams::HashFull(n) => write!(f, "hashfull {n}"),
+            InfoParams::MultiPv(n) => write!(f, "multipv {n}"),
+            InfoParams::Nodes(n) => write!(f, "nodes {n}"),
+            InfoParams::Nps(n) => write!(f, "nps {n}"),
+            InfoParams::Pv(ref moves) => {
+                write!(f, "pv")?;
+                for m in moves {
+                    write!(f, " {m}")?;
+                }
+                Ok(())
+            }
+            InfoParams::Score(v, ref kind) => match *kind {
+                ScoreKind::CpExact => write!(f, "score cp {v}"),
+                ScoreKind::CpLowerbound => write!(f, "score cp {v} lowerbound"),
+                ScoreKind::CpUpperbound => write!(f, "score cp {v} upperbound"),
+                ScoreKind::MateExact => write!(f, "score mate {v}"),
+                ScoreKind::MateSignOnly => {
+                    write!(f, "score mate {}", if v < 0 { "-" } else { "+" })
+                }
+                ScoreKind::MateLowerbound => write!(f, "score mate {v} lowerbound"),
+                ScoreKind::MateUpperbound => write!(f, "score mate {v} upperbound"),
+            },
+            InfoParams::Text(ref s) => write!(f, "string {s}"),
+            InfoParams::Time(t) => write!(f, "time {}", t.as_millis()),
+        }
+    }
+}
+
+/// Represents a kind of `InfoParams`, used to filter `info` commands.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum InfoKind {
+    CurrMove,
+    Depth,
+    HashFull,
+    MultiPv,
+    Nodes,
+    Nps,
+    Pv,
+    Score,
+    Text,
+    Time,
+}
+
+impl InfoParams {
+    /// Returns the kind of the parameter.
+    pub fn kind(&self) -> InfoKind {
+        match self {
+            InfoParams::CurrMove(_) => InfoKind::CurrMove,
+            InfoParams::Depth(..) => InfoKind::Depth,
+            InfoParams::HashFull(_) => InfoKind::HashFull,
+            InfoParams::MultiPv(_) => InfoKind::MultiPv,
+            InfoParams::Nodes(_) => InfoKind::Nodes,
+            InfoParams::Nps(_) => InfoKind::Nps,
+            InfoParams::Pv(_) => InfoKind::Pv,
+            InfoParams::Score(..) => InfoKind::Score,
+            InfoParams::Text(_) => InfoKind::Text,
+            InfoParams::Time(_) => InfoKind::Time,
+        }
+    }
+}
+
 /// Represents parameters of "checkmate" command.
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum CheckmateParams {
@@ -115,6 +391,47 @@ pub enum EngineCommand {
     Unknown,
 }
 
+impl fmt::Display for EngineCommand {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            EngineCommand::Id(IdParams::Name(ref s)) => write!(f, "id name {s}"),
+            EngineCommand::Id(IdParams::Author(ref s)) => write!(f, "id author {s}"),
+            EngineCommand::BestMove(BestMoveParams::MakeMove(ref m, None)) => {
+                write!(f, "bestmove {m}")
+            }
+            EngineCommand::BestMove(BestMoveParams::MakeMove(ref m, Some(ref pm))) => {
+                write!(f, "bestmove {m} ponder {pm}")
+            }
+            EngineCommand::BestMove(BestMoveParams::Resign) => write!(f, "bestmove resign"),
+            EngineCommand::BestMove(BestMoveParams::Win) => write!(f, "bestmove win"),
+            EngineCommand::Checkmate(CheckmateParams::Mate(ref moves)) => {
+                write!(f, "checkmate")?;
+                for m in moves {
+                    write!(f, " {m}")?;
+                }
+                Ok(())
+            }
+            EngineCommand::Checkmate(CheckmateParams::NoMate) => write!(f, "checkmate nomate"),
+            EngineCommand::Checkmate(CheckmateParams::NotImplemented) => {
+                write!(f, "checkmate notimplemented")
+            }
+            EngineCommand::Checkmate(CheckmateParams::Timeout) => write!(f, "checkmate timeout"),
+            EngineCommand::Info(ref entries) => {
+                write!(f, "info")?;
+                for e in entries {
+                    write!(f, " {e}")?;
+                }
+                Ok(())
+            }
+            EngineCommand::Option(ref opt) => write!(f, "option {opt}"),
+            EngineCommand::ReadyOk => write!(f, "readyok"),
+            EngineCommand::UsiOk => write!(f, "usiok"),
+            // The original text of unknown commands is not retained.
+            EngineCommand::Unknown => Ok(()),
+        }
+    }
+}
+
 impl EngineCommand {
     /// Parses a USI command string into a new instance of `EngineCommand`.
     pub fn parse(cmd: &str) -> Result<EngineCommand, Error> {
@@ -123,9 +440,24 @@ impl EngineCommand {
     }
 }
 
+/// Writes `<empty>` in place of an empty default value.
+struct FormatDefault<'a>(&'a str);
+
+impl fmt::Display for FormatDefault<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.0.is_empty() {
+            write!(f, "<empty>")
+        } else {
+            write!(f, "{}", self.0)
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use alloc::string::ToString;
+    use alloc::vec;
 
     #[test]
     fn parse() {
@@ -154,12 +486,16 @@ mod tests {
             "option name UseBook type check default true",
             "option name Selectivity type spin default 2 min 0 max 4",
             "option name Style type combo default Normal var Solid var Normal var Risky",
+            "option name Style type combo var Solid var Normal default Normal",
             "option name ResetLearning type button",
             "option name BookFile type string default public.bin",
             "option name LearningFile type filename default <empty>",
             "readyok",
             "usiok",
             "unknown command",
+            "info depth 20 seldepth 28 multipv 1 score cp 35 wdl 120 800 80 nodes 1000 \
+             tbhits 0 pv e2e4 e7e5",
+            "info depth 20 currmove e2e4 currmovenumber 1",
         ];
 
         let ng_cases = [
@@ -170,7 +506,6 @@ mod tests {
             "info depth 1 seldepth foo",
             "info multipv foo",
             "info score foo 1",
-            "info foo bar",
             "option foo bar baz",
             "option name foo bar",
         ];
@@ -183,4 +518,188 @@ mod tests {
             assert!(EngineCommand::parse(c).is_err(), "failed at #{i}");
         }
     }
+
+    #[test]
+    fn to_string() {
+        let cases = [
+            "id name Lesserkai",
+            "id author Program Writer",
+            "bestmove 7g7f",
+            "bestmove 8h2b+ ponder 3a2b",
+            "bestmove resign",
+            "bestmove win",
+            "checkmate nomate",
+            "checkmate notimplemented",
+            "checkmate timeout",
+            "checkmate G*8f 9f9g 8f8g 9g9h 8g8h",
+            "info time 1141 depth 3 seldepth 5 nodes 135125 score cp -1521 pv 3a3b L*4h 4c4d",
+            "info nodes 120000 nps 116391 multipv 1 currmove 1g1f hashfull 104",
+            "info nodes 12000000000 nps 3000000000",
+            "info string 7g7f (70%)",
+            "info score cp 100 lowerbound",
+            "info score mate +",
+            "info score mate -",
+            "info score mate 5 upperbound",
+            "option name UseBook type check default true",
+            "option name Selectivity type spin default 2 min 0 max 4",
+            "option name ResetLearning type button",
+            "option name BookFile type string default public.bin",
+            "option name LearningFile type filename default <empty>",
+            "readyok",
+            "usiok",
+        ];
+
+        for (i, c) in cases.iter().enumerate() {
+            assert_eq!(
+                *c,
+                EngineCommand::parse(c).unwrap().to_string(),
+                "failed at #{i}"
+            );
+        }
+    }
+
+    #[test]
+    fn parse_checkmate() {
+        let cases = [
+            ("checkmate nomate", CheckmateParams::NoMate),
+            ("checkmate notimplemented", CheckmateParams::NotImplemented),
+            ("checkmate timeout", CheckmateParams::Timeout),
+        ];
+        for (line, params) in cases {
+            assert_eq!(
+                EngineCommand::Checkmate(params),
+                EngineCommand::parse(line).unwrap(),
+                "{line}"
+            );
+        }
+    }
+
+    #[test]
+    fn parse_score() {
+        let cases = [
+            (
+                "info score cp 35",
+                InfoParams::Score(35, ScoreKind::CpExact),
+            ),
+            (
+                "info score cp 35 lowerbound",
+                InfoParams::Score(35, ScoreKind::CpLowerbound),
+            ),
+            (
+                "info score cp -35 upperbound",
+                InfoParams::Score(-35, ScoreKind::CpUpperbound),
+            ),
+            (
+                "info score mate 5",
+                InfoParams::Score(5, ScoreKind::MateExact),
+            ),
+            (
+                "info score mate -5 lowerbound",
+                InfoParams::Score(-5, ScoreKind::MateLowerbound),
+            ),
+            (
+                "info score mate 5 upperbound",
+                InfoParams::Score(5, ScoreKind::MateUpperbound),
+            ),
+        ];
+
+        for (i, (s, expected)) in cases.iter().enumerate() {
+            match EngineCommand::parse(s).unwrap() {
+                EngineCommand::Info(entries) => {
+                    assert_eq!(&vec![expected.clone()], &entries, "failed at #{i}")
+                }
+                _ => unreachable!(),
+            }
+        }
+
+        match EngineCommand::parse("info depth 3 wdl 120 800 80 tbhits 0 nodes 100").unwrap() {
+            EngineCommand::Info(entries) => assert_eq!(
+                vec![InfoParams::Depth(3, None), InfoParams::Nodes(100)],
+                entries
+            ),
+            _ => unreachable!(),
+        }
+
+        match EngineCommand::parse("info score cp 35 depth 3").unwrap() {
+            EngineCommand::Info(entries) => assert_eq!(
+                vec![
+                    InfoParams::Score(35, ScoreKind::CpExact),
+                    InfoParams::Depth(3, None)
+                ],
+                entries
+            ),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn validate_default() {
+        let cases = [
+            (
+                "option name Style type combo default Normal var Solid var Normal",
+                true,
+            ),
+            (
+                "option name Style type combo default Risky var Solid var Normal",
+                false,
+            ),
+            ("option name Style type combo var Solid var Normal", true),
+            ("option name Depth type spin default 0 min 1 max 10", false),
+            ("option name BookFile type string default book.db", true),
+        ];
+        for (line, valid) in cases {
+            match EngineCommand::parse(line).unwrap() {
+                EngineCommand::Option(params) => {
+                    assert_eq!(valid, params.validate_default().is_ok(), "{line}")
+                }
+                _ => unreachable!(),
+            }
+        }
+    }
+
+    #[test]
+    fn validate_option_value() {
+        let option = OptionParams {
+            name: "Style".to_string(),
+            value: OptionKind::Combo {
+                default: Some("Normal".to_string()),
+                vars: vec!["Normal".to_string(), "Risky".to_string()],
+            },
+        };
+        let risky = OptionValue::ComboChoice("Risky".to_string());
+        assert!(option.validate(&risky).is_ok());
+        match option.validate(&OptionValue::ComboChoice("Crazy".to_string())) {
+            Err(Error::InvalidOptionValue { name, value }) => {
+                assert_eq!(("Style", "Crazy"), (name.as_str(), value.as_str()))
+            }
+            res => panic!("unexpected result: {res:?}"),
+        }
+        assert!(option.validate(&OptionValue::Int(1)).is_err());
+        assert_eq!(Some(risky.clone()), option.value.parse_value("Risky"));
+
+        let spin = OptionKind::Spin {
+            default: Some(1),
+            min: Some(1),
+            max: Some(8),
+        };
+        assert_eq!(Some(OptionValue::Int(4)), spin.parse_value(" 4"));
+        assert_eq!(None, spin.parse_value("four"));
+        let button = OptionKind::Button { default: None };
+        assert_eq!(Some(OptionValue::Button), button.parse_value(""));
+        assert_eq!(None, button.parse_value("x"));
+
+        assert_eq!(
+            "setoption name Style value Risky",
+            crate::GuiCommand::SetOption(crate::SetOptionParams::from_value("Style", &risky))
+                .to_string()
+        );
+        assert_eq!(
+            "setoption name ClearHash",
+            crate::GuiCommand::SetOption(crate::SetOptionParams::from_value(
+                "ClearHash",
+                &OptionValue::Button
+            ))
+            .to_string()
+        );
+    }
 }
@@ -0,0 +1,479 @@
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+
+use super::parser::STARTPOS_SFEN;
+use super::{EngineCommand, GuiCommand, InfoParams, ScoreKind};
+use crate::error::Error;
+
+const UNSUPPORTED: Error = Error::InvalidArgument {
+    reason: "the command can't be expressed in the dialect",
+};
+
+/// A keyword which may differ between the dialects of `Dialect`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Keyword {
+    /// `usi`, the command starting the handshake.
+    Handshake,
+    /// `usiok`, the response finishing the handshake.
+    HandshakeOk,
+    /// `usinewgame`.
+    NewGame,
+    /// `gameover`.
+    GameOver,
+    /// `checkmate`, the response to `go mate`.
+    Checkmate,
+    /// `sfen` of the `position` command.
+    PositionFormat,
+    /// `byoyomi` of the `go` command.
+    Byoyomi,
+    /// `mate` of the `go` command.
+    Mate,
+    /// `win` of the `bestmove` command, declaring a win.
+    Win,
+}
+
+impl Keyword {
+    /// Returns the keyword in USI.
+    pub fn usi(self) -> &'static str {
+        match self {
+            Keyword::Handshake => "usi",
+            Keyword::HandshakeOk => "usiok",
+            Keyword::NewGame => "usinewgame",
+            Keyword::GameOver => "gameover",
+            Keyword::Checkmate => "checkmate",
+            Keyword::PositionFormat => "sfen",
+            Keyword::Byoyomi => "byoyomi",
+            Keyword::Mate => "mate",
+            Keyword::Win => "win",
+        }
+    }
+}
+
+/// `Dialect` is a protocol in the USI family, which `GuiCommand` and `EngineCommand`
+/// are translated from and to.
+///
+/// The commands are modeled after USI, and a dialect defines its keywords, initial position
+/// and the unit of mate scores. Commands using keywords without a counterpart in the dialect
+/// can't be formatted, and such lines are rejected when parsing. The other parameters,
+/// e.g. moves and positions, are passed through as is.
+///
+/// `Engine::connect_with_dialect`, `EngineBuilder::dialect` and `EngineServer::dialect` speak
+/// the dialect over the wire.
+///
+/// # Examples
+/// ```
+/// use usi::{Dialect, EngineCommand, GuiCommand, Uci};
+///
+/// assert_eq!("uci", Uci.format_gui(&GuiCommand::Usi).unwrap());
+/// assert_eq!(EngineCommand::UsiOk, Uci.parse_engine("uciok").unwrap());
+///
+/// let cmd = Uci.parse_gui("position startpos moves e2e4").unwrap();
+/// assert_eq!(
+///     GuiCommand::Position(
+///         "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 moves e2e4".to_string()
+///     ),
+///     cmd
+/// );
+/// assert_eq!(
+///     "position fen rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 moves e2e4",
+///     Uci.format_gui(&cmd).unwrap()
+/// );
+/// ```
+pub trait Dialect {
+    /// Returns the name of the dialect.
+    fn name(&self) -> &'static str;
+
+    /// Returns the keyword in the dialect, or `None` if the dialect has no counterpart.
+    fn keyword(&self, keyword: Keyword) -> Option<&'static str>;
+
+    /// Returns the initial position in the notation of `Keyword::PositionFormat`,
+    /// which `position startpos` stands for.
+    fn initial_position(&self) -> &'static str;
+
+    /// Returns `true` if `score mate` counts moves of the winning side rather than plies.
+    fn counts_mate_in_moves(&self) -> bool {
+        false
+    }
+
+    /// Formats a command sent from the GUI in the dialect.
+    /// Returns `Error::InvalidArgument` if the command can't be expressed in the dialect.
+    fn format_gui(&self, command: &GuiCommand) -> Result<String, Error> {
+        translate(self, &command.to_string(), true).ok_or(UNSUPPORTED)
+    }
+
+    /// Parses a command sent from the GUI in the dialect.
+    fn parse_gui(&self, line: &str) -> Result<GuiCommand, Error> {
+        let line = translate(self, line, false).ok_or(Error::IllegalSyntax)?;
+        GuiCommand::parse(&line)
+    }
+
+    /// Formats a command sent from the engine in the dialect.
+    /// Returns `Error::InvalidArgument` if the command can't be expressed in the dialect.
+    fn format_engine(&self, command: &EngineCommand) -> Result<String, Error> {
+        let line = if self.counts_mate_in_moves() {
+            map_mate_scores(command.clone(), plies_to_moves).to_string()
+        } else {
+            command.to_string()
+        };
+        translate(self, &line, true).ok_or(UNSUPPORTED)
+    }
+
+    /// Parses a command sent from the engine in the dialect.
+    /// Commands which don't exist in the dialect are `EngineCommand::Unknown`.
+    fn parse_engine(&self, line: &str) -> Result<EngineCommand, Error> {
+        let command = match translate(self, line, false) {
+            Some(line) => EngineCommand::parse(&line)?,
+            None => return Ok(EngineCommand::Unknown),
+        };
+        Ok(if self.counts_mate_in_moves() {
+            map_mate_scores(command, moves_to_plies)
+        } else {
+            command
+        })
+    }
+}
+
+impl fmt::Debug for dyn Dialect + Send + Sync {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// The Universal Shogi Interface, which is what `GuiCommand` and `EngineCommand` speak natively.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Usi;
+
+impl Dialect for Usi {
+    fn name(&self) -> &'static str {
+        "USI"
+    }
+
+    fn keyword(&self, keyword: Keyword) -> Option<&'static str> {
+        Some(keyword.usi())
+    }
+
+    fn initial_position(&self) -> &'static str {
+        STARTPOS_SFEN
+    }
+}
+
+/// The Universal Chess Interface of chess engines.
+///
+/// Positions are FENs. `byoyomi`, `gameover`, `go mate`, `checkmate` and `bestmove win`
+/// don't exist in UCI; use the nonstandard `ThinkParams::movetime` for a fixed time per move.
+/// Mate scores count moves in UCI, and are converted from and to plies.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Uci;
+
+impl Dialect for Uci {
+    fn name(&self) -> &'static str {
+        "UCI"
+    }
+
+    fn keyword(&self, keyword: Keyword) -> Option<&'static str> {
+        match keyword {
+            Keyword::Handshake => Some("uci"),
+            Keyword::HandshakeOk => Some("uciok"),
+            Keyword::NewGame => Some("ucinewgame"),
+            Keyword::PositionFormat => Some("fen"),
+            Keyword::GameOver
+            | Keyword::Checkmate
+            | Keyword::Byoyomi
+            | Keyword::Mate
+            | Keyword::Win => None,
+        }
+    }
+
+    fn initial_position(&self) -> &'static str {
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"
+    }
+
+    fn counts_mate_in_moves(&self) -> bool {
+        true
+    }
+}
+
+/// Converts the distances of the mate scores in `command` by `convert`.
+fn map_mate_scores(command: EngineCommand, convert: fn(i32) -> i32) -> EngineCommand {
+    match command {
+        EngineCommand::Info(params) => EngineCommand::Info(
+            params
+                .into_iter()
+                .map(|param| match param {
+                    InfoParams::Score(
+                        v,
+                        kind @ (ScoreKind::MateExact
+                        | ScoreKind::MateLowerbound
+                        | ScoreKind::MateUpperbound),
+                    ) => InfoParams::Score(convert(v), kind),
+                    param => param,
+                })
+                .collect(),
+        ),
+        command => command,
+    }
+}
+
+/// Converts mate in `n` moves of the winning side to plies, where negative values are mated.
+fn moves_to_plies(n: i32) -> i32 {
+    if n > 0 {
+        n.saturating_mul(2) - 1
+    } else {
+        n.saturating_mul(2)
+    }
+}
+
+/// Converts mate in `n` plies to moves of the winning side, where negative values are mated.
+fn plies_to_moves(n: i32) -> i32 {
+    n.signum() * n.unsigned_abs().div_ceil(2) as i32
+}
+
+/// Translates a line from USI to the dialect if `to_dialect` is `true`, or from the dialect to USI
+/// otherwise. Returns `None` if a keyword has no counterpart.
+fn translate<D: Dialect + ?Sized>(dialect: &D, line: &str, to_dialect: bool) -> Option<String> {
+    let line = line.trim();
+    let (command, rest) = line.split_once(' ').unwrap_or((line, ""));
+    let rest = rest.trim_start();
+
+    let mut translated = command;
+    for keyword in [
+        Keyword::Handshake,
+        Keyword::HandshakeOk,
+        Keyword::NewGame,
+        Keyword::GameOver,
+        Keyword::Checkmate,
+    ] {
+        translated = word(dialect, keyword, command, to_dialect)?;
+        if translated != command {
+            break;
+        }
+    }
+    let usi_command = if to_dialect { command } else { translated };
+
+    let rest = match usi_command {
+        "position" => {
+            let initial = (!to_dialect)
+                .then(|| rest.strip_prefix("startpos"))
+                .flatten()
+                .filter(|moves| moves.is_empty() || moves.starts_with(' '));
+            let rest = match initial {
+                Some(moves) => format!(
+                    "{} {}{}",
+                    dialect.keyword(Keyword::PositionFormat)?,
+                    dialect.initial_position(),
+                    moves
+                ),
+                None => rest.to_string(),
+            };
+            let (format, position) = rest.split_once(' ').unwrap_or((&rest, ""));
+            match word(dialect, Keyword::PositionFormat, format, to_dialect)? {
+                format if position.is_empty() => format.to_string(),
+                format => format!("{format} {position}"),
+            }
+        }
+        "go" => rest
+            .split_whitespace()
+            .map(|token| {
+                let token = word(dialect, Keyword::Byoyomi, token, to_dialect)?;
+                word(dialect, Keyword::Mate, token, to_dialect)
+            })
+            .collect::<Option<Vec<_>>>()?
+            .join(" "),
+        "bestmove" => {
+            let (mv, ponder) = rest.split_once(' ').unwrap_or((rest, ""));
+            match word(dialect, Keyword::Win, mv, to_dialect)? {
+                mv if ponder.is_empty() => mv.to_string(),
+                mv => format!("{mv} {ponder}"),
+            }
+        }
+        _ => rest.to_string(),
+    };
+
+    Some(if rest.is_empty() {
+        translated.to_string()
+    } else {
+        format!("{translated} {rest}")
+    })
+}
+
+/// Translates `token` if it is `keyword` in the source dialect.
+/// Returns `None` if it is the keyword in USI without a counterpart in the target dialect.
+fn word<'a, D: Dialect + ?Sized>(
+    dialect: &D,
+    keyword: Keyword,
+    token: &'a str,
+    to_dialect: bool,
+) -> Option<&'a str> {
+    let usi = keyword.usi();
+    let native = dialect.keyword(keyword);
+    if to_dialect {
+        if token == usi {
+            native
+        } else {
+            Some(token)
+        }
+    } else if Some(token) == native {
+        Some(usi)
+    } else if token == usi {
+        None
+    } else {
+        Some(token)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::*;
+    use alloc::vec;
+    use core::time::Duration;
+
+    #[test]
+    fn usi() {
+        let lines = [
+            "usi",
+            "position sfen lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL b - 1 moves 7g7f",
+            "go btime 1000 wtime 1000 byoyomi 3000",
+            "go mate infinite",
+            "gameover win",
+        ];
+        for line in lines {
+            let cmd = Usi.parse_gui(line).unwrap();
+            assert_eq!(GuiCommand::parse(line).unwrap(), cmd);
+            assert_eq!(line, Usi.format_gui(&cmd).unwrap());
+        }
+        assert_eq!(
+            GuiCommand::parse("position startpos").unwrap(),
+            Usi.parse_gui("position startpos").unwrap()
+        );
+        assert_eq!(
+            "bestmove win",
+            Usi.format_engine(&EngineCommand::BestMove(BestMoveParams::Win))
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn uci() {
+        let cases = [
+            ("uci", GuiCommand::Usi),
+            ("ucinewgame", GuiCommand::UsiNewGame),
+            (
+                "go wtime 1000 btime 2000 movetime 500",
+                GuiCommand::Go(
+                    ThinkParams::new()
+                        .btime(Duration::from_secs(2))
+                        .wtime(Duration::from_secs(1))
+                        .movetime(Duration::from_millis(500)),
+                ),
+            ),
+            (
+                "go nodes 100000 movestogo 20",
+                GuiCommand::Go(ThinkParams::new().nodes(100000).movestogo(20)),
+            ),
+            (
+                "position fen 8/8/8/8/8/8/8/K6k w - - 0 1 moves a1a2",
+                GuiCommand::Position("8/8/8/8/8/8/8/K6k w - - 0 1 moves a1a2".to_string()),
+            ),
+        ];
+        for (line, cmd) in &cases {
+            assert_eq!(*cmd, Uci.parse_gui(line).unwrap());
+        }
+        assert_eq!(
+            "go btime 2000 wtime 1000 movetime 500",
+            Uci.format_gui(&cases[2].1).unwrap()
+        );
+        assert_eq!(
+            "position fen rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            Uci.format_gui(&Uci.parse_gui("position startpos").unwrap())
+                .unwrap()
+        );
+
+        // USI keywords are not UCI.
+        assert!(Uci.parse_gui("usi").is_err());
+        assert!(Uci
+            .parse_gui("position sfen 9/9/9/9/9/9/9/9/9 b - 1")
+            .is_err());
+        assert!(Uci.parse_gui("go byoyomi 1000").is_err());
+        assert!(matches!(
+            Uci.format_gui(&GuiCommand::GameOver(GameOverKind::Win)),
+            Err(Error::InvalidArgument { .. })
+        ));
+        assert!(Uci
+            .format_gui(&GuiCommand::Go(
+                ThinkParams::new().byoyomi(Duration::from_secs(1))
+            ))
+            .is_err());
+        assert!(Uci
+            .format_gui(&GuiCommand::Go(
+                ThinkParams::new().mate(MateParam::Infinite)
+            ))
+            .is_err());
+
+        assert_eq!(EngineCommand::UsiOk, Uci.parse_engine("uciok").unwrap());
+        assert_eq!(EngineCommand::Unknown, Uci.parse_engine("usiok").unwrap());
+        assert_eq!(
+            EngineCommand::Unknown,
+            Uci.parse_engine("checkmate nomate").unwrap()
+        );
+        assert_eq!(
+            EngineCommand::BestMove(BestMoveParams::MakeMove(
+                "e2e4".to_string(),
+                Some("e7e5".to_string())
+            )),
+            Uci.parse_engine("bestmove e2e4 ponder e7e5").unwrap()
+        );
+        assert!(Uci
+            .format_engine(&EngineCommand::BestMove(BestMoveParams::Win))
+            .is_err());
+        assert_eq!(
+            "info depth 10 score cp 30 pv e2e4 e7e5",
+            Uci.format_engine(
+                &Uci.parse_engine("info depth 10 score cp 30 pv e2e4 e7e5")
+                    .unwrap()
+            )
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn uci_mate_scores() {
+        // Mate in 3 moves of the winning side is 5 plies, and mated in 2 moves is -4 plies.
+        let cases = [
+            (
+                "info score mate 3",
+                InfoParams::Score(5, ScoreKind::MateExact),
+            ),
+            (
+                "info score mate -2",
+                InfoParams::Score(-4, ScoreKind::MateExact),
+            ),
+            (
+                "info score mate 1 lowerbound",
+                InfoParams::Score(1, ScoreKind::MateLowerbound),
+            ),
+            ("info score cp 3", InfoParams::Score(3, ScoreKind::CpExact)),
+        ];
+        for (line, param) in cases {
+            let cmd = Uci.parse_engine(line).unwrap();
+            assert_eq!(EngineCommand::Info(vec![param]), cmd);
+            assert_eq!(line, Uci.format_engine(&cmd).unwrap());
+        }
+        assert_eq!(
+            "info score mate -3",
+            Uci.format_engine(&EngineCommand::Info(vec![InfoParams::Score(
+                -5,
+                ScoreKind::MateExact
+            )]))
+            .unwrap()
+        );
+        assert_eq!(
+            "info score mate 5",
+            Usi.format_engine(&Usi.parse_engine("info score mate 5").unwrap())
+                .unwrap()
+        );
+    }
+}
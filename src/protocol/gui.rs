@@ -1,5 +1,12 @@
-use std::fmt;
-use std::time::Duration;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+use core::str::FromStr;
+use core::time::Duration;
+
+use super::command::OptionValue;
+use super::parser::GuiCommandParser;
+use crate::error::Error;
 
 /// Represents parameters of "gameover" command.
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
@@ -19,13 +26,30 @@ impl fmt::Display for GameOverKind {
     }
 }
 
+impl FromStr for GameOverKind {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "win" => Ok(GameOverKind::Win),
+            "lose" => Ok(GameOverKind::Lose),
+            "draw" => Ok(GameOverKind::Draw),
+            _ => Err(Error::IllegalSyntax),
+        }
+    }
+}
+
+/// Represents a time limit of "go mate" command.
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum MateParam {
+    /// The time limit, sent in whole milliseconds.
     Timeout(Duration),
     Infinite,
 }
 
 /// Represents parameters of "go" command.
+///
+/// All times are `Duration`s, which are sent in whole milliseconds (truncated).
 #[derive(Clone, Debug, PartialEq, Eq, Hash, Default)]
 pub struct ThinkParams {
     ponder: bool,
@@ -36,6 +60,10 @@ pub struct ThinkParams {
     winc: Option<Duration>,
     infinite: bool,
     mate: Option<MateParam>,
+    depth: Option<i32>,
+    nodes: Option<u64>,
+    movestogo: Option<u32>,
+    movetime: Option<Duration>,
 }
 
 impl ThinkParams {
@@ -49,30 +77,35 @@ impl ThinkParams {
         self
     }
 
+    /// Sets the remaining time of the black player.
     #[must_use]
     pub fn btime(mut self, t: Duration) -> Self {
         self.btime = Some(t);
         self
     }
 
+    /// Sets the remaining time of the white player.
     #[must_use]
     pub fn wtime(mut self, t: Duration) -> Self {
         self.wtime = Some(t);
         self
     }
 
+    /// Sets the byoyomi time.
     #[must_use]
     pub fn byoyomi(mut self, t: Duration) -> Self {
         self.byoyomi = Some(t);
         self
     }
 
+    /// Sets the increment per move of the black player.
     #[must_use]
     pub fn binc(mut self, t: Duration) -> Self {
         self.binc = Some(t);
         self
     }
 
+    /// Sets the increment per move of the white player.
     #[must_use]
     pub fn winc(mut self, t: Duration) -> Self {
         self.winc = Some(t);
@@ -85,11 +118,102 @@ impl ThinkParams {
         self
     }
 
+    /// Requests a checkmate search within the time limit.
     #[must_use]
     pub fn mate(mut self, t: MateParam) -> Self {
         self.mate = Some(t);
         self
     }
+
+    /// Limits the search to `depth` with the nonstandard `go depth`, supported by many engines.
+    #[must_use]
+    pub fn depth(mut self, depth: i32) -> Self {
+        self.depth = Some(depth);
+        self
+    }
+
+    /// Limits the search to `nodes` with the nonstandard `go nodes` of UCI.
+    #[must_use]
+    pub fn nodes(mut self, nodes: u64) -> Self {
+        self.nodes = Some(nodes);
+        self
+    }
+
+    /// Sets the number of moves to the next time control with the nonstandard `go movestogo`
+    /// of UCI.
+    #[must_use]
+    pub fn movestogo(mut self, moves: u32) -> Self {
+        self.movestogo = Some(moves);
+        self
+    }
+
+    /// Searches exactly `t` with the nonstandard `go movetime` of UCI.
+    #[must_use]
+    pub fn movetime(mut self, t: Duration) -> Self {
+        self.movetime = Some(t);
+        self
+    }
+
+    /// Returns `true` if the search should be done in ponder mode.
+    pub fn is_ponder(&self) -> bool {
+        self.ponder
+    }
+
+    /// Returns the remaining time of the black player.
+    pub fn get_btime(&self) -> Option<Duration> {
+        self.btime
+    }
+
+    /// Returns the remaining time of the white player.
+    pub fn get_wtime(&self) -> Option<Duration> {
+        self.wtime
+    }
+
+    /// Returns the byoyomi time.
+    pub fn get_byoyomi(&self) -> Option<Duration> {
+        self.byoyomi
+    }
+
+    /// Returns the increment per move of the black player.
+    pub fn get_binc(&self) -> Option<Duration> {
+        self.binc
+    }
+
+    /// Returns the increment per move of the white player.
+    pub fn get_winc(&self) -> Option<Duration> {
+        self.winc
+    }
+
+    /// Returns `true` if the search should continue until `stop` is received.
+    pub fn is_infinite(&self) -> bool {
+        self.infinite
+    }
+
+    /// Returns the time limit of the checkmate search.
+    /// `None` means a regular search is requested.
+    pub fn get_mate(&self) -> Option<&MateParam> {
+        self.mate.as_ref()
+    }
+
+    /// Returns the depth limit of the search.
+    pub fn get_depth(&self) -> Option<i32> {
+        self.depth
+    }
+
+    /// Returns the node limit of the search.
+    pub fn get_nodes(&self) -> Option<u64> {
+        self.nodes
+    }
+
+    /// Returns the number of moves to the next time control.
+    pub fn get_movestogo(&self) -> Option<u32> {
+        self.movestogo
+    }
+
+    /// Returns the fixed search time.
+    pub fn get_movetime(&self) -> Option<Duration> {
+        self.movetime
+    }
 }
 
 impl fmt::Display for ThinkParams {
@@ -98,34 +222,116 @@ impl fmt::Display for ThinkParams {
             write!(f, " ponder")?;
         }
         if let Some(t) = self.btime {
-            write!(f, " btime {}", to_ms(t))?;
+            write!(f, " btime {}", t.as_millis())?;
         }
         if let Some(t) = self.wtime {
-            write!(f, " wtime {}", to_ms(t))?;
+            write!(f, " wtime {}", t.as_millis())?;
         }
         if let Some(t) = self.byoyomi {
-            write!(f, " byoyomi {}", to_ms(t))?;
+            write!(f, " byoyomi {}", t.as_millis())?;
         }
         if let Some(t) = self.binc {
-            write!(f, " binc {}", to_ms(t))?;
+            write!(f, " binc {}", t.as_millis())?;
         }
         if let Some(t) = self.winc {
-            write!(f, " winc {}", to_ms(t))?;
+            write!(f, " winc {}", t.as_millis())?;
         }
         if self.infinite {
             write!(f, " infinite")?;
         }
         if let Some(mate_opts) = &self.mate {
             match *mate_opts {
-                MateParam::Timeout(t) => write!(f, " mate {}", to_ms(t))?,
+                MateParam::Timeout(t) => write!(f, " mate {}", t.as_millis())?,
                 MateParam::Infinite => write!(f, " mate infinite")?,
             }
         }
+        if let Some(depth) = self.depth {
+            write!(f, " depth {}", depth)?;
+        }
+        if let Some(nodes) = self.nodes {
+            write!(f, " nodes {}", nodes)?;
+        }
+        if let Some(moves) = self.movestogo {
+            write!(f, " movestogo {}", moves)?;
+        }
+        if let Some(t) = self.movetime {
+            write!(f, " movetime {}", t.as_millis())?;
+        }
 
         Ok(())
     }
 }
 
+/// Represents parameters of "setoption" command.
+///
+/// Values may contain spaces. An empty value is sent as `<empty>`.
+///
+/// # Examples
+///
+/// ```
+/// use usi::{GuiCommand, SetOptionParams};
+///
+/// let cmd = GuiCommand::SetOption(SetOptionParams::new("BookFile", "my book.db"));
+/// assert_eq!("setoption name BookFile value my book.db", cmd.to_string());
+///
+/// let cmd = GuiCommand::SetOption(SetOptionParams::new("BookFile", ""));
+/// assert_eq!("setoption name BookFile value <empty>", cmd.to_string());
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct SetOptionParams {
+    pub name: String,
+    pub value: Option<String>,
+}
+
+impl SetOptionParams {
+    /// Creates parameters assigning `value` to the option.
+    pub fn new<N: Into<String>, V: Into<String>>(name: N, value: V) -> Self {
+        SetOptionParams {
+            name: name.into(),
+            value: Some(value.into()),
+        }
+    }
+
+    /// Creates parameters assigning a typed value. `OptionValue::Button` presses the button.
+    pub fn from_value<N: Into<String>>(name: N, value: &OptionValue) -> Self {
+        match value {
+            OptionValue::Button => SetOptionParams::button(name),
+            _ => SetOptionParams::new(name, value.to_string()),
+        }
+    }
+
+    /// Creates parameters without a value, used to press a button option.
+    pub fn button<N: Into<String>>(name: N) -> Self {
+        SetOptionParams {
+            name: name.into(),
+            value: None,
+        }
+    }
+}
+
+impl fmt::Display for SetOptionParams {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "name {}", self.name)?;
+        match self.value.as_deref() {
+            None => Ok(()),
+            Some("") => write!(f, " value <empty>"),
+            // Line breaks would terminate the command, so they are sent as spaces.
+            Some(v) if v.contains(['\r', '\n']) => {
+                write!(f, " value ")?;
+                for c in v.chars() {
+                    if c == '\r' || c == '\n' {
+                        write!(f, " ")?;
+                    } else {
+                        write!(f, "{c}")?;
+                    }
+                }
+                Ok(())
+            }
+            Some(v) => write!(f, " value {v}"),
+        }
+    }
+}
+
 /// Represents a USI command sent from the GUI.
 ///
 /// # Examples
@@ -141,30 +347,54 @@ impl fmt::Display for ThinkParams {
 /// ```
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum GuiCommand {
+    /// The nonstandard `bench` command with its arguments, supported by many engines.
+    Bench(Vec<String>),
     GameOver(GameOverKind),
     Go(ThinkParams),
     IsReady,
     Ponderhit,
     Position(String),
-    SetOption(String, Option<String>),
+    SetOption(SetOptionParams),
     Stop,
     Usi,
     UsiNewGame,
     Quit,
 }
 
+impl GuiCommand {
+    /// Parses a USI command string sent from the GUI into a new instance of `GuiCommand`.
+    ///
+    /// `position startpos` is converted into the equivalent `position sfen` command.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use usi::{GameOverKind, GuiCommand};
+    ///
+    /// let cmd = GuiCommand::parse("gameover win").unwrap();
+    /// assert_eq!(GuiCommand::GameOver(GameOverKind::Win), cmd);
+    /// ```
+    pub fn parse(cmd: &str) -> Result<GuiCommand, Error> {
+        GuiCommandParser::new(cmd).parse()
+    }
+}
+
 impl fmt::Display for GuiCommand {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
+            GuiCommand::Bench(ref args) => {
+                write!(f, "bench")?;
+                for arg in args {
+                    write!(f, " {arg}")?;
+                }
+                Ok(())
+            }
             GuiCommand::GameOver(ref r) => write!(f, "gameover {r}"),
             GuiCommand::Go(ref opt) => write!(f, "go{opt}"),
             GuiCommand::IsReady => write!(f, "isready"),
             GuiCommand::Ponderhit => write!(f, "ponderhit"),
             GuiCommand::Position(ref s) => write!(f, "position sfen {s}"),
-            GuiCommand::SetOption(ref n, None) => write!(f, "setoption name {n}"),
-            GuiCommand::SetOption(ref n, Some(ref v)) => {
-                write!(f, "setoption name {n} value {v}")
-            }
+            GuiCommand::SetOption(ref params) => write!(f, "setoption {params}"),
             GuiCommand::Stop => write!(f, "stop"),
             GuiCommand::Usi => write!(f, "usi"),
             GuiCommand::UsiNewGame => write!(f, "usinewgame"),
@@ -173,13 +403,10 @@ impl fmt::Display for GuiCommand {
     }
 }
 
-fn to_ms(t: Duration) -> u64 {
-    1000 * t.as_secs() + (t.subsec_nanos() as u64) / 1_000_000
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
+    use alloc::string::ToString;
 
     #[test]
     fn to_string() {
@@ -196,6 +423,14 @@ mod tests {
                         .byoyomi(Duration::from_secs(10)),
                 ),
             ),
+            (
+                "go byoyomi 1500 mate 999",
+                GuiCommand::Go(
+                    ThinkParams::new()
+                        .byoyomi(Duration::from_micros(1_500_999))
+                        .mate(MateParam::Timeout(Duration::from_micros(999_999))),
+                ),
+            ),
             (
                 "go btime 40000 wtime 50000 binc 10000 winc 10000",
                 GuiCommand::Go(
@@ -218,6 +453,7 @@ mod tests {
                 GuiCommand::Go(ThinkParams::new().mate(MateParam::Infinite)),
             ),
             ("go ponder", GuiCommand::Go(ThinkParams::new().ponder())),
+            ("go depth 20", GuiCommand::Go(ThinkParams::new().depth(20))),
             ("isready", GuiCommand::IsReady),
             ("ponderhit", GuiCommand::Ponderhit),
             (
@@ -230,11 +466,23 @@ mod tests {
             ),
             (
                 "setoption name foo",
-                GuiCommand::SetOption("foo".to_string(), None),
+                GuiCommand::SetOption(SetOptionParams::button("foo")),
             ),
             (
                 "setoption name foo value bar",
-                GuiCommand::SetOption("foo".to_string(), Some("bar".to_string())),
+                GuiCommand::SetOption(SetOptionParams::new("foo", "bar")),
+            ),
+            (
+                "setoption name foo value bar baz",
+                GuiCommand::SetOption(SetOptionParams::new("foo", "bar baz")),
+            ),
+            (
+                "setoption name foo value <empty>",
+                GuiCommand::SetOption(SetOptionParams::new("foo", "")),
+            ),
+            (
+                "setoption name foo value a b",
+                GuiCommand::SetOption(SetOptionParams::new("foo", "a\nb")),
             ),
             ("stop", GuiCommand::Stop),
             ("usi", GuiCommand::Usi),
@@ -246,4 +494,90 @@ mod tests {
             assert_eq!(c.0, c.1.to_string());
         }
     }
+
+    #[test]
+    fn parse() {
+        let ok_cases = [
+            "bench",
+            "bench 64 1 10 default depth",
+            "gameover win",
+            "gameover lose",
+            "gameover draw",
+            "go",
+            "go ponder",
+            "go btime 60000 wtime 50000 byoyomi 10000",
+            "go btime 40000 wtime 50000 binc 10000 winc 10000",
+            "go infinite",
+            "go mate 60000",
+            "go mate infinite",
+            "go depth 20",
+            "isready",
+            "ponderhit",
+            "position sfen lnsgkgsn1/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL w - 1",
+            "position sfen lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL b - 1 moves 7g7f",
+            "setoption name foo",
+            "setoption name foo value bar",
+            "setoption name foo value C:\\My Documents\\book.db",
+            "setoption name foo value <empty>",
+            "stop",
+            "usi",
+            "usinewgame",
+            "quit",
+        ];
+
+        let ng_cases = [
+            "",
+            "gameover",
+            "gameover foo",
+            "go btime",
+            "go btime foo",
+            "go foo",
+            "go mate",
+            "go mate foo",
+            "position",
+            "position sfen",
+            "position foo",
+            "setoption",
+            "setoption foo",
+            "setoption name",
+            "setoption name value bar",
+            "setoption name foo value",
+            "unknown command",
+        ];
+
+        for (i, c) in ok_cases.iter().enumerate() {
+            let cmd = GuiCommand::parse(c);
+            assert!(cmd.is_ok(), "failed at #{i}");
+            assert_eq!(*c, cmd.unwrap().to_string(), "failed at #{i}");
+        }
+
+        for (i, c) in ng_cases.iter().enumerate() {
+            assert!(GuiCommand::parse(c).is_err(), "failed at #{i}");
+        }
+
+        assert_eq!(
+            GuiCommand::Position(
+                "lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL b - 1 moves 7g7f"
+                    .to_string()
+            ),
+            GuiCommand::parse("position startpos moves 7g7f").unwrap()
+        );
+
+        assert_eq!(
+            GuiCommand::SetOption(SetOptionParams::new("foo", "")),
+            GuiCommand::parse("setoption name foo value <empty>").unwrap()
+        );
+        assert_eq!(
+            GuiCommand::SetOption(SetOptionParams::new("Book File", "a  b")),
+            GuiCommand::parse("setoption name Book File value a  b").unwrap()
+        );
+
+        match GuiCommand::parse("go mate 1000").unwrap() {
+            GuiCommand::Go(params) => assert_eq!(
+                Some(&MateParam::Timeout(Duration::from_secs(1))),
+                params.get_mate()
+            ),
+            _ => unreachable!(),
+        }
+    }
 }
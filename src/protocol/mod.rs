@@ -1,6 +1,11 @@
+mod bench;
 mod command;
+mod dialect;
 mod gui;
 mod parser;
 
+pub use self::bench::BenchReport;
 pub use self::command::*;
+pub use self::dialect::*;
 pub use self::gui::*;
+pub use self::parser::STARTPOS_SFEN;
@@ -1,19 +1,29 @@
-use itertools::Itertools;
-use std::str::SplitWhitespace;
-use std::time::Duration;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::str::SplitWhitespace;
+use core::time::Duration;
 
 use super::{
-    BestMoveParams, CheckmateParams, EngineCommand, IdParams, InfoParams, OptionKind, OptionParams,
-    ScoreKind,
+    BestMoveParams, CheckmateParams, EngineCommand, GameOverKind, GuiCommand, IdParams, InfoParams,
+    MateParam, OptionKind, OptionParams, ScoreKind, SetOptionParams, ThinkParams,
 };
 use crate::error::Error;
 
+/// SFEN of the initial position, used in place of `position startpos`.
+pub const STARTPOS_SFEN: &str = "lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL b - 1";
+
+/// The keys of `info` command which `InfoParams` represents.
+const INFO_KEYS: [&str; 10] = [
+    "depth", "time", "multipv", "nodes", "pv", "score", "currmove", "hashfull", "nps", "string",
+];
+
 pub struct EngineCommandParser<'a> {
     iter: SplitWhitespace<'a>,
 }
 
 impl<'a> EngineCommandParser<'a> {
-    pub fn new(cmd: &str) -> EngineCommandParser {
+    pub fn new(cmd: &'a str) -> EngineCommandParser<'a> {
         EngineCommandParser {
             iter: cmd.split_whitespace(),
         }
@@ -55,7 +65,7 @@ impl<'a> EngineCommandParser<'a> {
 
     fn parse_checkmate(mut self) -> Result<EngineCommand, Error> {
         match self.iter.next() {
-            Some("notimplemented") => Ok(EngineCommand::Checkmate(CheckmateParams::NoMate)),
+            Some("notimplemented") => Ok(EngineCommand::Checkmate(CheckmateParams::NotImplemented)),
             Some("timeout") => Ok(EngineCommand::Checkmate(CheckmateParams::Timeout)),
             Some("nomate") => Ok(EngineCommand::Checkmate(CheckmateParams::NoMate)),
             Some(s) => {
@@ -71,8 +81,8 @@ impl<'a> EngineCommandParser<'a> {
 
     fn parse_id(mut self) -> Result<EngineCommand, Error> {
         match self.iter.next() {
-            Some("name") => Ok(EngineCommand::Id(IdParams::Name(self.iter.join(" ")))),
-            Some("author") => Ok(EngineCommand::Id(IdParams::Author(self.iter.join(" ")))),
+            Some("name") => Ok(EngineCommand::Id(IdParams::Name(join(self.iter)))),
+            Some("author") => Ok(EngineCommand::Id(IdParams::Author(join(self.iter)))),
             _ => Err(Error::IllegalSyntax),
         }
     }
@@ -119,7 +129,7 @@ impl<'a> EngineCommandParser<'a> {
                     entries.push(InfoParams::MultiPv(multipv));
                 }
                 "nodes" => {
-                    let nodes: i32 = iter
+                    let nodes: u64 = iter
                         .next()
                         .and_then(|s| s.parse().ok())
                         .ok_or(Error::IllegalSyntax)?;
@@ -134,22 +144,15 @@ impl<'a> EngineCommandParser<'a> {
                 "score" => match (iter.next(), iter.next()) {
                     (Some("cp"), Some(cp)) => {
                         let cp: i32 = cp.parse()?;
-
-                        if let Some(&peek_kind) = iter.peek() {
-                            match peek_kind {
-                                "lowerbound" => {
-                                    iter.next();
-                                    entries.push(InfoParams::Score(cp, ScoreKind::CpLowerbound));
-                                }
-                                "upperbound" => {
-                                    iter.next();
-                                    entries.push(InfoParams::Score(cp, ScoreKind::CpUpperbound));
-                                }
-                                _ => {
-                                    entries.push(InfoParams::Score(cp, ScoreKind::CpExact));
-                                }
-                            }
+                        let kind = match iter.peek() {
+                            Some(&"lowerbound") => ScoreKind::CpLowerbound,
+                            Some(&"upperbound") => ScoreKind::CpUpperbound,
+                            _ => ScoreKind::CpExact,
+                        };
+                        if kind != ScoreKind::CpExact {
+                            iter.next();
                         }
+                        entries.push(InfoParams::Score(cp, kind));
                     }
                     (Some("mate"), Some("+")) => {
                         entries.push(InfoParams::Score(1, ScoreKind::MateSignOnly))
@@ -159,22 +162,15 @@ impl<'a> EngineCommandParser<'a> {
                     }
                     (Some("mate"), Some(ply)) => {
                         let ply: i32 = ply.parse()?;
-
-                        if let Some(&peek_kind) = iter.peek() {
-                            match peek_kind {
-                                "lowerbound" => {
-                                    iter.next();
-                                    entries.push(InfoParams::Score(ply, ScoreKind::MateLowerbound));
-                                }
-                                "upperbound" => {
-                                    iter.next();
-                                    entries.push(InfoParams::Score(ply, ScoreKind::MateUpperbound));
-                                }
-                                _ => {
-                                    entries.push(InfoParams::Score(ply, ScoreKind::MateExact));
-                                }
-                            }
+                        let kind = match iter.peek() {
+                            Some(&"lowerbound") => ScoreKind::MateLowerbound,
+                            Some(&"upperbound") => ScoreKind::MateUpperbound,
+                            _ => ScoreKind::MateExact,
+                        };
+                        if kind != ScoreKind::MateExact {
+                            iter.next();
                         }
+                        entries.push(InfoParams::Score(ply, kind));
                     }
                     _ => return Err(Error::IllegalSyntax),
                 },
@@ -190,18 +186,19 @@ impl<'a> EngineCommandParser<'a> {
                     entries.push(InfoParams::HashFull(hashfull));
                 }
                 "nps" => {
-                    let nps: i32 = iter
+                    let nps: u64 = iter
                         .next()
                         .and_then(|s| s.parse().ok())
                         .ok_or(Error::IllegalSyntax)?;
                     entries.push(InfoParams::Nps(nps));
                 }
                 "string" => {
-                    entries.push(InfoParams::Text(iter.join(" ")));
+                    entries.push(InfoParams::Text(join(iter)));
                     // "pv" or "str" must be the final item.
                     break;
                 }
-                _ => return Err(Error::IllegalSyntax),
+                // Skip extensions such as `tbhits`, `currmovenumber` and `wdl` with their values.
+                _ => while iter.next_if(|token| !INFO_KEYS.contains(token)).is_some() {},
             }
         }
 
@@ -246,12 +243,7 @@ impl<'a> EngineCommandParser<'a> {
                 while let Some(kind) = self.iter.next() {
                     match kind {
                         "default" => default = self.iter.next().map(parse_default),
-                        "var" => {
-                            self.iter.for_each(|v| {
-                                vars.push(v.to_string());
-                            });
-                            break;
-                        }
+                        "var" => vars.extend(self.iter.next().map(String::from)),
                         _ => {}
                     }
                 }
@@ -283,6 +275,151 @@ impl<'a> EngineCommandParser<'a> {
     }
 }
 
+pub struct GuiCommandParser<'a> {
+    cmd: &'a str,
+    iter: SplitWhitespace<'a>,
+}
+
+impl<'a> GuiCommandParser<'a> {
+    pub fn new(cmd: &'a str) -> GuiCommandParser<'a> {
+        GuiCommandParser {
+            cmd,
+            iter: cmd.split_whitespace(),
+        }
+    }
+
+    pub fn parse(mut self) -> Result<GuiCommand, Error> {
+        let command = self.iter.next().ok_or(Error::IllegalSyntax)?;
+
+        Ok(match command {
+            "bench" => GuiCommand::Bench(self.iter.map(String::from).collect()),
+            "gameover" => self.parse_gameover()?,
+            "go" => self.parse_go()?,
+            "isready" => GuiCommand::IsReady,
+            "ponderhit" => GuiCommand::Ponderhit,
+            "position" => self.parse_position()?,
+            "setoption" => self.parse_setoption()?,
+            "stop" => GuiCommand::Stop,
+            "usi" => GuiCommand::Usi,
+            "usinewgame" => GuiCommand::UsiNewGame,
+            "quit" => GuiCommand::Quit,
+            _ => return Err(Error::IllegalSyntax),
+        })
+    }
+
+    fn parse_gameover(mut self) -> Result<GuiCommand, Error> {
+        let kind: GameOverKind = self.iter.next().ok_or(Error::IllegalSyntax)?.parse()?;
+        Ok(GuiCommand::GameOver(kind))
+    }
+
+    fn parse_go(mut self) -> Result<GuiCommand, Error> {
+        let mut params = ThinkParams::new();
+
+        while let Some(kind) = self.iter.next() {
+            params = match kind {
+                "ponder" => params.ponder(),
+                "btime" => params.btime(self.next_duration()?),
+                "wtime" => params.wtime(self.next_duration()?),
+                "byoyomi" => params.byoyomi(self.next_duration()?),
+                "binc" => params.binc(self.next_duration()?),
+                "winc" => params.winc(self.next_duration()?),
+                "infinite" => params.infinite(),
+                "depth" => params.depth(self.iter.next().ok_or(Error::IllegalSyntax)?.parse()?),
+                "nodes" => params.nodes(self.iter.next().ok_or(Error::IllegalSyntax)?.parse()?),
+                "movestogo" => {
+                    params.movestogo(self.iter.next().ok_or(Error::IllegalSyntax)?.parse()?)
+                }
+                "movetime" => params.movetime(self.next_duration()?),
+                "mate" => match self.iter.next() {
+                    Some("infinite") => params.mate(MateParam::Infinite),
+                    Some(ms) => params.mate(MateParam::Timeout(Duration::from_millis(ms.parse()?))),
+                    None => return Err(Error::IllegalSyntax),
+                },
+                _ => return Err(Error::IllegalSyntax),
+            };
+        }
+
+        Ok(GuiCommand::Go(params))
+    }
+
+    fn parse_position(mut self) -> Result<GuiCommand, Error> {
+        let mut sfen = match self.iter.next() {
+            Some("sfen") => String::new(),
+            Some("startpos") => STARTPOS_SFEN.to_string(),
+            _ => return Err(Error::IllegalSyntax),
+        };
+
+        for token in self.iter {
+            if !sfen.is_empty() {
+                sfen.push(' ');
+            }
+            sfen.push_str(token);
+        }
+
+        if sfen.is_empty() {
+            return Err(Error::IllegalSyntax);
+        }
+        Ok(GuiCommand::Position(sfen))
+    }
+
+    fn parse_setoption(self) -> Result<GuiCommand, Error> {
+        // Both a name and a value may contain spaces, so the original text is sliced
+        // instead of joining tokens to preserve the value as is.
+        let rest = self.cmd.trim_start()["setoption".len()..].trim_start();
+        let rest = rest.strip_prefix("name").ok_or(Error::IllegalSyntax)?;
+        if !rest.starts_with(char::is_whitespace) {
+            return Err(Error::IllegalSyntax);
+        }
+
+        let (name, value) = match find_token(rest, "value") {
+            Some(pos) => {
+                let value = rest[pos + "value".len()..].trim_start();
+                if value.is_empty() {
+                    return Err(Error::IllegalSyntax);
+                }
+                (&rest[..pos], Some(parse_default(value.trim_end())))
+            }
+            None => (rest, None),
+        };
+
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(Error::IllegalSyntax);
+        }
+
+        Ok(GuiCommand::SetOption(SetOptionParams {
+            name: name.to_string(),
+            value,
+        }))
+    }
+
+    fn next_duration(&mut self) -> Result<Duration, Error> {
+        let ms: u64 = self.iter.next().ok_or(Error::IllegalSyntax)?.parse()?;
+        Ok(Duration::from_millis(ms))
+    }
+}
+
+/// Returns the byte position of the first whitespace-delimited `token` in `s`.
+fn find_token(s: &str, token: &str) -> Option<usize> {
+    s.match_indices(token).map(|(pos, _)| pos).find(|&pos| {
+        let before = s[..pos].chars().next_back();
+        let after = s[pos + token.len()..].chars().next();
+        before.is_none_or(char::is_whitespace) && after.is_none_or(char::is_whitespace)
+    })
+}
+
+/// Concatenates the remaining tokens with a single space.
+fn join<'a, I: Iterator<Item = &'a str>>(iter: I) -> String {
+    let mut s = String::new();
+    for (i, token) in iter.enumerate() {
+        if i > 0 {
+            s.push(' ');
+        }
+        s.push_str(token);
+    }
+    s
+}
+
 fn parse_default(s: &str) -> String {
     if s == "<empty>" {
         String::new()
@@ -0,0 +1,167 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::time::Duration;
+
+use crate::analysis::Score;
+use crate::board::Board;
+use crate::error::Error;
+
+/// The way a game ended, following its last move.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum GameEnding {
+    /// The side to move resigned.
+    Resign,
+    /// The side to move was checkmated.
+    Mate,
+    /// The side to move ran out of time.
+    TimeUp,
+    /// The last move was illegal, and the side to move won.
+    IllegalMove,
+    /// The game was drawn by repetition.
+    Sennichite,
+    /// The game was drawn by impasse or by reaching the maximum number of moves.
+    Jishogi,
+    /// The side to move won by the declaration of the entering king rule.
+    DeclareWin,
+    /// The game was interrupted without a result.
+    Interrupted,
+}
+
+/// A move of `GameRecord`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RecordedMove {
+    /// The move in USI notation.
+    pub mv: String,
+    /// The time spent on the move.
+    pub time: Option<Duration>,
+    /// The score of the position before the move, from the side making it.
+    pub score: Option<Score>,
+    pub comment: Option<String>,
+}
+
+impl RecordedMove {
+    pub fn new(mv: &str) -> Self {
+        RecordedMove {
+            mv: mv.to_string(),
+            time: None,
+            score: None,
+            comment: None,
+        }
+    }
+
+    #[must_use]
+    pub fn time(mut self, time: Duration) -> Self {
+        self.time = Some(time);
+        self
+    }
+
+    #[must_use]
+    pub fn score(mut self, score: Score) -> Self {
+        self.score = Some(score);
+        self
+    }
+
+    #[must_use]
+    pub fn comment(mut self, comment: &str) -> Self {
+        self.comment = Some(comment.to_string());
+        self
+    }
+}
+
+/// `GameRecord` is the record of a game: the players, the initial position,
+/// and the moves with the time spent, the evaluation and comments.
+///
+/// It can be exported to KIF and KI2, the formats read by most shogi GUIs.
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+/// use usi::{GameEnding, GameRecord, RecordedMove};
+///
+/// let mut record = GameRecord::new("lnsgkgsnl/1r5b1/ppppppppp/9/9/9/PPPPPPPPP/1B5R1/LNSGKGSNL b - 1");
+/// record.black_name = Some("Sente".to_string());
+/// record.white_name = Some("Gote".to_string());
+/// record.moves.push(RecordedMove::new("7g7f").time(Duration::from_secs(3)));
+/// record.moves.push(RecordedMove::new("3c3d").time(Duration::from_secs(5)));
+/// record.ending = Some(GameEnding::Resign);
+///
+/// let kif = record.to_kif().unwrap();
+/// assert!(kif.contains("   1 ７六歩(77)   ( 0:03/00:00:03)"));
+/// assert!(kif.ends_with("まで2手で後手の勝ち\n"));
+///
+/// assert!(record.to_ki2().unwrap().contains("▲７六歩    △３四歩"));
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GameRecord {
+    /// The SFEN of the initial position.
+    pub start: String,
+    /// The name of the black player.
+    pub black_name: Option<String>,
+    /// The name of the white player.
+    pub white_name: Option<String>,
+    /// Other headers such as `開始日時` or `棋戦`, as the key and the value.
+    pub headers: Vec<(String, String)>,
+    pub moves: Vec<RecordedMove>,
+    pub ending: Option<GameEnding>,
+}
+
+impl GameRecord {
+    /// Creates a record of a game from the SFEN `start` without moves.
+    pub fn new(start: &str) -> Self {
+        GameRecord {
+            start: start.to_string(),
+            black_name: None,
+            white_name: None,
+            headers: Vec::new(),
+            moves: Vec::new(),
+            ending: None,
+        }
+    }
+
+    /// Returns the moves in USI notation.
+    pub fn usi_moves(&self) -> Vec<String> {
+        self.moves.iter().map(|mv| mv.mv.clone()).collect()
+    }
+
+    /// Returns the position after all the moves as in `GuiCommand::Position`.
+    pub fn position(&self) -> String {
+        crate::csa::position(&self.start, &self.usi_moves())
+    }
+
+    /// Returns the board after all the moves.
+    pub fn board(&self) -> Result<Board, Error> {
+        let mut board = Board::from_sfen(&self.start)?;
+        for mv in &self.moves {
+            board.make_move(&mv.mv.parse()?)?;
+        }
+        Ok(board)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::STARTPOS_SFEN;
+    use alloc::vec;
+
+    #[test]
+    fn position() {
+        let mut record = GameRecord::new(STARTPOS_SFEN);
+        assert_eq!(STARTPOS_SFEN, record.position());
+
+        record.moves.push(RecordedMove::new("7g7f"));
+        record.moves.push(RecordedMove::new("3c3d"));
+        assert_eq!(vec!["7g7f", "3c3d"], record.usi_moves());
+        assert_eq!(
+            alloc::format!("{STARTPOS_SFEN} moves 7g7f 3c3d"),
+            record.position()
+        );
+        assert_eq!(3, record.board().unwrap().ply());
+
+        record.moves.push(RecordedMove::new("7g7f"));
+        assert!(record.board().is_err());
+    }
+}
@@ -0,0 +1,341 @@
+use alloc::format;
+use alloc::string::String;
+use core::fmt::Write;
+use core::time::Duration;
+
+use super::game::{GameEnding, GameRecord};
+use crate::analysis::Score;
+use crate::board::japanese::{
+    color_mark, kanji_numeral, ki2_move, kif_move, kind_letter, kind_name,
+};
+use crate::board::{Board, PieceKind, Square};
+use crate::clock::Color;
+use crate::error::Error;
+use crate::protocol::*;
+
+/// The number of moves on a line of KI2.
+const KI2_MOVES_PER_LINE: usize = 6;
+
+impl GameRecord {
+    /// Exports the record in KIF, with the time spent on each move and the total time
+    /// of the player, and the comments and the evaluations as comment lines.
+    ///
+    /// The initial position is written as `手合割：平手` for the standard position,
+    /// and as a board diagram otherwise. Scores are written from black's side.
+    /// Returns an error if a move doesn't match the position.
+    pub fn to_kif(&self) -> Result<String, Error> {
+        let mut board = Board::from_sfen(&self.start)?;
+        let mut kif = String::from("#KIF version=2.0 encoding=UTF-8\n");
+        self.write_headers(&mut kif, &board);
+        kif.push_str("手数----指手---------消費時間--\n");
+
+        let mut totals = [Duration::ZERO; 2];
+        let mut previous = None;
+        for (i, recorded) in self.moves.iter().enumerate() {
+            let mv = recorded.mv.parse()?;
+            let text = kif_move(&board, &mv, previous)?;
+            let _ = write!(kif, "{:>4} {}", i + 1, text);
+            if let Some(time) = recorded.time {
+                let total = &mut totals[side_index(board.side_to_move())];
+                *total += time;
+                let (secs, total) = (time.as_secs(), total.as_secs());
+                let padding = 13usize.saturating_sub(width(&text));
+                let _ = write!(
+                    kif,
+                    "{:padding$}({:>2}:{:02}/{:02}:{:02}:{:02})",
+                    "",
+                    secs / 60,
+                    secs % 60,
+                    total / 3600,
+                    total / 60 % 60,
+                    total % 60
+                );
+            }
+            kif.push('\n');
+            write_comments(
+                &mut kif,
+                recorded.score,
+                &recorded.comment,
+                board.side_to_move(),
+            );
+            board.make_move(&mv)?;
+            previous = Some(mv.to());
+        }
+
+        if let Some(ending) = self.ending {
+            let _ = writeln!(kif, "{:>4} {}", self.moves.len() + 1, ending_name(ending));
+            let _ = writeln!(kif, "{}", conclusion(ending, self.moves.len(), &board));
+        }
+        Ok(kif)
+    }
+
+    /// Exports the record in KI2, where moves are written with `▲` and `△` and the relative
+    /// position of the piece instead of the square it moves from.
+    ///
+    /// The times spent aren't written. Returns an error if a move doesn't match the position.
+    pub fn to_ki2(&self) -> Result<String, Error> {
+        let mut board = Board::from_sfen(&self.start)?;
+        let mut ki2 = String::new();
+        self.write_headers(&mut ki2, &board);
+
+        let mut on_line = 0;
+        let mut padding = 0;
+        let mut previous = None;
+        for recorded in &self.moves {
+            let mv = recorded.mv.parse()?;
+            let text = ki2_move(&board, &mv, previous)?;
+            let mark = color_mark(board.side_to_move());
+            if on_line > 0 {
+                let _ = write!(ki2, "{:padding$}", "");
+            }
+            let _ = write!(ki2, "{mark}{text}");
+            padding = 10usize.saturating_sub(width(&text));
+            on_line += 1;
+
+            let has_comment = recorded.score.is_some() || recorded.comment.is_some();
+            if has_comment || on_line == KI2_MOVES_PER_LINE {
+                ki2.push('\n');
+                on_line = 0;
+            }
+            write_comments(
+                &mut ki2,
+                recorded.score,
+                &recorded.comment,
+                board.side_to_move(),
+            );
+            board.make_move(&mv)?;
+            previous = Some(mv.to());
+        }
+        if on_line > 0 {
+            ki2.push('\n');
+        }
+
+        if let Some(ending) = self.ending {
+            let _ = writeln!(ki2, "{}", conclusion(ending, self.moves.len(), &board));
+        }
+        Ok(ki2)
+    }
+
+    fn write_headers(&self, out: &mut String, board: &Board) {
+        for (key, value) in &self.headers {
+            let _ = writeln!(out, "{key}：{value}");
+        }
+        if *board == Board::startpos() {
+            out.push_str("手合割：平手\n");
+        } else {
+            write_diagram(out, board);
+        }
+        if let Some(name) = &self.black_name {
+            let _ = writeln!(out, "先手：{name}");
+        }
+        if let Some(name) = &self.white_name {
+            let _ = writeln!(out, "後手：{name}");
+        }
+    }
+}
+
+/// Writes the board diagram of a position other than the standard one.
+fn write_diagram(out: &mut String, board: &Board) {
+    write_hand(out, board, Color::White);
+    out.push_str("  ９ ８ ７ ６ ５ ４ ３ ２ １\n+---------------------------+\n");
+    for rank in 1..=9 {
+        out.push('|');
+        for file in (1..=9).rev() {
+            match board.piece_at(Square::new(file, rank).unwrap()) {
+                Some(piece) => {
+                    out.push(match piece.color {
+                        Color::Black => ' ',
+                        Color::White => 'v',
+                    });
+                    out.push(kind_letter(piece.kind));
+                }
+                None => out.push_str(" ・"),
+            }
+        }
+        let _ = writeln!(out, "|{}", kanji_numeral(rank));
+    }
+    out.push_str("+---------------------------+\n");
+    write_hand(out, board, Color::Black);
+    if board.side_to_move() == Color::White {
+        out.push_str("後手番\n");
+    }
+}
+
+fn write_hand(out: &mut String, board: &Board, color: Color) {
+    out.push_str(match color {
+        Color::Black => "先手の持駒：",
+        Color::White => "後手の持駒：",
+    });
+    let mut empty = true;
+    for kind in PieceKind::HAND {
+        let count = board.hand(color, kind);
+        if count == 0 {
+            continue;
+        }
+        if !empty {
+            out.push('　');
+        }
+        empty = false;
+        out.push_str(kind_name(kind));
+        match count {
+            1 => {}
+            2..=9 => out.push(kanji_numeral(count)),
+            _ => {
+                out.push('十');
+                if count > 10 {
+                    out.push(kanji_numeral(count - 10));
+                }
+            }
+        }
+    }
+    if empty {
+        out.push_str("なし");
+    }
+    out.push('\n');
+}
+
+/// Writes the score from black's side and the comment as lines starting with `*`.
+fn write_comments(out: &mut String, score: Option<Score>, comment: &Option<String>, side: Color) {
+    if let Some(score) = score {
+        let score = match side {
+            Color::Black => score,
+            Color::White => Score::new(-score.value, flip_bound(score.kind)),
+        };
+        let _ = writeln!(out, "*評価値 {}", score.to_japanese());
+    }
+    if let Some(comment) = comment {
+        for line in comment.lines() {
+            let _ = writeln!(out, "*{line}");
+        }
+    }
+}
+
+fn flip_bound(kind: ScoreKind) -> ScoreKind {
+    match kind {
+        ScoreKind::CpLowerbound => ScoreKind::CpUpperbound,
+        ScoreKind::CpUpperbound => ScoreKind::CpLowerbound,
+        ScoreKind::MateLowerbound => ScoreKind::MateUpperbound,
+        ScoreKind::MateUpperbound => ScoreKind::MateLowerbound,
+        kind => kind,
+    }
+}
+
+fn ending_name(ending: GameEnding) -> &'static str {
+    match ending {
+        GameEnding::Resign => "投了",
+        GameEnding::Mate => "詰み",
+        GameEnding::TimeUp => "切れ負け",
+        GameEnding::IllegalMove => "反則勝ち",
+        GameEnding::Sennichite => "千日手",
+        GameEnding::Jishogi => "持将棋",
+        GameEnding::DeclareWin => "入玉勝ち",
+        GameEnding::Interrupted => "中断",
+    }
+}
+
+/// Returns the last line of a record, e.g. `まで86手で先手の勝ち`.
+fn conclusion(ending: GameEnding, moves: usize, board: &Board) -> String {
+    let side = |color| match color {
+        Color::Black => "先手",
+        Color::White => "後手",
+    };
+    let to_move = side(board.side_to_move());
+    let other = side(board.side_to_move().flip());
+    match ending {
+        GameEnding::Resign | GameEnding::Mate => format!("まで{moves}手で{other}の勝ち"),
+        GameEnding::TimeUp => format!("まで{moves}手で時間切れにより{other}の勝ち"),
+        GameEnding::IllegalMove => format!("まで{moves}手で{to_move}の反則勝ち"),
+        GameEnding::Sennichite => format!("まで{moves}手で千日手"),
+        GameEnding::Jishogi => format!("まで{moves}手で持将棋"),
+        GameEnding::DeclareWin => format!("まで{moves}手で入玉宣言により{to_move}の勝ち"),
+        GameEnding::Interrupted => format!("まで{moves}手で中断"),
+    }
+}
+
+fn side_index(color: Color) -> usize {
+    match color {
+        Color::Black => 0,
+        Color::White => 1,
+    }
+}
+
+/// Returns the width of `s` in a monospaced font, where non-ASCII letters are double width.
+fn width(s: &str) -> usize {
+    s.chars().map(|c| if c.is_ascii() { 1 } else { 2 }).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::record::RecordedMove;
+
+    fn record() -> GameRecord {
+        let mut record = GameRecord::new(STARTPOS_SFEN);
+        record.black_name = Some("alice".into());
+        record.white_name = Some("bob".into());
+        record.headers.push(("棋戦".into(), "test".into()));
+        for (mv, secs) in [("7g7f", 1), ("3c3d", 2), ("8h2b+", 61), ("3a2b", 3)] {
+            record
+                .moves
+                .push(RecordedMove::new(mv).time(Duration::from_secs(secs)));
+        }
+        record.moves[1] = record.moves[1]
+            .clone()
+            .score(Score::new(-30, ScoreKind::CpExact))
+            .comment("opening");
+        record.ending = Some(GameEnding::Resign);
+        record
+    }
+
+    #[test]
+    fn kif() {
+        assert_eq!(
+            "#KIF version=2.0 encoding=UTF-8\n\
+             棋戦：test\n\
+             手合割：平手\n\
+             先手：alice\n\
+             後手：bob\n\
+             手数----指手---------消費時間--\n   \
+             1 ７六歩(77)   ( 0:01/00:00:01)\n   \
+             2 ３四歩(33)   ( 0:02/00:00:02)\n\
+             *評価値 +30\n\
+             *opening\n   \
+             3 ２二角成(88) ( 1:01/00:01:02)\n   \
+             4 同　銀(31)   ( 0:03/00:00:05)\n   \
+             5 投了\n\
+             まで4手で後手の勝ち\n",
+            record().to_kif().unwrap()
+        );
+
+        let mut record = record();
+        record.moves.push(RecordedMove::new("2b2a"));
+        assert!(record.to_kif().is_err());
+    }
+
+    #[test]
+    fn ki2() {
+        assert_eq!(
+            "棋戦：test\n\
+             手合割：平手\n\
+             先手：alice\n\
+             後手：bob\n\
+             ▲７六歩    △３四歩\n\
+             *評価値 +30\n\
+             *opening\n\
+             ▲２二角成  △同　銀\n\
+             まで4手で後手の勝ち\n",
+            record().to_ki2().unwrap()
+        );
+    }
+
+    #[test]
+    fn diagram() {
+        let mut record = GameRecord::new("4k4/9/9/9/9/9/9/9/4K4 w G2P 1");
+        record.ending = Some(GameEnding::Interrupted);
+        let kif = record.to_kif().unwrap();
+        assert!(kif.contains("後手の持駒：なし\n"));
+        assert!(kif.contains("| ・ ・ ・ ・v玉 ・ ・ ・ ・|一\n"));
+        assert!(kif.contains("先手の持駒：金　歩二\n後手番\n"));
+        assert!(kif.ends_with("   1 中断\nまで0手で中断\n"));
+    }
+}
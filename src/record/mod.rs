@@ -0,0 +1,4 @@
+mod game;
+mod kif;
+
+pub use self::game::{GameEnding, GameRecord, RecordedMove};
@@ -0,0 +1,336 @@
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+
+use super::info::InfoSender;
+use super::options::OptionRegistry;
+use super::stop::StopToken;
+use crate::error::Error;
+use crate::protocol::*;
+
+/// `UsiEngine` is implemented by USI engines written in Rust.
+///
+/// The engine implements the game logic only. `EngineServer` drives the callbacks and
+/// takes care of the protocol: identifying the engine and declaring options on `usi`,
+/// and answering `isready` once `prepare` returns.
+///
+/// Searches should run on another thread so that `stop` and `ponderhit` can be handled
+/// while searching. The results are sent through the `Responder` passed to `go`.
+pub trait UsiEngine: Send + 'static {
+    /// Called with every command before `EngineServer` handles it. Returns `true` if the
+    /// command was handled, which skips the handling of `EngineServer` and the callbacks below,
+    /// e.g. to implement nonstandard commands or to answer `usi` by itself.
+    ///
+    /// `responder` belongs to the current search, which `go` starts even if it is handled,
+    /// and `quit` ends the server either way.
+    fn handle(&mut self, command: &GuiCommand, responder: &Responder) -> bool {
+        let _ = (command, responder);
+        false
+    }
+
+    /// Returns the name of the engine sent in `id name`.
+    fn name(&self) -> String;
+
+    /// Returns the author of the engine sent in `id author`.
+    fn author(&self) -> String;
+
+    /// Returns the options declared on `usi`. Called once by `EngineServer`.
+    /// Standard options such as `USI_Hash` are declared only if included, e.g. by
+    /// `OptionRegistry::with_usi_hash`.
+    fn options(&self) -> OptionRegistry {
+        OptionRegistry::new()
+    }
+
+    /// Called on `setoption` with the value validated against the declared option.
+    /// Invalid values are reported to the GUI by `info string` instead.
+    fn set_option(&mut self, name: &str, value: OptionValue) {
+        let _ = (name, value);
+    }
+
+    /// Called on `isready` until the engine gets ready, e.g. to load evaluation files.
+    /// `readyok` is sent once this returns, and `isready` is answered immediately afterwards
+    /// until an option is changed.
+    ///
+    /// `options` holds the current values of the declared options.
+    /// Loading started in the background can be awaited here.
+    fn prepare(&mut self, options: &OptionRegistry) {
+        let _ = options;
+    }
+
+    /// Called on `usinewgame`.
+    fn new_game(&mut self) {}
+
+    /// Called on `position` with the SFEN of the position, followed by the moves if any.
+    fn set_position(&mut self, sfen: &str) {
+        let _ = sfen;
+    }
+
+    /// Called on `go`. The search should end with `bestmove` sent through `responder`.
+    /// `info` sends the progress of the search, and `stop` tells when to stop it.
+    fn go(&mut self, params: ThinkParams, responder: &Responder, info: InfoSender, stop: StopToken);
+
+    /// Called on `stop` after the `StopToken` of the search is stopped.
+    /// The current search should send `bestmove` as soon as possible.
+    fn stop(&mut self, responder: &Responder) {
+        let _ = responder;
+    }
+
+    /// Called on `ponderhit` after the `StopToken` of the search records it.
+    /// The current ponder search continues as a normal search.
+    fn ponderhit(&mut self, responder: &Responder) {
+        let _ = responder;
+    }
+
+    /// Called on `gameover`.
+    fn game_over(&mut self, result: GameOverKind) {
+        let _ = result;
+    }
+
+    /// Called on the nonstandard `bench` command with its arguments.
+    /// The returned report is written to the GUI, and `None` means `bench` is not supported.
+    fn bench(&mut self, args: &[String]) -> Option<BenchReport> {
+        let _ = args;
+        None
+    }
+
+    /// Called when the GUI sends `quit` or closes the connection,
+    /// e.g. to persist learning data or close files.
+    ///
+    /// `EngineServer` returns after the grace period even if this hasn't returned.
+    fn quit(&mut self) {}
+}
+
+/// Represents whether the engine is searching, as seen by the protocol.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) enum SearchState {
+    #[default]
+    Idle,
+    /// `bestmove` may be sent at any time.
+    Searching,
+    /// `bestmove` waits for `stop`, as the search is `go infinite`.
+    Infinite,
+    /// `bestmove` waits for `stop` or `ponderhit`.
+    Pondering { infinite: bool },
+}
+
+/// The search state shared by the clones of a `Responder`.
+#[derive(Debug, Default)]
+struct Shared {
+    state: SearchState,
+    /// Counts the searches started so far, identifying the current one.
+    search: u64,
+}
+
+/// `Responder` sends commands from a `UsiEngine` to the GUI.
+///
+/// `Responder` keeps the output in protocol order: exactly one `bestmove` is sent per `go`,
+/// and `info` is sent only while searching, except `info string`.
+/// `bestmove` of a ponder or infinite search waits for `ponderhit` or `stop`, and
+/// a responder of a previous search can't send anything but `info string`.
+/// Commands breaking the order are not sent and fail with `Error::ProtocolViolation`.
+///
+/// `Responder` can be cloned and moved to search threads.
+#[derive(Clone, Debug)]
+pub struct Responder {
+    sender: Sender<String>,
+    shared: Arc<Mutex<Shared>>,
+    /// The search this responder was passed to, if any.
+    search: Option<u64>,
+    dialect: Arc<dyn Dialect + Send + Sync>,
+}
+
+impl Responder {
+    pub(crate) fn new(sender: Sender<String>) -> Self {
+        Responder::with_dialect(sender, Arc::new(Usi))
+    }
+
+    /// Creates a responder formatting the commands in `dialect`.
+    pub(crate) fn with_dialect(
+        sender: Sender<String>,
+        dialect: Arc<dyn Dialect + Send + Sync>,
+    ) -> Self {
+        Responder {
+            sender,
+            shared: Arc::default(),
+            search: None,
+            dialect,
+        }
+    }
+
+    /// Sends a command to the GUI.
+    /// Returns `Error::EngineClosed` when the GUI has closed the connection, and
+    /// `Error::InvalidArgument` if the command can't be expressed in the dialect of the server.
+    pub fn send(&self, command: &EngineCommand) -> Result<(), Error> {
+        let line = self.dialect.format_engine(command)?;
+        let mut shared = self.shared.lock().unwrap();
+        let stale = self.search.is_some_and(|search| search != shared.search);
+        match command {
+            EngineCommand::BestMove(_) if stale => {
+                return Err(Error::ProtocolViolation {
+                    reason: "bestmove of a previous search",
+                });
+            }
+            EngineCommand::BestMove(_) => match shared.state {
+                SearchState::Idle => {
+                    return Err(Error::ProtocolViolation {
+                        reason: "bestmove without a search",
+                    });
+                }
+                SearchState::Searching => shared.state = SearchState::Idle,
+                SearchState::Infinite | SearchState::Pondering { .. } => {
+                    return Err(Error::ProtocolViolation {
+                        reason: "bestmove before stop or ponderhit",
+                    });
+                }
+            },
+            EngineCommand::Info(params)
+                if (stale || shared.state == SearchState::Idle)
+                    && !params.iter().all(|p| matches!(p, InfoParams::Text(_))) =>
+            {
+                return Err(Error::ProtocolViolation {
+                    reason: "info outside a search",
+                });
+            }
+            _ => {}
+        }
+        // Send while locked so that the order of the state and the output agree.
+        self.sender.send(line).map_err(|_| Error::EngineClosed)
+    }
+
+    /// Sends a line which is not a command, such as the result of `bench`.
+    pub(crate) fn send_text(&self, text: String) -> Result<(), Error> {
+        self.sender.send(text).map_err(|_| Error::EngineClosed)
+    }
+
+    /// Returns the protocol spoken with the GUI.
+    pub(crate) fn dialect(&self) -> &(dyn Dialect + Send + Sync) {
+        self.dialect.as_ref()
+    }
+
+    /// Returns the current search state.
+    #[cfg(test)]
+    pub(crate) fn state(&self) -> SearchState {
+        self.shared.lock().unwrap().state
+    }
+
+    /// Records the start of a search, and returns the responder to pass to it.
+    /// Fails with `Error::ProtocolViolation` unless the previous search has sent `bestmove`.
+    pub(crate) fn start_search(&self, params: &ThinkParams) -> Result<Responder, Error> {
+        let mut shared = self.shared.lock().unwrap();
+        if shared.state != SearchState::Idle {
+            return Err(Error::ProtocolViolation {
+                reason: "go while searching",
+            });
+        }
+        shared.state = match (params.is_ponder(), params.is_infinite()) {
+            (true, infinite) => SearchState::Pondering { infinite },
+            (false, true) => SearchState::Infinite,
+            (false, false) => SearchState::Searching,
+        };
+        shared.search += 1;
+        Ok(Responder {
+            search: Some(shared.search),
+            ..self.clone()
+        })
+    }
+
+    /// Allows the current search to send `bestmove`. Returns `false` if it has already ended.
+    pub(crate) fn stop(&self) -> bool {
+        let mut shared = self.shared.lock().unwrap();
+        if shared.state == SearchState::Idle {
+            return false;
+        }
+        shared.state = SearchState::Searching;
+        true
+    }
+
+    /// Turns pondering into a normal search. Returns `false` if the engine is not pondering.
+    pub(crate) fn ponderhit(&self) -> bool {
+        let mut shared = self.shared.lock().unwrap();
+        shared.state = match shared.state {
+            SearchState::Pondering { infinite: true } => SearchState::Infinite,
+            SearchState::Pondering { infinite: false } => SearchState::Searching,
+            _ => return false,
+        };
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[test]
+    fn protocol_order() {
+        let (sender, receiver) = mpsc::channel();
+        let responder = Responder::new(sender);
+        let info = EngineCommand::Info(vec![InfoParams::Depth(1, None)]);
+        let text = EngineCommand::Info(vec![InfoParams::Text("hello".to_string())]);
+        let bestmove = EngineCommand::BestMove(BestMoveParams::Resign);
+
+        assert!(matches!(
+            responder.send(&info),
+            Err(Error::ProtocolViolation { .. })
+        ));
+        assert!(matches!(
+            responder.send(&bestmove),
+            Err(Error::ProtocolViolation { .. })
+        ));
+        responder.send(&text).unwrap();
+        assert!(!responder.ponderhit());
+        assert!(!responder.stop());
+
+        let search = responder
+            .start_search(&ThinkParams::new().ponder())
+            .unwrap();
+        search.send(&info).unwrap();
+        assert!(search.send(&bestmove).is_err());
+        assert!(responder.ponderhit());
+        assert_eq!(SearchState::Searching, responder.state());
+        search.send(&bestmove).unwrap();
+        assert!(search.send(&bestmove).is_err());
+        assert_eq!(SearchState::Idle, responder.state());
+
+        assert_eq!(
+            vec!["info string hello", "info depth 1", "bestmove resign"],
+            receiver.try_iter().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn bestmove_waits_for_stop() {
+        let (sender, receiver) = mpsc::channel();
+        let responder = Responder::new(sender);
+        let bestmove = EngineCommand::BestMove(BestMoveParams::Resign);
+
+        let search = responder
+            .start_search(&ThinkParams::new().ponder().infinite())
+            .unwrap();
+        assert!(responder.start_search(&ThinkParams::new()).is_err());
+        assert!(responder.ponderhit());
+        assert_eq!(SearchState::Infinite, responder.state());
+        assert!(matches!(
+            search.send(&bestmove),
+            Err(Error::ProtocolViolation { .. })
+        ));
+        assert!(responder.stop());
+        search.send(&bestmove).unwrap();
+        assert!(!responder.stop());
+
+        // A late bestmove of the previous search can't end the next one.
+        let next = responder.start_search(&ThinkParams::new()).unwrap();
+        assert!(matches!(
+            search.send(&bestmove),
+            Err(Error::ProtocolViolation { .. })
+        ));
+        assert!(search
+            .send(&EngineCommand::Info(vec![InfoParams::Depth(1, None)]))
+            .is_err());
+        next.send(&bestmove).unwrap();
+
+        assert_eq!(
+            vec!["bestmove resign", "bestmove resign"],
+            receiver.try_iter().collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,405 @@
+use std::fmt;
+use std::io::{self, BufRead, Write};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::engine::{Responder, UsiEngine};
+use super::info::{InfoSender, DEFAULT_INFO_INTERVAL};
+use super::options::OptionRegistry;
+use super::stop::StopToken;
+use crate::error::Error;
+use crate::protocol::*;
+
+/// The default time given to the engine to clean up after `quit`.
+const DEFAULT_QUIT_GRACE_PERIOD: Duration = Duration::from_secs(1);
+
+/// `EngineServer` runs a `UsiEngine` over stdin and stdout, the mirror image of `Engine`.
+///
+/// Lines from the GUI are parsed into `GuiCommand`s and dispatched to the engine.
+/// Responses are written on a dedicated thread and flushed line by line, so the engine can
+/// respond from any thread. The server returns after `quit` or when stdin is closed,
+/// once `UsiEngine::quit` returns or the grace period elapses.
+///
+/// # Examples
+///
+/// ```no_run
+/// use usi::{
+///     BestMoveParams, EngineCommand, EngineServer, InfoParams, InfoSender, Responder, StopToken,
+///     ThinkParams, UsiEngine,
+/// };
+///
+/// struct MyEngine;
+///
+/// impl UsiEngine for MyEngine {
+///     fn name(&self) -> String {
+///         "MyEngine".to_string()
+///     }
+///
+///     fn author(&self) -> String {
+///         "me".to_string()
+///     }
+///
+///     fn go(&mut self, _params: ThinkParams, responder: &Responder, info: InfoSender, _stop: StopToken) {
+///         let _ = info.send(vec![InfoParams::Depth(1, None)]);
+///         let _ = responder.send(&EngineCommand::BestMove(BestMoveParams::Resign));
+///     }
+/// }
+///
+/// EngineServer::new(MyEngine).run().unwrap();
+/// ```
+pub struct EngineServer<E> {
+    engine: E,
+    info_interval: Duration,
+    quit_grace_period: Duration,
+    dialect: Arc<dyn Dialect + Send + Sync>,
+}
+
+impl<E> fmt::Debug for EngineServer<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("EngineServer").finish_non_exhaustive()
+    }
+}
+
+impl<E: UsiEngine> EngineServer<E> {
+    /// Creates a server running `engine`.
+    pub fn new(engine: E) -> Self {
+        EngineServer {
+            engine,
+            info_interval: DEFAULT_INFO_INTERVAL,
+            quit_grace_period: DEFAULT_QUIT_GRACE_PERIOD,
+            dialect: Arc::new(Usi),
+        }
+    }
+
+    /// Sets the protocol spoken with the GUI, e.g. `Uci` to serve chess GUIs.
+    /// Defaults to `Usi`.
+    #[must_use]
+    pub fn dialect<D: Dialect + Send + Sync + 'static>(mut self, dialect: D) -> Self {
+        self.dialect = Arc::new(dialect);
+        self
+    }
+
+    /// Sets the minimum interval between `info` lines sent by `InfoSender`. Defaults to 100 ms.
+    #[must_use]
+    pub fn info_interval(mut self, interval: Duration) -> Self {
+        self.info_interval = interval;
+        self
+    }
+
+    /// Sets how long the server waits for `UsiEngine::quit` and pending responses
+    /// before returning. Defaults to 1 second.
+    #[must_use]
+    pub fn quit_grace_period(mut self, period: Duration) -> Self {
+        self.quit_grace_period = period;
+        self
+    }
+
+    /// Serves the GUI over stdin and stdout until `quit` is received or stdin is closed.
+    pub fn run(self) -> Result<(), Error> {
+        self.run_with(io::stdin().lock(), io::stdout())
+    }
+
+    /// Serves the GUI over `reader` and `writer` until `quit` is received or `reader` is closed.
+    pub fn run_with<R, W>(self, reader: R, mut writer: W) -> Result<(), Error>
+    where
+        R: BufRead,
+        W: Write + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::channel::<String>();
+        let (done_sender, done) = mpsc::channel::<()>();
+        thread::spawn(move || {
+            for line in receiver {
+                // Once the GUI is gone, responders fail as the receiver is dropped.
+                if writeln!(writer, "{}", line)
+                    .and_then(|_| writer.flush())
+                    .is_err()
+                {
+                    break;
+                }
+            }
+            let _ = done_sender.send(());
+        });
+
+        let mut engine = self.engine;
+        let responder = Responder::with_dialect(sender, self.dialect);
+        let mut dispatcher = Dispatcher::new(responder, self.info_interval);
+        let mut res = Ok(());
+        for line in reader.lines() {
+            match line {
+                Ok(line) if dispatcher.dispatch(&mut engine, &line) => break,
+                Ok(_) => {}
+                Err(e) => {
+                    res = Err(Error::EngineIo(e));
+                    break;
+                }
+            }
+        }
+        drop(dispatcher);
+
+        // The hook runs on another thread so that a hung engine can't keep the server alive.
+        let deadline = Instant::now() + self.quit_grace_period;
+        let (quit_sender, quit_done) = mpsc::channel::<()>();
+        thread::spawn(move || {
+            engine.quit();
+            let _ = quit_sender.send(());
+        });
+        let _ = quit_done.recv_timeout(self.quit_grace_period);
+
+        // Responders cloned to other threads may still be alive, so wait only for a while.
+        let _ = done.recv_timeout(deadline.saturating_duration_since(Instant::now()));
+        res
+    }
+}
+
+/// `Dispatcher` parses lines from the GUI and drives the callbacks of a `UsiEngine`.
+pub(crate) struct Dispatcher {
+    responder: Responder,
+    /// The responder passed to the current or last search.
+    search: Responder,
+    info_interval: Duration,
+    options: Option<OptionRegistry>,
+    ready: bool,
+    stop: StopToken,
+}
+
+impl Drop for Dispatcher {
+    fn drop(&mut self) {
+        // A search can't outlive the connection.
+        self.stop.stop();
+    }
+}
+
+impl Dispatcher {
+    pub(crate) fn new(responder: Responder, info_interval: Duration) -> Self {
+        Dispatcher {
+            search: responder.clone(),
+            responder,
+            info_interval,
+            options: None,
+            ready: false,
+            stop: StopToken::new(),
+        }
+    }
+
+    /// Parses `line` and dispatches it to `engine`.
+    /// Returns `true` if the command was `quit`.
+    pub(crate) fn dispatch<E: UsiEngine>(&mut self, engine: &mut E, line: &str) -> bool {
+        // Ignore illegal commands.
+        let Ok(command) = self
+            .responder
+            .dialect()
+            .parse_gui(line.trim_end_matches('\r'))
+        else {
+            return false;
+        };
+
+        let responder = &self.responder;
+        // Failures to respond mean the GUI is gone, which ends the input as well.
+        // The search state is kept even for commands the engine handles by itself.
+        match &command {
+            GuiCommand::Go(params) => match responder.start_search(params) {
+                Ok(search) => {
+                    self.stop = StopToken::new();
+                    self.search = search;
+                }
+                Err(_) => {
+                    let text = InfoParams::Text("go while searching is ignored".to_string());
+                    let _ = responder.send(&EngineCommand::Info(vec![text]));
+                    return false;
+                }
+            },
+            // Nothing to stop once `bestmove` is sent.
+            GuiCommand::Stop if !responder.stop() => return false,
+            GuiCommand::Stop | GuiCommand::Quit => self.stop.stop(),
+            GuiCommand::Ponderhit if responder.ponderhit() => self.stop.ponderhit(),
+            GuiCommand::Ponderhit => {
+                let text = InfoParams::Text("ponderhit without pondering is ignored".to_string());
+                let _ = responder.send(&EngineCommand::Info(vec![text]));
+                return false;
+            }
+            _ => {}
+        }
+        if engine.handle(&command, &self.search) {
+            return command == GuiCommand::Quit;
+        }
+
+        match command {
+            GuiCommand::Usi => {
+                let _ = responder.send(&EngineCommand::Id(IdParams::Name(engine.name())));
+                let _ = responder.send(&EngineCommand::Id(IdParams::Author(engine.author())));
+                let options = registry(&mut self.options, engine);
+                for option in options.options() {
+                    let _ = responder.send(&EngineCommand::Option(option.clone()));
+                }
+                let _ = responder.send(&EngineCommand::UsiOk);
+            }
+            GuiCommand::IsReady => {
+                if !self.ready {
+                    engine.prepare(registry(&mut self.options, engine));
+                    self.ready = true;
+                }
+                let _ = responder.send(&EngineCommand::ReadyOk);
+            }
+            GuiCommand::SetOption(params) => {
+                let options = registry(&mut self.options, engine);
+                match options.set(&params) {
+                    Ok(value) => {
+                        // The engine may have to reload files for the new value.
+                        self.ready = false;
+                        engine.set_option(&params.name, value)
+                    }
+                    Err(e) => {
+                        let text = InfoParams::Text(e.to_string());
+                        let _ = responder.send(&EngineCommand::Info(vec![text]));
+                    }
+                }
+            }
+            GuiCommand::UsiNewGame => engine.new_game(),
+            GuiCommand::Position(sfen) => engine.set_position(&sfen),
+            GuiCommand::Go(params) => {
+                let info = InfoSender::new(self.search.clone(), self.info_interval);
+                engine.go(params, &self.search, info, self.stop.clone())
+            }
+            GuiCommand::Stop => engine.stop(&self.search),
+            GuiCommand::Ponderhit => engine.ponderhit(&self.search),
+            GuiCommand::GameOver(result) => engine.game_over(result),
+            GuiCommand::Bench(args) => match engine.bench(&args) {
+                Some(report) => {
+                    for line in report.to_string().lines() {
+                        let _ = responder.send_text(line.to_string());
+                    }
+                }
+                None => {
+                    let text = InfoParams::Text("bench is not supported".to_string());
+                    let _ = responder.send(&EngineCommand::Info(vec![text]));
+                }
+            },
+            GuiCommand::Quit => return true,
+        }
+        false
+    }
+}
+
+/// Returns the options of `engine`, which are declared on the first call.
+fn registry<'a, E: UsiEngine>(
+    options: &'a mut Option<OptionRegistry>,
+    engine: &E,
+) -> &'a mut OptionRegistry {
+    options.get_or_insert_with(|| engine.options())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::ToyEngine;
+    use std::io::Read;
+
+    fn toy_engine() -> ToyEngine {
+        ToyEngine::new()
+            .options(
+                OptionRegistry::new()
+                    .check("USI_Ponder", false)
+                    .with_usi_hash(),
+            )
+            .info(vec![InfoParams::Depth(1, None)])
+    }
+
+    #[test]
+    fn run_with() {
+        let (mut reader, writer) = io::pipe().unwrap();
+        let input = "usi\r\nfoo\nisready\nsetoption name USI_Ponder value true\n\
+                     setoption name USI_Ponder value 1\nisready\nisready\ngo\nquit\nisready\n";
+        let engine = toy_engine();
+        let calls = engine.calls();
+        EngineServer::new(engine)
+            .run_with(input.as_bytes(), writer)
+            .unwrap();
+
+        let mut output = String::new();
+        reader.read_to_string(&mut output).unwrap();
+        assert_eq!(
+            "id name Toy\nid author usi-rs\noption name USI_Ponder type check default false\n\
+             option name USI_Hash type spin default 256 min 1 max 1048576\n\
+             usiok\nreadyok\ninfo string invalid value \"1\" for option USI_Ponder\n\
+             readyok\nreadyok\ninfo depth 1\nbestmove resign\n",
+            output
+        );
+        // The second `isready` after `setoption` prepares again, but not the third.
+        assert_eq!(
+            vec![
+                "prepare USI_Hash 256",
+                "set_option USI_Ponder true",
+                "prepare USI_Hash 256",
+                "quit",
+            ],
+            *calls.lock().unwrap()
+        );
+    }
+
+    #[test]
+    fn uci() {
+        let (mut reader, writer) = io::pipe().unwrap();
+        let input = "uci\nisready\ngo movetime 10\nquit\n";
+        EngineServer::new(toy_engine())
+            .dialect(Uci)
+            .run_with(input.as_bytes(), writer)
+            .unwrap();
+
+        let mut output = String::new();
+        reader.read_to_string(&mut output).unwrap();
+        assert!(output.contains("uciok\nreadyok\n"), "{output}");
+        assert!(output.ends_with("bestmove resign\n"), "{output}");
+    }
+
+    /// Answers `usi` and `go` by itself.
+    struct RawEngine;
+
+    impl UsiEngine for RawEngine {
+        fn handle(&mut self, command: &GuiCommand, responder: &Responder) -> bool {
+            let response = match command {
+                GuiCommand::Usi => EngineCommand::UsiOk,
+                GuiCommand::Go(_) => EngineCommand::BestMove(BestMoveParams::Resign),
+                _ => return false,
+            };
+            responder.send(&response).unwrap();
+            true
+        }
+
+        fn name(&self) -> String {
+            unreachable!()
+        }
+
+        fn author(&self) -> String {
+            unreachable!()
+        }
+
+        fn go(&mut self, _: ThinkParams, _: &Responder, _: InfoSender, _: StopToken) {
+            unreachable!()
+        }
+    }
+
+    #[test]
+    fn handle() {
+        let (mut reader, writer) = io::pipe().unwrap();
+        EngineServer::new(RawEngine)
+            .run_with("usi\nisready\ngo\nquit\n".as_bytes(), writer)
+            .unwrap();
+
+        let mut output = String::new();
+        reader.read_to_string(&mut output).unwrap();
+        assert_eq!("usiok\nreadyok\nbestmove resign\n", output);
+    }
+
+    #[test]
+    fn quit() {
+        for input in ["usi\nquit\n", "usi\n"] {
+            let engine = toy_engine();
+            let calls = engine.calls();
+            EngineServer::new(engine)
+                .run_with(input.as_bytes(), io::sink())
+                .unwrap();
+            assert_eq!(vec!["quit"], *calls.lock().unwrap());
+        }
+    }
+}
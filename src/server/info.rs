@@ -0,0 +1,123 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use super::engine::Responder;
+use crate::error::Error;
+use crate::protocol::*;
+
+/// The default minimum interval between `info` lines sent by `InfoSender`.
+pub(crate) const DEFAULT_INFO_INTERVAL: Duration = Duration::from_millis(100);
+
+/// `InfoSender` sends `info` lines of a search to the GUI.
+///
+/// Lines are rate limited: a line sent within the interval of the previous one is dropped,
+/// so a search thread can report as often as it likes. `send_now` bypasses the limit,
+/// e.g. for the final line before `bestmove`.
+///
+/// `pv` and `string` consume the rest of the line, so `pv` is always moved to the end
+/// of a line and `string` is sent as a separate line.
+///
+/// `InfoSender` can be cloned and moved to search threads. The clones share the limit.
+#[derive(Clone, Debug)]
+pub struct InfoSender {
+    responder: Responder,
+    interval: Duration,
+    last_sent: Arc<Mutex<Option<Instant>>>,
+}
+
+impl InfoSender {
+    pub(crate) fn new(responder: Responder, interval: Duration) -> Self {
+        InfoSender {
+            responder,
+            interval,
+            last_sent: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Sends `params` unless the previous line was sent within the interval.
+    /// Returns whether the line was sent. `string` is never dropped.
+    pub fn send(&self, params: Vec<InfoParams>) -> Result<bool, Error> {
+        let (params, texts) = split(params);
+        let mut sent = false;
+        if !params.is_empty() {
+            let now = Instant::now();
+            let mut last_sent = self.last_sent.lock().unwrap();
+            if last_sent.is_none_or(|last| now.duration_since(last) >= self.interval) {
+                *last_sent = Some(now);
+                drop(last_sent);
+                self.responder.send(&EngineCommand::Info(params))?;
+                sent = true;
+            }
+        }
+        self.send_texts(texts)?;
+        Ok(sent)
+    }
+
+    /// Sends `params` regardless of the interval.
+    pub fn send_now(&self, params: Vec<InfoParams>) -> Result<(), Error> {
+        let (params, texts) = split(params);
+        if !params.is_empty() {
+            *self.last_sent.lock().unwrap() = Some(Instant::now());
+            self.responder.send(&EngineCommand::Info(params))?;
+        }
+        self.send_texts(texts)
+    }
+
+    /// Sends `info string` with `text`.
+    pub fn send_string<S: Into<String>>(&self, text: S) -> Result<(), Error> {
+        self.send_texts(vec![text.into()])
+    }
+
+    fn send_texts(&self, texts: Vec<String>) -> Result<(), Error> {
+        for text in texts {
+            self.responder
+                .send(&EngineCommand::Info(vec![InfoParams::Text(text)]))?;
+        }
+        Ok(())
+    }
+}
+
+/// Separates `string` parameters and moves the last `pv` to the end.
+fn split(params: Vec<InfoParams>) -> (Vec<InfoParams>, Vec<String>) {
+    let mut rest = Vec::with_capacity(params.len());
+    let mut pv = None;
+    let mut texts = Vec::new();
+    for param in params {
+        match param {
+            InfoParams::Pv(_) => pv = Some(param),
+            InfoParams::Text(text) => texts.push(text),
+            _ => rest.push(param),
+        }
+    }
+    rest.extend(pv);
+    (rest, texts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+
+    #[test]
+    fn send() {
+        let (sender, receiver) = mpsc::channel();
+        let responder = Responder::new(sender)
+            .start_search(&ThinkParams::new())
+            .unwrap();
+        let info = InfoSender::new(responder, Duration::from_secs(60));
+
+        let params = vec![
+            InfoParams::Pv(vec!["7g7f".to_string()]),
+            InfoParams::Text("hello".to_string()),
+            InfoParams::Depth(1, None),
+        ];
+        assert!(info.send(params).unwrap());
+        assert!(!info.send(vec![InfoParams::Depth(2, None)]).unwrap());
+        info.send_now(vec![InfoParams::Depth(3, None)]).unwrap();
+
+        assert_eq!(
+            vec!["info depth 1 pv 7g7f", "info string hello", "info depth 3",],
+            receiver.try_iter().collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,13 @@
+mod engine;
+mod engine_server;
+mod info;
+mod options;
+mod stop;
+
+pub use self::engine::{Responder, UsiEngine};
+pub(crate) use self::engine_server::Dispatcher;
+pub use self::engine_server::EngineServer;
+pub use self::info::InfoSender;
+pub(crate) use self::info::DEFAULT_INFO_INTERVAL;
+pub use self::options::OptionRegistry;
+pub use self::stop::{StopToken, Stopped};
@@ -0,0 +1,340 @@
+use std::collections::HashMap;
+
+use crate::error::Error;
+use crate::protocol::*;
+
+const USI_HASH: &str = "USI_Hash";
+const USI_PONDER: &str = "USI_Ponder";
+const USI_MULTIPV: &str = "USI_MultiPV";
+
+/// `OptionRegistry` declares the options of a `UsiEngine`.
+///
+/// `EngineServer` sends the declared options on `usi`, and validates `setoption` against them
+/// before delivering the typed values to `UsiEngine::set_option`.
+///
+/// # Examples
+///
+/// ```
+/// use usi::{OptionRegistry, OptionValue, SetOptionParams};
+///
+/// let mut options = OptionRegistry::new()
+///     .spin("Threads", 1, 1, 256)
+///     .check("OwnBook", true)
+///     .combo("Style", "Normal", ["Solid", "Normal", "Risky"]);
+///
+/// assert_eq!(
+///     OptionValue::Int(4),
+///     options.set(&SetOptionParams::new("Threads", "4")).unwrap()
+/// );
+/// assert!(options.set(&SetOptionParams::new("Threads", "1000")).is_err());
+/// assert_eq!(Some(4), options.int("Threads"));
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct OptionRegistry {
+    options: Vec<OptionParams>,
+    values: HashMap<String, OptionValue>,
+}
+
+impl OptionRegistry {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        OptionRegistry::default()
+    }
+
+    /// Declares a spin option with the default value and the range.
+    #[must_use]
+    pub fn spin<N: Into<String>>(self, name: N, default: i32, min: i32, max: i32) -> Self {
+        self.declare(
+            name.into(),
+            OptionKind::Spin {
+                default: Some(default),
+                min: Some(min),
+                max: Some(max),
+            },
+            Some(OptionValue::Int(default)),
+        )
+    }
+
+    /// Declares a check option with the default value.
+    #[must_use]
+    pub fn check<N: Into<String>>(self, name: N, default: bool) -> Self {
+        self.declare(
+            name.into(),
+            OptionKind::Check {
+                default: Some(default),
+            },
+            Some(OptionValue::Bool(default)),
+        )
+    }
+
+    /// Declares a combo option with the default value and the choices.
+    #[must_use]
+    pub fn combo<N, D, I, V>(self, name: N, default: D, vars: I) -> Self
+    where
+        N: Into<String>,
+        D: Into<String>,
+        I: IntoIterator<Item = V>,
+        V: Into<String>,
+    {
+        let default = default.into();
+        self.declare(
+            name.into(),
+            OptionKind::Combo {
+                default: Some(default.clone()),
+                vars: vars.into_iter().map(Into::into).collect(),
+            },
+            Some(OptionValue::ComboChoice(default)),
+        )
+    }
+
+    /// Declares a string option with the default value.
+    #[must_use]
+    pub fn string<N: Into<String>, D: Into<String>>(self, name: N, default: D) -> Self {
+        let default = default.into();
+        self.declare(
+            name.into(),
+            OptionKind::String {
+                default: Some(default.clone()),
+            },
+            Some(OptionValue::String(default)),
+        )
+    }
+
+    /// Declares a filename option with the default value.
+    #[must_use]
+    pub fn filename<N: Into<String>, D: Into<String>>(self, name: N, default: D) -> Self {
+        let default = default.into();
+        self.declare(
+            name.into(),
+            OptionKind::Filename {
+                default: Some(default.clone()),
+            },
+            Some(OptionValue::String(default)),
+        )
+    }
+
+    /// Declares a button option.
+    #[must_use]
+    pub fn button<N: Into<String>>(self, name: N) -> Self {
+        self.declare(name.into(), OptionKind::Button { default: None }, None)
+    }
+
+    /// Declares `USI_Hash` with 256 MB by default unless it is declared already.
+    #[must_use]
+    pub fn with_usi_hash(self) -> Self {
+        if self.contains(USI_HASH) {
+            return self;
+        }
+        self.spin(USI_HASH, 256, 1, 1024 * 1024)
+    }
+
+    /// Declares `USI_Ponder` unless it is declared already.
+    /// Declare it only if the engine can ponder, as GUIs enable pondering with it.
+    #[must_use]
+    pub fn with_usi_ponder(self) -> Self {
+        if self.contains(USI_PONDER) {
+            return self;
+        }
+        self.check(USI_PONDER, false)
+    }
+
+    /// Declares `USI_MultiPV` up to 800 PVs unless it is declared already.
+    /// Declare it only if the engine can report multiple PVs.
+    #[must_use]
+    pub fn with_usi_multipv(self) -> Self {
+        if self.contains(USI_MULTIPV) {
+            return self;
+        }
+        self.spin(USI_MULTIPV, 1, 1, 800)
+    }
+
+    /// Declares all the standard options `USI_Hash`, `USI_Ponder` and `USI_MultiPV`
+    /// unless they are declared already.
+    #[must_use]
+    pub fn with_standard_options(self) -> Self {
+        self.with_usi_hash().with_usi_ponder().with_usi_multipv()
+    }
+
+    fn declare(mut self, name: String, kind: OptionKind, value: Option<OptionValue>) -> Self {
+        self.options.retain(|option| option.name != name);
+        match value {
+            Some(value) => self.values.insert(name.clone(), value),
+            None => self.values.remove(&name),
+        };
+        self.options.push(OptionParams { name, value: kind });
+        self
+    }
+
+    /// Returns the declared options in the declared order.
+    pub fn options(&self) -> &[OptionParams] {
+        &self.options
+    }
+
+    /// Returns `true` if the option named `name` is declared.
+    pub fn contains(&self, name: &str) -> bool {
+        self.options.iter().any(|option| option.name == name)
+    }
+
+    /// Returns the hash size in MB set by `USI_Hash`.
+    pub fn usi_hash(&self) -> Option<i32> {
+        self.int(USI_HASH)
+    }
+
+    /// Returns whether pondering is enabled by `USI_Ponder`.
+    pub fn usi_ponder(&self) -> Option<bool> {
+        self.bool(USI_PONDER)
+    }
+
+    /// Returns the number of PVs set by `USI_MultiPV`.
+    pub fn usi_multipv(&self) -> Option<i32> {
+        self.int(USI_MULTIPV)
+    }
+
+    /// Returns the current value of the option named `name`. Buttons have no value.
+    pub fn get(&self, name: &str) -> Option<&OptionValue> {
+        self.values.get(name)
+    }
+
+    /// Returns the current value of the check option named `name`.
+    pub fn bool(&self, name: &str) -> Option<bool> {
+        match self.get(name)? {
+            OptionValue::Bool(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    /// Returns the current value of the spin option named `name`.
+    pub fn int(&self, name: &str) -> Option<i32> {
+        match self.get(name)? {
+            OptionValue::Int(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    /// Returns the current value of the string, filename or combo option named `name`.
+    pub fn str(&self, name: &str) -> Option<&str> {
+        match self.get(name)? {
+            OptionValue::String(v) | OptionValue::ComboChoice(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    /// Validates `params` against the declared option and updates the current value.
+    ///
+    /// Returns `Error::UnknownOption` if the option is not declared,
+    /// `Error::OptionOutOfRange` if a spin value is outside the range,
+    /// or `Error::InvalidOptionValue` if the value doesn't fit the type of the option.
+    pub fn set(&mut self, params: &SetOptionParams) -> Result<OptionValue, Error> {
+        let option = self
+            .options
+            .iter()
+            .find(|option| option.name == params.name)
+            .ok_or_else(|| Error::UnknownOption {
+                name: params.name.clone(),
+            })?;
+
+        let raw = params.value.as_deref().unwrap_or("");
+        let value = option.value.parse_valid(&option.name, raw)?;
+
+        if value != OptionValue::Button {
+            self.values.insert(params.name.clone(), value.clone());
+        }
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set() {
+        let mut options = OptionRegistry::new()
+            .spin("USI_Hash", 16, 1, 1024)
+            .check("OwnBook", false)
+            .combo("Style", "Normal", ["Solid", "Normal", "Risky"])
+            .filename("EvalFile", "eval.bin")
+            .button("ClearHash");
+
+        assert_eq!(
+            vec!["USI_Hash", "OwnBook", "Style", "EvalFile", "ClearHash"],
+            options
+                .options()
+                .iter()
+                .map(|o| o.name.as_str())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(Some(16), options.int("USI_Hash"));
+        assert_eq!(Some("Normal"), options.str("Style"));
+
+        let ok_cases = [
+            (
+                SetOptionParams::new("USI_Hash", " 256"),
+                OptionValue::Int(256),
+            ),
+            (
+                SetOptionParams::new("OwnBook", "true"),
+                OptionValue::Bool(true),
+            ),
+            (
+                SetOptionParams::new("Style", "Risky"),
+                OptionValue::ComboChoice("Risky".to_string()),
+            ),
+            (
+                SetOptionParams::new("EvalFile", ""),
+                OptionValue::String(String::new()),
+            ),
+            (SetOptionParams::button("ClearHash"), OptionValue::Button),
+        ];
+        for (params, expected) in ok_cases {
+            assert_eq!(expected, options.set(&params).unwrap());
+        }
+        assert_eq!(Some(256), options.int("USI_Hash"));
+        assert_eq!(Some(true), options.bool("OwnBook"));
+        assert_eq!(None, options.get("ClearHash"));
+
+        assert!(matches!(
+            options.set(&SetOptionParams::new("USI_Hash", "0")),
+            Err(Error::OptionOutOfRange { given: 0, .. })
+        ));
+        let err_cases = [
+            SetOptionParams::new("OwnBook", "yes"),
+            SetOptionParams::new("Style", "Crazy"),
+        ];
+        for params in err_cases {
+            assert!(matches!(
+                options.set(&params),
+                Err(Error::InvalidOptionValue { .. })
+            ));
+        }
+        assert!(matches!(
+            options.set(&SetOptionParams::new("Threads", "4")),
+            Err(Error::UnknownOption { .. })
+        ));
+        assert_eq!(Some(256), options.int("USI_Hash"));
+    }
+
+    #[test]
+    fn standard_options() {
+        let options = OptionRegistry::new()
+            .spin("USI_Hash", 16, 1, 1024)
+            .with_standard_options();
+
+        assert_eq!(
+            vec!["USI_Hash", "USI_Ponder", "USI_MultiPV"],
+            options
+                .options()
+                .iter()
+                .map(|o| o.name.as_str())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(Some(16), options.usi_hash());
+        assert_eq!(Some(false), options.usi_ponder());
+        assert_eq!(Some(1), options.usi_multipv());
+
+        let options = OptionRegistry::new().with_usi_hash();
+        assert!(options.contains("USI_Hash"));
+        assert!(!options.contains("USI_Ponder"));
+        assert!(!options.contains("USI_MultiPV"));
+    }
+}
@@ -0,0 +1,117 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+#[derive(Debug, Default)]
+struct Inner {
+    stopped: AtomicBool,
+    ponderhit: AtomicBool,
+    wakers: Mutex<Vec<Waker>>,
+}
+
+/// `StopToken` tells a search of a `UsiEngine` when to stop.
+///
+/// `EngineServer` passes a new token to every `go`, and stops it on `stop` or `quit`.
+/// Search threads can poll `is_stopped` or await `stopped`.
+///
+/// `StopToken` can be cloned and moved to search threads.
+#[derive(Clone, Debug, Default)]
+pub struct StopToken {
+    inner: Arc<Inner>,
+}
+
+impl StopToken {
+    /// Creates a token which is not stopped.
+    pub fn new() -> Self {
+        StopToken::default()
+    }
+
+    /// Returns `true` if the search should stop.
+    pub fn is_stopped(&self) -> bool {
+        self.inner.stopped.load(Ordering::Acquire)
+    }
+
+    /// Returns `true` if `ponderhit` was received, so the ponder search is now a normal search.
+    pub fn is_ponderhit(&self) -> bool {
+        self.inner.ponderhit.load(Ordering::Acquire)
+    }
+
+    /// Stops the search.
+    pub fn stop(&self) {
+        self.inner.stopped.store(true, Ordering::Release);
+        for waker in self.inner.wakers.lock().unwrap().drain(..) {
+            waker.wake();
+        }
+    }
+
+    pub(crate) fn ponderhit(&self) {
+        self.inner.ponderhit.store(true, Ordering::Release);
+    }
+
+    /// Returns a future which completes when the search is stopped.
+    pub fn stopped(&self) -> Stopped {
+        Stopped {
+            token: self.clone(),
+        }
+    }
+}
+
+/// A future returned by `StopToken::stopped`.
+#[derive(Debug)]
+#[must_use = "futures do nothing unless polled"]
+pub struct Stopped {
+    token: StopToken,
+}
+
+impl Future for Stopped {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.token.is_stopped() {
+            return Poll::Ready(());
+        }
+        let mut wakers = self.token.inner.wakers.lock().unwrap();
+        // Check again as `stop` may have drained the wakers in the meantime.
+        if self.token.is_stopped() {
+            return Poll::Ready(());
+        }
+        if !wakers.iter().any(|waker| waker.will_wake(cx.waker())) {
+            wakers.push(cx.waker().clone());
+        }
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::task::Wake;
+    use std::thread;
+
+    struct CountWaker(Mutex<usize>);
+
+    impl Wake for CountWaker {
+        fn wake(self: Arc<Self>) {
+            *self.0.lock().unwrap() += 1;
+        }
+    }
+
+    #[test]
+    fn stop() {
+        let token = StopToken::new();
+        let waker = Arc::new(CountWaker(Mutex::new(0)));
+        let mut stopped = token.stopped();
+        let task_waker = Waker::from(waker.clone());
+        let mut cx = Context::from_waker(&task_waker);
+        assert!(Pin::new(&mut stopped).poll(&mut cx).is_pending());
+        assert!(!token.is_stopped());
+
+        let search = token.clone();
+        thread::spawn(move || search.stop()).join().unwrap();
+        assert!(token.is_stopped());
+        assert_eq!(1, *waker.0.lock().unwrap());
+        assert!(Pin::new(&mut stopped).poll(&mut cx).is_ready());
+    }
+}
@@ -0,0 +1,247 @@
+//! Engines and buffers shared by the tests.
+
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+pub(crate) use crate::client::SharedBuffer;
+use crate::{
+    BenchReport, BestMoveParams, CheckmateParams, EngineCommand, InfoParams, InfoSender,
+    OptionRegistry, OptionValue, Responder, StopToken, ThinkParams, UsiEngine,
+};
+
+impl SharedBuffer {
+    /// Returns the lines written so far.
+    pub(crate) fn lines(&self) -> Vec<String> {
+        let buf = self.0.lock().unwrap();
+        String::from_utf8_lossy(&buf)
+            .lines()
+            .map(String::from)
+            .collect()
+    }
+}
+
+/// A configurable engine named "Toy" by "usi-rs".
+///
+/// `go` sends the info lines and then the bestmove, `resign` by default. The bestmove of
+/// infinite and ponder searches, or of all searches after `wait`, is held until `stop` or
+/// `ponderhit`. The callbacks are logged, e.g. `set_option USI_Ponder true`, in `calls`.
+#[derive(Clone, Debug)]
+pub(crate) struct ToyEngine {
+    name: String,
+    options: OptionRegistry,
+    info: Vec<InfoParams>,
+    bestmove: BestMoveParams,
+    ponderhit: Option<BestMoveParams>,
+    mate: Option<Vec<String>>,
+    bench: Option<BenchReport>,
+    new_game_delay: Duration,
+    wait: bool,
+    hang: bool,
+    background: bool,
+    pending: Option<BestMoveParams>,
+    calls: Arc<Mutex<Vec<String>>>,
+}
+
+impl Default for ToyEngine {
+    fn default() -> Self {
+        ToyEngine {
+            name: "Toy".to_string(),
+            options: OptionRegistry::new(),
+            info: Vec::new(),
+            bestmove: BestMoveParams::Resign,
+            ponderhit: None,
+            mate: None,
+            bench: None,
+            new_game_delay: Duration::ZERO,
+            wait: false,
+            hang: false,
+            background: false,
+            pending: None,
+            calls: Arc::default(),
+        }
+    }
+}
+
+impl ToyEngine {
+    pub(crate) fn new() -> Self {
+        ToyEngine::default()
+    }
+
+    /// Sets the name sent in `id name`.
+    #[must_use]
+    pub(crate) fn name(mut self, name: &str) -> Self {
+        self.name = name.to_string();
+        self
+    }
+
+    /// Sets the options declared on `usi`.
+    #[must_use]
+    pub(crate) fn options(mut self, options: OptionRegistry) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Sets the info sent by `go`.
+    #[must_use]
+    pub(crate) fn info(mut self, info: Vec<InfoParams>) -> Self {
+        self.info = info;
+        self
+    }
+
+    /// Sets the bestmove.
+    #[must_use]
+    pub(crate) fn bestmove(mut self, mv: &str, ponder: Option<&str>) -> Self {
+        self.bestmove = BestMoveParams::MakeMove(mv.to_string(), ponder.map(String::from));
+        self
+    }
+
+    /// Sets the bestmove sent on `ponderhit`, e.g. the reply to the move pondered on.
+    #[must_use]
+    pub(crate) fn ponderhit_bestmove(mut self, mv: &str, ponder: Option<&str>) -> Self {
+        self.ponderhit = Some(BestMoveParams::MakeMove(
+            mv.to_string(),
+            ponder.map(String::from),
+        ));
+        self
+    }
+
+    /// Answers `go mate` with `checkmate` and `moves`.
+    #[must_use]
+    pub(crate) fn mate(mut self, moves: &[&str]) -> Self {
+        self.mate = Some(moves.iter().map(|mv| mv.to_string()).collect());
+        self
+    }
+
+    /// Answers `bench` with `report`.
+    #[must_use]
+    pub(crate) fn bench(mut self, report: BenchReport) -> Self {
+        self.bench = Some(report);
+        self
+    }
+
+    /// Blocks `usinewgame` for `delay`, as if the engine hung.
+    #[must_use]
+    pub(crate) fn new_game_delay(mut self, delay: Duration) -> Self {
+        self.new_game_delay = delay;
+        self
+    }
+
+    /// Holds the bestmove of every search until `stop` or `ponderhit`.
+    #[must_use]
+    pub(crate) fn wait(mut self) -> Self {
+        self.wait = true;
+        self
+    }
+
+    /// Answers `stop` with the info only, never sending the bestmove held.
+    #[must_use]
+    pub(crate) fn hang(mut self) -> Self {
+        self.hang = true;
+        self
+    }
+
+    /// Sends the bestmove of `go` from another thread.
+    #[must_use]
+    pub(crate) fn background(mut self) -> Self {
+        self.background = true;
+        self
+    }
+
+    /// Returns the log of the callbacks.
+    pub(crate) fn calls(&self) -> Arc<Mutex<Vec<String>>> {
+        self.calls.clone()
+    }
+
+    fn log(&self, call: String) {
+        self.calls.lock().unwrap().push(call);
+    }
+
+    fn send_info(&self, responder: &Responder) {
+        if !self.info.is_empty() {
+            responder
+                .send(&EngineCommand::Info(self.info.clone()))
+                .unwrap();
+        }
+    }
+}
+
+impl UsiEngine for ToyEngine {
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn author(&self) -> String {
+        "usi-rs".to_string()
+    }
+
+    fn options(&self) -> OptionRegistry {
+        self.options.clone()
+    }
+
+    fn set_option(&mut self, name: &str, value: OptionValue) {
+        self.log(format!("set_option {name} {value}"));
+    }
+
+    fn prepare(&mut self, options: &OptionRegistry) {
+        match options.usi_hash() {
+            Some(hash) => self.log(format!("prepare USI_Hash {hash}")),
+            None => self.log("prepare".to_string()),
+        }
+    }
+
+    fn new_game(&mut self) {
+        self.log("new_game".to_string());
+        thread::sleep(self.new_game_delay);
+    }
+
+    fn set_position(&mut self, sfen: &str) {
+        self.log(format!("set_position {sfen}"));
+    }
+
+    fn go(&mut self, params: ThinkParams, responder: &Responder, info: InfoSender, _: StopToken) {
+        if let (Some(moves), Some(_)) = (&self.mate, params.get_mate()) {
+            let checkmate = EngineCommand::Checkmate(CheckmateParams::Mate(moves.clone()));
+            responder.send(&checkmate).unwrap();
+            return;
+        }
+        if !self.info.is_empty() {
+            info.send(self.info.clone()).unwrap();
+        }
+        let bestmove = self.bestmove.clone();
+        if self.wait || params.is_infinite() || params.is_ponder() {
+            self.pending = Some(bestmove);
+        } else if self.background {
+            let responder = responder.clone();
+            thread::spawn(move || {
+                responder.send(&EngineCommand::BestMove(bestmove)).unwrap();
+            });
+        } else {
+            responder.send(&EngineCommand::BestMove(bestmove)).unwrap();
+        }
+    }
+
+    fn stop(&mut self, responder: &Responder) {
+        if self.hang {
+            self.send_info(responder);
+        } else if let Some(bestmove) = self.pending.take() {
+            responder.send(&EngineCommand::BestMove(bestmove)).unwrap();
+        }
+    }
+
+    fn ponderhit(&mut self, responder: &Responder) {
+        if let Some(bestmove) = self.pending.take() {
+            let bestmove = self.ponderhit.clone().unwrap_or(bestmove);
+            responder.send(&EngineCommand::BestMove(bestmove)).unwrap();
+        }
+    }
+
+    fn bench(&mut self, args: &[String]) -> Option<BenchReport> {
+        self.log(format!("bench {}", args.join(" ")));
+        self.bench.clone()
+    }
+
+    fn quit(&mut self) {
+        self.log("quit".to_string());
+    }
+}